@@ -0,0 +1,73 @@
+use crate::diagnostics::Diagnostic;
+use crate::lexer::{try_tokenize_with_spans, LexOptions};
+use crate::parser::parse_with_recovery;
+use crate::resolver::resolve;
+use crate::typechecker::check;
+
+
+// Runs every stage before the interpreter and collects what they report. A source that doesn't
+// parse only has its parse errors, one for each broken statement, since the later stages would
+// report problems that are only there because of the missing statements. A source with too many
+// tokens only has that one diagnostic.
+pub fn check_source(source: &str, options: &LexOptions) -> Vec<Diagnostic> {
+    let (tokens, spans) = match try_tokenize_with_spans(source, options) {
+        Ok(tokens) => tokens,
+        Err(error) => return vec![Diagnostic::from(&error)],
+    };
+    let module = match parse_with_recovery(&tokens, &spans) {
+        (Some(module), errors) if errors.is_empty() => module,
+        (_, errors) => return errors.iter().map(Diagnostic::from).collect(),
+    };
+
+    let mut diagnostics = resolve(&module);
+    diagnostics.extend(check(&module));
+    diagnostics
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::span::Span;
+
+    use super::*;
+
+    #[test]
+    fn test_diagnostics_from_every_stage_are_collected() {
+        let source = "fun two(): string {\n    x: int = 1\n    return 2\n}\n";
+        assert_eq!(check_source(source, &LexOptions::default()), vec![
+            Diagnostic::warning("Variable 'x' is never read"),
+            Diagnostic::error("Function 'two' should return string but returns int").with_span(Span::new(39, 47)),
+        ]);
+    }
+
+    #[test]
+    fn test_too_many_tokens_is_reported() {
+        let options = LexOptions { max_tokens: 10, ..LexOptions::default() };
+        let diagnostics = check_source(&"x = 1\n".repeat(10), &options);
+        assert_eq!(diagnostics, vec![Diagnostic::error("Source has more than 10 tokens").with_span(Span::new(16, 17))]);
+    }
+
+    #[test]
+    fn test_parse_error_is_reported() {
+        let diagnostics = check_source("x: int = )\n", &LexOptions::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+    }
+
+    #[test]
+    fn test_each_broken_statement_is_reported() {
+        let diagnostics = check_source("fun main() {\n    x: int = )\n    print(1)\n    y = 2 +\n}\n", &LexOptions::default());
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(Diagnostic::is_error));
+    }
+
+    #[test]
+    fn test_broken_statement_with_a_block_is_skipped_to_its_closing_brace() {
+        let diagnostics = check_source("fun main() {\n if x > {\n a = 1\n }\n print(1)\n}\n", &LexOptions::default());
+        let messages: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+        assert_eq!(messages, vec!["Expected a colon after a map key, found Assign"]);
+
+        let diagnostics = check_source("fun main() {\n while {\n if true {\n pass\n }\n }\n print(1)\n y = 2 +\n}\n", &LexOptions::default());
+        assert_eq!(diagnostics.len(), 2);
+    }
+}
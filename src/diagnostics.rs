@@ -0,0 +1,174 @@
+use std::fmt;
+
+use crate::span::{Span, Position};
+use crate::lexer::{LexOptions, LexError};
+use crate::parser::ParseError;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    // Where in the source code the problem is, if it's known
+    pub span: Option<Span>,
+}
+
+
+impl Diagnostic {
+    pub fn error(message: &str) -> Diagnostic {
+        Diagnostic { severity: Severity::Error, message: message.to_string(), span: None }
+    }
+
+    pub fn warning(message: &str) -> Diagnostic {
+        Diagnostic { severity: Severity::Warning, message: message.to_string(), span: None }
+    }
+
+    pub fn with_span(self, span: Span) -> Diagnostic {
+        Diagnostic { span: Some(span), ..self }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Diagnostic {
+        let found = error.found.as_deref().unwrap_or("EOF");
+        Diagnostic::error(&format!("{}, found {}", error.message, found)).with_span(error.span)
+    }
+}
+
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Diagnostic {
+        Diagnostic::error(&error.message).with_span(error.span)
+    }
+}
+
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "Error: {}", self.message),
+            Severity::Warning => write!(f, "Warning: {}", self.message),
+        }
+    }
+}
+
+
+// A single line JSON object for editors and other tools. The line and column are counted from 1 and the
+// length is in characters, all of them null when the diagnostic has no span.
+pub fn diagnostic_to_json(diagnostic: &Diagnostic, src: &str, options: &LexOptions) -> String {
+    let severity = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+
+    let (line, column, length) = match diagnostic.span {
+        Some(span) => {
+            let position = Position::at_offset(src, span.start, options.tab_width);
+            let length = src[span.start..span.end].chars().count();
+            (position.line.to_string(), position.column.to_string(), length.to_string())
+        },
+        None => ("null".to_string(), "null".to_string(), "null".to_string()),
+    };
+
+    format!(
+        "{{\"severity\": \"{}\", \"message\": \"{}\", \"line\": {}, \"column\": {}, \"length\": {}}}",
+        severity, escape_json(&diagnostic.message), line, column, length
+    )
+}
+
+
+// One JSON object per line, so a tool can read the diagnostics as they arrive
+pub fn diagnostics_to_json_lines(diagnostics: &[Diagnostic], src: &str, options: &LexOptions) -> String {
+    diagnostics.iter()
+        .map(|diagnostic| diagnostic_to_json(diagnostic, src, options) + "\n")
+        .collect()
+}
+
+
+pub(crate) fn escape_json(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::{tokenize_with_spans, try_tokenize_with_spans};
+    use crate::parser::parse_with_spans;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_error_as_json() {
+        let source = "fun main() {\n    x: int = )\n}\n";
+        let (tokens, spans) = tokenize_with_spans(source, &LexOptions::default());
+        let error = parse_with_spans(&tokens, &spans).unwrap_err();
+
+        let json = diagnostic_to_json(&Diagnostic::from(&error), source, &LexOptions::default());
+
+        assert_eq!(
+            json,
+            r#"{"severity": "error", "message": "Expected an atomic expression, found CloseParen", "line": 2, "column": 14, "length": 1}"#
+        );
+    }
+
+    #[test]
+    fn test_lex_error_as_json() {
+        let source = "x: int = 1\nx++\n";
+        let error = try_tokenize_with_spans(source, &LexOptions::default()).unwrap_err();
+
+        let json = diagnostic_to_json(&Diagnostic::from(&error), source, &LexOptions::default());
+
+        assert_eq!(
+            json,
+            r#"{"severity": "error", "message": "'++' is not supported, use '+= 1' instead", "line": 2, "column": 2, "length": 2}"#
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_without_span_as_json() {
+        let diagnostic = Diagnostic::warning("Unused variable 'x'");
+
+        assert_eq!(
+            diagnostic_to_json(&diagnostic, "", &LexOptions::default()),
+            r#"{"severity": "warning", "message": "Unused variable 'x'", "line": null, "column": null, "length": null}"#
+        );
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let source = "x = \"a\tb\"\n";
+        let diagnostics = vec![
+            Diagnostic::error("Unexpected \"quote\"").with_span(Span::new(4, 10)),
+            Diagnostic::warning("Tab in string").with_span(Span::new(6, 7)),
+        ];
+
+        assert_eq!(diagnostics_to_json_lines(&diagnostics, source, &LexOptions::default()), concat!(
+            r#"{"severity": "error", "message": "Unexpected \"quote\"", "line": 1, "column": 5, "length": 6}"#, "\n",
+            r#"{"severity": "warning", "message": "Tab in string", "line": 1, "column": 7, "length": 1}"#, "\n",
+        ));
+    }
+}
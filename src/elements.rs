@@ -22,10 +22,15 @@ pub enum Literal {
     String(String),
     Boolean(bool),
     None,
+
+    // Number literals with an explicit type suffix, like 255u8 or 3.0f32
+    TypedInteger(u64, NumberType),
+    TypedFloat(f64, NumberType),
 }
 
 
 impl Literal {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(literal: &str) -> Option<Literal> {
         match literal {
             "true" => Some(Literal::Boolean(true)),
@@ -34,6 +39,101 @@ impl Literal {
             _ => None,
         }
     }
+
+    // Reads back the text of a number, boolean or None from to_display_string. Chars and strings
+    // are displayed without quotes, so they can't be told apart from other text.
+    pub fn parse(text: &str) -> Option<Literal> {
+        if let Some(literal) = Literal::from_str(text) {
+            return Some(literal);
+        }
+        match text.parse::<i64>() {
+            Ok(value) => Some(Literal::Integer(value)),
+            Err(_) => text.parse::<f64>().ok().map(Literal::Float),
+        }
+    }
+
+    // The text of the value, as printed by the interpreter. Floats use their shortest form that reads
+    // back to the same value, and the type suffix of a typed number is left out.
+    pub fn to_display_string(&self) -> String {
+        match self {
+            Literal::Float(value) | Literal::TypedFloat(value, _) => format!("{:?}", value),
+            Literal::Integer(value) => value.to_string(),
+            Literal::TypedInteger(value, _) => value.to_string(),
+            Literal::Char(value) => value.to_string(),
+            Literal::String(value) => value.clone(),
+            Literal::Boolean(value) => value.to_string(),
+            Literal::None => "None".to_string(),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberType {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+
+impl NumberType {
+    pub fn from_suffix(suffix: &str) -> Option<NumberType> {
+        match suffix {
+            "i8" => Some(NumberType::I8),
+            "i16" => Some(NumberType::I16),
+            "i32" => Some(NumberType::I32),
+            "i64" => Some(NumberType::I64),
+            "u8" => Some(NumberType::U8),
+            "u16" => Some(NumberType::U16),
+            "u32" => Some(NumberType::U32),
+            "u64" => Some(NumberType::U64),
+            "f32" => Some(NumberType::F32),
+            "f64" => Some(NumberType::F64),
+            _ => None,
+        }
+    }
+
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            NumberType::I8 => "i8",
+            NumberType::I16 => "i16",
+            NumberType::I32 => "i32",
+            NumberType::I64 => "i64",
+            NumberType::U8 => "u8",
+            NumberType::U16 => "u16",
+            NumberType::U32 => "u32",
+            NumberType::U64 => "u64",
+            NumberType::F32 => "f32",
+            NumberType::F64 => "f64",
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, NumberType::F32 | NumberType::F64)
+    }
+
+    // The largest value an integer literal of this type can hold. Literals are never negative,
+    // since a minus sign is parsed as a unary operator.
+    pub fn max_integer(&self) -> Option<u64> {
+        match self {
+            NumberType::I8 => Some(i8::MAX as u64),
+            NumberType::I16 => Some(i16::MAX as u64),
+            NumberType::I32 => Some(i32::MAX as u64),
+            NumberType::I64 => Some(i64::MAX as u64),
+            NumberType::U8 => Some(u8::MAX as u64),
+            NumberType::U16 => Some(u16::MAX as u64),
+            NumberType::U32 => Some(u32::MAX as u64),
+            NumberType::U64 => Some(u64::MAX),
+            NumberType::F32 | NumberType::F64 => None,
+        }
+    }
 }
 
 
@@ -67,6 +167,17 @@ pub enum Operator {
     GreaterThan,
     LessThanOrEqual,
     GreaterThanOrEqual,
+
+    // Membership, written with the in keyword
+    In,
+
+    // Identity, written as is and is not
+    Is,
+    IsNot,
+
+    // Ranges of ints or chars, which leave out the end unless they are inclusive
+    Range,
+    InclusiveRange,
 }
 
 
@@ -77,20 +188,39 @@ pub enum Keyword {
     From,
 
     Function,
+    Const,
     Return,
 
+    Struct,
+
     Variable,
+    Let,
+    Global,
+    Delete,
+    Pass,
+    Do,
 
     If,
+    Elif,
     Else,
 
     For,
     In,
+    Is,
+    Not,
     While,
+    Repeat,
+    Break,
+    Continue,
+
+    Try,
+    Except,
+    Raise,
 }
 
 
 impl Keyword {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(keyword: &str) -> Option<Keyword> {
         match keyword {
             "using" => Some(Keyword::Using),
@@ -98,18 +228,94 @@ impl Keyword {
             "from" => Some(Keyword::From),
 
             "fun" => Some(Keyword::Function),
+            "const" => Some(Keyword::Const),
             "return" => Some(Keyword::Return),
 
+            "struct" => Some(Keyword::Struct),
+
             "var" => Some(Keyword::Variable),
+            "let" => Some(Keyword::Let),
+            "global" => Some(Keyword::Global),
+            "del" => Some(Keyword::Delete),
+            "pass" => Some(Keyword::Pass),
+            "do" => Some(Keyword::Do),
 
             "if" => Some(Keyword::If),
+            "elif" => Some(Keyword::Elif),
             "else" => Some(Keyword::Else),
 
             "for" => Some(Keyword::For),
             "in" => Some(Keyword::In),
+            "is" => Some(Keyword::Is),
+            "not" => Some(Keyword::Not),
             "while" => Some(Keyword::While),
+            "repeat" => Some(Keyword::Repeat),
+            "break" => Some(Keyword::Break),
+            "continue" => Some(Keyword::Continue),
+
+            "try" => Some(Keyword::Try),
+            "except" => Some(Keyword::Except),
+            "raise" => Some(Keyword::Raise),
 
             _ => None,
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Keyword::Using => "using",
+            Keyword::As => "as",
+            Keyword::From => "from",
+
+            Keyword::Function => "fun",
+            Keyword::Const => "const",
+            Keyword::Return => "return",
+
+            Keyword::Struct => "struct",
+
+            Keyword::Variable => "var",
+            Keyword::Let => "let",
+            Keyword::Global => "global",
+            Keyword::Delete => "del",
+            Keyword::Pass => "pass",
+            Keyword::Do => "do",
+
+            Keyword::If => "if",
+            Keyword::Elif => "elif",
+            Keyword::Else => "else",
+
+            Keyword::For => "for",
+            Keyword::In => "in",
+            Keyword::Is => "is",
+            Keyword::Not => "not",
+            Keyword::While => "while",
+            Keyword::Repeat => "repeat",
+            Keyword::Break => "break",
+            Keyword::Continue => "continue",
+
+            Keyword::Try => "try",
+            Keyword::Except => "except",
+            Keyword::Raise => "raise",
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display_string_round_trips() {
+        for literal in [Literal::Integer(-42), Literal::Boolean(true), Literal::Boolean(false), Literal::None] {
+            assert_eq!(Literal::parse(&literal.to_display_string()), Some(literal));
+        }
+    }
+
+    #[test]
+    fn test_float_display_string() {
+        assert_eq!(Literal::Float(2.0).to_display_string(), "2.0");
+        assert_eq!(Literal::Float(0.1).to_display_string(), "0.1");
+        assert_eq!(Literal::TypedFloat(1.5, NumberType::F32).to_display_string(), "1.5");
+    }
 }
@@ -0,0 +1,387 @@
+use crate::interpreter::{Environment, Value, RuntimeError};
+use crate::interpreter::formatting::format_template;
+
+
+type Builtin = fn(Vec<Value>) -> Result<Value, RuntimeError>;
+
+
+// Functions that every environment starts with, which the resolver also uses to know which
+// functions can be called without being defined or imported
+pub const BUILTIN_FUNCTIONS: &[(&str, Builtin)] = &[
+    ("print", print),
+    ("abs", abs),
+    ("min", |arguments| extreme("min", arguments, i64::min, f64::min)),
+    ("max", |arguments| extreme("max", arguments, i64::max, f64::max)),
+    ("exit", exit),
+    ("len", len),
+    ("push", push),
+    ("pop", pop),
+    ("typeof", type_of),
+    ("enumerate", enumerate),
+    ("as_int", as_int),
+    ("as_float", as_float),
+    ("as_string", as_string),
+    ("format", format),
+];
+
+
+pub fn define_builtins(environment: &mut Environment) {
+    for (name, function) in BUILTIN_FUNCTIONS {
+        environment.define_native_function(name, *function);
+    }
+}
+
+
+// Prints the values separated by spaces on a line of their own
+fn print(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let line = arguments.iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(" ");
+    println!("{}", line);
+    Ok(Value::None)
+}
+
+
+fn abs(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match arguments.as_slice() {
+        [Value::Integer(value)] => value.checked_abs()
+            .map(Value::Integer)
+            .ok_or_else(|| RuntimeError::new("Integer overflow")),
+        [Value::Float(value)] => Ok(Value::Float(value.abs())),
+        [value] => Err(not_a_number("abs", value)),
+        _ => Err(RuntimeError::new(&format!("Function 'abs' expects 1 arguments, found {}", arguments.len()))),
+    }
+}
+
+
+// Stops the program with the given exit code, including from within a function or a try block
+fn exit(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match arguments.as_slice() {
+        [Value::Integer(code)] => match i32::try_from(*code) {
+            Ok(code) => Err(RuntimeError::exit(code)),
+            Err(_) => Err(RuntimeError::new(&format!("Exit code {} is out of range", code))),
+        },
+        [value] => Err(RuntimeError::new(&format!("Function 'exit' expects an int, found {}", value.type_name()))),
+        _ => Err(RuntimeError::new(&format!("Function 'exit' expects 1 arguments, found {}", arguments.len()))),
+    }
+}
+
+
+// Strings are measured in unicode scalar values, like they are indexed
+fn len(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let length = match arguments.as_slice() {
+        [Value::String(value)] => value.chars().count(),
+        [Value::Array(values)] => values.borrow().len(),
+        [Value::Tuple(values)] => values.len(),
+        [Value::Map(entries)] => entries.borrow().len(),
+        [value] => return Err(RuntimeError::new(&format!("Function 'len' expects a collection, found {}", value.type_name()))),
+        _ => return Err(RuntimeError::new(&format!("Function 'len' expects 1 arguments, found {}", arguments.len()))),
+    };
+    Ok(Value::Integer(length as i64))
+}
+
+
+// Appends to the array in place, so the caller's array grows too
+fn push(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match arguments.as_slice() {
+        [Value::Array(values), value] => {
+            values.borrow_mut().push(value.clone());
+            Ok(Value::None)
+        },
+        [value, _] => Err(not_an_array("push", value)),
+        _ => Err(RuntimeError::new(&format!("Function 'push' expects 2 arguments, found {}", arguments.len()))),
+    }
+}
+
+
+fn pop(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match arguments.as_slice() {
+        [Value::Array(values)] => values.borrow_mut()
+            .pop()
+            .ok_or_else(|| RuntimeError::new("Cannot pop from an empty array")),
+        [value] => Err(not_an_array("pop", value)),
+        _ => Err(RuntimeError::new(&format!("Function 'pop' expects 1 arguments, found {}", arguments.len()))),
+    }
+}
+
+
+// Pairs each item with its index, as (index, item) tuples that a two variable for loop unpacks
+fn enumerate(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let items = match arguments.as_slice() {
+        [Value::Array(values)] => values.borrow().clone(),
+        [Value::Tuple(values)] => values.clone(),
+        [Value::String(value)] => value.chars().map(Value::Char).collect(),
+        [value] => return Err(RuntimeError::new(&format!("Function 'enumerate' expects a sequence, found {}", value.type_name()))),
+        _ => return Err(RuntimeError::new(&format!("Function 'enumerate' expects 1 arguments, found {}", arguments.len()))),
+    };
+    let pairs = items.into_iter()
+        .enumerate()
+        .map(|(index, item)| Value::Tuple(vec![Value::Integer(index as i64), item]))
+        .collect();
+    Ok(Value::array(pairs))
+}
+
+
+// The name of the runtime type, as it appears in error messages
+fn type_of(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match arguments.as_slice() {
+        [value] => Ok(Value::String(value.type_name().to_string())),
+        _ => Err(RuntimeError::new(&format!("Function 'typeof' expects 1 arguments, found {}", arguments.len()))),
+    }
+}
+
+
+// Type guards, which give the value as the type if it is one or converts to one without losing
+// anything, and fail otherwise. Unlike a cast, as_int doesn't truncate a float with a fraction, and
+// as_string only takes strings and chars rather than showing any value.
+fn as_int(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let value = single_argument("as_int", arguments)?;
+    let converted = match &value {
+        Value::Integer(value) => Some(*value),
+        Value::Float(float) if float.fract() == 0.0 && *float >= i64::MIN as f64 && *float < i64::MAX as f64 => Some(*float as i64),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    };
+    converted.map(Value::Integer).ok_or_else(|| cannot_convert("as_int", &value, "int"))
+}
+
+
+fn as_float(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let value = single_argument("as_float", arguments)?;
+    let converted = match &value {
+        Value::Integer(value) => Some(*value as f64),
+        Value::Float(value) => Some(*value),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    };
+    converted.map(Value::Float).ok_or_else(|| cannot_convert("as_float", &value, "float"))
+}
+
+
+fn as_string(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match single_argument("as_string", arguments)? {
+        value @ Value::String(_) => Ok(value),
+        Value::Char(value) => Ok(Value::String(value.to_string())),
+        value => Err(cannot_convert("as_string", &value, "string")),
+    }
+}
+
+
+fn single_argument(name: &str, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let count = arguments.len();
+    match <[Value; 1]>::try_from(arguments) {
+        Ok([value]) => Ok(value),
+        Err(_) => Err(RuntimeError::new(&format!("Function '{}' expects 1 arguments, found {}", name, count))),
+    }
+}
+
+
+// Strings are quoted, so that an empty or blank one still shows up in the message
+fn cannot_convert(name: &str, value: &Value, target: &str) -> RuntimeError {
+    let shown = match value {
+        Value::String(text) => format!("{:?}", text),
+        value => value.to_string(),
+    };
+    RuntimeError::new(&format!("Function '{}' cannot convert {} {} to {}", name, value.type_name(), shown, target))
+}
+
+
+// Fills the placeholders of a template string, like format("{:06.2}", 3.14159) giving "003.14"
+fn format(arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match arguments.split_first() {
+        Some((Value::String(template), arguments)) => Ok(Value::String(format_template(template, arguments)?)),
+        Some((value, _)) => Err(RuntimeError::new(&format!("Function 'format' expects a string template, found {}", value.type_name()))),
+        None => Err(RuntimeError::new("Function 'format' expects at least 1 argument")),
+    }
+}
+
+
+// The smallest or largest of any number of arguments. Like arithmetic, mixing integers and floats
+// promotes the result to a float.
+fn extreme(name: &str, arguments: Vec<Value>, pick_integer: fn(i64, i64) -> i64, pick_float: fn(f64, f64) -> f64) -> Result<Value, RuntimeError> {
+    let mut arguments = arguments.into_iter();
+    let mut result = match arguments.next() {
+        Some(value @ (Value::Integer(_) | Value::Float(_))) => value,
+        Some(value) => return Err(not_a_number(name, &value)),
+        None => return Err(RuntimeError::new(&format!("Function '{}' expects at least 1 argument", name))),
+    };
+
+    for argument in arguments {
+        result = match (result, argument) {
+            (Value::Integer(left), Value::Integer(right)) => Value::Integer(pick_integer(left, right)),
+            (Value::Integer(left), Value::Float(right)) => Value::Float(pick_float(left as f64, right)),
+            (Value::Float(left), Value::Integer(right)) => Value::Float(pick_float(left, right as f64)),
+            (Value::Float(left), Value::Float(right)) => Value::Float(pick_float(left, right)),
+            (_, argument) => return Err(not_a_number(name, &argument)),
+        };
+    }
+
+    Ok(result)
+}
+
+
+fn not_a_number(name: &str, value: &Value) -> RuntimeError {
+    RuntimeError::new(&format!("Function '{}' expects numbers, found {}", name, value.type_name()))
+}
+
+
+fn not_an_array(name: &str, value: &Value) -> RuntimeError {
+    RuntimeError::new(&format!("Function '{}' expects an array, found {}", name, value.type_name()))
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+    use crate::tree::Statement;
+    use crate::interpreter::{evaluate_expression, StructValue};
+
+    use super::*;
+
+    fn evaluate(source: &str) -> Result<Value, RuntimeError> {
+        let module = parse(&tokenize(source)).unwrap();
+        match &module.statements.statements[0].node {
+            Statement::Expression(expression) => evaluate_expression(expression, &Environment::new()),
+            statement => panic!("Expected an expression statement, found {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn test_print() {
+        assert_eq!(evaluate("print(\"pig\", 1, [2.5])"), Ok(Value::None));
+        assert_eq!(evaluate("print()"), Ok(Value::None));
+    }
+
+    #[test]
+    fn test_abs() {
+        assert_eq!(evaluate("abs(-3)"), Ok(Value::Integer(3)));
+        assert_eq!(evaluate("abs(-2.5)"), Ok(Value::Float(2.5)));
+    }
+
+    #[test]
+    fn test_min_and_max() {
+        assert_eq!(evaluate("max(1, 2)"), Ok(Value::Integer(2)));
+        assert_eq!(evaluate("min(1.5, 2)"), Ok(Value::Float(1.5)));
+        assert_eq!(evaluate("max(3, 1, 2.0)"), Ok(Value::Float(3.0)));
+        assert_eq!(evaluate("min(4)"), Ok(Value::Integer(4)));
+    }
+
+    #[test]
+    fn test_min_without_arguments() {
+        assert_eq!(evaluate("min()"), Err(RuntimeError::new("Function 'min' expects at least 1 argument")));
+    }
+
+    #[test]
+    fn test_exit() {
+        assert_eq!(evaluate("exit(2)"), Err(RuntimeError::exit(2)));
+        assert_eq!(evaluate("exit(1.0)"), Err(RuntimeError::new("Function 'exit' expects an int, found float")));
+    }
+
+    #[test]
+    fn test_len() {
+        assert_eq!(evaluate("len([1, 2, 3])"), Ok(Value::Integer(3)));
+        assert_eq!(evaluate("len(\"héllo\")"), Ok(Value::Integer(5)));
+        assert_eq!(evaluate("len(1)"), Err(RuntimeError::new("Function 'len' expects a collection, found int")));
+    }
+
+    #[test]
+    fn test_push_and_pop() {
+        let values = Value::array(vec![Value::Integer(1)]);
+
+        assert_eq!(push(vec![values.clone(), Value::Integer(2)]), Ok(Value::None));
+        assert_eq!(values, Value::array(vec![Value::Integer(1), Value::Integer(2)]));
+
+        assert_eq!(pop(vec![values.clone()]), Ok(Value::Integer(2)));
+        assert_eq!(pop(vec![values.clone()]), Ok(Value::Integer(1)));
+        assert_eq!(pop(vec![values]), Err(RuntimeError::new("Cannot pop from an empty array")));
+    }
+
+    #[test]
+    fn test_print_array_pushed_into_itself() {
+        let values = Value::array(vec![Value::Integer(1)]);
+
+        push(vec![values.clone(), values.clone()]).unwrap();
+
+        assert_eq!(values.to_string(), "[1, [...]]");
+        assert_eq!(Value::Tuple(vec![values.clone(), values]).to_string(), "([1, [...]], [1, [...]])");
+    }
+
+    #[test]
+    fn test_print_map_and_struct_inside_themselves() {
+        let values = Value::array(vec![]);
+        let map = Value::map(vec![(Value::Char('a'), values.clone())]);
+        let point = Value::structure(StructValue { name: "Point".to_string(), fields: vec![("x".to_string(), values.clone())] });
+
+        push(vec![values.clone(), map.clone()]).unwrap();
+        push(vec![values.clone(), point.clone()]).unwrap();
+
+        assert_eq!(map.to_string(), "{a: [{...}, Point { x: [...] }]}");
+        assert_eq!(point.to_string(), "Point { x: [{a: [...]}, Point { ... }] }");
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let pair = |index: i64, item: Value| Value::Tuple(vec![Value::Integer(index), item]);
+
+        assert_eq!(evaluate("enumerate([5, 6])"), Ok(Value::array(vec![pair(0, Value::Integer(5)), pair(1, Value::Integer(6))])));
+        assert_eq!(evaluate("enumerate(\"ab\")"), Ok(Value::array(vec![pair(0, Value::Char('a')), pair(1, Value::Char('b'))])));
+        assert_eq!(evaluate("enumerate([])"), Ok(Value::array(vec![])));
+        assert_eq!(evaluate("enumerate(1)"), Err(RuntimeError::new("Function 'enumerate' expects a sequence, found int")));
+    }
+
+    #[test]
+    fn test_typeof() {
+        let type_name = |name: &str| Ok(Value::String(name.to_string()));
+
+        assert_eq!(evaluate("typeof(1)"), type_name("int"));
+        assert_eq!(evaluate("typeof(1.5)"), type_name("float"));
+        assert_eq!(evaluate("typeof(true)"), type_name("bool"));
+        assert_eq!(evaluate("typeof('a')"), type_name("char"));
+        assert_eq!(evaluate("typeof(\"a\")"), type_name("string"));
+        assert_eq!(evaluate("typeof([1, 2])"), type_name("array"));
+        assert_eq!(type_of(vec![Value::Tuple(vec![Value::Integer(1)])]), type_name("tuple"));
+        assert_eq!(evaluate("typeof({1: 2})"), type_name("map"));
+        assert_eq!(evaluate("typeof(None)"), type_name("None"));
+        assert_eq!(evaluate("typeof()"), Err(RuntimeError::new("Function 'typeof' expects 1 arguments, found 0")));
+    }
+
+    #[test]
+    fn test_as_int() {
+        assert_eq!(evaluate("as_int(3.0)"), Ok(Value::Integer(3)));
+        assert_eq!(evaluate("as_int(4)"), Ok(Value::Integer(4)));
+        assert_eq!(evaluate("as_int(\"-12\")"), Ok(Value::Integer(-12)));
+        assert_eq!(evaluate("as_int(\"x\")"), Err(RuntimeError::new("Function 'as_int' cannot convert string \"x\" to int")));
+        assert_eq!(evaluate("as_int(3.5)"), Err(RuntimeError::new("Function 'as_int' cannot convert float 3.5 to int")));
+        assert_eq!(evaluate("as_int(true)"), Err(RuntimeError::new("Function 'as_int' cannot convert bool true to int")));
+    }
+
+    #[test]
+    fn test_as_float_and_as_string() {
+        assert_eq!(evaluate("as_float(2)"), Ok(Value::Float(2.0)));
+        assert_eq!(evaluate("as_float(\"1.5\")"), Ok(Value::Float(1.5)));
+        assert_eq!(evaluate("as_float('a')"), Err(RuntimeError::new("Function 'as_float' cannot convert char a to float")));
+
+        assert_eq!(evaluate("as_string(\"pig\")"), Ok(Value::String("pig".to_string())));
+        assert_eq!(evaluate("as_string('a')"), Ok(Value::String("a".to_string())));
+        assert_eq!(evaluate("as_string(1)"), Err(RuntimeError::new("Function 'as_string' cannot convert int 1 to string")));
+        assert_eq!(evaluate("as_string()"), Err(RuntimeError::new("Function 'as_string' expects 1 arguments, found 0")));
+    }
+
+    #[test]
+    fn test_format() {
+        let text = |text: &str| Ok(Value::String(text.to_string()));
+
+        assert_eq!(evaluate("format(\"{:05}\", 42)"), text("00042"));
+        assert_eq!(evaluate("format(\"{:.3} and {:.1}\", 3.14159, 2.25)"), text("3.142 and 2.2"));
+        assert_eq!(evaluate("format(\"{} {}\", 1)"), Err(RuntimeError::new("Format template has 2 placeholders, but 1 arguments were given")));
+        assert_eq!(evaluate("format(1)"), Err(RuntimeError::new("Function 'format' expects a string template, found int")));
+    }
+
+    #[test]
+    fn test_builtins_reject_non_numbers() {
+        assert_eq!(evaluate("max(1, \"two\")"), Err(RuntimeError::new("Function 'max' expects numbers, found string")));
+        assert_eq!(evaluate("abs(true)"), Err(RuntimeError::new("Function 'abs' expects numbers, found bool")));
+    }
+}
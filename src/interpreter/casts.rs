@@ -0,0 +1,82 @@
+use crate::elements::Identifier;
+use crate::interpreter::{Value, RuntimeError};
+
+
+// Casting between int and float truncates towards zero, ints and chars convert through
+// the unicode code point, and any value can be cast to its string representation.
+pub fn evaluate_cast(value: Value, target_type: &Identifier) -> Result<Value, RuntimeError> {
+    let target = target_type.as_string();
+
+    match (value, target.as_str()) {
+        (value, "string") => Ok(Value::String(value.to_string())),
+
+        (Value::Integer(value), "float") => Ok(Value::Float(value as f64)),
+        (Value::Float(value), "int") => float_to_integer(value),
+
+        (Value::Integer(value), "char") => u32::try_from(value).ok()
+            .and_then(char::from_u32)
+            .map(Value::Char)
+            .ok_or_else(|| RuntimeError::new(&format!("{} is not a valid char code point", value))),
+        (Value::Char(value), "int") => Ok(Value::Integer(value as i64)),
+
+        (value, target) if value.type_name() == target => Ok(value),
+
+        (value, target) => Err(RuntimeError::new(&format!("Cannot cast {} to {}", value.type_name(), target))),
+    }
+}
+
+
+fn float_to_integer(value: f64) -> Result<Value, RuntimeError> {
+    let truncated = value.trunc();
+    if truncated.is_finite() && truncated >= i64::MIN as f64 && truncated < i64::MAX as f64 {
+        Ok(Value::Integer(truncated as i64))
+    } else {
+        Err(RuntimeError::new(&format!("{:?} is out of range for an int", value)))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn type_name(name: &str) -> Identifier {
+        Identifier::Simple(name.to_string())
+    }
+
+    #[test]
+    fn test_cast_int_to_float() {
+        assert_eq!(evaluate_cast(Value::Integer(3), &type_name("float")), Ok(Value::Float(3.0)));
+    }
+
+    #[test]
+    fn test_cast_float_to_int_truncates() {
+        assert_eq!(evaluate_cast(Value::Float(-3.7), &type_name("int")), Ok(Value::Integer(-3)));
+    }
+
+    #[test]
+    fn test_cast_char_to_int() {
+        assert_eq!(evaluate_cast(Value::Char('A'), &type_name("int")), Ok(Value::Integer(65)));
+    }
+
+    #[test]
+    fn test_cast_int_to_char() {
+        assert_eq!(evaluate_cast(Value::Integer(97), &type_name("char")), Ok(Value::Char('a')));
+    }
+
+    #[test]
+    fn test_cast_to_string() {
+        assert_eq!(evaluate_cast(Value::Float(1.5), &type_name("string")), Ok(Value::String("1.5".to_string())));
+        assert_eq!(evaluate_cast(Value::Boolean(true), &type_name("string")), Ok(Value::String("true".to_string())));
+    }
+
+    #[test]
+    fn test_invalid_casts() {
+        assert_eq!(
+            evaluate_cast(Value::String("1".to_string()), &type_name("int")),
+            Err(RuntimeError::new("Cannot cast string to int"))
+        );
+        assert!(evaluate_cast(Value::Integer(-1), &type_name("char")).is_err());
+        assert!(evaluate_cast(Value::Float(f64::NAN), &type_name("int")).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::elements::Identifier;
+use crate::tree::{Function, StructDefinition};
+use crate::interpreter::{Value, RuntimeError};
+use crate::interpreter::builtins::define_builtins;
+
+
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 200;
+
+
+// A function implemented in Rust which can be called from SheepPig code
+pub type NativeFunction = Rc<dyn Fn(Vec<Value>) -> Result<Value, RuntimeError>>;
+
+
+// Variables are held in a stack of scopes, with a new scope for each statement block. Each function
+// call gets an environment of its own, which shares the functions and the globals but none of the
+// other variables. Variables declared at the top level of the module, outside any block, are globals.
+// A function can read any global, but can only assign to the ones it has declared with `global`, so
+// that assigning to a global by accident is an error instead of silently changing it.
+#[derive(Clone)]
+pub struct Environment {
+    // Expressions are evaluated with a shared environment, so the scopes can change behind one for
+    // the assignments within expressions
+    scopes: RefCell<Vec<HashMap<String, Value>>>,
+    globals: Rc<RefCell<HashMap<String, Value>>>,
+    // The globals that the running function has declared it assigns to
+    global_names: HashSet<String>,
+    native_functions: Rc<HashMap<String, NativeFunction>>,
+    functions: Rc<HashMap<String, Rc<Function>>>,
+    structs: Rc<HashMap<String, Rc<StructDefinition>>>,
+    call_depth: usize,
+    max_call_depth: usize,
+    // How many more blocks may run, shared with the environments of the calls, when there's a limit
+    steps_left: Option<Rc<Cell<usize>>>,
+    // Conditions take any value by its truthiness, rather than only a bool
+    is_dynamic: bool,
+}
+
+
+impl Default for Environment {
+    fn default() -> Environment {
+        Environment::new()
+    }
+}
+
+
+impl Environment {
+    pub fn new() -> Environment {
+        let mut environment = Environment::without_builtins();
+        define_builtins(&mut environment);
+        environment
+    }
+
+    // An environment with no native functions, for running code that must not have side effects
+    pub fn without_builtins() -> Environment {
+        Environment {
+            scopes: RefCell::new(vec![]),
+            globals: Rc::new(RefCell::new(HashMap::new())),
+            global_names: HashSet::new(),
+            native_functions: Rc::new(HashMap::new()),
+            functions: Rc::new(HashMap::new()),
+            structs: Rc::new(HashMap::new()),
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            steps_left: None,
+            is_dynamic: false,
+        }
+    }
+
+    // Deep recursion is stopped with an error before it can overflow the stack of the interpreter
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    // Stops code that must finish, like the body of a const function, when it runs too long
+    pub fn set_max_steps(&mut self, max_steps: usize) {
+        self.steps_left = Some(Rc::new(Cell::new(max_steps)));
+    }
+
+    pub fn step(&self) -> Result<(), RuntimeError> {
+        match &self.steps_left {
+            Some(steps_left) if steps_left.get() == 0 => Err(RuntimeError::new("Maximum number of steps exceeded")),
+            Some(steps_left) => {
+                steps_left.set(steps_left.get() - 1);
+                Ok(())
+            },
+            None => Ok(()),
+        }
+    }
+
+    pub fn set_dynamic(&mut self, is_dynamic: bool) {
+        self.is_dynamic = is_dynamic;
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        self.is_dynamic
+    }
+
+    // The environment that the body of a called function runs in
+    pub fn enter_call(&self) -> Result<Environment, RuntimeError> {
+        if self.call_depth >= self.max_call_depth {
+            return Err(RuntimeError::new("Maximum recursion depth exceeded"));
+        }
+
+        Ok(Environment {
+            scopes: RefCell::new(vec![HashMap::new()]),
+            globals: self.globals.clone(),
+            global_names: HashSet::new(),
+            native_functions: self.native_functions.clone(),
+            functions: self.functions.clone(),
+            structs: self.structs.clone(),
+            call_depth: self.call_depth + 1,
+            max_call_depth: self.max_call_depth,
+            steps_left: self.steps_left.clone(),
+            is_dynamic: self.is_dynamic,
+        })
+    }
+
+    pub fn define_function(&mut self, function: Function) {
+        Rc::make_mut(&mut self.functions).insert(function.name.as_string(), Rc::new(function));
+    }
+
+    pub fn function(&self, name: &Identifier) -> Option<Rc<Function>> {
+        self.functions.get(&name.as_string()).cloned()
+    }
+
+    pub fn define_struct(&mut self, definition: StructDefinition) {
+        Rc::make_mut(&mut self.structs).insert(definition.name.as_string(), Rc::new(definition));
+    }
+
+    pub fn struct_definition(&self, name: &Identifier) -> Option<Rc<StructDefinition>> {
+        self.structs.get(&name.as_string()).cloned()
+    }
+
+    pub fn define_native_function(&mut self, name: &str, function: impl Fn(Vec<Value>) -> Result<Value, RuntimeError> + 'static) {
+        Rc::make_mut(&mut self.native_functions).insert(name.to_string(), Rc::new(function));
+    }
+
+    pub fn native_function(&self, name: &Identifier) -> Result<NativeFunction, RuntimeError> {
+        self.native_functions.get(&name.as_string())
+            .cloned()
+            .ok_or_else(|| RuntimeError::new(&format!("Undefined function '{}'", name.as_string())))
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.get_mut().push(HashMap::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.scopes.get_mut().pop();
+    }
+
+    pub fn declare(&mut self, name: &Identifier, value: Value) {
+        match self.scopes.get_mut().last_mut() {
+            Some(scope) => scope.insert(name.as_string(), value),
+            None => self.globals.borrow_mut().insert(name.as_string(), value),
+        };
+    }
+
+    pub fn declare_global(&mut self, name: &Identifier) -> Result<(), RuntimeError> {
+        if !self.globals.borrow().contains_key(&name.as_string()) {
+            return Err(RuntimeError::new(&format!("Undefined global variable '{}'", name.as_string())));
+        }
+        self.global_names.insert(name.as_string());
+        Ok(())
+    }
+
+    // Top level code assigns to globals freely, while a function must declare them first
+    pub fn assign(&self, name: &Identifier, value: Value) -> Result<(), RuntimeError> {
+        let is_declared_global = self.global_names.contains(&name.as_string());
+        let mut scopes = self.scopes.borrow_mut();
+        let mut globals = self.globals.borrow_mut();
+        let variable = if is_declared_global {
+            globals.get_mut(&name.as_string())
+        } else {
+            scopes.iter_mut().rev().find_map(|scope| scope.get_mut(&name.as_string()))
+        };
+
+        let variable = match variable {
+            Some(variable) => Some(variable),
+            None => match globals.get_mut(&name.as_string()) {
+                Some(_) if self.call_depth > 0 => return Err(RuntimeError::new(&format!(
+                    "Cannot assign to global variable '{}' in a function without declaring it global", name.as_string()
+                ))),
+                variable => variable,
+            },
+        };
+
+        match variable {
+            Some(variable) => {
+                *variable = value;
+                Ok(())
+            },
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    pub fn remove(&mut self, name: &Identifier) -> Result<Value, RuntimeError> {
+        self.scopes.get_mut().iter_mut().rev()
+            .find_map(|scope| scope.remove(&name.as_string()))
+            .or_else(|| self.globals.borrow_mut().remove(&name.as_string()))
+            .ok_or_else(|| undefined_variable(name))
+    }
+
+    pub fn get(&self, name: &Identifier) -> Result<Value, RuntimeError> {
+        self.scopes.borrow().iter().rev()
+            .find_map(|scope| scope.get(&name.as_string()))
+            .cloned()
+            .or_else(|| self.globals.borrow().get(&name.as_string()).cloned())
+            .ok_or_else(|| undefined_variable(name))
+    }
+}
+
+
+fn undefined_variable(name: &Identifier) -> RuntimeError {
+    RuntimeError::new(&format!("Undefined variable '{}'", name.as_string()))
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+
+use crate::interpreter::Value;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    // The value given to a raise statement, which an except block receives instead of the message
+    // It's boxed to keep errors small, since every result in the interpreter carries one.
+    pub value: Option<Box<Value>>,
+    // Set when the program called exit, which is carried like an error so that it stops everything
+    // between the call and the top of the module, but can't be caught
+    pub exit_code: Option<i32>,
+}
+
+
+impl RuntimeError {
+    pub fn new(message: &str) -> RuntimeError {
+        RuntimeError { message: message.to_string(), value: None, exit_code: None }
+    }
+
+    pub fn raised(value: Value) -> RuntimeError {
+        RuntimeError { message: value.to_string(), value: Some(Box::new(value)), exit_code: None }
+    }
+
+    pub fn exit(code: i32) -> RuntimeError {
+        RuntimeError { message: format!("Exited with code {}", code), value: None, exit_code: Some(code) }
+    }
+}
+
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Runtime error: {}", self.message)
+    }
+}
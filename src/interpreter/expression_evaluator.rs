@@ -0,0 +1,447 @@
+use crate::elements::{Identifier, Operator};
+use crate::tree::{Expression, AtomicExpression, ArrayIndex, FunctionCallExpression, MapLiteralExpression, ComprehensionExpression, StructLiteralExpression};
+use crate::interpreter::{Value, StructValue, RuntimeError};
+use crate::interpreter::environment::Environment;
+use crate::interpreter::statement_executor::{call_function, loop_items, declare_loop_variables};
+use crate::interpreter::operators::{evaluate_unary_operation, evaluate_binary_operation};
+use crate::interpreter::casts::evaluate_cast;
+use crate::interpreter::indexing::{index_value, slice_value};
+
+
+// Evaluation is always left to right. The left operand of a binary operation is evaluated before
+// the right one, and function arguments and array elements are evaluated in the order they are
+// written, so side effects happen in the order they appear in the source.
+pub fn evaluate_expression(expression: &Expression, environment: &Environment) -> Result<Value, RuntimeError> {
+    match expression {
+        Expression::TernaryCondition { condition, true_value, false_value } => {
+            if evaluate_condition(condition, environment)? {
+                evaluate_expression(true_value, environment)
+            } else {
+                evaluate_expression(false_value, environment)
+            }
+        },
+
+        Expression::BinaryOperation { left, operator: operator @ (Operator::And | Operator::Or), right }
+        => evaluate_logical_operation(left, operator, right, environment),
+
+        Expression::BinaryOperation { left, operator, right } => {
+            let left = evaluate_expression(left, environment)?;
+            let right = evaluate_expression(right, environment)?;
+            evaluate_binary_operation(operator, left, right)
+        },
+
+        Expression::UnaryOperation { operator, operand } => {
+            evaluate_unary_operation(operator, evaluate_expression(operand, environment)?)
+        },
+
+        Expression::Cast(cast) => evaluate_cast(evaluate_expression(&cast.value, environment)?, &cast.target_type),
+
+        Expression::Assignment(assignment) => {
+            let value = evaluate_expression(&assignment.value, environment)?;
+            environment.assign(&assignment.target, value.clone())?;
+            Ok(value)
+        },
+
+        Expression::Tuple(values) => Ok(Value::Tuple(evaluate_list(values, environment)?)),
+
+        Expression::Spread(_) => Err(RuntimeError::new("A spread can only be used in an array literal or an argument list")),
+
+        Expression::Atomic(atom) => evaluate_atomic(atom, environment),
+    }
+}
+
+
+fn evaluate_atomic(atom: &AtomicExpression, environment: &Environment) -> Result<Value, RuntimeError> {
+    match atom {
+        AtomicExpression::Literal(literal) => Ok(Value::from_literal(literal)),
+        AtomicExpression::Identifier(identifier) => evaluate_identifier(identifier, environment),
+        AtomicExpression::FunctionCall(call) => evaluate_function_call(call, environment),
+        AtomicExpression::ArrayLiteral(array) => Ok(Value::array(evaluate_list(&array.values, environment)?)),
+        AtomicExpression::MapLiteral(map) => evaluate_map(map, environment),
+        AtomicExpression::Comprehension(comprehension) => evaluate_comprehension(comprehension, environment),
+        AtomicExpression::StructLiteral(literal) => evaluate_struct_literal(literal, environment),
+        AtomicExpression::Parenthesized(parenthesized) => evaluate_expression(&parenthesized.value, environment),
+
+        AtomicExpression::ArrayIndex(array_index) => {
+            let value = evaluate_atomic(&array_index.array, environment)?;
+            match &array_index.index {
+                ArrayIndex::Single(index) => index_value(value, evaluate_expression(index, environment)?),
+                ArrayIndex::Slice { start, end } => {
+                    let start = start.as_ref().map(|start| evaluate_expression(start, environment)).transpose()?;
+                    let end = end.as_ref().map(|end| evaluate_expression(end, environment)).transpose()?;
+                    slice_value(value, start, end)
+                },
+            }
+        },
+    }
+}
+
+
+// A compound name like `p.x` reads a field of the struct held by the variable named by its first part
+fn evaluate_identifier(identifier: &Identifier, environment: &Environment) -> Result<Value, RuntimeError> {
+    let names = match identifier {
+        Identifier::Simple(_) => return environment.get(identifier),
+        Identifier::Compound(names) => names,
+    };
+
+    let mut value = environment.get(&Identifier::Simple(names[0].clone()))?;
+    for name in &names[1..] {
+        value = match value {
+            Value::Struct(value) => {
+                let value = value.borrow();
+                value.fields.iter()
+                    .find(|(field, _)| field == name)
+                    .map(|(_, value)| value.clone())
+                    .ok_or_else(|| RuntimeError::new(&format!("Struct '{}' has no field '{}'", value.name, name)))?
+            },
+            value => return Err(RuntimeError::new(&format!("Cannot read field '{}' of {}", name, value.type_name()))),
+        };
+    }
+    Ok(value)
+}
+
+
+fn evaluate_function_call(call: &FunctionCallExpression, environment: &Environment) -> Result<Value, RuntimeError> {
+    let arguments = evaluate_list(&call.parameters, environment)?;
+    let keyword_arguments = call.keyword_arguments.iter()
+        .map(|(name, value)| Ok((name.clone(), evaluate_expression(value, environment)?)))
+        .collect::<Result<Vec<_>, RuntimeError>>()?;
+    call_function(&call.name, arguments, keyword_arguments, environment)
+}
+
+
+// The elements of a spread array are spliced into the list in place of the spread
+fn evaluate_list(expressions: &[Expression], environment: &Environment) -> Result<Vec<Value>, RuntimeError> {
+    let mut values = vec![];
+    for expression in expressions {
+        match expression {
+            Expression::Spread(spread) => match evaluate_expression(&spread.value, environment)? {
+                Value::Array(elements) => values.extend(elements.borrow().iter().cloned()),
+                value => return Err(RuntimeError::new(&format!("Cannot spread {}", value.type_name()))),
+            },
+            expression => values.push(evaluate_expression(expression, environment)?),
+        }
+    }
+    Ok(values)
+}
+
+
+// A key that appears more than once keeps the position of its first entry and the last value
+fn evaluate_map(map: &MapLiteralExpression, environment: &Environment) -> Result<Value, RuntimeError> {
+    let mut entries: Vec<(Value, Value)> = vec![];
+    for (key, value) in &map.entries {
+        let key = evaluate_expression(key, environment)?;
+        let value = evaluate_expression(value, environment)?;
+        match entries.iter_mut().find(|(existing, _)| *existing == key) {
+            Some(entry) => entry.1 = value,
+            None => entries.push((key, value)),
+        }
+    }
+    Ok(Value::map(entries))
+}
+
+
+// The loop variables live in a scope of their own, so they don't leak out of the comprehension
+fn evaluate_comprehension(comprehension: &ComprehensionExpression, environment: &Environment) -> Result<Value, RuntimeError> {
+    let iterable = evaluate_expression(&comprehension.iterable, environment)?;
+    let items = loop_items(iterable, comprehension.variables.len())?;
+
+    let mut environment = environment.clone();
+    environment.push_scope();
+
+    let mut values = vec![];
+    for item in items {
+        declare_loop_variables(&comprehension.variables, item, &mut environment)?;
+        if let Some(condition) = &comprehension.condition {
+            if !evaluate_condition(condition, &environment)? {
+                continue;
+            }
+        }
+        values.push(evaluate_expression(&comprehension.output, &environment)?);
+    }
+    Ok(Value::array(values))
+}
+
+
+// The fields are evaluated in the order they are written, and then put in the order of the definition
+fn evaluate_struct_literal(literal: &StructLiteralExpression, environment: &Environment) -> Result<Value, RuntimeError> {
+    let struct_name = literal.name.as_string();
+    let definition = environment.struct_definition(&literal.name)
+        .ok_or_else(|| RuntimeError::new(&format!("Undefined struct '{}'", struct_name)))?;
+
+    let mut values = vec![];
+    for (name, value) in &literal.fields {
+        if !definition.fields.iter().any(|field| field.name == *name) {
+            return Err(RuntimeError::new(&format!("Struct '{}' has no field '{}'", struct_name, name.as_string())));
+        }
+        if values.iter().any(|(given, _)| given == name) {
+            return Err(RuntimeError::new(&format!("Field '{}' is given more than once", name.as_string())));
+        }
+        values.push((name.clone(), evaluate_expression(value, environment)?));
+    }
+
+    let mut fields = vec![];
+    for field in &definition.fields {
+        match values.iter().position(|(given, _)| *given == field.name) {
+            Some(index) => fields.push((field.name.as_string(), values.swap_remove(index).1)),
+            None => return Err(RuntimeError::new(&format!(
+                "Struct '{}' is missing field '{}'", struct_name, field.name.as_string()
+            ))),
+        }
+    }
+    Ok(Value::structure(StructValue { name: struct_name, fields }))
+}
+
+
+// Only a bool is a condition, unless the environment is dynamic, when any value is taken by its truthiness
+pub fn evaluate_condition(condition: &Expression, environment: &Environment) -> Result<bool, RuntimeError> {
+    match evaluate_expression(condition, environment)? {
+        Value::Boolean(value) => Ok(value),
+        value if environment.is_dynamic() => Ok(value.is_truthy()),
+        value => Err(RuntimeError::new(&format!("Expected a bool condition, found {}", value.type_name()))),
+    }
+}
+
+
+// The right operand is only evaluated when the left operand does not decide the result
+fn evaluate_logical_operation(left: &Expression, operator: &Operator, right: &Expression, environment: &Environment) -> Result<Value, RuntimeError> {
+    let left = evaluate_condition(left, environment)?;
+    match (operator, left) {
+        (Operator::And, false) => Ok(Value::Boolean(false)),
+        (Operator::Or, true) => Ok(Value::Boolean(true)),
+        _ => Ok(Value::Boolean(evaluate_condition(right, environment)?)),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use crate::elements::{Identifier, Literal};
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+    use crate::tree::Statement;
+
+    use super::*;
+
+    #[test]
+    fn test_evaluate_bitwise_not_expression() {
+        let expression = Expression::UnaryOperation {
+            operator: Operator::BitwiseNot,
+            operand: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5))).into()),
+        };
+
+        assert_eq!(evaluate_expression(&expression, &Environment::new()), Ok(Value::Integer(-6)));
+    }
+
+    #[test]
+    fn test_evaluate_nested_bitwise_expression() {
+        let expression = Expression::BinaryOperation {
+            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(12))).into()),
+            operator: Operator::BitwiseXor,
+            right: Box::new(Expression::BinaryOperation {
+                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))).into()),
+                operator: Operator::BitwiseLeftShift,
+                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(2))).into()),
+            }.into()),
+        };
+
+        assert_eq!(evaluate_expression(&expression, &Environment::new()), Ok(Value::Integer(8)));
+    }
+
+    #[test]
+    fn test_evaluate_cast_expression() {
+        let expression = Expression::Cast(crate::tree::CastExpression {
+            value: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Char('a'))).into()),
+            target_type: crate::elements::Identifier::Simple("int".to_string()),
+        });
+
+        assert_eq!(evaluate_expression(&expression, &Environment::new()), Ok(Value::Integer(97)));
+    }
+
+    #[test]
+    fn test_logical_and_short_circuits() {
+        let expression = Expression::BinaryOperation {
+            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Boolean(false))).into()),
+            operator: Operator::And,
+            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))).into()),
+        };
+
+        assert_eq!(evaluate_expression(&expression, &Environment::new()), Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_evaluate_string_index_and_slice() {
+        let index = Expression::Atomic(AtomicExpression::ArrayIndex(crate::tree::ArrayIndexExpression {
+            array: Box::new(AtomicExpression::Literal(Literal::String("héllo".to_string()))),
+            index: ArrayIndex::Single(Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))).into())),
+        }));
+        assert_eq!(evaluate_expression(&index, &Environment::new()), Ok(Value::Char('é')));
+
+        let slice = Expression::Atomic(AtomicExpression::ArrayIndex(crate::tree::ArrayIndexExpression {
+            array: Box::new(AtomicExpression::Literal(Literal::String("héllo".to_string()))),
+            index: ArrayIndex::Slice {
+                start: Some(Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))).into())),
+                end: None,
+            },
+        }));
+        assert_eq!(evaluate_expression(&slice, &Environment::new()), Ok(Value::String("éllo".to_string())));
+    }
+
+    // Records the value of each call in a log and returns it unchanged
+    fn logging_environment() -> (Environment, Rc<RefCell<Vec<Value>>>) {
+        let log = Rc::new(RefCell::new(vec![]));
+        let mut environment = Environment::new();
+
+        let record_log = log.clone();
+        environment.define_native_function("record", move |arguments| {
+            record_log.borrow_mut().extend(arguments.iter().cloned());
+            Ok(arguments.into_iter().next().unwrap_or(Value::None))
+        });
+        environment.define_native_function("pair", |arguments| Ok(Value::array(arguments)));
+
+        (environment, log)
+    }
+
+    fn parse_expression(source: &str) -> Expression {
+        let module = parse(&tokenize(source)).unwrap();
+        match &module.statements.statements[0].node {
+            Statement::Expression(expression) => expression.clone(),
+            statement => panic!("Expected an expression statement, found {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn test_truthiness() {
+        let falsy = [
+            Value::Integer(0), Value::Float(0.0), Value::String(String::new()), Value::array(vec![]), Value::None, Value::Boolean(false),
+        ];
+        for value in falsy {
+            assert!(!value.is_truthy(), "{:?} should be falsy", value);
+        }
+
+        let truthy = [
+            Value::Integer(-1), Value::Float(0.5), Value::String("0".to_string()), Value::array(vec![Value::None]), Value::Char('a'),
+        ];
+        for value in truthy {
+            assert!(value.is_truthy(), "{:?} should be truthy", value);
+        }
+    }
+
+    #[test]
+    fn test_dynamic_conditions_use_truthiness() {
+        let mut environment = Environment::new();
+        environment.set_dynamic(true);
+
+        assert_eq!(evaluate_expression(&parse_expression("0 ? 1 : 2"), &environment), Ok(Value::Integer(2)));
+        assert_eq!(evaluate_expression(&parse_expression("\"a\" && [1]"), &environment), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_expression(&parse_expression("None || 0.0"), &environment), Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_strict_conditions_require_a_bool() {
+        assert_eq!(
+            evaluate_expression(&parse_expression("0 ? 1 : 2"), &Environment::new()),
+            Err(RuntimeError::new("Expected a bool condition, found int"))
+        );
+    }
+
+    #[test]
+    fn test_evaluation_order_is_left_to_right() {
+        let integers = |values: &[i64]| values.iter().map(|value| Value::Integer(*value)).collect::<Vec<Value>>();
+
+        let (environment, log) = logging_environment();
+        let result = evaluate_expression(&parse_expression("pair(record(1), record(2))"), &environment);
+        assert_eq!(result, Ok(Value::array(integers(&[1, 2]))));
+        assert_eq!(*log.borrow(), integers(&[1, 2]));
+
+        let (environment, log) = logging_environment();
+        let result = evaluate_expression(&parse_expression("record(1) - record(2) * record(3)"), &environment);
+        assert_eq!(result, Ok(Value::Integer(-5)));
+        assert_eq!(*log.borrow(), integers(&[1, 2, 3]));
+
+        let (environment, log) = logging_environment();
+        let result = evaluate_expression(&parse_expression("[record(1), record(2), record(3)]"), &environment);
+        assert_eq!(result, Ok(Value::array(integers(&[1, 2, 3]))));
+        assert_eq!(*log.borrow(), integers(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_nested_ternaries_only_evaluate_the_taken_branch() {
+        let cases = [
+            ("record(false) ? record(1) : record(true) ? record(2) : record(3)", 2, vec![Value::Boolean(false), Value::Boolean(true), Value::Integer(2)]),
+            ("record(false) ? record(1) : record(false) ? record(2) : record(3)", 3, vec![Value::Boolean(false), Value::Boolean(false), Value::Integer(3)]),
+            ("record(true) ? record(false) ? record(1) : record(2) : record(3)", 2, vec![Value::Boolean(true), Value::Boolean(false), Value::Integer(2)]),
+            ("record(false) ? record(true) ? record(1) : record(2) : record(3)", 3, vec![Value::Boolean(false), Value::Integer(3)]),
+        ];
+
+        for (source, expected, calls) in cases {
+            let (environment, log) = logging_environment();
+            assert_eq!(evaluate_expression(&parse_expression(source), &environment), Ok(Value::Integer(expected)), "{}", source);
+            assert_eq!(*log.borrow(), calls, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_pipe_calls_in_order() {
+        let integers = |values: &[i64]| values.iter().map(|value| Value::Integer(*value)).collect::<Vec<Value>>();
+
+        let (environment, log) = logging_environment();
+        let result = evaluate_expression(&parse_expression("record(1) |> pair(record(2)) |> record"), &environment);
+        assert_eq!(result, Ok(Value::array(integers(&[1, 2]))));
+        assert_eq!(*log.borrow(), vec![Value::Integer(1), Value::Integer(2), Value::array(integers(&[1, 2]))]);
+    }
+
+    #[test]
+    fn test_evaluate_map_literal() {
+        let result = evaluate_expression(&parse_expression("({\"a\": 1, \"b\": 2, \"a\": 3})"), &Environment::new());
+
+        assert_eq!(result, Ok(Value::map(vec![
+            (Value::String("a".to_string()), Value::Integer(3)),
+            (Value::String("b".to_string()), Value::Integer(2)),
+        ])));
+    }
+
+    #[test]
+    fn test_spread_is_flattened() {
+        let (mut environment, _) = logging_environment();
+        environment.declare(&Identifier::Simple("middle".to_string()), Value::array(vec![Value::Integer(2), Value::Integer(3)]));
+        let integers = |values: &[i64]| values.iter().map(|value| Value::Integer(*value)).collect::<Vec<Value>>();
+
+        let result = evaluate_expression(&parse_expression("[1, *middle, 9]"), &environment);
+        assert_eq!(result, Ok(Value::array(integers(&[1, 2, 3, 9]))));
+
+        let result = evaluate_expression(&parse_expression("pair(*middle, *[4])"), &environment);
+        assert_eq!(result, Ok(Value::array(integers(&[2, 3, 4]))));
+    }
+
+    #[test]
+    fn test_evaluate_comprehension() {
+        let integers = |values: &[i64]| values.iter().map(|value| Value::Integer(*value)).collect::<Vec<Value>>();
+
+        let result = evaluate_expression(&parse_expression("[x * 2 for x in [1, 2, 3]]"), &Environment::new());
+        assert_eq!(result, Ok(Value::array(integers(&[2, 4, 6]))));
+
+        let result = evaluate_expression(&parse_expression("[x for x in [3, -1, 0, 2] if x > 0]"), &Environment::new());
+        assert_eq!(result, Ok(Value::array(integers(&[3, 2]))));
+
+        let result = evaluate_expression(&parse_expression("[key + value for key, value in ({1: 10, 2: 20})]"), &Environment::new());
+        assert_eq!(result, Ok(Value::array(integers(&[11, 22]))));
+    }
+
+    #[test]
+    fn test_comprehension_condition_must_be_a_bool() {
+        let result = evaluate_expression(&parse_expression("[x for x in [1] if x]"), &Environment::new());
+
+        assert_eq!(result, Err(RuntimeError::new("Expected a bool condition, found int")));
+    }
+
+    #[test]
+    fn test_spread_of_non_array() {
+        let result = evaluate_expression(&parse_expression("[*1]"), &Environment::new());
+
+        assert_eq!(result, Err(RuntimeError::new("Cannot spread int")));
+    }
+}
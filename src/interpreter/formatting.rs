@@ -0,0 +1,232 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::interpreter::{Value, RuntimeError};
+
+
+// A larger width or precision is an invalid placeholder, so a placeholder can't ask for a huge string
+const MAX_FORMAT_WIDTH: usize = 1000;
+
+
+// How a placeholder shows its value, read from what follows the colon in {:>8.2}. The alignment is
+// < or >, and numbers go on the right by default while anything else goes on the left. A zero before
+// the width pads a number with zeros after its sign instead of with spaces.
+#[derive(Debug, Default, PartialEq)]
+struct FormatSpec {
+    align: Option<char>,
+    zero_pad: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+
+// Substitutes the arguments into the {} placeholders of the template in order. {{ and }} are a
+// literal brace. There must be exactly as many arguments as placeholders.
+pub fn format_template(template: &str, arguments: &[Value]) -> Result<String, RuntimeError> {
+    let mut output = String::new();
+    let count = arguments.len();
+    let mut arguments = arguments.iter();
+    let mut placeholders = 0;
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            },
+            '{' => {
+                let spec = read_placeholder(&mut chars)?;
+                placeholders += 1;
+                // The rest of the template is still read when the arguments run out, to count its placeholders
+                if let Some(value) = arguments.next() {
+                    output.push_str(&format_value(value, &spec)?);
+                }
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            },
+            '}' => return Err(RuntimeError::new("Unmatched } in format template, use }} for a literal brace")),
+            c => output.push(c),
+        }
+    }
+
+    if placeholders != count {
+        return Err(RuntimeError::new(&format!(
+            "Format template has {} placeholders, but {} arguments were given", placeholders, count
+        )));
+    }
+    Ok(output)
+}
+
+
+// Reads the rest of a placeholder after its opening brace, up to and including the closing one
+fn read_placeholder(chars: &mut Peekable<Chars>) -> Result<FormatSpec, RuntimeError> {
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(c) => text.push(c),
+            None => return Err(RuntimeError::new("Unclosed { in format template")),
+        }
+    }
+
+    if text.is_empty() {
+        return Ok(FormatSpec::default());
+    }
+    text.strip_prefix(':')
+        .and_then(parse_format_spec)
+        .ok_or_else(|| RuntimeError::new(&format!("Invalid format placeholder {{{}}}", text)))
+}
+
+
+fn parse_format_spec(spec: &str) -> Option<FormatSpec> {
+    let mut chars = spec.chars().peekable();
+    let mut format_spec = FormatSpec::default();
+
+    if let Some(&align @ ('<' | '>')) = chars.peek() {
+        format_spec.align = Some(align);
+        chars.next();
+    }
+    if chars.peek() == Some(&'0') {
+        format_spec.zero_pad = true;
+        chars.next();
+    }
+    if chars.peek().is_some_and(char::is_ascii_digit) {
+        format_spec.width = read_digits(&mut chars)?;
+    }
+    if chars.peek() == Some(&'.') {
+        chars.next();
+        format_spec.precision = Some(read_digits(&mut chars)?);
+    }
+
+    match chars.next() {
+        None => Some(format_spec),
+        Some(_) => None,
+    }
+}
+
+
+fn read_digits(chars: &mut Peekable<Chars>) -> Option<usize> {
+    let mut digits = String::new();
+    while let Some(&c) = chars.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        digits.push(c);
+        chars.next();
+    }
+    digits.parse().ok().filter(|&number| number <= MAX_FORMAT_WIDTH)
+}
+
+
+// A precision rounds a number to that many decimal places, so an int with one is shown as a float
+fn format_value(value: &Value, spec: &FormatSpec) -> Result<String, RuntimeError> {
+    let is_number = matches!(value, Value::Integer(_) | Value::Float(_));
+    let text = match (value, spec.precision) {
+        (Value::Integer(value), Some(precision)) => format!("{:.*}", precision, *value as f64),
+        (Value::Float(value), Some(precision)) => format!("{:.*}", precision, value),
+        (value, Some(_)) => return Err(RuntimeError::new(&format!(
+            "A format precision only applies to numbers, found {}", value.type_name()
+        ))),
+        (value, None) => value.to_string(),
+    };
+
+    let padding = spec.width.saturating_sub(text.chars().count());
+    if padding == 0 {
+        return Ok(text);
+    }
+
+    if spec.zero_pad {
+        if !is_number {
+            return Err(RuntimeError::new(&format!("Only numbers can be padded with zeros, found {}", value.type_name())));
+        }
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text.as_str()),
+        };
+        return Ok(format!("{}{}{}", sign, "0".repeat(padding), digits));
+    }
+
+    let align = spec.align.unwrap_or(if is_number { '>' } else { '<' });
+    Ok(match align {
+        '<' => format!("{}{}", text, " ".repeat(padding)),
+        _ => format!("{}{}", " ".repeat(padding), text),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn format(template: &str, arguments: &[Value]) -> Result<String, RuntimeError> {
+        format_template(template, arguments)
+    }
+
+    #[test]
+    fn test_format_placeholders() {
+        let result = format("{} has {} legs", &[Value::String("pig".to_string()), Value::Integer(4)]);
+        assert_eq!(result, Ok("pig has 4 legs".to_string()));
+
+        assert_eq!(format("{{}} {}", &[Value::Boolean(true)]), Ok("{} true".to_string()));
+    }
+
+    #[test]
+    fn test_format_zero_padded_integers() {
+        assert_eq!(format("{:05}", &[Value::Integer(42)]), Ok("00042".to_string()));
+        assert_eq!(format("{:05}", &[Value::Integer(-42)]), Ok("-0042".to_string()));
+        assert_eq!(format("{:02}", &[Value::Integer(1234)]), Ok("1234".to_string()));
+    }
+
+    #[test]
+    fn test_format_float_precision() {
+        assert_eq!(format("{:.2}", &[Value::Float(1.23456)]), Ok("1.23".to_string()));
+        assert_eq!(format("{:08.3}", &[Value::Float(-2.5)]), Ok("-002.500".to_string()));
+        assert_eq!(format("{:.1}", &[Value::Integer(7)]), Ok("7.0".to_string()));
+    }
+
+    #[test]
+    fn test_format_width_and_alignment() {
+        assert_eq!(format("[{:4}]", &[Value::Integer(7)]), Ok("[   7]".to_string()));
+        assert_eq!(format("[{:4}]", &[Value::Char('a')]), Ok("[a   ]".to_string()));
+        assert_eq!(format("[{:<4}]", &[Value::Integer(7)]), Ok("[7   ]".to_string()));
+        assert_eq!(format("[{:>4}]", &[Value::String("ab".to_string())]), Ok("[  ab]".to_string()));
+    }
+
+    #[test]
+    fn test_format_argument_count_mismatch() {
+        assert_eq!(
+            format("{} and {} or {}", &[Value::Integer(1)]),
+            Err(RuntimeError::new("Format template has 3 placeholders, but 1 arguments were given"))
+        );
+        assert_eq!(
+            format("{}", &[Value::Integer(1), Value::Integer(2), Value::Integer(3)]),
+            Err(RuntimeError::new("Format template has 1 placeholders, but 3 arguments were given"))
+        );
+    }
+
+    #[test]
+    fn test_format_invalid_templates() {
+        assert_eq!(format("{", &[]), Err(RuntimeError::new("Unclosed { in format template")));
+        assert_eq!(format("}", &[]), Err(RuntimeError::new("Unmatched } in format template, use }} for a literal brace")));
+        assert_eq!(format("{0}", &[Value::Integer(1)]), Err(RuntimeError::new("Invalid format placeholder {0}")));
+        assert_eq!(format("{:.}", &[Value::Float(1.0)]), Err(RuntimeError::new("Invalid format placeholder {:.}")));
+        assert_eq!(
+            format("{:99999999999999999999}", &[Value::Integer(1)]),
+            Err(RuntimeError::new("Invalid format placeholder {:99999999999999999999}"))
+        );
+        assert_eq!(format("{:>1001}", &[Value::Integer(1)]), Err(RuntimeError::new("Invalid format placeholder {:>1001}")));
+        assert_eq!(format("{:.1001}", &[Value::Float(1.0)]), Err(RuntimeError::new("Invalid format placeholder {:.1001}")));
+        assert_eq!(format("{:1000}", &[Value::Integer(1)]).map(|text| text.len()), Ok(1000));
+        assert_eq!(
+            format("{:.2}", &[Value::Boolean(true)]),
+            Err(RuntimeError::new("A format precision only applies to numbers, found bool"))
+        );
+        assert_eq!(
+            format("{:03}", &[Value::Char('a')]),
+            Err(RuntimeError::new("Only numbers can be padded with zeros, found char"))
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::interpreter::{Value, RuntimeError};
+
+
+// Strings are indexed by unicode scalar values rather than bytes, so indexing never splits a
+// multi-byte character. Arrays and tuples also take a negative index, counting back from the end.
+pub fn index_value(value: Value, index: Value) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Array(values) => element(&values.borrow(), index),
+        Value::Tuple(values) => element(&values, index),
+        Value::String(string) => {
+            let index = get_index(index)?;
+            let length = string.chars().count();
+            string.chars()
+                .nth(index.min(length))
+                .map(Value::Char)
+                .ok_or_else(|| out_of_bounds(index, length))
+        },
+        value => Err(RuntimeError::new(&format!("Cannot index into {}", value.type_name()))),
+    }
+}
+
+
+// Slicing an array gives a new array, so changing the slice doesn't change the original
+pub fn slice_value(value: Value, start: Option<Value>, end: Option<Value>) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Array(values) => Ok(Value::array(elements(&values.borrow(), start, end)?.to_vec())),
+        Value::Tuple(values) => Ok(Value::Tuple(elements(&values, start, end)?.to_vec())),
+        Value::String(string) => {
+            let length = string.chars().count();
+            let start = start.map(get_index).transpose()?.unwrap_or(0);
+            let end = end.map(get_index).transpose()?.unwrap_or(length);
+
+            if end > length {
+                return Err(out_of_bounds(end, length));
+            }
+            if start > end {
+                return Err(RuntimeError::new(&format!("Slice start {} is after its end {}", start, end)));
+            }
+
+            Ok(Value::String(string.chars().skip(start).take(end - start).collect()))
+        },
+        value => Err(RuntimeError::new(&format!("Cannot slice {}", value.type_name()))),
+    }
+}
+
+
+fn element(values: &[Value], index: Value) -> Result<Value, RuntimeError> {
+    let index = get_index_from_end(index, values.len())?;
+    values.get(index).cloned().ok_or_else(|| out_of_bounds(index, values.len()))
+}
+
+
+fn elements(values: &[Value], start: Option<Value>, end: Option<Value>) -> Result<&[Value], RuntimeError> {
+    let length = values.len();
+    let start = start.map(|start| get_index_from_end(start, length)).transpose()?.unwrap_or(0);
+    let end = end.map(|end| get_index_from_end(end, length)).transpose()?.unwrap_or(length);
+
+    if end > length {
+        return Err(out_of_bounds(end, length));
+    }
+    if start > end {
+        return Err(RuntimeError::new(&format!("Slice start {} is after its end {}", start, end)));
+    }
+    Ok(&values[start..end])
+}
+
+
+// Takes the element at the index out of the array, which every reference to the array sees
+pub fn remove_element(value: Value, index: Value) -> Result<Value, RuntimeError> {
+    match value {
+        Value::Array(values) => {
+            let index = get_index(index)?;
+            let length = values.borrow().len();
+            if index >= length {
+                return Err(out_of_bounds(index, length));
+            }
+            values.borrow_mut().remove(index);
+            Ok(Value::Array(values))
+        },
+        value => Err(RuntimeError::new(&format!("Cannot delete an element of {}", value.type_name()))),
+    }
+}
+
+
+// Changes the element of the array in place, so every reference to the array sees it
+pub fn assign_element(array: Value, index: Value, value: Value) -> Result<(), RuntimeError> {
+    match array {
+        Value::Array(values) => {
+            let length = values.borrow().len();
+            let index = get_index_from_end(index, length)?;
+            if index >= length {
+                return Err(out_of_bounds(index, length));
+            }
+            values.borrow_mut()[index] = value;
+            Ok(())
+        },
+        array => Err(RuntimeError::new(&format!("Cannot assign to an element of {}", array.type_name()))),
+    }
+}
+
+
+// Replaces the elements in the slice with the elements of another array, which may have a different length
+pub fn assign_slice(array: Value, start: Option<Value>, end: Option<Value>, value: Value) -> Result<(), RuntimeError> {
+    let values = match array {
+        Value::Array(values) => values,
+        array => return Err(RuntimeError::new(&format!("Cannot assign to a slice of {}", array.type_name()))),
+    };
+    let replacement = match value {
+        Value::Array(replacement) => replacement.borrow().clone(),
+        value => return Err(RuntimeError::new(&format!("Cannot assign {} to a slice", value.type_name()))),
+    };
+
+    let length = values.borrow().len();
+    let start = start.map(|start| get_index_from_end(start, length)).transpose()?.unwrap_or(0);
+    let end = end.map(|end| get_index_from_end(end, length)).transpose()?.unwrap_or(length);
+
+    if end > length {
+        return Err(out_of_bounds(end, length));
+    }
+    if start > end {
+        return Err(RuntimeError::new(&format!("Slice start {} is after its end {}", start, end)));
+    }
+
+    values.borrow_mut().splice(start..end, replacement);
+    Ok(())
+}
+
+
+// A negative index counts back from the end, so -1 is the last element
+fn get_index_from_end(index: Value, length: usize) -> Result<usize, RuntimeError> {
+    match index {
+        Value::Integer(index) if index < 0 => usize::try_from(index.unsigned_abs())
+            .ok()
+            .and_then(|from_end| length.checked_sub(from_end))
+            .ok_or_else(|| RuntimeError::new(&format!("Index {} is out of bounds for length {}", index, length))),
+        index => get_index(index),
+    }
+}
+
+
+fn get_index(index: Value) -> Result<usize, RuntimeError> {
+    match index {
+        Value::Integer(index) => usize::try_from(index)
+            .map_err(|_| RuntimeError::new(&format!("Index {} is negative", index))),
+        index => Err(RuntimeError::new(&format!("An index must be an int, found {}", index.type_name()))),
+    }
+}
+
+
+fn out_of_bounds(index: usize, length: usize) -> RuntimeError {
+    RuntimeError::new(&format!("Index {} is out of bounds for length {}", index, length))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn string(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    #[test]
+    fn test_index_string() {
+        assert_eq!(index_value(string("héllo"), Value::Integer(1)), Ok(Value::Char('é')));
+        assert_eq!(index_value(string("héllo"), Value::Integer(4)), Ok(Value::Char('o')));
+    }
+
+    #[test]
+    fn test_slice_string() {
+        assert_eq!(slice_value(string("héllo"), Some(Value::Integer(1)), Some(Value::Integer(3))), Ok(string("él")));
+        assert_eq!(slice_value(string("héllo"), None, Some(Value::Integer(2))), Ok(string("hé")));
+        assert_eq!(slice_value(string("héllo"), Some(Value::Integer(3)), None), Ok(string("lo")));
+    }
+
+    #[test]
+    fn test_index_and_slice_arrays_and_tuples() {
+        let integers = |values: &[i64]| values.iter().copied().map(Value::Integer).collect::<Vec<Value>>();
+        let values = Value::array(integers(&[1, 2, 3]));
+
+        assert_eq!(index_value(values.clone(), Value::Integer(0)), Ok(Value::Integer(1)));
+        assert_eq!(index_value(values.clone(), Value::Integer(-1)), Ok(Value::Integer(3)));
+        assert_eq!(index_value(Value::Tuple(integers(&[1, 2])), Value::Integer(-2)), Ok(Value::Integer(1)));
+        assert_eq!(slice_value(values.clone(), Some(Value::Integer(-2)), None), Ok(Value::array(integers(&[2, 3]))));
+        assert_eq!(slice_value(Value::Tuple(integers(&[1, 2, 3])), None, Some(Value::Integer(1))), Ok(Value::Tuple(integers(&[1]))));
+        assert_eq!(index_value(values.clone(), Value::Integer(3)), Err(RuntimeError::new("Index 3 is out of bounds for length 3")));
+        assert_eq!(index_value(values, Value::Integer(-4)), Err(RuntimeError::new("Index -4 is out of bounds for length 3")));
+    }
+
+    #[test]
+    fn test_index_out_of_bounds() {
+        // The string is 5 characters long but 6 bytes long
+        assert_eq!(
+            index_value(string("héllo"), Value::Integer(5)),
+            Err(RuntimeError::new("Index 5 is out of bounds for length 5"))
+        );
+        assert!(slice_value(string("héllo"), Some(Value::Integer(2)), Some(Value::Integer(6))).is_err());
+        assert!(index_value(string("héllo"), Value::Integer(-1)).is_err());
+    }
+
+    #[test]
+    fn test_assign_element_from_the_end() {
+        let values = Value::array(vec![Value::Integer(1), Value::Integer(2)]);
+
+        assert_eq!(assign_element(values.clone(), Value::Integer(-1), Value::Integer(5)), Ok(()));
+        assert_eq!(values, Value::array(vec![Value::Integer(1), Value::Integer(5)]));
+        assert_eq!(
+            assign_element(values, Value::Integer(-3), Value::Integer(5)),
+            Err(RuntimeError::new("Index -3 is out of bounds for length 2"))
+        );
+    }
+
+    #[test]
+    fn test_assign_slice() {
+        let values = Value::array(vec![Value::Integer(1), Value::Integer(2), Value::Integer(3)]);
+
+        let result = assign_slice(values.clone(), Some(Value::Integer(-2)), None, Value::array(vec![Value::Integer(7)]));
+        assert_eq!(result, Ok(()));
+        assert_eq!(values, Value::array(vec![Value::Integer(1), Value::Integer(7)]));
+    }
+
+    #[test]
+    fn test_index_requires_int() {
+        assert_eq!(
+            index_value(string("abc"), Value::Char('a')),
+            Err(RuntimeError::new("An index must be an int, found char"))
+        );
+    }
+}
@@ -0,0 +1,17 @@
+mod value;
+mod errors;
+mod operators;
+mod casts;
+mod indexing;
+mod environment;
+mod formatting;
+mod builtins;
+mod expression_evaluator;
+mod statement_executor;
+
+pub use value::{Value, ValueKind, StructValue};
+pub use errors::RuntimeError;
+pub use environment::{Environment, NativeFunction};
+pub use builtins::BUILTIN_FUNCTIONS;
+pub use expression_evaluator::evaluate_expression;
+pub use statement_executor::{execute_module, execute_block, execute_statement, call_function, ControlFlow};
@@ -0,0 +1,776 @@
+use std::cmp::Ordering;
+
+use crate::elements::Operator;
+use crate::interpreter::{Value, ValueKind, RuntimeError};
+
+
+// Ranges are built as arrays, so a longer one is an error rather than running out of memory
+const MAX_RANGE_LENGTH: i128 = 10_000_000;
+
+
+// Applies the operator to two values of the kinds it was chosen for
+type BinaryOperation = fn(&Operator, Value, Value) -> Result<Value, RuntimeError>;
+
+
+pub fn evaluate_unary_operation(operator: &Operator, operand: Value) -> Result<Value, RuntimeError> {
+    match (operator, operand) {
+        (Operator::Plus, value @ (Value::Integer(_) | Value::Float(_))) => Ok(value),
+
+        (Operator::Minus, Value::Integer(value)) => value.checked_neg()
+            .map(Value::Integer)
+            .ok_or_else(|| RuntimeError::new("Integer overflow")),
+        (Operator::Minus, Value::Float(value)) => Ok(Value::Float(-value)),
+
+        (Operator::Not, Value::Boolean(value)) => Ok(Value::Boolean(!value)),
+
+        // Integers are two's complement, so ~x is always -x - 1
+        (Operator::BitwiseNot, Value::Integer(value)) => Ok(Value::Integer(!value)),
+
+        (operator, operand) => Err(RuntimeError::new(&format!(
+            "Cannot apply unary operator {:?} to {}", operator, operand.type_name()
+        ))),
+    }
+}
+
+
+pub fn evaluate_binary_operation(operator: &Operator, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    match binary_operation(operator, left.kind(), right.kind()) {
+        Some(operation) => operation(operator, left, right),
+        None => Err(operand_type_error(operator, &left, &right)),
+    }
+}
+
+
+// Chooses how to apply the operator to values of these kinds, or None if it can't be applied to
+// them. Supporting an operator for another kind of value only takes an arm here and the function it
+// gives, which can assume the operands are of the kinds it was chosen for.
+fn binary_operation(operator: &Operator, left: ValueKind, right: ValueKind) -> Option<BinaryOperation> {
+    let operation: BinaryOperation = match (operator, left, right) {
+        // && and || short circuit, so the expression evaluator applies them itself, and ! and ~ are unary
+        (Operator::And | Operator::Or | Operator::Not | Operator::BitwiseNot, _, _) => unsupported_operation,
+
+        (Operator::Plus | Operator::Minus | Operator::Times | Operator::Divide | Operator::Modulo | Operator::Power, _, _) => {
+            match (left, right) {
+                (ValueKind::Integer, ValueKind::Integer) => integer_arithmetic,
+                // Mixing integers and floats promotes the integer to a float
+                (left, right) if left.is_number() && right.is_number() => float_arithmetic,
+                (ValueKind::String, ValueKind::String) if *operator == Operator::Plus => string_concatenation,
+                (ValueKind::Char, ValueKind::Integer) if matches!(operator, Operator::Plus | Operator::Minus) => char_offset,
+                (ValueKind::Integer, ValueKind::Char) if *operator == Operator::Plus => char_offset,
+                _ => return None,
+            }
+        },
+
+        (
+            Operator::BitwiseAnd | Operator::BitwiseOr | Operator::BitwiseXor | Operator::BitwiseLeftShift | Operator::BitwiseRightShift,
+            ValueKind::Integer,
+            ValueKind::Integer,
+        ) => bitwise_operation,
+
+        (
+            Operator::Equal | Operator::NotEqual
+            | Operator::LessThan | Operator::LessThanOrEqual
+            | Operator::GreaterThan | Operator::GreaterThanOrEqual,
+            left,
+            right,
+        ) if are_ordered(left, right) => comparison,
+        (Operator::Equal | Operator::NotEqual, _, _) => structural_equality,
+
+        (Operator::In, _, ValueKind::Array | ValueKind::Tuple | ValueKind::Map) => collection_membership,
+        (Operator::In, ValueKind::Char | ValueKind::String, ValueKind::String) => substring_membership,
+
+        (Operator::Range | Operator::InclusiveRange, ValueKind::Integer, ValueKind::Integer)
+        | (Operator::Range | Operator::InclusiveRange, ValueKind::Char, ValueKind::Char) => range,
+
+        (Operator::Is | Operator::IsNot, _, _) => identity,
+
+        _ => return None,
+    };
+    Some(operation)
+}
+
+
+// Values of these kinds can be put in order, and so are compared with an ordering rather than
+// element by element
+fn are_ordered(left: ValueKind, right: ValueKind) -> bool {
+    match (left, right) {
+        (left, right) if left.is_number() && right.is_number() => true,
+        (ValueKind::Char | ValueKind::String | ValueKind::Boolean | ValueKind::None, _) => left == right,
+        _ => false,
+    }
+}
+
+
+fn unsupported_operation(operator: &Operator, _: Value, _: Value) -> Result<Value, RuntimeError> {
+    Err(RuntimeError::new(&format!("Unsupported binary operator {:?}", operator)))
+}
+
+
+fn integer_arithmetic(operator: &Operator, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    let (left, right) = (integer(left), integer(right));
+    let result = match operator {
+        Operator::Plus => left.checked_add(right),
+        Operator::Minus => left.checked_sub(right),
+        Operator::Times => left.checked_mul(right),
+        Operator::Divide => {
+            if right == 0 {
+                return Err(RuntimeError::new("Division by zero"));
+            }
+            left.checked_div(right)
+        },
+        Operator::Modulo => {
+            if right == 0 {
+                return Err(RuntimeError::new("Modulo by zero"));
+            }
+            left.checked_rem(right)
+        },
+        // A negative power of an integer is a fraction, so it's a float, as in 2 ** -3 == 0.125
+        Operator::Power if right < 0 => return Ok(Value::Float((left as f64).powf(right as f64))),
+        Operator::Power => integer_power(left, right),
+        _ => panic!("Operator {:?} is not an arithmetic operator", operator),
+    };
+
+    result.map(Value::Integer).ok_or_else(|| RuntimeError::new("Integer overflow"))
+}
+
+
+// Only 0, 1 and -1 can be raised to a power too large for checked_pow without overflowing
+fn integer_power(base: i64, exponent: i64) -> Option<i64> {
+    match u32::try_from(exponent) {
+        Ok(exponent) => base.checked_pow(exponent),
+        Err(_) => match base {
+            0 | 1 => Some(base),
+            -1 => Some(if exponent % 2 == 0 { 1 } else { -1 }),
+            _ => None,
+        },
+    }
+}
+
+
+// Like for integers, the result of a modulo has the sign of the left operand, so -5.5 % 2.0 is -1.5
+fn float_arithmetic(operator: &Operator, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    let (left, right) = (float(left), float(right));
+    match operator {
+        Operator::Plus => Ok(Value::Float(left + right)),
+        Operator::Minus => Ok(Value::Float(left - right)),
+        Operator::Times => Ok(Value::Float(left * right)),
+        Operator::Divide => Ok(Value::Float(left / right)),
+        Operator::Modulo if right == 0.0 => Err(RuntimeError::new("Modulo by zero")),
+        Operator::Modulo => Ok(Value::Float(left % right)),
+        Operator::Power => Ok(Value::Float(left.powf(right))),
+        _ => Err(RuntimeError::new(&format!("Operator {:?} is not supported on floats", operator))),
+    }
+}
+
+
+fn string_concatenation(_: &Operator, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    match (left, right) {
+        (Value::String(left), Value::String(right)) => Ok(Value::String(left + &right)),
+        (left, right) => panic!("Expected two strings, found {} and {}", left.type_name(), right.type_name()),
+    }
+}
+
+
+// Moving a char by an int moves its code point, as in 'a' + 1 == 'b'
+fn char_offset(operator: &Operator, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    match (left, right) {
+        (Value::Char(left), Value::Integer(right)) if *operator == Operator::Minus => char_from_code((left as i64).checked_sub(right)),
+        (Value::Char(left), Value::Integer(right)) => char_from_code((left as i64).checked_add(right)),
+        (Value::Integer(left), Value::Char(right)) => char_from_code(left.checked_add(right as i64)),
+        (left, right) => panic!("Expected a char and an int, found {} and {}", left.type_name(), right.type_name()),
+    }
+}
+
+
+fn char_from_code(code: Option<i64>) -> Result<Value, RuntimeError> {
+    let code = code.ok_or_else(|| RuntimeError::new("Integer overflow"))?;
+    u32::try_from(code).ok()
+        .and_then(char::from_u32)
+        .map(Value::Char)
+        .ok_or_else(|| RuntimeError::new(&format!("{} is not a valid char code point", code)))
+}
+
+
+// The ints or chars from the start up to the end, as an array. Chars skip the code points that
+// aren't valid chars.
+fn range(operator: &Operator, start: Value, end: Value) -> Result<Value, RuntimeError> {
+    let inclusive = *operator == Operator::InclusiveRange;
+    match (start, end) {
+        (Value::Integer(start), Value::Integer(end)) => {
+            let length = end as i128 - start as i128 + if inclusive { 1 } else { 0 };
+            if length > MAX_RANGE_LENGTH {
+                return Err(RuntimeError::new(&format!("Range from {} to {} has more than {} elements", start, end, MAX_RANGE_LENGTH)));
+            }
+            let values = if inclusive { (start..=end).map(Value::Integer).collect() } else { (start..end).map(Value::Integer).collect() };
+            Ok(Value::array(values))
+        },
+        (Value::Char(start), Value::Char(end)) => {
+            let values = if inclusive { (start..=end).map(Value::Char).collect() } else { (start..end).map(Value::Char).collect() };
+            Ok(Value::array(values))
+        },
+        (start, end) => panic!("Expected two ints or two chars, found {} and {}", start.type_name(), end.type_name()),
+    }
+}
+
+
+// Bitwise operators are only defined on integers, and act on the 64 bit two's complement
+// representation. Shifting by a negative amount or by 64 or more is an error. A left shift
+// discards the bits shifted out, and a right shift is arithmetic, so the sign is preserved.
+fn bitwise_operation(operator: &Operator, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    let (left, right) = (integer(left), integer(right));
+    match operator {
+        Operator::BitwiseAnd => Ok(Value::Integer(left & right)),
+        Operator::BitwiseOr => Ok(Value::Integer(left | right)),
+        Operator::BitwiseXor => Ok(Value::Integer(left ^ right)),
+        Operator::BitwiseLeftShift => Ok(Value::Integer(left << get_shift_amount(right)?)),
+        Operator::BitwiseRightShift => Ok(Value::Integer(left >> get_shift_amount(right)?)),
+        _ => panic!("Operator {:?} is not a bitwise operator", operator),
+    }
+}
+
+
+// A float comparison involving NaN has no ordering, so only != holds
+fn comparison(operator: &Operator, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    let ordering = compare(&left, &right);
+    let result = match operator {
+        Operator::Equal => ordering == Some(Ordering::Equal),
+        Operator::NotEqual => ordering != Some(Ordering::Equal),
+        Operator::LessThan => ordering == Some(Ordering::Less),
+        Operator::LessThanOrEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        Operator::GreaterThan => ordering == Some(Ordering::Greater),
+        Operator::GreaterThanOrEqual => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        _ => panic!("Operator {:?} is not a relational operator", operator),
+    };
+
+    Ok(Value::Boolean(result))
+}
+
+
+// Numbers compare by value, with integers promoted to floats when compared with a float. Chars
+// compare by code point, strings lexicographically and booleans with false before true.
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Integer(left), Value::Integer(right)) => left.partial_cmp(right),
+        (Value::Integer(left), Value::Float(right)) => (*left as f64).partial_cmp(right),
+        (Value::Float(left), Value::Integer(right)) => left.partial_cmp(&(*right as f64)),
+        (Value::Float(left), Value::Float(right)) => left.partial_cmp(right),
+        (Value::Char(left), Value::Char(right)) => left.partial_cmp(right),
+        (Value::String(left), Value::String(right)) => left.partial_cmp(right),
+        (Value::Boolean(left), Value::Boolean(right)) => left.partial_cmp(right),
+        (Value::None, Value::None) => Some(Ordering::Equal),
+        (left, right) => panic!("Cannot order {} and {}", left.type_name(), right.type_name()),
+    }
+}
+
+
+fn structural_equality(operator: &Operator, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    let equal = values_equal(&left, &right);
+    Ok(Value::Boolean(if *operator == Operator::Equal { equal } else { !equal }))
+}
+
+
+// Values of different types are never equal
+fn values_equal(left: &Value, right: &Value) -> bool {
+    equal_within(left, right, &mut Vec::new())
+}
+
+
+// The pairs of shared values being compared are tracked, since comparing arrays that contain
+// themselves would otherwise never end. A pair met again inside itself is taken to be equal, so the
+// result depends on the rest of the elements.
+fn equal_within(left: &Value, right: &Value, comparing: &mut Vec<(*const (), *const ())>) -> bool {
+    if are_ordered(left.kind(), right.kind()) {
+        return compare(left, right) == Some(Ordering::Equal);
+    }
+
+    let (Some(left_pointer), Some(right_pointer)) = (left.shared_pointer(), right.shared_pointer()) else {
+        return collections_equal(left, right, comparing);
+    };
+    let pair = (left_pointer, right_pointer);
+    if left_pointer == right_pointer || comparing.contains(&pair) {
+        return true;
+    }
+    comparing.push(pair);
+    let equal = collections_equal(left, right, comparing);
+    comparing.pop();
+    equal
+}
+
+
+// Collections and structs are equal when their elements are, compared like with ==. Map entries
+// can be in any order.
+fn collections_equal(left: &Value, right: &Value, comparing: &mut Vec<(*const (), *const ())>) -> bool {
+    let all_equal = |left: &[Value], right: &[Value], comparing: &mut Vec<(*const (), *const ())>| {
+        left.len() == right.len() && left.iter().zip(right).all(|(left, right)| equal_within(left, right, comparing))
+    };
+
+    match (left, right) {
+        (Value::Array(left), Value::Array(right)) => all_equal(&left.borrow(), &right.borrow(), comparing),
+        (Value::Tuple(left), Value::Tuple(right)) => all_equal(left, right, comparing),
+        (Value::Map(left), Value::Map(right)) => {
+            let (left, right) = (left.borrow(), right.borrow());
+            left.len() == right.len() && left.iter().all(|(key, value)| {
+                right.iter().any(|(other_key, other_value)| {
+                    equal_within(key, other_key, comparing) && equal_within(value, other_value, comparing)
+                })
+            })
+        },
+        (Value::Struct(left), Value::Struct(right)) => {
+            let (left, right) = (left.borrow(), right.borrow());
+            left.name == right.name
+                && left.fields.len() == right.fields.len()
+                && left.fields.iter().zip(&right.fields).all(|((name, left), (other_name, right))| {
+                    name == other_name && equal_within(left, right, comparing)
+                })
+        },
+        _ => false,
+    }
+}
+
+
+// Arrays, maps and structs are shared, so two of them are the same only if they are the same value,
+// even when they hold equal elements. Every other value is copied, so it's the same as any value
+// equal to it, which makes x is None hold only for None.
+fn identity(operator: &Operator, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    let same = match (left.shared_pointer(), right.shared_pointer()) {
+        (Some(left), Some(right)) => left == right,
+        _ => values_equal(&left, &right),
+    };
+    Ok(Value::Boolean(if *operator == Operator::Is { same } else { !same }))
+}
+
+
+// Whether the item is an element of an array or tuple, or a key of a map. Elements are compared
+// like with ==, so 1 is in [1.0].
+fn collection_membership(_: &Operator, item: Value, collection: Value) -> Result<Value, RuntimeError> {
+    let is_item = |element: &Value| values_equal(&item, element);
+    let found = match &collection {
+        Value::Array(values) => values.borrow().iter().any(is_item),
+        Value::Tuple(values) => values.iter().any(is_item),
+        Value::Map(entries) => entries.borrow().iter().any(|(key, _)| is_item(key)),
+        collection => panic!("Expected a collection, found {}", collection.type_name()),
+    };
+    Ok(Value::Boolean(found))
+}
+
+
+// Whether the item is a char or substring of a string
+fn substring_membership(_: &Operator, item: Value, string: Value) -> Result<Value, RuntimeError> {
+    let found = match (&item, &string) {
+        (Value::Char(item), Value::String(string)) => string.contains(*item),
+        (Value::String(item), Value::String(string)) => string.contains(item.as_str()),
+        (item, string) => panic!("Expected a char or string in a string, found {} and {}", item.type_name(), string.type_name()),
+    };
+    Ok(Value::Boolean(found))
+}
+
+
+fn integer(value: Value) -> i64 {
+    match value {
+        Value::Integer(value) => value,
+        value => panic!("Expected an int, found {}", value.type_name()),
+    }
+}
+
+
+fn float(value: Value) -> f64 {
+    match value {
+        Value::Integer(value) => value as f64,
+        Value::Float(value) => value,
+        value => panic!("Expected a number, found {}", value.type_name()),
+    }
+}
+
+
+fn get_shift_amount(amount: i64) -> Result<u32, RuntimeError> {
+    if (0..64).contains(&amount) {
+        Ok(amount as u32)
+    } else {
+        Err(RuntimeError::new(&format!("Shift amount {} is out of range, it must be between 0 and 63", amount)))
+    }
+}
+
+
+fn operand_type_error(operator: &Operator, left: &Value, right: &Value) -> RuntimeError {
+    RuntimeError::new(&format!(
+        "Cannot apply operator {:?} to {} and {}", operator, left.type_name(), right.type_name()
+    ))
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::interpreter::StructValue;
+
+    use super::*;
+
+    #[test]
+    fn test_bitwise_and() {
+        let result = evaluate_binary_operation(&Operator::BitwiseAnd, Value::Integer(6), Value::Integer(3));
+        assert_eq!(result, Ok(Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_bitwise_or() {
+        let result = evaluate_binary_operation(&Operator::BitwiseOr, Value::Integer(6), Value::Integer(3));
+        assert_eq!(result, Ok(Value::Integer(7)));
+    }
+
+    #[test]
+    fn test_bitwise_xor() {
+        let result = evaluate_binary_operation(&Operator::BitwiseXor, Value::Integer(6), Value::Integer(3));
+        assert_eq!(result, Ok(Value::Integer(5)));
+    }
+
+    #[test]
+    fn test_bitwise_left_shift() {
+        let result = evaluate_binary_operation(&Operator::BitwiseLeftShift, Value::Integer(3), Value::Integer(4));
+        assert_eq!(result, Ok(Value::Integer(48)));
+    }
+
+    #[test]
+    fn test_bitwise_right_shift_keeps_sign() {
+        let result = evaluate_binary_operation(&Operator::BitwiseRightShift, Value::Integer(-16), Value::Integer(2));
+        assert_eq!(result, Ok(Value::Integer(-4)));
+    }
+
+    #[test]
+    fn test_shift_out_of_range() {
+        let too_far = evaluate_binary_operation(&Operator::BitwiseLeftShift, Value::Integer(1), Value::Integer(64));
+        let negative = evaluate_binary_operation(&Operator::BitwiseRightShift, Value::Integer(1), Value::Integer(-1));
+
+        assert!(too_far.is_err());
+        assert!(negative.is_err());
+    }
+
+    #[test]
+    fn test_bitwise_not() {
+        let result = evaluate_unary_operation(&Operator::BitwiseNot, Value::Integer(5));
+        assert_eq!(result, Ok(Value::Integer(-6)));
+    }
+
+    #[test]
+    fn test_bitwise_on_floats_is_an_error() {
+        let result = evaluate_binary_operation(&Operator::BitwiseAnd, Value::Float(1.0), Value::Integer(3));
+        assert_eq!(result, Err(RuntimeError::new("Cannot apply operator BitwiseAnd to float and int")));
+    }
+
+    #[test]
+    fn test_bitwise_not_on_float_is_an_error() {
+        let result = evaluate_unary_operation(&Operator::BitwiseNot, Value::Float(5.0));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_integer_division_by_zero() {
+        let result = evaluate_binary_operation(&Operator::Divide, Value::Integer(1), Value::Integer(0));
+        assert_eq!(result, Err(RuntimeError::new("Division by zero")));
+    }
+
+    #[test]
+    fn test_float_modulo() {
+        let result = evaluate_binary_operation(&Operator::Modulo, Value::Float(5.5), Value::Float(2.0));
+        assert_eq!(result, Ok(Value::Float(1.5)));
+
+        let result = evaluate_binary_operation(&Operator::Modulo, Value::Float(-5.5), Value::Float(2.0));
+        assert_eq!(result, Ok(Value::Float(-1.5)));
+    }
+
+    #[test]
+    fn test_mixed_modulo_promotes_to_float() {
+        let result = evaluate_binary_operation(&Operator::Modulo, Value::Integer(7), Value::Float(2.5));
+        assert_eq!(result, Ok(Value::Float(2.0)));
+    }
+
+    #[test]
+    fn test_modulo_by_zero() {
+        let result = evaluate_binary_operation(&Operator::Modulo, Value::Integer(1), Value::Integer(0));
+        assert_eq!(result, Err(RuntimeError::new("Modulo by zero")));
+
+        let result = evaluate_binary_operation(&Operator::Modulo, Value::Float(1.5), Value::Integer(0));
+        assert_eq!(result, Err(RuntimeError::new("Modulo by zero")));
+    }
+
+    #[test]
+    fn test_integer_power() {
+        let result = evaluate_binary_operation(&Operator::Power, Value::Integer(2), Value::Integer(3));
+        assert_eq!(result, Ok(Value::Integer(8)));
+
+        let result = evaluate_binary_operation(&Operator::Power, Value::Integer(-1), Value::Integer(i64::MAX));
+        assert_eq!(result, Ok(Value::Integer(-1)));
+    }
+
+    #[test]
+    fn test_negative_power_is_a_float() {
+        let result = evaluate_binary_operation(&Operator::Power, Value::Integer(2), Value::Integer(-3));
+        assert_eq!(result, Ok(Value::Float(0.125)));
+    }
+
+    #[test]
+    fn test_float_power() {
+        let result = evaluate_binary_operation(&Operator::Power, Value::Float(2.0), Value::Integer(3));
+        assert_eq!(result, Ok(Value::Float(8.0)));
+
+        let result = evaluate_binary_operation(&Operator::Power, Value::Integer(4), Value::Float(0.5));
+        assert_eq!(result, Ok(Value::Float(2.0)));
+    }
+
+    #[test]
+    fn test_integer_power_overflow() {
+        let result = evaluate_binary_operation(&Operator::Power, Value::Integer(2), Value::Integer(64));
+        assert_eq!(result, Err(RuntimeError::new("Integer overflow")));
+    }
+
+    #[test]
+    fn test_mixed_arithmetic_promotes_to_float() {
+        let result = evaluate_binary_operation(&Operator::Plus, Value::Integer(1), Value::Float(0.5));
+        assert_eq!(result, Ok(Value::Float(1.5)));
+    }
+
+    #[test]
+    fn test_compare_numbers() {
+        let less = evaluate_binary_operation(&Operator::LessThan, Value::Integer(2), Value::Integer(3));
+        assert_eq!(less, Ok(Value::Boolean(true)));
+
+        let promoted = evaluate_binary_operation(&Operator::Equal, Value::Integer(2), Value::Float(2.0));
+        assert_eq!(promoted, Ok(Value::Boolean(true)));
+
+        let greater = evaluate_binary_operation(&Operator::GreaterThanOrEqual, Value::Float(1.5), Value::Integer(2));
+        assert_eq!(greater, Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_compare_chars_and_strings() {
+        let chars = evaluate_binary_operation(&Operator::LessThan, Value::Char('a'), Value::Char('b'));
+        assert_eq!(chars, Ok(Value::Boolean(true)));
+
+        let strings = evaluate_binary_operation(
+            &Operator::GreaterThan, Value::String("apple".to_string()), Value::String("apricot".to_string())
+        );
+        assert_eq!(strings, Ok(Value::Boolean(false)));
+
+        let not_equal = evaluate_binary_operation(
+            &Operator::NotEqual, Value::String("a".to_string()), Value::String("a".to_string())
+        );
+        assert_eq!(not_equal, Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_compare_booleans() {
+        let result = evaluate_binary_operation(&Operator::LessThanOrEqual, Value::Boolean(false), Value::Boolean(true));
+        assert_eq!(result, Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_equal_arrays_are_not_the_same_array() {
+        let values = Value::array(vec![Value::Integer(1), Value::Integer(2)]);
+        let copy = Value::array(vec![Value::Integer(1), Value::Float(2.0)]);
+
+        assert_eq!(evaluate_binary_operation(&Operator::Equal, values.clone(), copy.clone()), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::Is, values.clone(), copy.clone()), Ok(Value::Boolean(false)));
+        assert_eq!(evaluate_binary_operation(&Operator::IsNot, values.clone(), copy), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::Is, values.clone(), values), Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_compare_arrays_that_contain_themselves() {
+        let values = Value::array(vec![Value::Integer(1)]);
+        let other = Value::array(vec![Value::Integer(1)]);
+        if let (Value::Array(inner), Value::Array(other_inner)) = (&values, &other) {
+            inner.borrow_mut().push(values.clone());
+            other_inner.borrow_mut().push(other.clone());
+        }
+
+        assert_eq!(evaluate_binary_operation(&Operator::Equal, values.clone(), values.clone()), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::Equal, values.clone(), other.clone()), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::In, values.clone(), values.clone()), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::Equal, values, Value::array(vec![])), Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_compare_maps_and_structs_that_contain_themselves() {
+        let cyclic_map = || {
+            let values = Value::array(vec![]);
+            let map = Value::map(vec![(Value::Integer(1), values.clone())]);
+            if let Value::Array(inner) = &values {
+                inner.borrow_mut().push(map.clone());
+            }
+            map
+        };
+        let cyclic_struct = || {
+            let values = Value::array(vec![]);
+            let point = Value::structure(StructValue { name: "Point".to_string(), fields: vec![("x".to_string(), values.clone())] });
+            if let Value::Array(inner) = &values {
+                inner.borrow_mut().push(point.clone());
+            }
+            point
+        };
+
+        assert_eq!(evaluate_binary_operation(&Operator::Equal, cyclic_map(), cyclic_map()), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::Equal, cyclic_struct(), cyclic_struct()), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::Is, cyclic_struct(), cyclic_struct()), Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_is_none() {
+        assert_eq!(evaluate_binary_operation(&Operator::Is, Value::None, Value::None), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::Is, Value::Integer(0), Value::None), Ok(Value::Boolean(false)));
+        assert_eq!(evaluate_binary_operation(&Operator::IsNot, Value::Integer(0), Value::None), Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_compare_collections() {
+        let tuple = |values: Vec<Value>| Value::Tuple(values);
+        let equal = evaluate_binary_operation(
+            &Operator::Equal, tuple(vec![Value::Integer(1), Value::None]), tuple(vec![Value::Float(1.0), Value::None])
+        );
+        assert_eq!(equal, Ok(Value::Boolean(true)));
+
+        let map = |entries: Vec<(i64, i64)>| Value::map(entries.into_iter().map(|(key, value)| (Value::Integer(key), Value::Integer(value))).collect());
+        assert_eq!(evaluate_binary_operation(&Operator::Equal, map(vec![(1, 2), (3, 4)]), map(vec![(3, 4), (1, 2)])), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::NotEqual, map(vec![(1, 2)]), map(vec![(1, 3)])), Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_char_arithmetic() {
+        assert_eq!(evaluate_binary_operation(&Operator::Plus, Value::Char('a'), Value::Integer(1)), Ok(Value::Char('b')));
+        assert_eq!(evaluate_binary_operation(&Operator::Plus, Value::Integer(2), Value::Char('a')), Ok(Value::Char('c')));
+        assert_eq!(evaluate_binary_operation(&Operator::Minus, Value::Char('b'), Value::Integer(1)), Ok(Value::Char('a')));
+        assert_eq!(
+            evaluate_binary_operation(&Operator::Minus, Value::Char('a'), Value::Integer(100)),
+            Err(RuntimeError::new("-3 is not a valid char code point"))
+        );
+        assert_eq!(
+            evaluate_binary_operation(&Operator::Plus, Value::Char('\u{D7FF}'), Value::Integer(1)),
+            Err(RuntimeError::new("55296 is not a valid char code point"))
+        );
+    }
+
+    #[test]
+    fn test_compare_chars() {
+        assert_eq!(evaluate_binary_operation(&Operator::LessThan, Value::Char('a'), Value::Char('z')), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::Equal, Value::Char('b'), Value::Char('b')), Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_ranges() {
+        let chars = |values: &str| Value::array(values.chars().map(Value::Char).collect());
+
+        assert_eq!(evaluate_binary_operation(&Operator::Range, Value::Char('a'), Value::Char('d')), Ok(chars("abc")));
+        assert_eq!(evaluate_binary_operation(&Operator::InclusiveRange, Value::Char('a'), Value::Char('d')), Ok(chars("abcd")));
+        assert_eq!(
+            evaluate_binary_operation(&Operator::Range, Value::Integer(1), Value::Integer(3)),
+            Ok(Value::array(vec![Value::Integer(1), Value::Integer(2)]))
+        );
+        assert_eq!(evaluate_binary_operation(&Operator::Range, Value::Integer(3), Value::Integer(1)), Ok(Value::array(vec![])));
+        assert!(evaluate_binary_operation(&Operator::Range, Value::Char('a'), Value::Integer(3)).is_err());
+    }
+
+    #[test]
+    fn test_range_that_is_too_long_is_an_error() {
+        assert_eq!(
+            evaluate_binary_operation(&Operator::Range, Value::Integer(0), Value::Integer(i64::MAX)),
+            Err(RuntimeError::new("Range from 0 to 9223372036854775807 has more than 10000000 elements"))
+        );
+        assert_eq!(
+            evaluate_binary_operation(&Operator::InclusiveRange, Value::Integer(i64::MIN), Value::Integer(i64::MAX)),
+            Err(RuntimeError::new("Range from -9223372036854775808 to 9223372036854775807 has more than 10000000 elements"))
+        );
+        assert!(evaluate_binary_operation(&Operator::Range, Value::Integer(i64::MAX), Value::Integer(i64::MIN)).is_ok());
+    }
+
+    #[test]
+    fn test_compare_different_types() {
+        let equal = evaluate_binary_operation(&Operator::Equal, Value::Integer(1), Value::String("1".to_string()));
+        assert_eq!(equal, Ok(Value::Boolean(false)));
+
+        let not_equal = evaluate_binary_operation(&Operator::NotEqual, Value::Char('1'), Value::Integer(1));
+        assert_eq!(not_equal, Ok(Value::Boolean(true)));
+
+        let ordered = evaluate_binary_operation(&Operator::LessThan, Value::Integer(1), Value::String("1".to_string()));
+        assert_eq!(ordered, Err(RuntimeError::new("Cannot apply operator LessThan to int and string")));
+    }
+
+    #[test]
+    fn test_compare_nan() {
+        let result = evaluate_binary_operation(&Operator::Equal, Value::Float(f64::NAN), Value::Float(f64::NAN));
+        assert_eq!(result, Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_membership() {
+        let values = Value::array(vec![Value::Integer(1), Value::Integer(2)]);
+        assert_eq!(evaluate_binary_operation(&Operator::In, Value::Float(2.0), values.clone()), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::In, Value::Integer(3), values), Ok(Value::Boolean(false)));
+
+        let text = Value::String("sheep".to_string());
+        assert_eq!(evaluate_binary_operation(&Operator::In, Value::Char('h'), text.clone()), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate_binary_operation(&Operator::In, Value::String("pig".to_string()), text), Ok(Value::Boolean(false)));
+
+        let map = Value::map(vec![(Value::String("a".to_string()), Value::Integer(1))]);
+        assert_eq!(evaluate_binary_operation(&Operator::In, Value::String("a".to_string()), map), Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_membership_in_non_collection() {
+        assert_eq!(
+            evaluate_binary_operation(&Operator::In, Value::Integer(1), Value::Integer(2)),
+            Err(RuntimeError::new("Cannot apply operator In to int and int"))
+        );
+    }
+
+    #[test]
+    fn test_each_combination_of_operand_kinds() {
+        let text = |text: &str| Value::String(text.to_string());
+        let array = || Value::array(vec![Value::Integer(1), Value::Integer(2)]);
+        let type_error = |message: &str| Err(RuntimeError::new(message));
+
+        let cases = vec![
+            (Operator::Plus, Value::Integer(1), Value::Integer(2), Ok(Value::Integer(3))),
+            (Operator::Plus, Value::Integer(1), Value::Float(0.5), Ok(Value::Float(1.5))),
+            (Operator::Minus, Value::Float(1.5), Value::Integer(1), Ok(Value::Float(0.5))),
+            (Operator::Times, Value::Float(1.5), Value::Float(2.0), Ok(Value::Float(3.0))),
+            (Operator::Divide, Value::Integer(7), Value::Integer(2), Ok(Value::Integer(3))),
+            (Operator::Power, Value::Integer(2), Value::Float(0.5), Ok(Value::Float(2.0_f64.sqrt()))),
+            (Operator::Plus, text("sheep"), text("pig"), Ok(text("sheeppig"))),
+            (Operator::Minus, text("sheep"), text("pig"), type_error("Cannot apply operator Minus to string and string")),
+            (Operator::Plus, Value::Char('a'), Value::Integer(2), Ok(Value::Char('c'))),
+            (Operator::Minus, Value::Char('c'), Value::Integer(2), Ok(Value::Char('a'))),
+            (Operator::Plus, Value::Integer(1), Value::Char('a'), Ok(Value::Char('b'))),
+            (Operator::Minus, Value::Integer(1), Value::Char('a'), type_error("Cannot apply operator Minus to int and char")),
+            (Operator::Plus, Value::Boolean(true), Value::Boolean(true), type_error("Cannot apply operator Plus to bool and bool")),
+            (Operator::Times, array(), Value::Integer(2), type_error("Cannot apply operator Times to array and int")),
+
+            (Operator::BitwiseXor, Value::Integer(6), Value::Integer(3), Ok(Value::Integer(5))),
+            (Operator::BitwiseOr, Value::Boolean(true), Value::Integer(1), type_error("Cannot apply operator BitwiseOr to bool and int")),
+
+            (Operator::Equal, Value::Integer(1), Value::Float(1.0), Ok(Value::Boolean(true))),
+            (Operator::Equal, text("1"), Value::Integer(1), Ok(Value::Boolean(false))),
+            (Operator::NotEqual, Value::None, Value::None, Ok(Value::Boolean(false))),
+            (Operator::Equal, array(), array(), Ok(Value::Boolean(true))),
+            (Operator::NotEqual, array(), Value::None, Ok(Value::Boolean(true))),
+            (Operator::LessThan, Value::Boolean(false), Value::Boolean(true), Ok(Value::Boolean(true))),
+            (Operator::GreaterThanOrEqual, text("b"), text("a"), Ok(Value::Boolean(true))),
+            (Operator::LessThan, text("a"), Value::Integer(1), type_error("Cannot apply operator LessThan to string and int")),
+            (Operator::LessThan, array(), array(), type_error("Cannot apply operator LessThan to array and array")),
+
+            (Operator::In, Value::Integer(2), array(), Ok(Value::Boolean(true))),
+            (Operator::In, text("ee"), text("sheep"), Ok(Value::Boolean(true))),
+            (Operator::In, Value::Integer(1), text("1"), type_error("Cannot apply operator In to int and string")),
+
+            (Operator::Is, Value::None, Value::None, Ok(Value::Boolean(true))),
+            (Operator::IsNot, array(), array(), Ok(Value::Boolean(true))),
+
+            (Operator::Range, Value::Integer(1), Value::Integer(3), Ok(array())),
+            (Operator::InclusiveRange, Value::Char('a'), Value::Char('b'), Ok(Value::array(vec![Value::Char('a'), Value::Char('b')]))),
+            (Operator::Range, Value::Integer(1), Value::Char('b'), type_error("Cannot apply operator Range to int and char")),
+
+            (Operator::And, Value::Boolean(true), Value::Boolean(true), type_error("Unsupported binary operator And")),
+        ];
+
+        for (operator, left, right, expected) in cases {
+            let description = format!("{:?} {:?} {:?}", operator, left, right);
+            assert_eq!(evaluate_binary_operation(&operator, left, right), expected, "{}", description);
+        }
+    }
+}
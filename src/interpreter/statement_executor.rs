@@ -0,0 +1,1040 @@
+use crate::elements::Identifier;
+use crate::tree::{Module, Function, StatementBlock, Statement, LoopStatement, ForStatement, RepeatStatement, TryStatement, Reference, ArrayIndex};
+use crate::interpreter::{Value, RuntimeError};
+use crate::interpreter::environment::Environment;
+use crate::interpreter::expression_evaluator::{evaluate_expression, evaluate_condition};
+use crate::interpreter::indexing::{remove_element, assign_element, assign_slice};
+
+
+// How execution continues after a statement
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlFlow {
+    Next,
+    // Execution continues after an expression statement, with its value. The value is discarded,
+    // unless the statement ends a function body, where it's the return value, or is entered in the REPL.
+    Value(Value),
+    Break(Option<Identifier>),
+    Continue(Option<Identifier>),
+    Return(Value),
+    // The program called exit, which only reaches the caller of execute_module
+    Exit(i32),
+}
+
+
+// The top level statements run first to initialise the module, and then main is called if there is
+// one, with its return value as the result. Variables declared by the top level statements are
+// globals, which functions can use, and they remain in the environment afterwards. Calling exit
+// anywhere stops the whole program, giving its exit code as the result.
+pub fn execute_module(module: &Module, environment: &mut Environment) -> Result<ControlFlow, RuntimeError> {
+    match run_module(module, environment) {
+        Err(RuntimeError { exit_code: Some(code), .. }) => Ok(ControlFlow::Exit(code)),
+        result => result,
+    }
+}
+
+
+fn run_module(module: &Module, environment: &mut Environment) -> Result<ControlFlow, RuntimeError> {
+    for definition in &module.structs {
+        environment.define_struct(definition.node.clone());
+    }
+    for function in &module.functions {
+        environment.define_function(function.node.clone());
+    }
+
+    match execute_statements(&module.statements, environment)? {
+        ControlFlow::Next | ControlFlow::Value(_) => {},
+        flow => return Ok(flow),
+    }
+
+    match module.main_function() {
+        Some(main) => Ok(ControlFlow::Return(call_function(&main.name, vec![], vec![], environment)?)),
+        None => Ok(ControlFlow::Next),
+    }
+}
+
+
+pub fn execute_block(block: &StatementBlock, environment: &mut Environment) -> Result<ControlFlow, RuntimeError> {
+    environment.step()?;
+    environment.push_scope();
+    let result = execute_statements(block, environment);
+    environment.pop_scope();
+    result
+}
+
+
+// Only the value of the last statement is kept
+fn execute_statements(block: &StatementBlock, environment: &mut Environment) -> Result<ControlFlow, RuntimeError> {
+    let mut flow = ControlFlow::Next;
+    for statement in &block.statements {
+        flow = execute_statement(statement, environment)?;
+        match flow {
+            ControlFlow::Next | ControlFlow::Value(_) => {},
+            flow => return Ok(flow),
+        }
+    }
+    Ok(flow)
+}
+
+
+pub fn execute_statement(statement: &Statement, environment: &mut Environment) -> Result<ControlFlow, RuntimeError> {
+    match statement {
+        Statement::Declaration(declaration) => {
+            let value = evaluate_expression(&declaration.value, environment)?;
+            environment.declare(&declaration.name, value);
+        },
+
+        Statement::Assignment(assignment) => {
+            let value = evaluate_expression(&assignment.value, environment)?;
+            for reference in &assignment.references {
+                execute_assignment(reference, value.clone(), environment)?;
+            }
+        },
+
+        Statement::Expression(expression) => return Ok(ControlFlow::Value(evaluate_expression(expression, environment)?)),
+
+        Statement::Return(return_statement) => {
+            let value = match &return_statement.value {
+                Some(value) => evaluate_expression(value, environment)?,
+                None => Value::None,
+            };
+            return Ok(ControlFlow::Return(value));
+        },
+
+        Statement::Delete(reference) => execute_delete(reference, environment)?,
+
+        Statement::Global(name) => environment.declare_global(name)?,
+
+        Statement::Pass => {},
+
+        Statement::Raise(value) => return Err(RuntimeError::raised(evaluate_expression(value, environment)?)),
+
+        Statement::Block(block) => return execute_block(block, environment).map(discard_value),
+
+        Statement::Conditional(conditional) => {
+            if evaluate_condition(&conditional.condition, environment)? {
+                return execute_block(&conditional.body, environment).map(discard_value);
+            } else if let Some(else_body) = &conditional.else_body {
+                return execute_block(else_body, environment).map(discard_value);
+            }
+        },
+
+        Statement::Loop(loop_statement) => return execute_loop(loop_statement, environment).map(discard_value),
+
+        Statement::For(for_statement) => return execute_for(for_statement, environment),
+
+        Statement::Repeat(repeat_statement) => return execute_repeat(repeat_statement, environment),
+
+        Statement::Try(try_statement) => return execute_try(try_statement, environment).map(discard_value),
+
+        Statement::Break(break_statement) => return Ok(ControlFlow::Break(break_statement.label.clone())),
+        Statement::Continue(continue_statement) => return Ok(ControlFlow::Continue(continue_statement.label.clone())),
+    }
+    Ok(ControlFlow::Next)
+}
+
+
+fn execute_assignment(reference: &Reference, value: Value, environment: &mut Environment) -> Result<(), RuntimeError> {
+    match reference {
+        Reference::Identifier(name) => environment.assign(name, value),
+
+        // Arrays are shared, so changing the array held by the variable changes the variable
+        Reference::ArrayReference { array, index } => match &**array {
+            Reference::Identifier(name) => {
+                let array = environment.get(name)?;
+                match index {
+                    ArrayIndex::Single(index) => assign_element(array, evaluate_expression(index, environment)?, value),
+                    ArrayIndex::Slice { start, end } => {
+                        let start = start.as_ref().map(|start| evaluate_expression(start, environment)).transpose()?;
+                        let end = end.as_ref().map(|end| evaluate_expression(end, environment)).transpose()?;
+                        assign_slice(array, start, end, value)
+                    },
+                }
+            },
+            array => Err(RuntimeError::new(&format!("Unsupported assignment to an element of {:?}", array))),
+        },
+    }
+}
+
+
+fn execute_delete(reference: &Reference, environment: &mut Environment) -> Result<(), RuntimeError> {
+    match reference {
+        Reference::Identifier(name) => environment.remove(name).map(|_| ()),
+
+        Reference::ArrayReference { array, index: ArrayIndex::Single(index) } => match &**array {
+            Reference::Identifier(name) => {
+                let index = evaluate_expression(index, environment)?;
+                let value = remove_element(environment.get(name)?, index)?;
+                environment.assign(name, value)
+            },
+            array => Err(RuntimeError::new(&format!("Unsupported deletion from {:?}", array))),
+        },
+
+        Reference::ArrayReference { .. } => Err(RuntimeError::new("Cannot delete a slice")),
+    }
+}
+
+
+// Functions defined in the module take priority over native functions with the same name, which
+// only take positional arguments
+pub fn call_function(
+    name: &Identifier, arguments: Vec<Value>, keyword_arguments: Vec<(Identifier, Value)>, environment: &Environment,
+) -> Result<Value, RuntimeError> {
+    let function = match environment.function(name) {
+        Some(function) => function,
+        None => {
+            let native_function = environment.native_function(name)?;
+            if !keyword_arguments.is_empty() {
+                return Err(RuntimeError::new(&format!("Function '{}' doesn't take keyword arguments", name.as_string())));
+            }
+            return native_function(arguments);
+        },
+    };
+
+    let mut call_environment = bind_arguments(&function, arguments, keyword_arguments, environment)?;
+
+    match execute_block(&function.body, &mut call_environment)? {
+        ControlFlow::Return(value) | ControlFlow::Value(value) => Ok(value),
+        ControlFlow::Next => Ok(Value::None),
+        ControlFlow::Break(_) | ControlFlow::Continue(_) => Err(RuntimeError::new("Loop control outside of a loop")),
+        ControlFlow::Exit(code) => Err(RuntimeError::exit(code)),
+    }
+}
+
+
+// Kept apart from call_function, so that the stack frame of each call in a deep recursion stays small
+fn bind_arguments(
+    function: &Function, arguments: Vec<Value>, keyword_arguments: Vec<(Identifier, Value)>, environment: &Environment,
+) -> Result<Environment, RuntimeError> {
+    let keywords: Vec<&Identifier> = keyword_arguments.iter().map(|(name, _)| name).collect();
+    function.check_arguments(arguments.len(), &keywords).map_err(|message| RuntimeError::new(&message))?;
+
+    let mut call_environment = environment.enter_call()?;
+    for (parameter, argument) in function.parameters.iter().zip(arguments) {
+        call_environment.declare(&parameter.name, argument);
+    }
+    for (name, argument) in keyword_arguments {
+        call_environment.declare(&name, argument);
+    }
+    Ok(call_environment)
+}
+
+
+// The else block only runs when the loop ends because its condition is false, not after a break
+fn execute_loop(loop_statement: &LoopStatement, environment: &mut Environment) -> Result<ControlFlow, RuntimeError> {
+    while evaluate_condition(&loop_statement.condition, environment)? {
+        match execute_block(&loop_statement.body, environment)? {
+            ControlFlow::Break(label) if is_target(&label, &loop_statement.label) => return Ok(ControlFlow::Next),
+            ControlFlow::Continue(label) if is_target(&label, &loop_statement.label) => continue,
+            ControlFlow::Next | ControlFlow::Value(_) => {},
+            flow => return Ok(flow),
+        }
+    }
+
+    match &loop_statement.else_body {
+        Some(else_body) => execute_block(else_body, environment),
+        None => Ok(ControlFlow::Next),
+    }
+}
+
+
+// The else block only runs when every item has been looped over, not after a break
+fn execute_for(for_statement: &ForStatement, environment: &mut Environment) -> Result<ControlFlow, RuntimeError> {
+    let iterable = evaluate_expression(&for_statement.iterable, environment)?;
+
+    for item in loop_items(iterable, for_statement.variables.len())? {
+        environment.push_scope();
+        let result = declare_loop_variables(&for_statement.variables, item, environment)
+            .and_then(|_| execute_block(&for_statement.body, environment));
+        environment.pop_scope();
+
+        match result? {
+            ControlFlow::Break(label) if is_target(&label, &for_statement.label) => return Ok(ControlFlow::Next),
+            ControlFlow::Continue(label) if is_target(&label, &for_statement.label) => continue,
+            ControlFlow::Next | ControlFlow::Value(_) => {},
+            flow => return Ok(flow),
+        }
+    }
+
+    match &for_statement.else_body {
+        Some(else_body) => execute_block(else_body, environment),
+        None => Ok(ControlFlow::Next),
+    }
+}
+
+
+fn execute_repeat(repeat_statement: &RepeatStatement, environment: &mut Environment) -> Result<ControlFlow, RuntimeError> {
+    let count = match evaluate_expression(&repeat_statement.count, environment)? {
+        Value::Integer(count) if count < 0 => return Err(RuntimeError::new(&format!("Repeat count can't be negative, found {}", count))),
+        Value::Integer(count) => count,
+        value => return Err(RuntimeError::new(&format!("Repeat count must be an int, found {}", value.type_name()))),
+    };
+
+    for _ in 0..count {
+        match execute_block(&repeat_statement.body, environment)? {
+            ControlFlow::Break(None) => break,
+            ControlFlow::Continue(None) => continue,
+            ControlFlow::Next | ControlFlow::Value(_) => {},
+            flow => return Ok(flow),
+        }
+    }
+
+    Ok(ControlFlow::Next)
+}
+
+
+// A map gives its keys to a single loop variable, and its entries as pairs to two variables
+pub fn loop_items(iterable: Value, variable_count: usize) -> Result<Vec<Value>, RuntimeError> {
+    match iterable {
+        Value::Array(values) => Ok(values.borrow().clone()),
+        Value::Tuple(values) => Ok(values),
+        Value::String(value) => Ok(value.chars().map(Value::Char).collect()),
+        Value::Map(entries) if variable_count == 2 => Ok(entries.borrow().iter().map(|(key, value)| Value::Tuple(vec![key.clone(), value.clone()])).collect()),
+        Value::Map(entries) => Ok(entries.borrow().iter().map(|(key, _)| key.clone()).collect()),
+        value => Err(RuntimeError::new(&format!("Cannot iterate over {}", value.type_name()))),
+    }
+}
+
+
+pub fn declare_loop_variables(variables: &[Identifier], item: Value, environment: &mut Environment) -> Result<(), RuntimeError> {
+    let pair = match &item {
+        Value::Tuple(values) if values.len() == 2 => Some(values.clone()),
+        Value::Array(values) if values.borrow().len() == 2 => Some(values.borrow().clone()),
+        _ => None,
+    };
+
+    match (variables, pair) {
+        ([variable], _) => environment.declare(variable, item),
+        ([first, second], Some(pair)) => {
+            let mut pair = pair.into_iter();
+            environment.declare(first, pair.next().unwrap());
+            environment.declare(second, pair.next().unwrap());
+        },
+        _ => return Err(RuntimeError::new(&format!(
+            "Cannot unpack {} into {} loop variables", item.type_name(), variables.len()
+        ))),
+    }
+    Ok(())
+}
+
+
+// The handler gets the raised value, or the message of an error from the interpreter itself
+fn execute_try(try_statement: &TryStatement, environment: &mut Environment) -> Result<ControlFlow, RuntimeError> {
+    let handler_body = match &try_statement.handler_body {
+        Some(handler_body) => handler_body,
+        None => return execute_block(&try_statement.body, environment),
+    };
+
+    let error = match execute_block(&try_statement.body, environment) {
+        Err(error) if error.exit_code.is_none() => error,
+        result => return result,
+    };
+
+    environment.push_scope();
+    if let Some(handler_var) = &try_statement.handler_var {
+        environment.declare(handler_var, error.value.map_or(Value::String(error.message), |value| *value));
+    }
+    let result = execute_block(handler_body, environment);
+    environment.pop_scope();
+    result
+}
+
+
+// A block nested in a statement gives no value, even when it ends with an expression statement
+fn discard_value(flow: ControlFlow) -> ControlFlow {
+    match flow {
+        ControlFlow::Value(_) => ControlFlow::Next,
+        flow => flow,
+    }
+}
+
+
+// An unlabeled break or continue applies to the innermost loop
+fn is_target(label: &Option<Identifier>, loop_label: &Option<Identifier>) -> bool {
+    label.is_none() || label == loop_label
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+    use crate::interpreter::StructValue;
+
+    use super::*;
+
+    fn run(source: &str) -> Environment {
+        let module = parse(&tokenize(source)).unwrap();
+        let mut environment = Environment::new();
+        execute_module(&module, &mut environment).unwrap();
+        environment
+    }
+
+    fn variable(name: &str) -> Identifier {
+        Identifier::Simple(name.to_string())
+    }
+
+    #[test]
+    fn test_loop_else_runs_when_condition_ends_loop() {
+        let environment = run(concat!(
+            "var i: int = 0\n",
+            "var finished: bool = false\n",
+            "while i < 3 {\n",
+            "    i += 1\n",
+            "} else {\n",
+            "    finished = true\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("i")), Ok(Value::Integer(3)));
+        assert_eq!(environment.get(&variable("finished")), Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_break_skips_loop_else() {
+        let environment = run(concat!(
+            "var i: int = 0\n",
+            "var finished: bool = false\n",
+            "while i < 3 {\n",
+            "    if i == 1 {\n",
+            "        break\n",
+            "    }\n",
+            "    i += 1\n",
+            "} else {\n",
+            "    finished = true\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("i")), Ok(Value::Integer(1)));
+        assert_eq!(environment.get(&variable("finished")), Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_for_else_runs_when_items_run_out() {
+        let environment = run(concat!(
+            "var total: int = 0\n",
+            "var finished: bool = false\n",
+            "for i in [1, 2, 3] {\n",
+            "    total += i\n",
+            "} else {\n",
+            "    finished = true\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("total")), Ok(Value::Integer(6)));
+        assert_eq!(environment.get(&variable("finished")), Ok(Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_break_skips_for_else() {
+        let environment = run(concat!(
+            "var total: int = 0\n",
+            "var finished: bool = false\n",
+            "for i in [1, 2, 3] {\n",
+            "    if i == 2 {\n",
+            "        break\n",
+            "    }\n",
+            "    total += i\n",
+            "} else {\n",
+            "    finished = true\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("total")), Ok(Value::Integer(1)));
+        assert_eq!(environment.get(&variable("finished")), Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_block_declarations_do_not_leak() {
+        let environment = run(concat!(
+            "var x: int = 1\n",
+            "{\n",
+            "    y: int = 2\n",
+            "    x = y\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("x")), Ok(Value::Integer(2)));
+        assert_eq!(environment.get(&variable("y")), Err(RuntimeError::new("Undefined variable 'y'")));
+    }
+
+    #[test]
+    fn test_power_of_negative_exponent_is_a_float() {
+        let environment = run("whole: int = 2 ** 3\nfraction: float = 2 ** -3\nscaled: float = 2.0 ** 3\n");
+
+        assert_eq!(environment.get(&variable("whole")), Ok(Value::Integer(8)));
+        assert_eq!(environment.get(&variable("fraction")), Ok(Value::Float(0.125)));
+        assert_eq!(environment.get(&variable("scaled")), Ok(Value::Float(8.0)));
+    }
+
+    #[test]
+    fn test_do_block_declarations_do_not_leak() {
+        let environment = run(concat!(
+            "var total: int = 0\n",
+            "do {\n",
+            "    step: int = 5\n",
+            "    total += step\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("total")), Ok(Value::Integer(5)));
+        assert_eq!(environment.get(&variable("step")), Err(RuntimeError::new("Undefined variable 'step'")));
+    }
+
+    #[test]
+    fn test_deleted_variable_is_not_readable() {
+        let environment = run(concat!(
+            "var x: int = 1\n",
+            "del x\n",
+        ));
+
+        assert_eq!(environment.get(&variable("x")), Err(RuntimeError::new("Undefined variable 'x'")));
+    }
+
+    #[test]
+    fn test_call_with_keyword_arguments() {
+        let environment = run(concat!(
+            "fun scale(x: int, *, factor: int, offset: int): int {\n",
+            "    return x * factor + offset\n",
+            "}\n",
+            "result: int = scale(2, offset = 1, factor = 10)\n",
+        ));
+
+        assert_eq!(environment.get(&variable("result")), Ok(Value::Integer(21)));
+    }
+
+    #[test]
+    fn test_keyword_only_parameter_passed_positionally_is_an_error() {
+        let module = parse(&tokenize("fun scale(x: int, *, factor: int) {\n    pass\n}\nscale(1, 2)\n")).unwrap();
+        let result = execute_module(&module, &mut Environment::new());
+        assert_eq!(result, Err(RuntimeError::new("Keyword-only parameter 'factor' of function 'scale' can't be passed positionally")));
+
+        let module = parse(&tokenize("abs(value = 1)\n")).unwrap();
+        let result = execute_module(&module, &mut Environment::new());
+        assert_eq!(result, Err(RuntimeError::new("Function 'abs' doesn't take keyword arguments")));
+    }
+
+    #[test]
+    fn test_push_in_a_function_is_seen_by_the_caller() {
+        let environment = run(concat!(
+            "fun fill(var values: array) {\n",
+            "    push(values, 3)\n",
+            "}\n",
+            "values: array = [1, 2]\n",
+            "copy: array = values\n",
+            "fill(copy)\n",
+            "last: int = pop(values)\n",
+        ));
+
+        assert_eq!(environment.get(&variable("last")), Ok(Value::Integer(3)));
+        assert_eq!(environment.get(&variable("copy")), Ok(Value::array(vec![Value::Integer(1), Value::Integer(2)])));
+    }
+
+    #[test]
+    fn test_arrays_maps_and_structs_are_passed_by_reference() {
+        let environment = run(concat!(
+            "struct Point {\n",
+            "    x: int\n",
+            "    y: int\n",
+            "}\n",
+            "fun change(var count: int, var name: string, var values: array) {\n",
+            "    count += 1\n",
+            "    name = \"changed\"\n",
+            "    values[0] = count\n",
+            "}\n",
+            "fun same(ages: map, point: Point, other_ages: map, other_point: Point): bool {\n",
+            "    return ages is other_ages && point is other_point\n",
+            "}\n",
+            "var count: int = 1\n",
+            "var name: string = \"original\"\n",
+            "values: array = [0]\n",
+            "change(count, name, values)\n",
+            "ages: map = {\"ann\": 30}\n",
+            "point: Point = Point { x: 1, y: 2 }\n",
+            "shared: bool = same(ages, point, ages, point)\n",
+            "distinct: bool = same(ages, point, {\"ann\": 30}, Point { x: 1, y: 2 })\n",
+        ));
+
+        assert_eq!(environment.get(&variable("count")), Ok(Value::Integer(1)));
+        assert_eq!(environment.get(&variable("name")), Ok(Value::String("original".to_string())));
+        assert_eq!(environment.get(&variable("values")), Ok(Value::array(vec![Value::Integer(2)])));
+        assert_eq!(environment.get(&variable("shared")), Ok(Value::Boolean(true)));
+        assert_eq!(environment.get(&variable("distinct")), Ok(Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_assign_to_negative_index() {
+        let environment = run(concat!(
+            "values: array = [1, 2, 3]\n",
+            "values[-1] = 9\n",
+            "values[-3:-1] = [4]\n",
+        ));
+
+        assert_eq!(environment.get(&variable("values")), Ok(Value::array(vec![Value::Integer(4), Value::Integer(9)])));
+    }
+
+    #[test]
+    fn test_read_back_a_negative_index() {
+        let environment = run(concat!(
+            "values: array = [1, 2, 3]\n",
+            "values[-1] = 9\n",
+            "last: int = values[-1]\n",
+            "first: int = values[0]\n",
+            "rest: array = values[1:]\n",
+        ));
+
+        assert_eq!(environment.get(&variable("last")), Ok(Value::Integer(9)));
+        assert_eq!(environment.get(&variable("first")), Ok(Value::Integer(1)));
+        assert_eq!(environment.get(&variable("rest")), Ok(Value::array(vec![Value::Integer(2), Value::Integer(9)])));
+    }
+
+    #[test]
+    fn test_assign_to_negative_index_out_of_range() {
+        let module = parse(&tokenize("values: array = [1, 2, 3]\nvalues[-4] = 9\n")).unwrap();
+        let result = execute_module(&module, &mut Environment::new());
+
+        assert_eq!(result, Err(RuntimeError::new("Index -4 is out of bounds for length 3")));
+    }
+
+    #[test]
+    fn test_delete_array_element() {
+        let environment = run(concat!(
+            "var values: array = [1, 2, 3]\n",
+            "del values[1]\n",
+        ));
+
+        assert_eq!(environment.get(&variable("values")), Ok(Value::array(vec![Value::Integer(1), Value::Integer(3)])));
+    }
+
+    #[test]
+    fn test_delete_undefined_variable() {
+        let module = parse(&tokenize("del x\n")).unwrap();
+        let result = execute_statements(&module.statements, &mut Environment::new());
+
+        assert_eq!(result, Err(RuntimeError::new("Undefined variable 'x'")));
+    }
+
+    #[test]
+    fn test_recursive_function() {
+        let environment = run(concat!(
+            "fun fib(n: int): int {\n",
+            "    return n < 2 ? n : fib(n - 1) + fib(n - 2)\n",
+            "}\n",
+            "var result: int = fib(10)\n",
+        ));
+
+        assert_eq!(environment.get(&variable("result")), Ok(Value::Integer(55)));
+    }
+
+    #[test]
+    fn test_for_loop_over_map_keys() {
+        let environment = run(concat!(
+            "ages: map = {\"ann\": 30, \"bob\": 40}\n",
+            "var names: string = \"\"\n",
+            "for name in ages {\n",
+            "    names = names + name\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("names")), Ok(Value::String("annbob".to_string())));
+    }
+
+    #[test]
+    fn test_for_loop_over_map_entries() {
+        let environment = run(concat!(
+            "ages: map = {\"ann\": 30, \"bob\": 40}\n",
+            "var total: int = 0\n",
+            "for name, age in ages {\n",
+            "    total = total + age\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("total")), Ok(Value::Integer(70)));
+    }
+
+    #[test]
+    fn test_for_loop_over_enumerate() {
+        let environment = run(concat!(
+            "var indices: array = []\n",
+            "var items: array = []\n",
+            "for i, x in enumerate([\"a\", \"b\", \"c\"]) {\n",
+            "    push(indices, i)\n",
+            "    push(items, x)\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("indices")), Ok(Value::array(vec![Value::Integer(0), Value::Integer(1), Value::Integer(2)])));
+        assert_eq!(environment.get(&variable("items")), Ok(Value::array(vec![
+            Value::String("a".to_string()), Value::String("b".to_string()), Value::String("c".to_string()),
+        ])));
+    }
+
+    #[test]
+    fn test_for_loop_cannot_unpack_non_pairs() {
+        let module = parse(&tokenize("for a, b in [1, 2] {\n}\n")).unwrap();
+        let result = execute_module(&module, &mut Environment::new());
+
+        assert_eq!(result, Err(RuntimeError::new("Cannot unpack int into 2 loop variables")));
+    }
+
+    #[test]
+    fn test_struct_fields() {
+        let environment = run(concat!(
+            "struct Point {\n",
+            "    x: int\n",
+            "    y: int\n",
+            "}\n",
+            "p: Point = Point { y: 2, x: 1 }\n",
+            "sum: int = p.x + p.y\n",
+        ));
+
+        assert_eq!(environment.get(&variable("sum")), Ok(Value::Integer(3)));
+        assert_eq!(environment.get(&variable("p")), Ok(Value::structure(StructValue {
+            name: "Point".to_string(),
+            fields: vec![("x".to_string(), Value::Integer(1)), ("y".to_string(), Value::Integer(2))],
+        })));
+    }
+
+    #[test]
+    fn test_struct_literal_must_match_the_definition() {
+        let definition = "struct Point {\n    x: int\n    y: int\n}\n";
+        let cases = [
+            ("p: Line = Line { x: 1 }\n", "Undefined struct 'Line'"),
+            ("p: Point = Point { x: 1 }\n", "Struct 'Point' is missing field 'y'"),
+            ("p: Point = Point { x: 1, y: 2, z: 3 }\n", "Struct 'Point' has no field 'z'"),
+            ("p: Point = Point { x: 1, y: 2 }\nz: int = p.z\n", "Struct 'Point' has no field 'z'"),
+        ];
+
+        for (source, message) in cases {
+            let module = parse(&tokenize(&format!("{}{}", definition, source))).unwrap();
+            let result = execute_module(&module, &mut Environment::new());
+            assert_eq!(result, Err(RuntimeError::new(message)));
+        }
+    }
+
+    #[test]
+    fn test_main_runs_after_top_level_statements() {
+        let module = parse(&tokenize(concat!(
+            "fun main(): int {\n",
+            "    global limit\n",
+            "    limit = limit * 2\n",
+            "    return limit + 1\n",
+            "}\n",
+            "var limit: int = 10\n",
+        ))).unwrap();
+        let mut environment = Environment::new();
+
+        let result = execute_module(&module, &mut environment);
+
+        assert_eq!(result, Ok(ControlFlow::Return(Value::Integer(21))));
+        assert_eq!(environment.get(&variable("limit")), Ok(Value::Integer(20)));
+    }
+
+    #[test]
+    fn test_dynamic_if_and_while_use_truthiness() {
+        let module = parse(&tokenize(concat!(
+            "var remaining: array = [1, 2, 3]\n",
+            "var total: int = 0\n",
+            "while remaining {\n",
+            "    total += pop(remaining)\n",
+            "}\n",
+            "if \"\" {\n",
+            "    total = 0\n",
+            "}\n",
+        ))).unwrap();
+        let mut environment = Environment::new();
+        environment.set_dynamic(true);
+
+        execute_module(&module, &mut environment).unwrap();
+
+        assert_eq!(environment.get(&variable("total")), Ok(Value::Integer(6)));
+    }
+
+    #[test]
+    fn test_repeat_runs_its_body_count_times() {
+        let environment = run(concat!(
+            "var runs: int = 0\n",
+            "var count: int = 4\n",
+            "repeat count times {\n",
+            "    runs += 1\n",
+            "    count = 0\n",
+            "}\n",
+            "repeat 0 {\n",
+            "    runs += 1\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("runs")), Ok(Value::Integer(4)));
+    }
+
+    #[test]
+    fn test_repeat_count_must_be_a_non_negative_int() {
+        for (count, message) in [("-1", "Repeat count can't be negative, found -1"), ("2.0", "Repeat count must be an int, found float")] {
+            let module = parse(&tokenize(&format!("repeat {} {{\n    pass\n}}\n", count))).unwrap();
+            let result = execute_module(&module, &mut Environment::new());
+
+            assert_eq!(result.unwrap_err().message, message);
+        }
+    }
+
+    #[test]
+    fn test_for_loop_over_char_range() {
+        let environment = run(concat!(
+            "var letters: string = \"\"\n",
+            "for letter in 'a'..'d' {\n",
+            "    letters = letters + letter as string\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("letters")), Ok(Value::String("abc".to_string())));
+    }
+
+    #[test]
+    fn test_assign_and_test_in_a_condition() {
+        let environment = run(concat!(
+            "var values: array = [3, 0, 5]\n",
+            "var n: int = 0\n",
+            "var total: int = 0\n",
+            "while (n := pop(values)) > 0 {\n",
+            "    total += n\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("n")), Ok(Value::Integer(0)));
+        assert_eq!(environment.get(&variable("total")), Ok(Value::Integer(5)));
+    }
+
+    #[test]
+    fn test_function_assigns_a_declared_global() {
+        let environment = run(concat!(
+            "fun count() {\n",
+            "    global total\n",
+            "    total += 1\n",
+            "}\n",
+            "var total: int = 0\n",
+            "count()\n",
+            "count()\n",
+        ));
+
+        assert_eq!(environment.get(&variable("total")), Ok(Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_function_cannot_assign_an_undeclared_global() {
+        let module = parse(&tokenize(concat!(
+            "fun reset() {\n",
+            "    total = 0\n",
+            "}\n",
+            "var total: int = 5\n",
+            "reset()\n",
+        ))).unwrap();
+        let mut environment = Environment::new();
+
+        let result = execute_module(&module, &mut environment);
+
+        assert_eq!(
+            result,
+            Err(RuntimeError::new("Cannot assign to global variable 'total' in a function without declaring it global"))
+        );
+        assert_eq!(environment.get(&variable("total")), Ok(Value::Integer(5)));
+    }
+
+    #[test]
+    fn test_local_shadows_a_global_of_the_same_name() {
+        let environment = run(concat!(
+            "fun reset(): int {\n",
+            "    total: int = 0\n",
+            "    total = 7\n",
+            "    return total\n",
+            "}\n",
+            "var total: int = 5\n",
+            "result: int = reset()\n",
+        ));
+
+        assert_eq!(environment.get(&variable("result")), Ok(Value::Integer(7)));
+        assert_eq!(environment.get(&variable("total")), Ok(Value::Integer(5)));
+    }
+
+    #[test]
+    fn test_global_declaration_needs_an_existing_global() {
+        let module = parse(&tokenize("fun f() {\n    global missing\n}\nf()\n")).unwrap();
+        let result = execute_module(&module, &mut Environment::new());
+        assert_eq!(result, Err(RuntimeError::new("Undefined global variable 'missing'")));
+    }
+
+    #[test]
+    fn test_exit_stops_the_program() {
+        let module = parse(&tokenize(concat!(
+            "fun main() {\n",
+            "    try {\n",
+            "        stop(3)\n",
+            "    } except {\n",
+            "        count = 100\n",
+            "    }\n",
+            "    count = 200\n",
+            "}\n",
+            "fun stop(code: int) {\n",
+            "    exit(code)\n",
+            "    count = 300\n",
+            "}\n",
+            "var count: int = 1\n",
+        ))).unwrap();
+        let mut environment = Environment::new();
+
+        let result = execute_module(&module, &mut environment);
+
+        assert_eq!(result, Ok(ControlFlow::Exit(3)));
+        assert_eq!(environment.get(&variable("count")), Ok(Value::Integer(1)));
+    }
+
+    #[test]
+    fn test_only_the_trailing_expression_is_returned() {
+        let environment = run(concat!(
+            "fun half(x: float) {\n",
+            "    x * 4.0\n",
+            "    if x < 0.0 {\n",
+            "        0.0\n",
+            "    }\n",
+            "    x / 2.0\n",
+            "}\n",
+            "fun nothing(x: float) {\n",
+            "    if x < 0.0 {\n",
+            "        0.0\n",
+            "    }\n",
+            "}\n",
+            "var result: float = half(3.0)\n",
+            "var empty: float = nothing(-1.0)\n",
+        ));
+
+        assert_eq!(environment.get(&variable("result")), Ok(Value::Float(1.5)));
+        assert_eq!(environment.get(&variable("empty")), Ok(Value::None));
+    }
+
+    #[test]
+    fn test_expression_statement_gives_its_value() {
+        let module = parse(&tokenize("1 + 2\n")).unwrap();
+        let result = execute_statement(&module.statements.statements[0], &mut Environment::new());
+
+        assert_eq!(result, Ok(ControlFlow::Value(Value::Integer(3))));
+    }
+
+    #[test]
+    fn test_infinite_recursion_exceeds_call_depth() {
+        let module = parse(&tokenize(concat!(
+            "fun forever(n: int): int {\n",
+            "    return forever(n + 1)\n",
+            "}\n",
+            "forever(0)\n",
+        ))).unwrap();
+        let mut environment = Environment::new();
+        environment.set_max_call_depth(50);
+
+        let result = execute_module(&module, &mut environment);
+
+        assert_eq!(result, Err(RuntimeError::new("Maximum recursion depth exceeded")));
+    }
+
+    #[test]
+    fn test_default_call_depth_does_not_overflow_stack() {
+        let module = parse(&tokenize(concat!(
+            "fun forever(n: int): int {\n",
+            "    return forever(n + 1)\n",
+            "}\n",
+            "forever(0)\n",
+        ))).unwrap();
+
+        let result = execute_module(&module, &mut Environment::new());
+
+        assert_eq!(result, Err(RuntimeError::new("Maximum recursion depth exceeded")));
+    }
+
+    #[test]
+    fn test_pass_does_nothing() {
+        let environment = run(concat!(
+            "var x: int = 1\n",
+            "if x == 1 {\n",
+            "    pass\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("x")), Ok(Value::Integer(1)));
+    }
+
+    #[test]
+    fn test_chained_assignment() {
+        let environment = run(concat!(
+            "var a: int = 1\n",
+            "var b: int = 2\n",
+            "a = b = 0\n",
+        ));
+
+        assert_eq!(environment.get(&variable("a")), Ok(Value::Integer(0)));
+        assert_eq!(environment.get(&variable("b")), Ok(Value::Integer(0)));
+    }
+
+    #[test]
+    fn test_else_if_chain_picks_one_branch() {
+        for (x, expected) in [(1, "one"), (2, "two"), (3, "other")] {
+            let environment = run(&format!(concat!(
+                "var x: int = {}\n",
+                "var branch: str = \"none\"\n",
+                "if x == 1 {{\n",
+                "    branch = \"one\"\n",
+                "}} else if x == 2 {{\n",
+                "    branch = \"two\"\n",
+                "}} else {{\n",
+                "    branch = \"other\"\n",
+                "}}\n",
+            ), x));
+
+            assert_eq!(environment.get(&variable("branch")), Ok(Value::String(expected.to_string())));
+        }
+    }
+
+    #[test]
+    fn test_try_catches_division_by_zero() {
+        let environment = run(concat!(
+            "var message: str = \"\"\n",
+            "var result: int = 0\n",
+            "try {\n",
+            "    result = 1 / 0\n",
+            "    result = 2\n",
+            "} except e {\n",
+            "    message = e\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("result")), Ok(Value::Integer(0)));
+        assert_eq!(environment.get(&variable("message")), Ok(Value::String("Division by zero".to_string())));
+    }
+
+    #[test]
+    fn test_try_without_handler_raises_again() {
+        let module = parse(&tokenize("try {\n    1 / 0\n}\n")).unwrap();
+        let result = execute_module(&module, &mut Environment::new());
+
+        assert_eq!(result, Err(RuntimeError::new("Division by zero")));
+    }
+
+    #[test]
+    fn test_raised_value_is_caught() {
+        let environment = run(concat!(
+            "var caught: array = []\n",
+            "try {\n",
+            "    raise [1, 2]\n",
+            "} except e {\n",
+            "    caught = e\n",
+            "}\n",
+        ));
+
+        assert_eq!(environment.get(&variable("caught")), Ok(Value::array(vec![Value::Integer(1), Value::Integer(2)])));
+    }
+
+    #[test]
+    fn test_uncaught_raise_is_an_error() {
+        let module = parse(&tokenize("raise \"boom\"\n")).unwrap();
+        let result = execute_module(&module, &mut Environment::new());
+
+        assert_eq!(result, Err(RuntimeError::raised(Value::String("boom".to_string()))));
+        assert_eq!(result.unwrap_err().message, "boom");
+    }
+}
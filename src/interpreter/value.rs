@@ -0,0 +1,208 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::elements::Literal;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Char(char),
+    String(String),
+    // Arrays, maps and structs are shared references, so every copy of an array sees the elements
+    // pushed to any of them
+    Array(Rc<RefCell<Vec<Value>>>),
+    Tuple(Vec<Value>),
+    // Entries are kept in the order they were inserted
+    Map(Rc<RefCell<Vec<(Value, Value)>>>),
+    Struct(Rc<RefCell<StructValue>>),
+    None,
+}
+
+
+// Fields are kept in the order of the struct definition
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructValue {
+    pub name: String,
+    pub fields: Vec<(String, Value)>,
+}
+
+
+impl Value {
+    pub fn array(values: Vec<Value>) -> Value {
+        Value::Array(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn map(entries: Vec<(Value, Value)>) -> Value {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    pub fn structure(value: StructValue) -> Value {
+        Value::Struct(Rc::new(RefCell::new(value)))
+    }
+
+    // Where a shared value lives, which is the same for every reference to it, or None for a value
+    // that is copied
+    pub(crate) fn shared_pointer(&self) -> Option<*const ()> {
+        match self {
+            Value::Array(values) => Some(Rc::as_ptr(values) as *const ()),
+            Value::Map(entries) => Some(Rc::as_ptr(entries) as *const ()),
+            Value::Struct(value) => Some(Rc::as_ptr(value) as *const ()),
+            _ => None,
+        }
+    }
+
+    pub fn from_literal(literal: &Literal) -> Value {
+        match literal {
+            Literal::Integer(value) => Value::Integer(*value),
+            Literal::Float(value) => Value::Float(*value),
+            Literal::Boolean(value) => Value::Boolean(*value),
+            Literal::Char(value) => Value::Char(*value),
+            Literal::String(value) => Value::String(value.clone()),
+            Literal::None => Value::None,
+            // All integers are 64 bit signed while interpreting, so u64 values above i64::MAX wrap around
+            Literal::TypedInteger(value, _) => Value::Integer(*value as i64),
+            Literal::TypedFloat(value, _) => Value::Float(*value),
+        }
+    }
+
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::Integer(_) => ValueKind::Integer,
+            Value::Float(_) => ValueKind::Float,
+            Value::Boolean(_) => ValueKind::Boolean,
+            Value::Char(_) => ValueKind::Char,
+            Value::String(_) => ValueKind::String,
+            Value::Array(_) => ValueKind::Array,
+            Value::Tuple(_) => ValueKind::Tuple,
+            Value::Map(_) => ValueKind::Map,
+            Value::Struct(_) => ValueKind::Struct,
+            Value::None => ValueKind::None,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.kind().name()
+    }
+
+    // Zero, an empty string or array, and None are false as a condition in dynamic mode, and
+    // everything else is true
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Boolean(value) => *value,
+            Value::Integer(value) => *value != 0,
+            Value::Float(value) => *value != 0.0,
+            Value::String(value) => !value.is_empty(),
+            Value::Array(values) => !values.borrow().is_empty(),
+            Value::None => false,
+            _ => true,
+        }
+    }
+}
+
+
+// The kind of a value without its contents, which is enough to choose how an operator applies to it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueKind {
+    Integer,
+    Float,
+    Boolean,
+    Char,
+    String,
+    Array,
+    Tuple,
+    Map,
+    Struct,
+    None,
+}
+
+
+impl ValueKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueKind::Integer => "int",
+            ValueKind::Float => "float",
+            ValueKind::Boolean => "bool",
+            ValueKind::Char => "char",
+            ValueKind::String => "string",
+            ValueKind::Array => "array",
+            ValueKind::Tuple => "tuple",
+            ValueKind::Map => "map",
+            ValueKind::Struct => "struct",
+            ValueKind::None => "None",
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, ValueKind::Integer | ValueKind::Float)
+    }
+}
+
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", display_string(self, &mut Vec::new()))
+    }
+}
+
+
+// The shared values being printed are tracked, since an array that contains itself would otherwise
+// be printed forever. It is printed as [...] where it appears inside itself, and a map or struct as
+// {...} or Point { ... }.
+fn display_string(value: &Value, enclosing: &mut Vec<*const ()>) -> String {
+    let Some(pointer) = value.shared_pointer() else {
+        return display_contents(value, enclosing);
+    };
+    if enclosing.contains(&pointer) {
+        return match value {
+            Value::Struct(value) => format!("{} {{ ... }}", value.borrow().name),
+            Value::Map(_) => "{...}".to_string(),
+            _ => "[...]".to_string(),
+        };
+    }
+
+    enclosing.push(pointer);
+    let string = display_contents(value, enclosing);
+    enclosing.pop();
+    string
+}
+
+
+fn display_contents(value: &Value, enclosing: &mut Vec<*const ()>) -> String {
+    match value {
+        Value::Integer(value) => Literal::Integer(*value).to_display_string(),
+        Value::Float(value) => Literal::Float(*value).to_display_string(),
+        Value::Boolean(value) => value.to_string(),
+        Value::Char(value) => value.to_string(),
+        Value::String(value) => value.clone(),
+        Value::Array(values) => format!("[{}]", join_values(&values.borrow(), enclosing)),
+        Value::Tuple(values) => format!("({})", join_values(values, enclosing)),
+        Value::Map(entries) => {
+            let entries = entries.borrow().iter()
+                .map(|(key, value)| format!("{}: {}", display_string(key, enclosing), display_string(value, enclosing)))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("{{{}}}", entries)
+        },
+        Value::Struct(value) => {
+            let value = value.borrow();
+            let fields = value.fields.iter()
+                .map(|(field, value)| format!("{}: {}", field, display_string(value, enclosing)))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("{} {{ {} }}", value.name, fields)
+        },
+        Value::None => "None".to_string(),
+    }
+}
+
+
+fn join_values(values: &[Value], enclosing: &mut Vec<*const ()>) -> String {
+    values.iter()
+        .map(|value| display_string(value, enclosing))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,26 @@
+use std::fmt;
+
+use crate::span::Span;
+
+
+// Malformed source, like an unknown character or an unterminated literal, and source going over the
+// limits that guard against huge inputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub message: String,
+    pub span: Span,
+}
+
+
+impl LexError {
+    pub fn new(message: &str, span: Span) -> LexError {
+        LexError { message: message.to_string(), span }
+    }
+}
+
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Lex error: {}", self.message)
+    }
+}
@@ -1,9 +1,109 @@
 mod tokenizer;
 mod preprocessor;
+mod source_chars;
+mod errors;
 
 use crate::tokens::Token;
+use crate::span::{Span, Spanned, Position};
+
+pub use errors::LexError;
+pub(crate) use tokenizer::is_spread_position;
+
+
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+// Far more than any program written by hand, while keeping untrusted input from using up the memory
+pub const DEFAULT_MAX_TOKENS: usize = 10_000_000;
+
+
+#[derive(Debug, Clone)]
+pub struct LexOptions {
+    // Keep comments so that documentation can be attached to the tree
+    pub trivia: bool,
+    // Number of columns between tab stops when reporting positions
+    pub tab_width: usize,
+    // Read integer literals too large for an int as floats, instead of rejecting them
+    pub promote_large_integers: bool,
+    // Lexing stops with an error once the source has more tokens than this
+    pub max_tokens: usize,
+    // Give malformed source an error token instead of stopping with an error, so the rest of the
+    // source can still be read. Going over the token limit still stops lexing.
+    pub lenient: bool,
+}
+
+
+impl Default for LexOptions {
+    fn default() -> LexOptions {
+        LexOptions { trivia: false, tab_width: DEFAULT_TAB_WIDTH, promote_large_integers: false, max_tokens: DEFAULT_MAX_TOKENS, lenient: false }
+    }
+}
 
 
 pub fn tokenize(src: &str) -> Vec<Token> {
-    preprocessor::preprocess(&tokenizer::tokenize(src))
+    tokenize_with_options(src, &LexOptions::default())
+}
+
+
+pub fn tokenize_with_options(src: &str, options: &LexOptions) -> Vec<Token> {
+    tokenize_with_spans(src, options).0
+}
+
+
+// Tokens along with the span of the source code each one was read from. Any lexing error panics.
+pub fn tokenize_with_spans(src: &str, options: &LexOptions) -> (Vec<Token>, Vec<Span>) {
+    try_tokenize_with_spans(src, options).unwrap_or_else(|error| panic!("{}", error.message))
+}
+
+
+// Gives an error instead of panicking when the source can't be read, for source that can't be trusted
+pub fn try_tokenize_with_spans(src: &str, options: &LexOptions) -> Result<(Vec<Token>, Vec<Span>), LexError> {
+    Ok(preprocessor::preprocess(&tokenizer::tokenize(src, options)?, options)?
+        .into_iter()
+        .map(|Spanned { node, span, .. }| (node, span))
+        .unzip())
+}
+
+
+// The line and column where a span starts, with tabs advancing the column to the next tab stop
+pub fn position(src: &str, span: Span, options: &LexOptions) -> Position {
+    Position::at_offset(src, span.start, options.tab_width)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_column_after_leading_tab() {
+        let source = "fun main() {\n\tx = 1\n}\n";
+        let (tokens, spans) = tokenize_with_spans(source, &LexOptions::default());
+
+        let index = tokens.iter().position(|token| matches!(token, Token::Identifier(identifier) if identifier.as_string() == "x")).unwrap();
+
+        assert_eq!(position(source, spans[index], &LexOptions::default()), Position { line: 2, column: 5 });
+        assert_eq!(position(source, spans[index], &LexOptions { tab_width: 8, ..LexOptions::default() }), Position { line: 2, column: 9 });
+    }
+
+    #[test]
+    fn test_too_many_tokens_is_an_error() {
+        let options = LexOptions { max_tokens: 100, ..LexOptions::default() };
+        let source = "x = 1\n".repeat(1000);
+
+        assert_eq!(
+            try_tokenize_with_spans(&source, &options),
+            Err(LexError::new("Source has more than 100 tokens", Span::new(150, 151)))
+        );
+        assert!(try_tokenize_with_spans("x = 1\n", &options).is_ok());
+    }
+
+    #[test]
+    fn test_line_numbers_with_crlf_line_endings() {
+        let source = "fun main() {\r\n\r\n    x = 1\r\n}\r\n";
+        let (tokens, spans) = tokenize_with_spans(source, &LexOptions::default());
+
+        let index = tokens.iter().position(|token| matches!(token, Token::Identifier(identifier) if identifier.as_string() == "x")).unwrap();
+
+        assert_eq!(position(source, spans[index], &LexOptions::default()), Position { line: 3, column: 5 });
+    }
 }
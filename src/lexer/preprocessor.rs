@@ -1,37 +1,65 @@
 use std::iter::Peekable;
 use std::slice::Iter;
 
-use crate::elements::{Identifier, Operator, Literal, Keyword};
+use crate::elements::{Identifier, Keyword};
 use crate::tokens::Token;
+use crate::span::Spanned;
+use crate::lexer::{LexOptions, LexError};
 
 
-pub fn preprocess(input: &[Token]) -> Vec<Token> {
+pub fn preprocess(input: &[Spanned<Token>], options: &LexOptions) -> Result<Vec<Spanned<Token>>, LexError> {
     let mut tokens = input.iter().peekable();
 
-    let mut output: Vec<Token> = vec![];
+    let mut output: Vec<Spanned<Token>> = vec![];
+    // Parentheses and square brackets that are open, where a new line doesn't end a statement
+    let mut bracket_depth = 0;
 
     while let Some(token) = tokens.next() {
-        match token {
+        match &token.node {
+            Token::OpenParen | Token::OpenSquareBracket => bracket_depth += 1,
+            Token::CloseParen | Token::CloseSquareBracket => bracket_depth -= 1,
+            _ => (),
+        }
+
+        match &token.node {
 
-            // Skip redundant newlines
-            Token::Newline => if output.last() == Some(&Token::Newline) {
+            // Skip redundant newlines, including any before the first statement
+            Token::Newline if output.last().is_none_or(|last| last.node == Token::Newline) => continue,
+
+            // Only comments documenting a function are kept, the rest are trivia
+            Token::Comment(_) => {
+                let comment = combine_comments(token, &mut tokens);
+                if precedes_function(&tokens) {
+                    output.push(comment);
+                }
                 continue;
             },
 
+            // A comment at the end of a line is kept with the statement it follows
+            Token::TrailingComment(_) => {
+                let ends_statement = matches!(peek_token(&mut tokens), Some(Token::Newline) | Some(Token::EndOfModule) | None);
+                if bracket_depth > 0 || !ends_statement {
+                    continue;
+                }
+            },
+
             // Newline after opening brackets is redundant
             Token::OpenParen | Token::OpenBrace | Token::OpenSquareBracket
-            => if let Some(Token::Newline) = tokens.peek() {
+            => if let Some(Token::Newline) = peek_token(&mut tokens) {
                 tokens.next();
             },
 
             // Newline after a list separator is redundant
-            Token::ListSeparator => if let Some(Token::Newline) = tokens.peek() {
+            Token::ListSeparator => if let Some(Token::Newline) = peek_token(&mut tokens) {
                 tokens.next();
             },
 
-            // Combine compound identifiers
-            Token::Identifier(_) => if let Some(Token::Dot) = tokens.peek() {
-                let new_token = combine_compound_identifier(token, &mut tokens);
+            // Combine compound identifiers, or give an error token in their place when lenient
+            Token::Identifier(_) => if let Some(Token::Dot) = peek_token(&mut tokens) {
+                let new_token = match combine_compound_identifier(token, &mut tokens) {
+                    Err(error) if options.lenient => Spanned::new(Token::Error(error.message), error.span),
+                    result => result?,
+                };
                 output.push(new_token);
                 continue;  // We can skip to the next token, since we don't want to push the old identifier
             }
@@ -40,27 +68,70 @@ pub fn preprocess(input: &[Token]) -> Vec<Token> {
         }
         output.push(token.clone());
     }
-    output
+    Ok(output)
 }
 
 
-fn combine_compound_identifier(token: &Token, tokens: &mut Peekable<Iter<Token>>) -> Token {
-    let current_identifier = match token {
+fn peek_token<'a>(tokens: &mut Peekable<Iter<'a, Spanned<Token>>>) -> Option<&'a Token> {
+    tokens.peek().map(|token| &token.node)
+}
+
+
+fn combine_compound_identifier(token: &Spanned<Token>, tokens: &mut Peekable<Iter<Spanned<Token>>>) -> Result<Spanned<Token>, LexError> {
+    let current_identifier = match &token.node {
         Token::Identifier(identifier) => identifier,
         _ => panic!("Token must be Token::Identifier, found {:?}", token),
     };
 
     let mut identifiers = vec![current_identifier.as_string()];
+    let mut span = token.span;
+
+    while let Some(Token::Dot) = peek_token(tokens) {
+        let dot = tokens.next().unwrap();
+        // The token after the dot is left in place when it isn't a name, so a newline still ends the statement
+        match tokens.next_if(|next| matches!(next.node, Token::Identifier(_))) {
+            Some(Spanned { node: Token::Identifier(identifier), span: identifier_span, .. }) => {
+                identifiers.push(identifier.as_string());
+                span = span.merge(*identifier_span);
+            },
+            _ => return Err(LexError::new("Expected a name after the dot", span.merge(dot.span))),
+        }
+    }
+
+    Ok(Spanned::new(Token::Identifier(Identifier::Compound(identifiers)), span))
+}
+
+
+fn combine_comments(token: &Spanned<Token>, tokens: &mut Peekable<Iter<Spanned<Token>>>) -> Spanned<Token> {
+    let mut lines = match &token.node {
+        Token::Comment(comment) => vec![comment.clone()],
+        _ => panic!("Token must be Token::Comment, found {:?}", token),
+    };
+    let mut span = token.span;
 
-    while let Some(Token::Dot) = tokens.peek() {
-        tokens.next();  // Consume the dot
-        match tokens.next() {
-            Some(Token::Identifier(identifier)) => identifiers.push(identifier.as_string()),
-            _ => panic!("Expected identifier after dot, found {:?}", tokens.peek()),
+    // Consecutive comment lines form a single block
+    loop {
+        let mut lookahead = tokens.clone();
+        if lookahead.next().map(|token| &token.node) != Some(&Token::Newline) {
+            break;
         }
+        match lookahead.next() {
+            Some(Spanned { node: Token::Comment(comment), span: comment_span, .. }) => {
+                lines.push(comment.clone());
+                span = span.merge(*comment_span);
+            },
+            _ => break,
+        }
+        *tokens = lookahead;
     }
 
-    Token::Identifier(Identifier::Compound(identifiers))
+    Spanned::new(Token::Comment(lines.join("\n")), span)
+}
+
+
+fn precedes_function(tokens: &Peekable<Iter<Spanned<Token>>>) -> bool {
+    let mut lookahead = tokens.clone().skip_while(|token| token.node == Token::Newline);
+    lookahead.next().map(|token| &token.node) == Some(&Token::Keyword(Keyword::Function))
 }
 
 
@@ -68,12 +139,24 @@ fn combine_compound_identifier(token: &Token, tokens: &mut Peekable<Iter<Token>>
 mod tests {
     use super::*;
 
+    fn spanned(tokens: Vec<Token>) -> Vec<Spanned<Token>> {
+        tokens.into_iter().map(Spanned::from).collect()
+    }
+
     #[test]
     fn test_remove_redundant_newlines() {
-        let input = vec![Token::Newline, Token::Newline, Token::Newline];
-        let expected = vec![Token::Newline];
+        let input = vec![Token::Keyword(Keyword::Pass), Token::Newline, Token::Newline, Token::Newline];
+        let expected = vec![Token::Keyword(Keyword::Pass), Token::Newline];
+
+        assert_eq!(preprocess(&spanned(input), &LexOptions::default()).unwrap(), expected);
+    }
 
-        assert_eq!(preprocess(&input), expected);
+    #[test]
+    fn test_remove_leading_newlines() {
+        let input = vec![Token::Newline, Token::Newline, Token::EndOfModule];
+        let expected = vec![Token::EndOfModule];
+
+        assert_eq!(preprocess(&spanned(input), &LexOptions::default()).unwrap(), expected);
     }
 
     #[test]
@@ -81,7 +164,7 @@ mod tests {
         let input = vec![Token::OpenBrace, Token::Newline];
         let expected = vec![Token::OpenBrace];
 
-        assert_eq!(preprocess(&input), expected);
+        assert_eq!(preprocess(&spanned(input), &LexOptions::default()).unwrap(), expected);
     }
 
     #[test]
@@ -89,7 +172,7 @@ mod tests {
         let input = vec![Token::ListSeparator, Token::Newline];
         let expected = vec![Token::ListSeparator];
 
-        assert_eq!(preprocess(&input), expected);
+        assert_eq!(preprocess(&spanned(input), &LexOptions::default()).unwrap(), expected);
     }
 
     #[test]
@@ -97,7 +180,7 @@ mod tests {
         let input = vec![Token::Identifier(Identifier::Simple("foo".to_string())), Token::Assign];
         let expected = vec![Token::Identifier(Identifier::Simple("foo".to_string())), Token::Assign];
 
-        assert_eq!(preprocess(&input), expected);
+        assert_eq!(preprocess(&spanned(input), &LexOptions::default()).unwrap(), expected);
     }
 
     #[test]
@@ -105,6 +188,69 @@ mod tests {
         let input = vec![Token::Identifier(Identifier::Simple("foo".to_string())), Token::Dot, Token::Identifier(Identifier::Simple("bar".to_string())), Token::Assign];
         let expected = vec![Token::Identifier(Identifier::Compound(vec!["foo".to_string(), "bar".to_string()])), Token::Assign];
 
-        assert_eq!(preprocess(&input), expected);
+        assert_eq!(preprocess(&spanned(input), &LexOptions::default()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_keep_comment_before_function() {
+        let input = vec![
+            Token::Comment("First line".to_string()), Token::Newline,
+            Token::Comment("Second line".to_string()), Token::Newline,
+            Token::Keyword(Keyword::Function),
+        ];
+        let expected = vec![
+            Token::Comment("First line\nSecond line".to_string()), Token::Newline,
+            Token::Keyword(Keyword::Function),
+        ];
+
+        assert_eq!(preprocess(&spanned(input), &LexOptions::default()).unwrap(), expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_drop_comment_not_before_function() {
+        let input = vec![
+            Token::Identifier(Identifier::Simple("foo".to_string())), Token::Comment("trivia".to_string()), Token::Newline,
+            Token::Identifier(Identifier::Simple("bar".to_string())),
+        ];
+        let expected = vec![
+            Token::Identifier(Identifier::Simple("foo".to_string())), Token::Newline,
+            Token::Identifier(Identifier::Simple("bar".to_string())),
+        ];
+
+        assert_eq!(preprocess(&spanned(input), &LexOptions::default()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_keep_trailing_comment_at_end_of_line() {
+        let input = vec![
+            Token::Identifier(Identifier::Simple("foo".to_string())), Token::TrailingComment("kept".to_string()), Token::Newline,
+            Token::OpenParen, Token::Identifier(Identifier::Simple("bar".to_string())), Token::TrailingComment("dropped".to_string()), Token::Newline,
+            Token::CloseParen, Token::TrailingComment("dropped".to_string()), Token::Identifier(Identifier::Simple("baz".to_string())),
+        ];
+        let expected = vec![
+            Token::Identifier(Identifier::Simple("foo".to_string())), Token::TrailingComment("kept".to_string()), Token::Newline,
+            Token::OpenParen, Token::Identifier(Identifier::Simple("bar".to_string())), Token::Newline,
+            Token::CloseParen, Token::Identifier(Identifier::Simple("baz".to_string())),
+        ];
+
+        assert_eq!(preprocess(&spanned(input), &LexOptions::default()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_missing_name_after_dot() {
+        let input = vec![
+            Token::Identifier(Identifier::Simple("foo".to_string())), Token::Dot, Token::Newline,
+            Token::Identifier(Identifier::Simple("bar".to_string())),
+        ];
+
+        let result = preprocess(&spanned(input.clone()), &LexOptions::default());
+        assert_eq!(result.unwrap_err().message, "Expected a name after the dot");
+
+        let options = LexOptions { lenient: true, ..LexOptions::default() };
+        let expected = vec![
+            Token::Error("Expected a name after the dot".to_string()), Token::Newline,
+            Token::Identifier(Identifier::Simple("bar".to_string())),
+        ];
+        assert_eq!(preprocess(&spanned(input), &options).unwrap(), expected);
+    }
+}
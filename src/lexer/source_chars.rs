@@ -0,0 +1,61 @@
+// A peekable iterator over the characters of the source code which keeps track of the byte
+// offset of the next character, so that tokens can be given spans.
+#[derive(Debug, Clone)]
+pub struct SourceChars<'a> {
+    source: &'a str,
+    offset: usize,
+    current: Option<char>,
+}
+
+
+impl<'a> SourceChars<'a> {
+    pub fn new(source: &'a str) -> SourceChars<'a> {
+        SourceChars { source, offset: 0, current: source.chars().next() }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn peek(&mut self) -> Option<&char> {
+        self.current.as_ref()
+    }
+
+    pub fn next_if_eq(&mut self, expected: &char) -> Option<char> {
+        if self.current == Some(*expected) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+
+impl Iterator for SourceChars<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let current = self.current?;
+        self.offset += current.len_utf8();
+        self.current = self.source[self.offset..].chars().next();
+        Some(current)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_offsets_count_bytes() {
+        let mut chars = SourceChars::new("aé b");
+
+        assert_eq!(chars.next(), Some('a'));
+        assert_eq!(chars.offset(), 1);
+        assert_eq!(chars.next(), Some('é'));
+        assert_eq!(chars.offset(), 3);
+        assert_eq!(chars.peek(), Some(&' '));
+        assert_eq!(chars.offset(), 3);
+    }
+}
@@ -1,257 +1,471 @@
-use std::str::Chars;
-use std::iter::Peekable;
-
-use crate::elements::{Identifier, Literal, Operator, Keyword};
+use crate::elements::{Identifier, Literal, NumberType, Operator, Keyword};
 use crate::tokens::Token;
+use crate::span::{Span, Spanned};
+use crate::lexer::{LexOptions, LexError};
+use crate::lexer::source_chars::SourceChars;
 
 
-pub fn tokenize(source_code: &str) -> Vec<Token> {
+pub fn tokenize(source_code: &str, options: &LexOptions) -> Result<Vec<Spanned<Token>>, LexError> {
     let mut tokens: Vec<Token> = Vec::new();
-    let mut chars = source_code.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        match c {
-            '(' => tokens.push(Token::OpenParen),
-            ')' => tokens.push(Token::CloseParen),
-            '{' => tokens.push(Token::OpenBrace),
-            '}' => tokens.push(Token::CloseBrace),
-            '[' => tokens.push(Token::OpenSquareBracket),
-            ']' => tokens.push(Token::CloseSquareBracket),
-            ',' => tokens.push(Token::ListSeparator),
-            ':' => tokens.push(Token::Colon),
+    let mut spans: Vec<Span> = Vec::new();
+    let mut chars = SourceChars::new(source_code);
+
+    loop {
+        let start = chars.offset();
+        if tokens.len() > options.max_tokens {
+            let message = format!("Source has more than {} tokens", options.max_tokens);
+            return Err(LexError::new(&message, spans[options.max_tokens]));
+        }
 
-            '.' => {
-                if let Some('0'..='9') = chars.peek() {
-                    read_number_literal(c, &mut chars, &mut tokens);
-                } else {
-                    tokens.push(Token::Dot);
-                }
-            },
+        let c = match chars.next() {
+            Some(c) => c,
+            None => break,
+        };
 
-            '+' => {
-                if let Some('=') = chars.peek(){
-                    chars.next();
-                    tokens.push(Token::BinaryAssign(Operator::Plus));
-                } else {
-                    tokens.push(Token::Operator(Operator::Plus));
-                }
-            },
+        if let Err(message) = read_token(c, &mut chars, &mut tokens, options) {
+            recover(message, Span::new(start, chars.offset()), &mut tokens, options)?;
+        }
 
-            '-' => {
-                if let Some('=') = chars.peek(){
-                    chars.next();
-                    tokens.push(Token::BinaryAssign(Operator::Minus));
-                } else {
-                    tokens.push(Token::Operator(Operator::Minus));
-                }
-            },
+        // Every token produced from this character onwards spans the characters consumed
+        spans.resize(tokens.len(), Span::new(start, chars.offset()));
+    }
 
-            '*' => {
-                if let Some('*') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::Operator(Operator::Power));
-                } else if let Some('=') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::BinaryAssign(Operator::Times));
-                } else {
-                    tokens.push(Token::Operator(Operator::Times));
-                }
-            },
+    tokens.push(Token::EndOfModule);
+    spans.push(Span::new(source_code.len(), source_code.len()));
 
-            '/' => {
-                if let Some('*') = chars.peek() {
-                    eat_block_comment(&mut chars);
-                } else if let Some('=') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::BinaryAssign(Operator::Divide));
-                } else {
-                    tokens.push(Token::Operator(Operator::Divide));
-                }
-            },
+    Ok(tokens.into_iter()
+        .zip(spans)
+        .map(|(token, span)| Spanned::new(token, span))
+        .collect())
+}
 
-            '\\' => {
-                if let Some('\n') | Some('\r') = chars.peek() {
-                    eat_whitespace('\\', &mut chars, &mut tokens, false)
-                } else {
-                    panic!("Unexpected character: {}", c);
-                }
-            },
 
-            '%' => {
-                if let Some('=') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::BinaryAssign(Operator::Modulo));
-                } else {
-                    tokens.push(Token::Operator(Operator::Modulo));
-                }
-            },
+// Reads the token or tokens starting with the character, which has already been consumed. An error
+// spans the characters read up to the point it was found.
+fn read_token(c: char, chars: &mut SourceChars, tokens: &mut Vec<Token>, options: &LexOptions) -> Result<(), String> {
+    match c {
+        '(' => tokens.push(Token::OpenParen),
+        ')' => tokens.push(Token::CloseParen),
+        '{' => tokens.push(Token::OpenBrace),
+        '}' => tokens.push(Token::CloseBrace),
+        '[' => tokens.push(Token::OpenSquareBracket),
+        ']' => tokens.push(Token::CloseSquareBracket),
+        ',' => tokens.push(Token::ListSeparator),
+        ':' => {
+            if chars.next_if_eq(&'=').is_some() {
+                tokens.push(Token::InlineAssign);
+            } else {
+                tokens.push(Token::Colon);
+            }
+        },
+        ';' => tokens.push(Token::Semicolon),
 
-            '&' => {
-                if let Some('&') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::Operator(Operator::And));
-                } else if let Some('=') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::BinaryAssign(Operator::BitwiseAnd));
+        '.' => {
+            if chars.next_if_eq(&'.').is_some() {
+                if chars.next_if_eq(&'=').is_some() {
+                    tokens.push(Token::InclusiveRange);
                 } else {
-                    tokens.push(Token::Operator(Operator::BitwiseAnd));
+                    tokens.push(Token::Range);
                 }
-            },
+            } else if let Some('0'..='9') = chars.peek() {
+                read_number_literal(c, chars, tokens, options)?;
+            } else {
+                tokens.push(Token::Dot);
+            }
+        },
 
-            '|' => {
-                if let Some('|') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::Operator(Operator::Or));
-                } else if let Some('=') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::BinaryAssign(Operator::BitwiseOr));
-                } else {
-                    tokens.push(Token::Operator(Operator::BitwiseOr));
-                }
-            },
+        // There are no increment or decrement operators, so ++ and -- are rejected rather than
+        // being read as two signs
+        '+' => {
+            if let Some('+') = chars.peek() {
+                chars.next();
+                return Err("'++' is not supported, use '+= 1' instead".to_string());
+            } else if let Some('=') = chars.peek(){
+                chars.next();
+                tokens.push(Token::BinaryAssign(Operator::Plus));
+            } else {
+                tokens.push(Token::Operator(Operator::Plus));
+            }
+        },
 
-            '^' => {
-                if let Some('=') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::BinaryAssign(Operator::BitwiseXor));
-                } else {
-                    tokens.push(Token::Operator(Operator::BitwiseXor));
-                }
-            },
+        '-' => {
+            if let Some('-') = chars.peek() {
+                chars.next();
+                return Err("'--' is not supported, use '-= 1' instead".to_string());
+            } else if let Some('=') = chars.peek(){
+                chars.next();
+                tokens.push(Token::BinaryAssign(Operator::Minus));
+            } else {
+                tokens.push(Token::Operator(Operator::Minus));
+            }
+        },
 
-            '~' => tokens.push(Token::Operator(Operator::BitwiseNot)),
+        '*' => {
+            if let Some('*') = chars.peek() {
+                chars.next();
+                tokens.push(Token::Operator(Operator::Power));
+            } else if let Some('=') = chars.peek() {
+                chars.next();
+                tokens.push(Token::BinaryAssign(Operator::Times));
+            } else if is_spread_position(tokens.last()) {
+                tokens.push(Token::Spread);
+            } else {
+                tokens.push(Token::Operator(Operator::Times));
+            }
+        },
 
-            '!' => {
-                if let Some('=') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::Operator(Operator::NotEqual));
-                } else {
-                    tokens.push(Token::Operator(Operator::Not));
+        '/' => {
+            if let Some('*') = chars.peek() {
+                let comment = eat_block_comment(chars);
+                if options.trivia {
+                    tokens.push(comment_token(comment, tokens));
                 }
+            } else if let Some('=') = chars.peek() {
+                chars.next();
+                tokens.push(Token::BinaryAssign(Operator::Divide));
+            } else {
+                tokens.push(Token::Operator(Operator::Divide));
             }
+        },
 
-            '<' => {
-                if let Some('<') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::Operator(Operator::BitwiseLeftShift));
-                } else if let Some('=') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::Operator(Operator::LessThanOrEqual));
-                } else {
-                    tokens.push(Token::Operator(Operator::LessThan));
-                }
-            },
+        // A line continuation swallows the whole line break, including both characters of a CRLF
+        '\\' => {
+            if let Some('\n') | Some('\r') = chars.peek() {
+                eat_whitespace('\\', chars, tokens, false, options.trivia)
+            } else {
+                return Err(format!("Unexpected character: {}", c));
+            }
+        },
 
-            '>' => {
-                if let Some('>') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::Operator(Operator::BitwiseRightShift));
-                } else if let Some('=') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::Operator(Operator::GreaterThanOrEqual));
-                } else {
-                    tokens.push(Token::Operator(Operator::GreaterThan));
-                }
-            },
+        '%' => {
+            if let Some('=') = chars.peek() {
+                chars.next();
+                tokens.push(Token::BinaryAssign(Operator::Modulo));
+            } else {
+                tokens.push(Token::Operator(Operator::Modulo));
+            }
+        },
 
-            '=' => {
-                if let Some('=') = chars.peek() {
-                    chars.next();
-                    tokens.push(Token::Operator(Operator::Equal));
-                } else {
-                    tokens.push(Token::Assign);
-                }
-            },
+        '&' => {
+            if let Some('&') = chars.peek() {
+                chars.next();
+                tokens.push(Token::Operator(Operator::And));
+            } else if let Some('=') = chars.peek() {
+                chars.next();
+                tokens.push(Token::BinaryAssign(Operator::BitwiseAnd));
+            } else {
+                tokens.push(Token::Operator(Operator::BitwiseAnd));
+            }
+        },
 
-            '?' => tokens.push(Token::TernaryCondition),
+        '|' => {
+            if let Some('|') = chars.peek() {
+                chars.next();
+                tokens.push(Token::Operator(Operator::Or));
+            } else if let Some('>') = chars.peek() {
+                chars.next();
+                tokens.push(Token::Pipe);
+            } else if let Some('=') = chars.peek() {
+                chars.next();
+                tokens.push(Token::BinaryAssign(Operator::BitwiseOr));
+            } else {
+                tokens.push(Token::Operator(Operator::BitwiseOr));
+            }
+        },
 
-            '\'' => read_char_literal(&mut chars, &mut tokens),
-            '"' => read_string_literal(&mut chars, &mut tokens),
-            '0'..='9' => read_number_literal(c, &mut chars, &mut tokens),
-            'a'..='z' | 'A'..='Z' | '_' => read_alphanumeric_sequence(c, &mut chars, &mut tokens),
+        '^' => {
+            if let Some('=') = chars.peek() {
+                chars.next();
+                tokens.push(Token::BinaryAssign(Operator::BitwiseXor));
+            } else {
+                tokens.push(Token::Operator(Operator::BitwiseXor));
+            }
+        },
 
-            ' ' | '\t' | '\n' | '\r' => eat_whitespace(c, &mut chars, &mut tokens, true),
-            '#' => eat_inline_comment(&mut chars, &mut tokens),
+        '~' => tokens.push(Token::Operator(Operator::BitwiseNot)),
 
-            _ => panic!("Unexpected character: {}", c),
+        '!' => {
+            if let Some('=') = chars.peek() {
+                chars.next();
+                tokens.push(Token::Operator(Operator::NotEqual));
+            } else {
+                tokens.push(Token::Operator(Operator::Not));
+            }
         }
-    }
 
-    tokens.push(Token::EndOfModule);
-    tokens
-}
+        '<' => {
+            if let Some('<') = chars.peek() {
+                chars.next();
+                tokens.push(Token::Operator(Operator::BitwiseLeftShift));
+            } else if let Some('=') = chars.peek() {
+                chars.next();
+                tokens.push(Token::Operator(Operator::LessThanOrEqual));
+            } else {
+                tokens.push(Token::Operator(Operator::LessThan));
+            }
+        },
 
+        '>' => {
+            if let Some('>') = chars.peek() {
+                chars.next();
+                tokens.push(Token::Operator(Operator::BitwiseRightShift));
+            } else if let Some('=') = chars.peek() {
+                chars.next();
+                tokens.push(Token::Operator(Operator::GreaterThanOrEqual));
+            } else {
+                tokens.push(Token::Operator(Operator::GreaterThan));
+            }
+        },
 
-fn read_char_literal(chars: &mut Peekable<Chars>, tokens: &mut Vec<Token>) {
-    let char = chars.next();
-    match char {
-        Some('\'') => panic!("Empty character literal"),
-        Some('\\') => {
-            let escaped_char = convert_escaped_char(chars.next());
-            tokens.push(Token::Literal(Literal::Char(escaped_char)));
+        '=' => {
+            if let Some('=') = chars.peek() {
+                chars.next();
+                tokens.push(Token::Operator(Operator::Equal));
+            } else {
+                tokens.push(Token::Assign);
+            }
         },
-        Some(c) => tokens.push(Token::Literal(Literal::Char(c))),
-        None => panic!("Unexpected end of file"),
+
+        '?' => tokens.push(Token::TernaryCondition),
+
+        '\'' => read_char_literal(chars, tokens)?,
+        '"' => read_string_literal(chars, tokens)?,
+        'r' if is_raw_string_start(chars) => read_raw_string_literal(chars, tokens)?,
+        '0'..='9' => read_number_literal(c, chars, tokens, options)?,
+        'a'..='z' | 'A'..='Z' | '_' => read_alphanumeric_sequence(c, chars, tokens),
+
+        ' ' | '\t' | '\n' | '\r' => eat_whitespace(c, chars, tokens, true, options.trivia),
+        '#' => {
+            let comment = eat_inline_comment(chars);
+            if options.trivia {
+                tokens.push(comment_token(comment, tokens));
+            }
+        },
+
+        _ => return Err(format!("Unexpected character: {}", c)),
     }
+    Ok(())
+}
+
 
-    if chars.next() != Some('\'') {
-        panic!("Character literal must contain only one character");
+// A malformed literal is still read up to its closing quote, so that lexing can carry on after it,
+// but it gives the error instead of a token
+fn read_char_literal(chars: &mut SourceChars, tokens: &mut Vec<Token>) -> Result<(), String> {
+    let char = match chars.next() {
+        Some('\'') => return Err("Empty character literal".to_string()),
+        Some('\\') => convert_escaped_char(chars),
+        Some(c) => Ok(c),
+        None => return Err("Unexpected end of file".to_string()),
+    };
+
+    if let Err(message) = char {
+        skip_past_quote(chars, '\'');
+        return Err(message);
+    }
+    if chars.next_if_eq(&'\'').is_none() {
+        skip_past_quote(chars, '\'');
+        return Err("Character literal must contain only one character".to_string());
     }
+
+    tokens.push(Token::Literal(Literal::Char(char?)));
+    Ok(())
 }
 
-fn read_string_literal(chars: &mut Peekable<Chars>, tokens: &mut Vec<Token>) {
+fn skip_past_quote(chars: &mut SourceChars, quote: char) {
+    while let Some(&c) = chars.peek() {
+        if c == '\n' {
+            break;
+        }
+        chars.next();
+        if c == quote {
+            break;
+        }
+    }
+}
+
+fn read_string_literal(chars: &mut SourceChars, tokens: &mut Vec<Token>) -> Result<(), String> {
     let mut string = String::new();
+    let mut error = None;
+    let mut is_closed = false;
 
     while let Some(c) = chars.next() {
         match c {
-            '\\' => string.push(convert_escaped_char(chars.next())),
-            '"' => break,
+            '\\' => match convert_escaped_char(chars) {
+                Ok(c) => string.push(c),
+                Err(message) => { error.get_or_insert(message); },
+            },
+            '"' => {
+                is_closed = true;
+                break;
+            },
             _ => string.push(c),
         }
     }
 
+    if !is_closed {
+        return Err("Unterminated string literal".to_string());
+    }
+    if let Some(message) = error {
+        return Err(message);
+    }
     tokens.push(Token::Literal(Literal::String(string)));
+    Ok(())
 }
 
 
-fn convert_escaped_char(char: Option<char>) -> char {
-    return match char {
-        Some('n') => '\n',
-        Some('r') => '\r',
-        Some('t') => '\t',
-        Some('\'') => '\'',
-        Some('"') => '"',
-        Some('\\') => '\\',
-        Some('0') => '\0',
-        Some(_) => panic!("Unrecognised escape sequence"),
-        None => panic!("Unexpected end of file"),
+// Malformed source gives an error token when lexing leniently, and stops lexing otherwise
+fn recover(message: String, span: Span, tokens: &mut Vec<Token>, options: &LexOptions) -> Result<(), LexError> {
+    if options.lenient {
+        tokens.push(Token::Error(message));
+        Ok(())
+    } else {
+        Err(LexError::new(&message, span))
     }
 }
 
 
-fn read_number_literal(current: char, chars: &mut Peekable<Chars>, tokens: &mut Vec<Token>) {
+fn is_raw_string_start(chars: &SourceChars) -> bool {
+    let mut lookahead = chars.clone().skip_while(|c| *c == '#');
+    lookahead.next() == Some('"')
+}
+
+
+// Raw strings keep backslashes as they are. Wrapping the quotes in hashes, like r#"..."#,
+// allows the string to contain quotes, since it only ends at a quote followed by the same
+// number of hashes.
+fn read_raw_string_literal(chars: &mut SourceChars, tokens: &mut Vec<Token>) -> Result<(), String> {
+    let mut hashes = 0;
+    while chars.next_if_eq(&'#').is_some() {
+        hashes += 1;
+    }
+
+    if chars.next() != Some('"') {
+        return Err("Expected a double quote to start the raw string literal".to_string());
+    }
+
+    let mut string = String::new();
+
+    loop {
+        match chars.next() {
+            Some('"') => {
+                let mut closing_hashes = 0;
+                while closing_hashes < hashes && chars.next_if_eq(&'#').is_some() {
+                    closing_hashes += 1;
+                }
+                if closing_hashes == hashes {
+                    break;
+                }
+                string.push('"');
+                string.push_str(&"#".repeat(closing_hashes));
+            },
+            Some(c) => string.push(c),
+            None => return Err("Unterminated raw string literal".to_string()),
+        }
+    }
+
+    tokens.push(Token::Literal(Literal::String(string)));
+    Ok(())
+}
+
+
+// Reads the escape sequence after a backslash. Besides the single character escapes, \x7f gives an
+// ASCII character from two hex digits and \u{1F437} gives any unicode character from up to six.
+fn convert_escaped_char(chars: &mut SourceChars) -> Result<char, String> {
+    match chars.next() {
+        Some('n') => Ok('\n'),
+        Some('r') => Ok('\r'),
+        Some('t') => Ok('\t'),
+        Some('\'') => Ok('\''),
+        Some('"') => Ok('"'),
+        Some('\\') => Ok('\\'),
+        Some('0') => Ok('\0'),
+        Some('a') => Ok('\x07'),
+        Some('b') => Ok('\x08'),
+        Some('f') => Ok('\x0C'),
+        Some('x') => convert_hex_escape(chars),
+        Some('u') => convert_unicode_escape(chars),
+        Some(c) => Err(format!("Unrecognised escape sequence \\{}", c)),
+        None => Err("Unexpected end of file".to_string()),
+    }
+}
+
+
+fn convert_hex_escape(chars: &mut SourceChars) -> Result<char, String> {
+    let mut digits = String::new();
+    while digits.len() < 2 {
+        match chars.peek() {
+            Some(&c) if c.is_ascii_hexdigit() => {
+                digits.push(c);
+                chars.next();
+            },
+            _ => break,
+        }
+    }
+
+    match u8::from_str_radix(&digits, 16) {
+        Ok(code) if digits.len() == 2 && code <= 0x7F => Ok(code as char),
+        _ => Err(format!("Invalid escape sequence \\x{}, expected two hex digits up to 7f", digits)),
+    }
+}
+
+
+// Reads up to the closing brace, stopping early at the end of the literal or line so a missing
+// brace doesn't swallow the rest of the source
+fn convert_unicode_escape(chars: &mut SourceChars) -> Result<char, String> {
+    if chars.next_if_eq(&'{').is_none() {
+        return Err("Expected { after \\u in a unicode escape".to_string());
+    }
+
+    let mut digits = String::new();
+    loop {
+        match chars.peek() {
+            Some('}') => {
+                chars.next();
+                break;
+            },
+            Some('\'' | '"' | '\n') | None => return Err(format!("Unterminated unicode escape \\u{{{}", digits)),
+            Some(&c) => {
+                digits.push(c);
+                chars.next();
+            },
+        }
+    }
+
+    let code = match digits.len() {
+        1..=6 => u32::from_str_radix(&digits, 16).ok(),
+        _ => None,
+    };
+    code.and_then(char::from_u32)
+        .ok_or_else(|| format!("Invalid unicode escape \\u{{{}}}", digits))
+}
+
+
+fn read_number_literal(current: char, chars: &mut SourceChars, tokens: &mut Vec<Token>, options: &LexOptions) -> Result<(), String> {
     let mut number = String::new();
     number.push(current);
 
     let mut is_float = current == '.';
     let mut is_exponent = false;
 
-    while let Some(c) = chars.peek() {
+    while let Some(&c) = chars.peek() {
         match c {
-            '0'..='9' => number.push(*c),
+            '0'..='9' => number.push(c),
             '_' => (),
+            // A second dot starts a range, so 1..2 is the integer 1 followed by a range
+            '.' if starts_range(chars) => break,
             '.' => {
                 if is_float {
-                    panic!("Unexpected extra decimal point in number literal");
+                    skip_number(chars);
+                    return Err("Unexpected extra decimal point in number literal".to_string());
                 } else {
                     is_float = true;
-                    number.push(*c);
+                    number.push(c);
                 }
             },
             'E' | 'e' => {
                 is_float = true;
                 is_exponent = true;
-                number.push(*c);
+                number.push(c);
                 chars.next();
                 break;
             }
@@ -264,15 +478,84 @@ fn read_number_literal(current: char, chars: &mut Peekable<Chars>, tokens: &mut
         read_exponent(chars, &mut number);
     }
 
+    let literal = match read_number_suffix(chars) {
+        Some(number_type) => typed_number_literal(&number, is_float, number_type)?,
+        None if is_float => Literal::Float(parse_float(&number)?),
+        None => match number.parse() {
+            Ok(value) => Literal::Integer(value),
+            Err(_) if options.promote_large_integers => Literal::Float(parse_float(&number)?),
+            Err(_) => return Err(format!("Integer literal {} is out of range", number)),
+        },
+    };
+    tokens.push(Token::Literal(literal));
+    Ok(())
+}
+
+
+// Skips the rest of a malformed number, so that lexing can carry on after it
+fn skip_number(chars: &mut SourceChars) {
+    while let Some(&c) = chars.peek() {
+        if !(c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+            break;
+        }
+        chars.next();
+    }
+}
+
+
+// Whether the dot that is next is followed by another one
+fn starts_range(chars: &SourceChars) -> bool {
+    let mut ahead = chars.clone();
+    ahead.next();
+    ahead.peek() == Some(&'.')
+}
+
+
+// Only consumes the characters after a number if they make up a known type suffix
+fn read_number_suffix(chars: &mut SourceChars) -> Option<NumberType> {
+    let suffix: String = chars.clone()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+
+    let number_type = NumberType::from_suffix(&suffix)?;
+    for _ in 0..suffix.len() {
+        chars.next();
+    }
+    Some(number_type)
+}
+
+
+fn typed_number_literal(number: &str, is_float: bool, number_type: NumberType) -> Result<Literal, String> {
+    if number_type.is_float() {
+        let value = parse_float(number)?;
+        let in_range = match number_type {
+            NumberType::F32 => (value as f32).is_finite(),
+            _ => value.is_finite(),
+        };
+        if !in_range {
+            return Err(format!("Number literal {}{} is out of range", number, number_type.suffix()));
+        }
+        return Ok(Literal::TypedFloat(value, number_type));
+    }
+
     if is_float {
-        tokens.push(Token::Literal(Literal::Float(number.parse().unwrap())));
-    } else {
-        tokens.push(Token::Literal(Literal::Integer(number.parse().unwrap())));
+        return Err(format!("Number literal {} cannot have the integer suffix {}", number, number_type.suffix()));
     }
+
+    match number.parse::<u64>() {
+        Ok(value) if Some(value) <= number_type.max_integer() => Ok(Literal::TypedInteger(value, number_type)),
+        _ => Err(format!("Number literal {}{} is out of range", number, number_type.suffix())),
+    }
+}
+
+
+// An exponent without any digits, as in 1e or 1e+, isn't a number
+fn parse_float(number: &str) -> Result<f64, String> {
+    number.parse().map_err(|_| format!("Malformed number literal {}", number))
 }
 
 
-fn read_exponent(chars: &mut Peekable<Chars>, number: &mut String) {
+fn read_exponent(chars: &mut SourceChars, number: &mut String) {
     if let Some(c) = chars.peek() {
         match c {
             '+' | '-' => {
@@ -293,7 +576,7 @@ fn read_exponent(chars: &mut Peekable<Chars>, number: &mut String) {
 }
 
 
-fn read_alphanumeric_sequence(current: char, chars: &mut Peekable<Chars>, tokens: &mut Vec<Token>) {
+fn read_alphanumeric_sequence(current: char, chars: &mut SourceChars, tokens: &mut Vec<Token>) {
     let mut identifier = String::new();
     identifier.push(current);
 
@@ -322,7 +605,7 @@ fn match_keyword_or_literal(identifier: &str) -> Token {
 }
 
 
-fn eat_whitespace(current: char, chars: &mut Peekable<Chars>, tokens: &mut Vec<Token>, allow_newline: bool) {
+fn eat_whitespace(current: char, chars: &mut SourceChars, tokens: &mut Vec<Token>, allow_newline: bool, keep_comments: bool) {
     let mut is_newline = current == '\n' || current == '\r';
     while let Some(c) = chars.peek() {
         match c {
@@ -332,7 +615,16 @@ fn eat_whitespace(current: char, chars: &mut Peekable<Chars>, tokens: &mut Vec<T
                 chars.next();
             },
             '#' => {
-                eat_inline_comment(chars, tokens);
+                chars.next();
+                let comment = eat_inline_comment(chars);
+                if keep_comments {
+                    // A comment on its own line must not be mistaken for one trailing the previous line
+                    if allow_newline && is_newline && tokens.last() != Some(&Token::Newline) {
+                        tokens.push(Token::Newline);
+                    }
+                    let token = if is_newline { Token::Comment(comment) } else { comment_token(comment, tokens) };
+                    tokens.push(token);
+                }
             },
             _ => break,
         };
@@ -343,40 +635,70 @@ fn eat_whitespace(current: char, chars: &mut Peekable<Chars>, tokens: &mut Vec<T
 }
 
 
-fn eat_inline_comment(chars: &mut Peekable<Chars>, tokens: &mut Vec<Token>) {
+// A comment on the same line as the code before it trails that code
+fn comment_token(comment: String, tokens: &[Token]) -> Token {
+    match tokens.last() {
+        None | Some(Token::Newline) | Some(Token::Comment(_)) => Token::Comment(comment),
+        Some(_) => Token::TrailingComment(comment),
+    }
+}
+
+
+fn eat_inline_comment(chars: &mut SourceChars) -> String {
+    let mut comment = String::new();
     while let Some(c) = chars.peek() {
         match c {
             '\n' | '\r' => break,
-            _ => chars.next(),
+            _ => comment.push(*c),
         };
+        chars.next();
     }
+    comment.trim().to_string()
 }
 
 
-fn eat_block_comment(chars: &mut Peekable<Chars>) {
+// A star can only be a multiplication after an operand, so one that starts an element of a list is a spread
+pub(crate) fn is_spread_position(previous: Option<&Token>) -> bool {
+    matches!(previous, Some(Token::OpenSquareBracket | Token::OpenParen | Token::ListSeparator | Token::Newline))
+}
+
+
+fn eat_block_comment(chars: &mut SourceChars) -> String {
+    chars.next_if_eq(&'*');  // Consume the star opening the comment
+
+    let mut comment = String::new();
     while let Some(c) = chars.next() {
-        match c {
-            '*' => {
-                if let Some('/') = chars.peek() {
-                    chars.next();
-                    break;
-                }
-            },
-            _ => (),
+        if c == '*' && chars.peek() == Some(&'/') {
+            chars.next();
+            break;
         }
+        comment.push(c);
     }
+
+    comment.lines()
+        .map(str::trim)
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim()
+        .to_string()
 }
 
 
 #[cfg(test)]
 mod test {
-    use crate::tokens;
-
     use super::*;
 
+    #[test]
+    fn test_token_spans() {
+        let tokens = tokenize("ab = 'c'\n", &LexOptions::default()).unwrap();
+        let spans: Vec<Span> = tokens.iter().map(|token| token.span).collect();
+
+        assert_eq!(spans, vec![Span::new(0, 2), Span::new(3, 4), Span::new(5, 8), Span::new(8, 9), Span::new(9, 9)]);
+    }
+
     #[test]
     fn test_read_valid_alphanumeric_sequence() {
-        let mut chars = "read_this but don't read this".chars().peekable();
+        let mut chars = SourceChars::new("read_this but don't read this");
         let mut tokens = Vec::new();
 
         read_alphanumeric_sequence('_', &mut chars, &mut tokens);
@@ -387,7 +709,7 @@ mod test {
 
     #[test]
     fn test_read_keyword() {
-        let mut chars = "un name(params)".chars().peekable();
+        let mut chars = SourceChars::new("un name(params)");
         let mut tokens = Vec::new();
 
         read_alphanumeric_sequence('f', &mut chars, &mut tokens);
@@ -398,7 +720,7 @@ mod test {
 
     #[test]
     fn test_read_identifier_starting_with_keyword() {
-        let mut chars = "un_name".chars().peekable();
+        let mut chars = SourceChars::new("un_name");
         let mut tokens = Vec::new();
 
         read_alphanumeric_sequence('f', &mut chars, &mut tokens);
@@ -408,7 +730,7 @@ mod test {
 
     #[test]
     fn test_read_literal() {
-        let mut chars = "alse".chars().peekable();
+        let mut chars = SourceChars::new("alse");
         let mut tokens = Vec::new();
 
         read_alphanumeric_sequence('f', &mut chars, &mut tokens);
@@ -418,10 +740,10 @@ mod test {
 
     #[test]
     fn test_read_many_digit_integer() {
-        let mut chars = "234+3".chars().peekable();
+        let mut chars = SourceChars::new("234+3");
         let mut tokens = Vec::new();
 
-        read_number_literal('1', &mut chars, &mut tokens);
+        read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Integer(1234))]);
         assert_eq!(chars.next(), Some('+'));
@@ -429,10 +751,10 @@ mod test {
 
     #[test]
     fn test_read_single_digit() {
-        let mut chars = " but this is not an integer".chars().peekable();
+        let mut chars = SourceChars::new(" but this is not an integer");
         let mut tokens = Vec::new();
 
-        read_number_literal('1', &mut chars, &mut tokens);
+        read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Integer(1))]);
         assert_eq!(chars.next(), Some(' '));
@@ -440,51 +762,63 @@ mod test {
 
     #[test]
     fn test_read_integer_with_underscores() {
-        let mut chars = "23_456_789".chars().peekable();
+        let mut chars = SourceChars::new("23_456_789");
         let mut tokens = Vec::new();
 
-        read_number_literal('1', &mut chars, &mut tokens);
+        read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Integer(123456789))]);
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_read_float() {
-        let mut chars = ".141592".chars().peekable();
+        let mut chars = SourceChars::new(".141592");
         let mut tokens = Vec::new();
 
-        read_number_literal('3', &mut chars, &mut tokens);
+        read_number_literal('3', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Float(3.141592))]);
     }
 
     #[test]
     fn test_read_bigger_float() {
-        let mut chars = "234.5678".chars().peekable();
+        let mut chars = SourceChars::new("234.5678");
         let mut tokens = Vec::new();
 
-        read_number_literal('1', &mut chars, &mut tokens);
+        read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Float(1234.5678))]);
     }
 
     #[test]
     fn test_read_integer_as_a_float() {
-        let mut chars = "234. something else".chars().peekable();
+        let mut chars = SourceChars::new("234. something else");
         let mut tokens = Vec::new();
 
-        read_number_literal('1', &mut chars, &mut tokens);
+        read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Float(1234.0))]);
         assert_eq!(chars.next(), Some(' '));
     }
 
+    #[test]
+    fn test_integer_before_a_range_is_not_a_float() {
+        let mut chars = SourceChars::new("..2");
+        let mut tokens = Vec::new();
+
+        read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
+
+        assert_eq!(tokens, vec![Token::Literal(Literal::Integer(1))]);
+        assert_eq!(chars.next(), Some('.'));
+    }
+
     #[test]
     fn test_read_scientific_notation_big() {
-        let mut chars = ".2345E+67 and some more".chars().peekable();
+        let mut chars = SourceChars::new(".2345E+67 and some more");
         let mut tokens = Vec::new();
 
-        read_number_literal('1', &mut chars, &mut tokens);
+        read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Float(1.2345E+67))]);
         assert_eq!(chars.next(), Some(' '));
@@ -492,81 +826,298 @@ mod test {
 
     #[test]
     fn test_read_scientific_notation_tiny() {
-        let mut chars = ".2345e-67".chars().peekable();
+        let mut chars = SourceChars::new(".2345e-67");
         let mut tokens = Vec::new();
 
-        read_number_literal('1', &mut chars, &mut tokens);
+        read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Float(1.2345E-67))]);
     }
 
     #[test]
     fn test_read_scientific_notation_no_symbol() {
-        let mut chars = ".2345e67".chars().peekable();
+        let mut chars = SourceChars::new(".2345e67");
         let mut tokens = Vec::new();
 
-        read_number_literal('1', &mut chars, &mut tokens);
+        read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Float(1.2345E+67))]);
     }
 
+    #[test]
+    fn test_read_integer_with_type_suffix() {
+        let mut chars = SourceChars::new("55u8+1");
+        let mut tokens = Vec::new();
+
+        read_number_literal('2', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
+
+        assert_eq!(tokens, vec![Token::Literal(Literal::TypedInteger(255, NumberType::U8))]);
+        assert_eq!(chars.next(), Some('+'));
+    }
+
+    #[test]
+    fn test_read_float_with_type_suffix() {
+        let mut chars = SourceChars::new(".0f32 ");
+        let mut tokens = Vec::new();
+
+        read_number_literal('3', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
+
+        assert_eq!(tokens, vec![Token::Literal(Literal::TypedFloat(3.0, NumberType::F32))]);
+        assert_eq!(chars.next(), Some(' '));
+    }
+
+    #[test]
+    fn test_read_integer_with_float_suffix() {
+        let mut chars = SourceChars::new("f64");
+        let mut tokens = Vec::new();
+
+        read_number_literal('5', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
+
+        assert_eq!(tokens, vec![Token::Literal(Literal::TypedFloat(5.0, NumberType::F64))]);
+    }
+
+    #[test]
+    fn test_unknown_suffix_is_not_consumed() {
+        let mut chars = SourceChars::new("in");
+        let mut tokens = Vec::new();
+
+        read_number_literal('5', &mut chars, &mut tokens, &LexOptions::default()).unwrap();
+
+        assert_eq!(tokens, vec![Token::Literal(Literal::Integer(5))]);
+        assert_eq!(chars.next(), Some('i'));
+    }
+
+    #[test]
+    fn test_read_exponent_without_digits() {
+        for (source, number) in [("e", "1e"), ("e+", "1e+"), ("e-f64", "1e-")] {
+            let mut chars = SourceChars::new(source);
+            let mut tokens = Vec::new();
+
+            let result = read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default());
+
+            assert_eq!(result, Err(format!("Malformed number literal {}", number)));
+        }
+    }
+
+    #[test]
+    fn test_read_out_of_range_typed_integer() {
+        let mut chars = SourceChars::new("56u8");
+        let mut tokens = Vec::new();
+
+        let result = read_number_literal('2', &mut chars, &mut tokens, &LexOptions::default());
+
+        assert_eq!(result, Err("Number literal 256u8 is out of range".to_string()));
+    }
+
+    #[test]
+    fn test_read_large_integer_in_strict_mode() {
+        let mut chars = SourceChars::new("234567890123456789012345");
+        let mut tokens = Vec::new();
+
+        let result = read_number_literal('1', &mut chars, &mut tokens, &LexOptions::default());
+
+        assert_eq!(result, Err("Integer literal 1234567890123456789012345 is out of range".to_string()));
+    }
+
+    #[test]
+    fn test_read_large_integer_promoted_to_float() {
+        let mut chars = SourceChars::new("234567890123456789012345");
+        let mut tokens = Vec::new();
+        let options = LexOptions { promote_large_integers: true, ..LexOptions::default() };
+
+        read_number_literal('1', &mut chars, &mut tokens, &options).unwrap();
+
+        assert_eq!(tokens, vec![Token::Literal(Literal::Float(1234567890123456789012345.0))]);
+    }
+
     #[test]
     fn test_read_char_literal() {
-        let mut chars = "a'".chars().peekable();
+        let mut chars = SourceChars::new("a'");
         let mut tokens = Vec::new();
 
-        read_char_literal(&mut chars, &mut tokens);
+        read_char_literal(&mut chars, &mut tokens).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Char('a'))]);
     }
 
     #[test]
     fn test_read_escaped_char() {
-        let mut chars = "\\n'".chars().peekable();
+        let mut chars = SourceChars::new("\\n'");
         let mut tokens = Vec::new();
 
-        read_char_literal(&mut chars, &mut tokens);
+        read_char_literal(&mut chars, &mut tokens).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::Char('\n'))]);
     }
 
+    fn escape(text: &str) -> Result<char, String> {
+        convert_escaped_char(&mut SourceChars::new(text))
+    }
+
     #[test]
     fn test_convert_escaped_char() {
-        assert_eq!(convert_escaped_char(Some('n')), '\n');
-        assert_eq!(convert_escaped_char(Some('r')), '\r');
-        assert_eq!(convert_escaped_char(Some('t')), '\t');
-        assert_eq!(convert_escaped_char(Some('\'')), '\'');
-        assert_eq!(convert_escaped_char(Some('"')), '"');
-        assert_eq!(convert_escaped_char(Some('\\')), '\\');
-        assert_eq!(convert_escaped_char(Some('0')), '\0');
+        assert_eq!(escape("n"), Ok('\n'));
+        assert_eq!(escape("r"), Ok('\r'));
+        assert_eq!(escape("t"), Ok('\t'));
+        assert_eq!(escape("'"), Ok('\''));
+        assert_eq!(escape("\""), Ok('"'));
+        assert_eq!(escape("\\"), Ok('\\'));
+        assert_eq!(escape("0"), Ok('\0'));
+    }
+
+    #[test]
+    fn test_convert_control_char_escapes() {
+        assert_eq!(escape("a"), Ok('\x07'));
+        assert_eq!(escape("b"), Ok('\x08'));
+        assert_eq!(escape("f"), Ok('\x0C'));
+    }
+
+    #[test]
+    fn test_convert_hex_and_unicode_escapes() {
+        assert_eq!(escape("x41"), Ok('A'));
+        assert_eq!(escape("x7f"), Ok('\x7F'));
+        assert_eq!(escape("u{e9}"), Ok('é'));
+        assert_eq!(escape("u{1F437}"), Ok('🐷'));
+    }
+
+    #[test]
+    fn test_malformed_escapes_are_errors() {
+        assert_eq!(escape("q"), Err("Unrecognised escape sequence \\q".to_string()));
+        assert_eq!(escape("x4"), Err("Invalid escape sequence \\x4, expected two hex digits up to 7f".to_string()));
+        assert_eq!(escape("x80"), Err("Invalid escape sequence \\x80, expected two hex digits up to 7f".to_string()));
+        assert_eq!(escape("u41"), Err("Expected { after \\u in a unicode escape".to_string()));
+        assert_eq!(escape("u{ZZZ}"), Err("Invalid unicode escape \\u{ZZZ}".to_string()));
+        assert_eq!(escape("u{D800}"), Err("Invalid unicode escape \\u{D800}".to_string()));
+        assert_eq!(escape("u{41\""), Err("Unterminated unicode escape \\u{41".to_string()));
+        assert_eq!(escape(""), Err("Unexpected end of file".to_string()));
+    }
+
+    #[test]
+    fn test_read_control_char_escapes_in_literals() {
+        let mut chars = SourceChars::new("\\a\\b\\f\"");
+        let mut tokens = Vec::new();
+        read_string_literal(&mut chars, &mut tokens).unwrap();
+        assert_eq!(tokens, vec![Token::Literal(Literal::String("\x07\x08\x0C".to_string()))]);
+
+        let mut chars = SourceChars::new("\\b'");
+        let mut tokens = Vec::new();
+        read_char_literal(&mut chars, &mut tokens).unwrap();
+        assert_eq!(tokens, vec![Token::Literal(Literal::Char('\x08'))]);
+    }
+
+    #[test]
+    fn test_bad_escape_is_an_error_token_when_lenient() {
+        let options = LexOptions { lenient: true, ..LexOptions::default() };
+        let tokens: Vec<Token> = tokenize("a = '\\q'\nb = \"\\u{ZZZ} c\"\n", &options).unwrap()
+            .into_iter()
+            .map(|token| token.node)
+            .collect();
+
+        assert_eq!(tokens, vec![
+            Token::Identifier(Identifier::Simple("a".to_string())),
+            Token::Assign,
+            Token::Error("Unrecognised escape sequence \\q".to_string()),
+            Token::Newline,
+            Token::Identifier(Identifier::Simple("b".to_string())),
+            Token::Assign,
+            Token::Error("Invalid unicode escape \\u{ZZZ}".to_string()),
+            Token::Newline,
+            Token::EndOfModule,
+        ]);
+    }
+
+    #[test]
+    fn test_malformed_source_is_an_error_token_when_lenient() {
+        let options = LexOptions { lenient: true, ..LexOptions::default() };
+        let tokens: Vec<Token> = tokenize("c = 'ab'\ni++\nx = `\n", &options).unwrap()
+            .into_iter()
+            .map(|token| token.node)
+            .collect();
+
+        assert_eq!(tokens, vec![
+            Token::Identifier(Identifier::Simple("c".to_string())),
+            Token::Assign,
+            Token::Error("Character literal must contain only one character".to_string()),
+            Token::Newline,
+            Token::Identifier(Identifier::Simple("i".to_string())),
+            Token::Error("'++' is not supported, use '+= 1' instead".to_string()),
+            Token::Newline,
+            Token::Identifier(Identifier::Simple("x".to_string())),
+            Token::Assign,
+            Token::Error("Unexpected character: `".to_string()),
+            Token::Newline,
+            Token::EndOfModule,
+        ]);
+    }
+
+    #[test]
+    fn test_bad_escape_is_a_lex_error_when_strict() {
+        let result = tokenize("a = 1\nb = '\\q'\n", &LexOptions::default());
+        assert_eq!(result, Err(LexError::new("Unrecognised escape sequence \\q", Span::new(10, 14))));
+
+        let result = tokenize("\"\\xZZ\"", &LexOptions::default());
+        assert_eq!(result, Err(LexError::new("Invalid escape sequence \\x, expected two hex digits up to 7f", Span::new(0, 6))));
     }
 
     #[test]
     fn test_read_string_literal() {
-        let mut chars = "this is a string\" but this is not a string".chars().peekable();
+        let mut chars = SourceChars::new("this is a string\" but this is not a string");
         let mut tokens = Vec::new();
 
-        read_string_literal(&mut chars, &mut tokens);
+        read_string_literal(&mut chars, &mut tokens).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::String("this is a string".to_string()))]);
     }
 
     #[test]
     fn test_read_string_literal_with_escaped_doublequote() {
-        let mut chars = "this is a string with a \\\" in it\" but this is not a string".chars().peekable();
+        let mut chars = SourceChars::new("this is a string with a \\\" in it\" but this is not a string");
         let mut tokens = Vec::new();
 
-        read_string_literal(&mut chars, &mut tokens);
+        read_string_literal(&mut chars, &mut tokens).unwrap();
 
         assert_eq!(tokens, vec![Token::Literal(Literal::String("this is a string with a \" in it".to_string()))]);
     }
 
+    #[test]
+    fn test_read_raw_string_literal_keeps_escapes() {
+        let mut chars = SourceChars::new("\"\\n\" but this is not a string");
+        let mut tokens = Vec::new();
+
+        read_raw_string_literal(&mut chars, &mut tokens).unwrap();
+
+        assert_eq!(tokens, vec![Token::Literal(Literal::String("\\n".to_string()))]);
+        assert_eq!(chars.next(), Some(' '));
+    }
+
+    #[test]
+    fn test_read_raw_string_literal_with_hashes() {
+        let mut chars = SourceChars::new("##\"say \"hi\"# to them\"## but this is not a string");
+        let mut tokens = Vec::new();
+
+        read_raw_string_literal(&mut chars, &mut tokens).unwrap();
+
+        assert_eq!(tokens, vec![Token::Literal(Literal::String("say \"hi\"# to them".to_string()))]);
+        assert_eq!(chars.next(), Some(' '));
+    }
+
+    #[test]
+    fn test_raw_string_prefix_is_not_an_identifier() {
+        let tokens = tokenize("r\"C:\\new\\temp\" r", &LexOptions::default()).unwrap();
+
+        assert_eq!(tokens, vec![
+            Token::Literal(Literal::String("C:\\new\\temp".to_string())),
+            Token::Identifier(Identifier::Simple("r".to_string())),
+            Token::EndOfModule,
+        ]);
+    }
+
     #[test]
     fn test_eat_whitespace() {
-        let mut chars = "      \tHello?".chars().peekable();
+        let mut chars = SourceChars::new("      \tHello?");
         let mut tokens = Vec::new();
 
-        eat_whitespace(' ', &mut chars, &mut tokens, true);
+        eat_whitespace(' ', &mut chars, &mut tokens, true, false);
 
         assert_eq!(tokens, vec![]);
         assert_eq!(chars.next(), Some('H'));
@@ -574,10 +1125,10 @@ mod test {
 
     #[test]
     fn test_eat_whitespace_with_one_newline() {
-        let mut chars = "      \nHello?".chars().peekable();
+        let mut chars = SourceChars::new("      \nHello?");
         let mut tokens = Vec::new();
 
-        eat_whitespace(' ', &mut chars, &mut tokens, true);
+        eat_whitespace(' ', &mut chars, &mut tokens, true, false);
 
         assert_eq!(tokens, vec![Token::Newline]);
         assert_eq!(chars.next(), Some('H'));
@@ -585,10 +1136,10 @@ mod test {
 
     #[test]
     fn test_eat_whitespace_with_two_newlines() {
-        let mut chars = "      \n\nHello?".chars().peekable();
+        let mut chars = SourceChars::new("      \n\nHello?");
         let mut tokens = Vec::new();
 
-        eat_whitespace(' ', &mut chars, &mut tokens, true);
+        eat_whitespace(' ', &mut chars, &mut tokens, true, false);
 
         assert_eq!(tokens, vec![Token::Newline]);
         assert_eq!(chars.next(), Some('H'));
@@ -596,21 +1147,91 @@ mod test {
 
     #[test]
     fn test_eat_whitspace_with_just_newline() {
-        let mut chars = "Hello?".chars().peekable();
+        let mut chars = SourceChars::new("Hello?");
         let mut tokens = Vec::new();
 
-        eat_whitespace('\n', &mut chars, &mut tokens, true);
+        eat_whitespace('\n', &mut chars, &mut tokens, true, false);
 
         assert_eq!(tokens, vec![Token::Newline]);
         assert_eq!(chars.next(), Some('H'));
     }
 
+    #[test]
+    fn test_increment_is_rejected() {
+        assert_eq!(
+            tokenize("i++\n", &LexOptions::default()),
+            Err(LexError::new("'++' is not supported, use '+= 1' instead", Span::new(1, 3)))
+        );
+    }
+
+    #[test]
+    fn test_decrement_is_rejected() {
+        assert_eq!(
+            tokenize("--i\n", &LexOptions::default()),
+            Err(LexError::new("'--' is not supported, use '-= 1' instead", Span::new(0, 2)))
+        );
+    }
+
+    #[test]
+    fn test_malformed_source_is_a_lex_error() {
+        let error = |source: &str| tokenize(source, &LexOptions::default()).unwrap_err();
+
+        assert_eq!(error("x = `y`"), LexError::new("Unexpected character: `", Span::new(4, 5)));
+        assert_eq!(error("c = 'ab'"), LexError::new("Character literal must contain only one character", Span::new(4, 8)));
+        assert_eq!(error("c = ''"), LexError::new("Empty character literal", Span::new(4, 6)));
+        assert_eq!(error("s = \"abc"), LexError::new("Unterminated string literal", Span::new(4, 8)));
+        assert_eq!(error("x = 1.2.3 + 1"), LexError::new("Unexpected extra decimal point in number literal", Span::new(4, 9)));
+        assert_eq!(
+            error("x = 99999999999999999999999"),
+            LexError::new("Integer literal 99999999999999999999999 is out of range", Span::new(4, 27))
+        );
+    }
+
+    #[test]
+    fn test_separated_signs_are_allowed() {
+        let tokens: Vec<Token> = tokenize("1 - -2", &LexOptions::default()).unwrap().into_iter().map(|token| token.node).collect();
+
+        assert_eq!(tokens, vec![
+            Token::Literal(Literal::Integer(1)),
+            Token::Operator(Operator::Minus),
+            Token::Operator(Operator::Minus),
+            Token::Literal(Literal::Integer(2)),
+            Token::EndOfModule,
+        ]);
+    }
+
+    #[test]
+    fn test_crlf_line_endings_give_one_newline() {
+        let tokens: Vec<Token> = tokenize("x\r\ny\r\n\r\nz", &LexOptions::default()).unwrap().into_iter().map(|token| token.node).collect();
+
+        assert_eq!(tokens, vec![
+            Token::Identifier(Identifier::Simple("x".to_string())),
+            Token::Newline,
+            Token::Identifier(Identifier::Simple("y".to_string())),
+            Token::Newline,
+            Token::Identifier(Identifier::Simple("z".to_string())),
+            Token::EndOfModule,
+        ]);
+    }
+
+    #[test]
+    fn test_line_continuation_before_crlf() {
+        let tokens: Vec<Token> = tokenize("x \\\r\n  y\r\n", &LexOptions::default()).unwrap().into_iter().map(|token| token.node).collect();
+
+        assert_eq!(tokens, vec![
+            Token::Identifier(Identifier::Simple("x".to_string())),
+            Token::Identifier(Identifier::Simple("y".to_string())),
+            Token::Newline,
+            Token::EndOfModule,
+        ]);
+    }
+
     #[test]
     fn test_eat_whitespace_with_newline_not_allowed() {
-        let mut chars = "      \nHello?".chars().peekable();
+        let mut chars = SourceChars::new("      \nHello?");
         let mut tokens = Vec::new();
 
-        eat_whitespace(' ', &mut chars, &mut tokens, false);
+        eat_whitespace(' ', &mut chars, &mut tokens, false, false);
 
         assert_eq!(tokens, vec![]);
         assert_eq!(chars.next(), Some('H'));
@@ -618,19 +1239,18 @@ mod test {
 
     #[test]
     fn test_eat_inline_comment() {
-        let mut chars = "this is a comment\nBut this is not".chars().peekable();
-        let mut tokens = Vec::new();
+        let mut chars = SourceChars::new("this is a comment\nBut this is not");
 
-        eat_inline_comment(&mut chars, &mut tokens);
+        let comment = eat_inline_comment(&mut chars);
 
-        assert_eq!(tokens, vec![]);
+        assert_eq!(comment, "this is a comment");
         assert_eq!(chars.next(), Some('\n'));
         assert_eq!(chars.next(), Some('B'));
     }
 
     #[test]
     fn test_eat_block_comment() {
-        let mut chars = "*this is a comment */But this is not".chars().peekable();
+        let mut chars = SourceChars::new("*this is a comment */But this is not");
 
         eat_block_comment(&mut chars);
 
@@ -639,21 +1259,54 @@ mod test {
 
     #[test]
     fn test_eat_whitespace_with_an_inline_comment() {
-        let mut chars = "      # this is a comment\n     Hello?".chars().peekable();
+        let mut chars = SourceChars::new("      # this is a comment\n     Hello?");
         let mut tokens = Vec::new();
 
-        eat_whitespace(' ', &mut chars, &mut tokens, true);
+        eat_whitespace(' ', &mut chars, &mut tokens, true, false);
 
         assert_eq!(tokens, vec![Token::Newline]);
         assert_eq!(chars.next(), Some('H'));
     }
 
+    #[test]
+    fn test_eat_whitespace_keeping_comments() {
+        let mut chars = SourceChars::new("   # trailing\n   # own line\n   Hello?");
+        let mut tokens = Vec::new();
+
+        eat_whitespace(' ', &mut chars, &mut tokens, true, true);
+
+        assert_eq!(tokens, vec![
+            Token::Comment("trailing".to_string()),
+            Token::Newline,
+            Token::Comment("own line".to_string()),
+            Token::Newline,
+        ]);
+        assert_eq!(chars.next(), Some('H'));
+    }
+
+    #[test]
+    fn test_eat_whitespace_keeping_a_comment_after_code() {
+        let mut chars = SourceChars::new("  # the value\n  # own line\n  Hello?");
+        let mut tokens = vec![Token::Literal(Literal::Integer(1))];
+
+        eat_whitespace(' ', &mut chars, &mut tokens, true, true);
+
+        assert_eq!(tokens, vec![
+            Token::Literal(Literal::Integer(1)),
+            Token::TrailingComment("the value".to_string()),
+            Token::Newline,
+            Token::Comment("own line".to_string()),
+            Token::Newline,
+        ]);
+        assert_eq!(chars.next(), Some('H'));
+    }
+
     #[test]
     fn test_eat_whitespace_with_a_block_comment() {
-        let mut chars = "      /* this is a comment */      Hello?".chars().peekable();
+        let mut chars = SourceChars::new("      /* this is a comment */      Hello?");
         let mut tokens = Vec::new();
 
-        eat_whitespace(' ', &mut chars, &mut tokens, true);
+        eat_whitespace(' ', &mut chars, &mut tokens, true, false);
 
         assert_eq!(tokens, vec![]);
         assert_eq!(chars.next(), Some('/'));
@@ -1,5 +1,13 @@
 pub mod elements;
 pub mod tokens;
+pub mod span;
 pub mod lexer;
 pub mod tree;
+pub mod tree_printer;
+pub mod source_printer;
 pub mod parser;
+pub mod diagnostics;
+pub mod resolver;
+pub mod typechecker;
+pub mod checker;
+pub mod interpreter;
@@ -1,8 +1,95 @@
 mod repl;
 
+use std::env;
+use std::fs::read_to_string;
+use std::process::ExitCode;
+
+use sheeppig::checker::check_source;
+use sheeppig::lexer::{position, try_tokenize_with_spans, LexOptions};
+use sheeppig::tokens::tokens_to_json;
+
 use repl::repl;
 
 
-fn main() {
-    repl();
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+
+    match args.get(1).map(String::as_str) {
+        Some("check") => match args.get(2) {
+            Some(path) => check(path),
+            None => {
+                eprintln!("Usage: sheeppig check <file>");
+                ExitCode::FAILURE
+            },
+        },
+        Some("--emit-tokens-json") => match args.get(2) {
+            Some(path) => emit_tokens_json(path),
+            None => {
+                eprintln!("Usage: sheeppig --emit-tokens-json <file>");
+                ExitCode::FAILURE
+            },
+        },
+        _ => {
+            repl();
+            ExitCode::SUCCESS
+        },
+    }
+}
+
+
+fn read_source(path: &str) -> Option<String> {
+    match read_to_string(path) {
+        Ok(source) => Some(source),
+        Err(error) => {
+            eprintln!("Failed to read {}: {}", path, error);
+            None
+        },
+    }
+}
+
+
+// Reports every diagnostic for the file without running it, and fails if any of them is an error
+fn check(path: &str) -> ExitCode {
+    let Some(source) = read_source(path) else {
+        return ExitCode::FAILURE;
+    };
+
+    let options = LexOptions::default();
+    let diagnostics = check_source(&source, &options);
+    for diagnostic in &diagnostics {
+        match diagnostic.span {
+            Some(span) => {
+                let start = position(&source, span, &options);
+                println!("{}:{}:{}: {}", path, start.line, start.column, diagnostic);
+            },
+            None => println!("{}: {}", path, diagnostic),
+        }
+    }
+
+    if diagnostics.iter().any(|diagnostic| diagnostic.is_error()) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+
+// Prints the tokens of the file as a JSON array, for tools that want to work with the lexer's output
+fn emit_tokens_json(path: &str) -> ExitCode {
+    let Some(source) = read_source(path) else {
+        return ExitCode::FAILURE;
+    };
+
+    let options = LexOptions::default();
+    match try_tokenize_with_spans(&source, &options) {
+        Ok((tokens, spans)) => {
+            println!("{}", tokens_to_json(&tokens, &spans));
+            ExitCode::SUCCESS
+        },
+        Err(error) => {
+            let start = position(&source, error.span, &options);
+            eprintln!("{}:{}:{}: {}", path, start.line, start.column, error);
+            ExitCode::FAILURE
+        },
+    }
 }
@@ -1,76 +1,296 @@
-use std::iter::Peekable;
-use std::slice::Iter;
-
-use crate::elements::{ Identifier, Literal, Operator, Keyword };
+use crate::elements::{Identifier, Keyword};
 use crate::tokens::Token;
+use crate::parser::token_stream::TokenStream;
+use crate::span::Spanned;
 use crate::tree::{
-    Expression, AtomicExpression, ParenthesizedExpression, FunctionCallExpression
+    Expression, AtomicExpression, ParenthesizedExpression, FunctionCallExpression, ArrayLiteralExpression, MapLiteralExpression, ArrayIndexExpression,
+    ArrayIndex, SpreadExpression, ComprehensionExpression, StructLiteralExpression,
 };
 
-use crate::parser::utils::{ handle_parse_error, handle_parse_error_for_option };
+use crate::parser::errors::ParseError;
+use crate::parser::utils::{ handle_parse_error, handle_parse_error_for_option, peek_is, expect };
 use crate::parser::expression_parser::parse_expression;
+use crate::parser::statement_parser::parse_loop_variables;
 
 
-pub fn parse_atomic(tokens: &mut Peekable<Iter<Token>>) -> Expression {
-    let atom = match tokens.next() {
+pub fn parse_atomic(tokens: &mut TokenStream) -> Result<Spanned<Expression>, ParseError> {
+    let start = tokens.peek_span();
+    let mut atom = match tokens.next() {
         Some(Token::Literal(literal)) => AtomicExpression::Literal(literal.clone()),
 
         Some(Token::OpenParen) => AtomicExpression::Parenthesized(
-            parse_parenthesized(tokens)
+            parse_parenthesized(tokens)?
         ),
 
         Some(Token::Identifier(identifier)) => {
             match tokens.peek() {
                 Some(Token::OpenParen) => AtomicExpression::FunctionCall(
-                    parse_function_call(identifier, tokens)
+                    parse_function_call(identifier, tokens)?
                 ),
 
-                // TODO: Array indexing
+                Some(Token::OpenBrace) if is_struct_literal(identifier, tokens) => AtomicExpression::StructLiteral(
+                    parse_struct_literal(identifier, tokens)?
+                ),
 
                 _ => AtomicExpression::Identifier(identifier.clone()),
             }
         }
 
-        // TODO: Array literals
+        Some(Token::OpenSquareBracket) => parse_array_literal(tokens)?,
+
+        Some(Token::Keyword(Keyword::If)) => AtomicExpression::Parenthesized(ParenthesizedExpression {
+            value: Box::new(parse_if_expression(tokens)?),
+        }),
+
+        // A brace can only start a map in an expression, since blocks are handled as statements
+        Some(Token::OpenBrace) => AtomicExpression::MapLiteral(
+            parse_map_literal(tokens)?
+        ),
+
+        // The lexer has already found what is wrong with a malformed literal
+        Some(token @ Token::Error(message)) => handle_parse_error(tokens, message, token)?,
 
-        token => handle_parse_error_for_option("Expected an atomic expression.", token),
+        token => handle_parse_error_for_option(tokens, "Expected an atomic expression", token)?,
     };
-    Expression::Atomic(atom)
+
+    while peek_is(tokens, &Token::OpenSquareBracket) {
+        tokens.next();
+        atom = AtomicExpression::ArrayIndex(ArrayIndexExpression {
+            array: Box::new(atom),
+            index: parse_array_index(tokens)?,
+        });
+    }
+
+    Ok(Spanned::new(Expression::Atomic(atom), tokens.span_from(start)))
+}
+
+
+fn parse_parenthesized(tokens: &mut TokenStream) -> Result<ParenthesizedExpression, ParseError> {
+    let expression = parse_expression(tokens)?;
+    expect(tokens, Token::CloseParen, "Expected closing parenthesis")?;
+
+    Ok(ParenthesizedExpression{ value: Box::new(expression) })
 }
 
 
-fn parse_parenthesized(tokens: &mut Peekable<Iter<Token>>) -> ParenthesizedExpression {
-    let expression = parse_expression(tokens);
+// An if expression is a ternary written with blocks, like `if c { 1 } else { 2 }`, after the if
+// keyword. It's kept in parentheses, so that it stays a single operand when printed as a ternary.
+// The else branch is required, and each branch holds a single expression.
+fn parse_if_expression(tokens: &mut TokenStream) -> Result<Spanned<Expression>, ParseError> {
+    let condition = parse_expression(tokens)?;
+    let true_value = parse_branch(tokens)?;
 
-    match tokens.peek() {
-        Some(Token::CloseParen) => {
-            tokens.next()
+    let false_value = match tokens.next() {
+        Some(Token::Keyword(Keyword::Elif)) => parse_if_expression(tokens)?,
+        Some(Token::Keyword(Keyword::Else)) if peek_is(tokens, &Token::Keyword(Keyword::If)) => {
+            tokens.next();
+            parse_if_expression(tokens)?
         },
-        _ => handle_parse_error_for_option("Expected closing parenthesis", tokens.peek()),
+        Some(Token::Keyword(Keyword::Else)) => parse_branch(tokens)?,
+        token => handle_parse_error_for_option(tokens, "Expected an else branch in the if expression", token)?,
     };
-    ParenthesizedExpression{ value: Box::new(expression) }
+
+    let span = condition.span.merge(false_value.span);
+    Ok(Spanned::new(Expression::TernaryCondition {
+        condition: Box::new(condition),
+        true_value: Box::new(true_value),
+        false_value: Box::new(false_value),
+    }, span))
 }
 
 
-fn parse_function_call(identifier: &Identifier, tokens: &mut Peekable<Iter<Token>>) -> FunctionCallExpression {
-    let parameters = parse_parameter_list(tokens);
+fn parse_branch(tokens: &mut TokenStream) -> Result<Spanned<Expression>, ParseError> {
+    expect(tokens, Token::OpenBrace, "Expected an open brace before the value of the branch")?;
+    while peek_is(tokens, &Token::Newline) {
+        tokens.next();
+    }
+    let value = parse_expression(tokens)?;
+    while peek_is(tokens, &Token::Newline) {
+        tokens.next();
+    }
+    expect(tokens, Token::CloseBrace, "Expected a closing brace after the value of the branch")?;
+    Ok(value)
+}
 
-    FunctionCallExpression {
-        name: identifier.clone(),
-        parameters,
+
+// Parses the values of an array literal, after the opening bracket
+// A for after the first value makes the array a comprehension rather than a literal
+fn parse_array_literal(tokens: &mut TokenStream) -> Result<AtomicExpression, ParseError> {
+    let mut values = vec![];
+    // Values must be separated by commas, so a missing one is an error rather than two values
+    let mut needs_separator = false;
+
+    while let Some(token) = tokens.peek() {
+        match token {
+            Token::Newline => {tokens.next();},
+            Token::ListSeparator => {
+                tokens.next();
+                needs_separator = false;
+                if let Some(Token::ListSeparator) = tokens.peek() {
+                    handle_parse_error_for_option::<()>(tokens, "Expected an array value", tokens.peek())?;
+                }
+            },
+            Token::CloseSquareBracket => {
+                tokens.next();
+                return Ok(AtomicExpression::ArrayLiteral(ArrayLiteralExpression { values }));
+            },
+            Token::Keyword(Keyword::For) if values.len() == 1 => {
+                let output = values.remove(0);
+                return Ok(AtomicExpression::Comprehension(parse_comprehension(output, tokens)?));
+            },
+            _ if needs_separator => handle_parse_error(tokens, "Expected a comma between array values", token)?,
+            _ => {
+                values.push(parse_list_element(tokens)?);
+                needs_separator = true;
+            },
+        }
+    }
+
+    handle_parse_error_for_option(tokens, "Expected a closing square bracket", None)
+}
+
+
+// Parses the rest of a comprehension, from the for after its output to the closing square bracket
+fn parse_comprehension(output: Expression, tokens: &mut TokenStream) -> Result<ComprehensionExpression, ParseError> {
+    expect(tokens, Token::Keyword(Keyword::For), "Expected for keyword")?;
+    let variables = parse_loop_variables(tokens)?;
+    let iterable = parse_expression(tokens)?;
+
+    let condition = if peek_is(tokens, &Token::Keyword(Keyword::If)) {
+        tokens.next();
+        Some(Box::new(parse_expression(tokens)?))
+    } else {
+        None
+    };
+
+    expect(tokens, Token::CloseSquareBracket, "Expected a closing square bracket after the comprehension")?;
+
+    Ok(ComprehensionExpression {
+        output: Box::new(output.into()),
+        variables,
+        iterable: Box::new(iterable),
+        condition,
+    })
+}
+
+
+// A capitalised name followed by a brace, a field name and a colon on the same line starts a struct
+// literal. Anything else is left alone, so that a condition can be followed by the block it guards.
+fn is_struct_literal(name: &Identifier, tokens: &TokenStream) -> bool {
+    let is_capitalised = match name {
+        Identifier::Simple(name) => name.starts_with(|c: char| c.is_uppercase()),
+        Identifier::Compound(_) => false,
+    };
+
+    let mut lookahead = tokens.clone();
+    is_capitalised
+        && lookahead.next() == Some(&Token::OpenBrace)
+        && matches!(lookahead.next(), Some(Token::Identifier(Identifier::Simple(_))))
+        && lookahead.next() == Some(&Token::Colon)
+}
+
+
+// Parses the fields of a struct literal, from the opening brace after its name
+fn parse_struct_literal(name: &Identifier, tokens: &mut TokenStream) -> Result<StructLiteralExpression, ParseError> {
+    expect(tokens, Token::OpenBrace, "Expected an open brace after the struct name")?;
+    let mut fields = vec![];
+
+    while let Some(token) = tokens.peek() {
+        match token {
+            Token::Newline => {tokens.next();},
+            Token::ListSeparator => {
+                tokens.next();
+                if let Some(Token::ListSeparator) = tokens.peek() {
+                    handle_parse_error_for_option::<()>(tokens, "Expected a struct field", tokens.peek())?;
+                }
+            },
+            Token::CloseBrace => {
+                tokens.next();
+                return Ok(StructLiteralExpression { name: name.clone(), fields });
+            },
+            Token::Identifier(field @ Identifier::Simple(_)) => {
+                tokens.next();
+                expect(tokens, Token::Colon, "Expected a colon after a field name")?;
+                let value = parse_expression(tokens)?.node;
+                fields.push((field.clone(), value));
+            },
+            _ => handle_parse_error(tokens, "Expected a field name", token)?,
+        }
     }
+
+    handle_parse_error_for_option(tokens, "Expected a closing brace", None)
 }
 
 
-fn parse_parameter_list(tokens: &mut Peekable<Iter<Token>>) -> Vec<Expression> {
-    if let Some(token) = tokens.next() {
+// Parses the key and value pairs of a map literal, after the opening brace
+fn parse_map_literal(tokens: &mut TokenStream) -> Result<MapLiteralExpression, ParseError> {
+    let mut entries = vec![];
+
+    while let Some(token) = tokens.peek() {
         match token {
-            Token::OpenParen => {},
-            _ => handle_parse_error("Expected a parameter list starting with an open parenthesis", token),
+            Token::Newline => {tokens.next();},
+            Token::ListSeparator => {
+                tokens.next();
+                if let Some(Token::ListSeparator) = tokens.peek() {
+                    handle_parse_error_for_option::<()>(tokens, "Expected a map entry", tokens.peek())?;
+                }
+            },
+            Token::CloseBrace => {
+                tokens.next();
+                return Ok(MapLiteralExpression { entries });
+            },
+            _ => {
+                let key = parse_expression(tokens)?.node;
+                expect(tokens, Token::Colon, "Expected a colon after a map key")?;
+                let value = parse_expression(tokens)?.node;
+                entries.push((key, value));
+            },
         }
     }
 
-    let mut parameters = vec![];
+    handle_parse_error_for_option(tokens, "Expected a closing brace", None)
+}
+
+
+// Parses either a single index or a slice with optional bounds, after the opening bracket
+fn parse_array_index(tokens: &mut TokenStream) -> Result<ArrayIndex, ParseError> {
+    let start = match tokens.peek() {
+        Some(Token::Colon) => None,
+        _ => Some(Box::new(parse_expression(tokens)?)),
+    };
+
+    let index = match (tokens.next(), start) {
+        (Some(Token::CloseSquareBracket), Some(index)) => return Ok(ArrayIndex::Single(index)),
+        (Some(Token::Colon), start) => {
+            let end = match tokens.peek() {
+                Some(Token::CloseSquareBracket) => None,
+                _ => Some(Box::new(parse_expression(tokens)?)),
+            };
+            ArrayIndex::Slice { start, end }
+        },
+        (token, _) => handle_parse_error_for_option(tokens, "Expected an index or a slice", token)?,
+    };
+
+    expect(tokens, Token::CloseSquareBracket, "Expected a closing square bracket")?;
+    Ok(index)
+}
+
+
+fn parse_function_call(identifier: &Identifier, tokens: &mut TokenStream) -> Result<FunctionCallExpression, ParseError> {
+    let mut call = FunctionCallExpression {
+        name: identifier.clone(),
+        parameters: vec![],
+        keyword_arguments: vec![],
+    };
+    parse_parameter_list(&mut call, tokens)?;
+
+    Ok(call)
+}
+
+
+// A name followed by = passes an argument by name, and every argument after it must be passed by name too
+fn parse_parameter_list(call: &mut FunctionCallExpression, tokens: &mut TokenStream) -> Result<(), ParseError> {
+    expect(tokens, Token::OpenParen, "Expected a parameter list starting with an open parenthesis")?;
 
     while let Some(token) = tokens.peek() {
         match token {
@@ -78,22 +298,44 @@ fn parse_parameter_list(tokens: &mut Peekable<Iter<Token>>) -> Vec<Expression> {
             Token::ListSeparator => {
                 tokens.next();
                 if let Some(Token::ListSeparator) | Some(Token::CloseParen) = tokens.peek() {
-                    handle_parse_error_for_option::<()>("Expected a parameter", tokens.peek());
+                    handle_parse_error_for_option::<()>(tokens, "Expected a parameter", tokens.peek())?;
                 }
             },
             Token::CloseParen => {
                 tokens.next();
                 break;
             }
-            _ => parameters.push(parse_expression(tokens)),
+            Token::Identifier(name @ Identifier::Simple(_)) if tokens.clone().nth(1) == Some(&Token::Assign) => {
+                tokens.next();
+                tokens.next();
+                call.keyword_arguments.push((name.clone(), parse_expression(tokens)?.node));
+            },
+            _ if !call.keyword_arguments.is_empty() => {
+                handle_parse_error_for_option::<()>(tokens, "Expected a keyword argument after a keyword argument", tokens.peek())?;
+            },
+            _ => call.parameters.push(parse_list_element(tokens)?),
         }
     }
-    parameters
+    Ok(())
+}
+
+
+// An element of an array literal or an argument, which may be spread
+fn parse_list_element(tokens: &mut TokenStream) -> Result<Expression, ParseError> {
+    if peek_is(tokens, &Token::Spread) {
+        tokens.next();
+        let value = parse_expression(tokens)?;
+        return Ok(Expression::Spread(SpreadExpression { value: Box::new(value) }));
+    }
+    Ok(parse_expression(tokens)?.node)
 }
 
 
 #[cfg(test)]
 mod test {
+    use crate::elements::{ Literal, Operator };
+    use crate::lexer::tokenize;
+
     use super::*;
 
 
@@ -104,13 +346,13 @@ mod test {
             Token::Newline,
             Token::Literal(Literal::String("This is the next expression".to_string())),
         ];
-        let iter_tokens = &mut tokens.iter().peekable();
+        let iter_tokens = &mut TokenStream::new(&tokens);
 
         let expected = Expression::Atomic(
             AtomicExpression::Literal(Literal::Integer(1))
         );
 
-        assert_eq!(parse_atomic(iter_tokens), expected);
+        assert_eq!(parse_atomic(iter_tokens).unwrap(), expected);
         assert_eq!(Token::Newline, *iter_tokens.next().unwrap());
     }
 
@@ -121,14 +363,207 @@ mod test {
             Token::Operator(Operator::Plus),
             Token::Literal(Literal::String("This is the next expression".to_string()))
         ];
-        let iter_tokens = &mut tokens.iter().peekable();
+        let iter_tokens = &mut TokenStream::new(&tokens);
 
         let expected = Expression::Atomic(
             AtomicExpression::Identifier(Identifier::Simple("identifier".to_string()))
         );
 
-        assert_eq!(parse_atomic(iter_tokens), expected);
+        assert_eq!(parse_atomic(iter_tokens).unwrap(), expected);
         assert_eq!(Token::Operator(Operator::Plus), *iter_tokens.next().unwrap());
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_index() {
+        let tokens = vec![
+            Token::Identifier(Identifier::Simple("s".to_string())),
+            Token::OpenSquareBracket,
+            Token::Literal(Literal::Integer(0)),
+            Token::CloseSquareBracket,
+        ];
+
+        let expected = Expression::Atomic(AtomicExpression::ArrayIndex(ArrayIndexExpression {
+            array: Box::new(AtomicExpression::Identifier(Identifier::Simple("s".to_string()))),
+            index: ArrayIndex::Single(Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(0))).into())),
+        }));
+
+        assert_eq!(parse_atomic(&mut TokenStream::new(&tokens)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_slice() {
+        let tokens = vec![
+            Token::Literal(Literal::String("text".to_string())),
+            Token::OpenSquareBracket,
+            Token::Colon,
+            Token::Literal(Literal::Integer(3)),
+            Token::CloseSquareBracket,
+        ];
+
+        let expected = Expression::Atomic(AtomicExpression::ArrayIndex(ArrayIndexExpression {
+            array: Box::new(AtomicExpression::Literal(Literal::String("text".to_string()))),
+            index: ArrayIndex::Slice {
+                start: None,
+                end: Some(Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3))).into())),
+            },
+        }));
+
+        assert_eq!(parse_atomic(&mut TokenStream::new(&tokens)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_array_literal() {
+        let tokens = vec![
+            Token::OpenSquareBracket,
+            Token::Literal(Literal::Integer(1)),
+            Token::ListSeparator,
+            Token::Literal(Literal::Float(4.5)),
+            Token::CloseSquareBracket,
+            Token::Newline,
+        ];
+        let iter_tokens = &mut TokenStream::new(&tokens);
+
+        let expected = Expression::Atomic(AtomicExpression::ArrayLiteral(ArrayLiteralExpression {
+            values: vec![
+                Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))),
+                Expression::Atomic(AtomicExpression::Literal(Literal::Float(4.5))),
+            ],
+        }));
+
+        assert_eq!(parse_atomic(iter_tokens).unwrap(), expected);
+        assert_eq!(Token::Newline, *iter_tokens.next().unwrap());
+    }
+
+    #[test]
+    fn test_parse_empty_map_literal() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::CloseBrace,
+        ];
+
+        let expected = Expression::Atomic(AtomicExpression::MapLiteral(MapLiteralExpression { entries: vec![] }));
+
+        assert_eq!(parse_atomic(&mut TokenStream::new(&tokens)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_map_literal() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::Literal(Literal::String("a".to_string())),
+            Token::Colon,
+            Token::Literal(Literal::Integer(1)),
+            Token::ListSeparator,
+            Token::Literal(Literal::String("b".to_string())),
+            Token::Colon,
+            Token::Literal(Literal::Integer(2)),
+            Token::CloseBrace,
+        ];
+
+        let expected = Expression::Atomic(AtomicExpression::MapLiteral(MapLiteralExpression {
+            entries: vec![
+                (
+                    Expression::Atomic(AtomicExpression::Literal(Literal::String("a".to_string()))),
+                    Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))),
+                ),
+                (
+                    Expression::Atomic(AtomicExpression::Literal(Literal::String("b".to_string()))),
+                    Expression::Atomic(AtomicExpression::Literal(Literal::Integer(2))),
+                ),
+            ],
+        }));
+
+        assert_eq!(parse_atomic(&mut TokenStream::new(&tokens)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_spread_in_array_literal() {
+        let tokens = vec![
+            Token::OpenSquareBracket,
+            Token::Literal(Literal::Integer(1)),
+            Token::ListSeparator,
+            Token::Spread,
+            Token::Identifier(Identifier::Simple("middle".to_string())),
+            Token::ListSeparator,
+            Token::Literal(Literal::Integer(9)),
+            Token::CloseSquareBracket,
+        ];
+
+        let expected = Expression::Atomic(AtomicExpression::ArrayLiteral(ArrayLiteralExpression {
+            values: vec![
+                Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))),
+                Expression::Spread(SpreadExpression {
+                    value: Box::new(Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("middle".to_string()))).into()),
+                }),
+                Expression::Atomic(AtomicExpression::Literal(Literal::Integer(9))),
+            ],
+        }));
+
+        assert_eq!(parse_atomic(&mut TokenStream::new(&tokens)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_spread_in_function_call() {
+        let tokens = vec![
+            Token::Identifier(Identifier::Simple("f".to_string())),
+            Token::OpenParen,
+            Token::Spread,
+            Token::Identifier(Identifier::Simple("args".to_string())),
+            Token::CloseParen,
+        ];
+
+        let expected = Expression::Atomic(AtomicExpression::FunctionCall(FunctionCallExpression {
+            name: Identifier::Simple("f".to_string()),
+            parameters: vec![
+                Expression::Spread(SpreadExpression {
+                    value: Box::new(Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("args".to_string()))).into()),
+                }),
+            ],
+            keyword_arguments: vec![],
+        }));
+
+        assert_eq!(parse_atomic(&mut TokenStream::new(&tokens)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_keyword_arguments() {
+        let tokens = tokenize("scale(x, factor = 2)");
+        let expected = Expression::Atomic(AtomicExpression::FunctionCall(FunctionCallExpression {
+            name: Identifier::Simple("scale".to_string()),
+            parameters: vec![Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("x".to_string())))],
+            keyword_arguments: vec![(Identifier::Simple("factor".to_string()), Expression::int(2))],
+        }));
+
+        assert_eq!(parse_atomic(&mut TokenStream::new(&tokens)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_positional_argument_after_keyword_argument_is_an_error() {
+        let tokens = tokenize("scale(factor = 2, x)");
+        let result = parse_atomic(&mut TokenStream::new(&tokens));
+
+        assert_eq!(result.unwrap_err().message, "Expected a keyword argument after a keyword argument");
+    }
+
+    #[test]
+    fn test_parse_array_literal_with_negative_values() {
+        let tokens = tokenize("[1, -2, 3]");
+        let expected = Expression::Atomic(AtomicExpression::ArrayLiteral(ArrayLiteralExpression {
+            values: vec![
+                Expression::int(1),
+                Expression::UnaryOperation { operator: Operator::Minus, operand: Box::new(Expression::int(2).into()) },
+                Expression::int(3),
+            ],
+        }));
+
+        assert_eq!(parse_atomic(&mut TokenStream::new(&tokens)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_array_literal_without_separator() {
+        let tokens = tokenize("[1 2]");
+        let result = parse_atomic(&mut TokenStream::new(&tokens));
+
+        assert_eq!(result.unwrap_err().message, "Expected a comma between array values");
+    }
+}
@@ -0,0 +1,29 @@
+use std::fmt;
+
+use crate::span::Span;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    // What was found instead of what was expected, or None at the end of the input
+    pub found: Option<String>,
+    pub span: Span,
+}
+
+
+impl ParseError {
+    pub fn new(message: &str, found: Option<String>, span: Span) -> ParseError {
+        ParseError { message: message.to_string(), found, span }
+    }
+}
+
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.found {
+            Some(found) => write!(f, "Parse error: {}, found {}", self.message, found),
+            None => write!(f, "Parse error: {}, found EOF", self.message),
+        }
+    }
+}
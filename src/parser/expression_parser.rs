@@ -1,70 +1,99 @@
-use std::iter::Peekable;
-use std::slice::Iter;
-
-use crate::elements::Operator;
+use crate::elements::{Identifier, Keyword, Operator};
 use crate::tokens::Token;
-use crate::tree::Expression;
+use crate::parser::token_stream::TokenStream;
+use crate::tree::{Expression, AtomicExpression, CastExpression, AssignmentExpression, FunctionCallExpression};
+use crate::span::Spanned;
 
-use crate::parser::utils::{handle_parse_error, handle_parse_error_for_option};
+use crate::parser::errors::ParseError;
+use crate::parser::utils::{handle_parse_error, handle_parse_error_for_option, handle_expression_parse_error, expect};
 use crate::parser::atomic_parser::parse_atomic;
+use crate::parser::precedence::UNARY_LEVEL;
 
 
-const NUM_PRECEDENCE_LEVELS: usize = 12;
-const PRECEDENCE_TABLE: [&[Operator]; NUM_PRECEDENCE_LEVELS] = [
-    &[Operator::Power],
-    &[], // Unary operators
-    &[Operator::Times, Operator::Divide, Operator::Modulo],
-    &[Operator::Plus, Operator::Minus],
-    &[Operator::BitwiseLeftShift, Operator::BitwiseRightShift],
-    &[Operator::LessThan, Operator::LessThanOrEqual, Operator::GreaterThan, Operator::GreaterThanOrEqual],
-    &[Operator::Equal, Operator::NotEqual],
-    &[Operator::BitwiseAnd],
-    &[Operator::BitwiseXor],
-    &[Operator::BitwiseOr],
-    &[Operator::And],
-    &[Operator::Or],
-];
-
-
-pub fn parse_expression(tokens: &mut Peekable<Iter<Token>>) -> Expression {
-    // let left = parse_logical_or(tokens);
-    let left = parse_binary_expression_with_precedence(tokens, NUM_PRECEDENCE_LEVELS-1);
+pub fn parse_expression(tokens: &mut TokenStream) -> Result<Spanned<Expression>, ParseError> {
+    let left = parse_pipe(tokens)?;
 
     match tokens.peek() {
+        // Binds looser than anything else, so the whole expression after it is assigned
+        Some(Token::InlineAssign) => {
+            let target = match &left.node {
+                Expression::Atomic(AtomicExpression::Identifier(identifier @ Identifier::Simple(_))) => identifier.clone(),
+                _ => handle_expression_parse_error("Only a variable can be assigned within an expression", &left)?,
+            };
+            tokens.next();
+            let value = parse_expression(tokens)?;
+            let span = left.span.merge(value.span);
+            Ok(Spanned::new(Expression::Assignment(AssignmentExpression {
+                target,
+                value: Box::new(value),
+            }), span))
+        },
         Some(Token::TernaryCondition) => {
             tokens.next();
-            let true_value = parse_expression(tokens);
-            match tokens.next() {
-                Some(Token::Colon) => {},
-                _ => handle_parse_error_for_option("Expected colon after ternary condition", tokens.peek()),
-            }
-            let false_value = parse_expression(tokens);
-            Expression::TernaryCondition {
+            let true_value = parse_expression(tokens)?;
+            expect(tokens, Token::Colon, "Expected colon after ternary condition")?;
+            let false_value = parse_expression(tokens)?;
+            let span = left.span.merge(false_value.span);
+            Ok(Spanned::new(Expression::TernaryCondition {
                 condition: Box::new(left),
                 true_value: Box::new(true_value),
                 false_value: Box::new(false_value),
-            }
+            }, span))
         },
-        _ => left,
+        _ => Ok(left),
     }
 }
 
 
-fn parse_binary_expression_with_precedence(tokens: &mut Peekable<Iter<Token>>, precedence: usize) -> Expression {
-    if precedence >= NUM_PRECEDENCE_LEVELS {
+// `x |> f` is sugar for `f(x)`, and `x |> f(y)` for `f(x, y)`. Pipes bind looser than any binary
+// operator, and chain from left to right.
+fn parse_pipe(tokens: &mut TokenStream) -> Result<Spanned<Expression>, ParseError> {
+    let loosest = tokens.precedence().num_levels() - 1;
+    let mut left = parse_binary_expression_with_precedence(tokens, loosest)?;
+
+    while let Some(Token::Pipe) = tokens.peek() {
+        tokens.next();
+        if !matches!(tokens.peek(), Some(Token::Identifier(_))) {
+            return handle_parse_error_for_option(tokens, "Expected a function name or call after a pipe", tokens.peek());
+        }
+        let function = parse_atomic(tokens)?;
+        let span = left.span.merge(function.span);
+        let call = match function.node {
+            Expression::Atomic(AtomicExpression::Identifier(name)) => FunctionCallExpression {
+                name,
+                parameters: vec![left.node],
+                keyword_arguments: vec![],
+            },
+            Expression::Atomic(AtomicExpression::FunctionCall(mut call)) => {
+                call.parameters.insert(0, left.node);
+                call
+            },
+            _ => return handle_parse_error_for_option(tokens, "Expected a function name or call after a pipe", tokens.peek()),
+        };
+        left = Spanned::new(Expression::Atomic(AtomicExpression::FunctionCall(call)), span);
+    }
+
+    Ok(left)
+}
+
+
+fn parse_binary_expression_with_precedence(tokens: &mut TokenStream, precedence: usize) -> Result<Spanned<Expression>, ParseError> {
+    if precedence >= tokens.precedence().num_levels() {
         panic!("Invalid precedence level: {}", precedence)
     }
 
-    let operators = PRECEDENCE_TABLE[precedence];
+    let operators = tokens.precedence().operators(precedence);
 
     match precedence {
+        // The right operand of a power may have a unary operator, as in 2 ** -1. Powers are still
+        // right associative, since a unary operand falls through to another power.
         0 => parse_binary_operation(tokens,
-            |tokens| parse_atomic(tokens),
-            |tokens| parse_binary_expression_with_precedence(tokens, precedence),
+            parse_atomic,
+            parse_unary,
             operators
         ),
 
-        1 => parse_unary(tokens),
+        UNARY_LEVEL => parse_cast(tokens),
 
         _ => parse_binary_operation(tokens,
             |tokens| parse_binary_expression_with_precedence(tokens, precedence - 1),
@@ -76,45 +105,81 @@ fn parse_binary_expression_with_precedence(tokens: &mut Peekable<Iter<Token>>, p
 
 
 fn parse_binary_operation<F, G>(
-    tokens: &mut Peekable<Iter<Token>>,
+    tokens: &mut TokenStream,
     parse_left: F,
     parse_right: G,
     operators: &[Operator],
-) -> Expression
+) -> Result<Spanned<Expression>, ParseError>
 where
-    F: Fn(&mut Peekable<Iter<Token>>) -> Expression,
-    G: Fn(&mut Peekable<Iter<Token>>) -> Expression,
+    F: Fn(&mut TokenStream) -> Result<Spanned<Expression>, ParseError>,
+    G: Fn(&mut TokenStream) -> Result<Spanned<Expression>, ParseError>,
 {
-    let left = parse_left(tokens);
-    match tokens.peek() {
-        Some(Token::Operator(operator)) => {
-            if operators.contains(operator) {
-                tokens.next();
-                Expression::BinaryOperation {
-                    left: Box::new(left),
-                    operator: operator.clone(),
-                    right: Box::new(parse_right(tokens)),
-                }
-            } else {
-                left
-            }
+    let left = parse_left(tokens)?;
+    let (operator, length) = match tokens.peek() {
+        Some(Token::Operator(operator)) => (operator.clone(), 1),
+        // The in of a for loop header is read by the for statement, so any other in is a membership test
+        Some(Token::Keyword(Keyword::In)) => (Operator::In, 1),
+        Some(Token::Range) => (Operator::Range, 1),
+        Some(Token::InclusiveRange) => (Operator::InclusiveRange, 1),
+        Some(Token::Keyword(Keyword::Is)) => match tokens.clone().nth(1) {
+            Some(Token::Keyword(Keyword::Not)) => (Operator::IsNot, 2),
+            _ => (Operator::Is, 1),
+        },
+        _ => return Ok(left),
+    };
+
+    if operators.contains(&operator) {
+        for _ in 0..length {
+            tokens.next();
         }
-        _ => left,
+        let right = parse_right(tokens)?;
+        let span = left.span.merge(right.span);
+        Ok(Spanned::new(Expression::BinaryOperation {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }, span))
+    } else {
+        Ok(left)
+    }
+}
+
+
+// A cast applies to the whole unary expression before it, so -x as float is (-x) as float
+fn parse_cast(tokens: &mut TokenStream) -> Result<Spanned<Expression>, ParseError> {
+    let mut value = parse_unary(tokens)?;
+
+    while let Some(Token::Keyword(Keyword::As)) = tokens.peek() {
+        tokens.next();
+        let target_type = match tokens.next() {
+            Some(Token::Identifier(identifier)) => identifier.clone(),
+            token => handle_parse_error_for_option(tokens, "Expected a type after as", token)?,
+        };
+        let span = tokens.span_from(value.span);
+        value = Spanned::new(Expression::Cast(CastExpression {
+            value: Box::new(value),
+            target_type,
+        }), span);
     }
+
+    Ok(value)
 }
 
 
-fn parse_unary(tokens: &mut Peekable<Iter<Token>>) -> Expression {
+fn parse_unary(tokens: &mut TokenStream) -> Result<Spanned<Expression>, ParseError> {
+    let start = tokens.peek_span();
     match tokens.peek() {
         Some(token @ Token::Operator(operator)) => match operator {
             Operator::Plus | Operator::Minus | Operator::Not | Operator::BitwiseNot => {
                 tokens.next();
-                Expression::UnaryOperation {
+                let operand = parse_unary(tokens)?;
+                let span = start.merge(operand.span);
+                Ok(Spanned::new(Expression::UnaryOperation {
                     operator: operator.clone(),
-                    operand: Box::new(parse_unary(tokens)),
-                }
+                    operand: Box::new(operand),
+                }, span))
             }
-            _ => handle_parse_error("Operator not allowed in unary expression", token),
+            _ => handle_parse_error(tokens, "Operator not allowed in unary expression", token),
         },
         _ => parse_binary_expression_with_precedence(tokens, 0),
     }
@@ -124,8 +189,9 @@ fn parse_unary(tokens: &mut Peekable<Iter<Token>>) -> Expression {
 
 #[cfg(test)]
 mod test {
-    use crate::elements::Literal;
-    use crate::tree::AtomicExpression;
+    use crate::elements::{Identifier, Literal};
+    use crate::lexer::{tokenize_with_spans, LexOptions};
+    use crate::span::Span;
 
     use super::*;
 
@@ -137,13 +203,13 @@ mod test {
             Token::Literal(Literal::Integer(2)),
             Token::Operator(Operator::Plus),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 0);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 0).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))),
+            left: Box::new(Expression::int(1).into()),
             operator: Operator::Power,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(2)))),
+            right: Box::new(Expression::int(2).into()),
         };
 
         assert_eq!(result, expected);
@@ -158,12 +224,12 @@ mod test {
             Token::Operator(Operator::Plus),
             Token::Literal(Literal::Integer(2)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 1);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 1).unwrap();
 
         let expected = Expression::UnaryOperation {
             operator: Operator::Minus,
-            operand: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))),
+            operand: Box::new(Expression::int(1).into()),
         };
 
         assert_eq!(result, expected);
@@ -177,10 +243,10 @@ mod test {
             Token::Operator(Operator::Plus),
             Token::Literal(Literal::Integer(2)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 1);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 1).unwrap();
 
-        let expected = Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)));
+        let expected = Expression::int(1);
 
         assert_eq!(result, expected);
         assert_eq!(Token::Operator(Operator::Plus), *tokens.next().unwrap());
@@ -194,16 +260,128 @@ mod test {
             Token::Operator(Operator::Power),
             Token::Literal(Literal::Integer(2)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 1);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 1).unwrap();
 
         let expected = Expression::UnaryOperation {
             operator: Operator::Minus,
             operand: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))),
+                left: Box::new(Expression::int(1).into()),
+                operator: Operator::Power,
+                right: Box::new(Expression::int(2).into()),
+            }.into()),
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_power_with_unary_exponent() {
+        let tokens = vec![
+            Token::Literal(Literal::Integer(2)),
+            Token::Operator(Operator::Power),
+            Token::Operator(Operator::Minus),
+            Token::Literal(Literal::Integer(1)),
+        ];
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_expression(tokens).unwrap();
+
+        let expected = Expression::BinaryOperation {
+            left: Box::new(Expression::int(2).into()),
+            operator: Operator::Power,
+            right: Box::new(Expression::UnaryOperation {
+                operator: Operator::Minus,
+                operand: Box::new(Expression::int(1).into()),
+            }.into()),
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_power_is_right_associative() {
+        let tokens = vec![
+            Token::Literal(Literal::Integer(2)),
+            Token::Operator(Operator::Power),
+            Token::Literal(Literal::Integer(3)),
+            Token::Operator(Operator::Power),
+            Token::Literal(Literal::Integer(4)),
+        ];
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_expression(tokens).unwrap();
+
+        let expected = Expression::BinaryOperation {
+            left: Box::new(Expression::int(2).into()),
+            operator: Operator::Power,
+            right: Box::new(Expression::BinaryOperation {
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::Power,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(2)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_cast() {
+        let tokens = vec![
+            Token::Identifier(Identifier::Simple("x".to_string())),
+            Token::Keyword(Keyword::As),
+            Token::Identifier(Identifier::Simple("float".to_string())),
+        ];
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_expression(tokens).unwrap();
+
+        let expected = Expression::Cast(CastExpression {
+            value: Box::new(Expression::ident("x").into()),
+            target_type: Identifier::Simple("float".to_string()),
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_cast_unary_precedence() {
+        let tokens = vec![
+            Token::Operator(Operator::Minus),
+            Token::Literal(Literal::Integer(1)),
+            Token::Keyword(Keyword::As),
+            Token::Identifier(Identifier::Simple("float".to_string())),
+        ];
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_expression(tokens).unwrap();
+
+        let expected = Expression::Cast(CastExpression {
+            value: Box::new(Expression::UnaryOperation {
+                operator: Operator::Minus,
+                operand: Box::new(Expression::int(1).into()),
+            }.into()),
+            target_type: Identifier::Simple("float".to_string()),
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_cast_binds_tighter_than_factors() {
+        let tokens = vec![
+            Token::Literal(Literal::Integer(3)),
+            Token::Operator(Operator::Times),
+            Token::Identifier(Identifier::Simple("x".to_string())),
+            Token::Keyword(Keyword::As),
+            Token::Identifier(Identifier::Simple("float".to_string())),
+        ];
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_expression(tokens).unwrap();
+
+        let expected = Expression::BinaryOperation {
+            left: Box::new(Expression::int(3).into()),
+            operator: Operator::Times,
+            right: Box::new(Expression::Cast(CastExpression {
+                value: Box::new(Expression::ident("x").into()),
+                target_type: Identifier::Simple("float".to_string()),
+            }).into()),
         };
 
         assert_eq!(result, expected);
@@ -216,13 +394,13 @@ mod test {
             Token::Operator(Operator::Times),
             Token::Literal(Literal::Integer(4)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 2);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 2).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::Times,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            right: Box::new(Expression::int(4).into()),
         };
 
         assert_eq!(result, expected);
@@ -237,17 +415,17 @@ mod test {
             Token::Operator(Operator::Power),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 2);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 2).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::Times,
             right: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                left: Box::new(Expression::int(4).into()),
                 operator: Operator::Power,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            }),
+                right: Box::new(Expression::int(5).into()),
+            }.into()),
         };
 
         assert_eq!(result, expected);
@@ -262,17 +440,17 @@ mod test {
             Token::Operator(Operator::Times),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 2);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 2).unwrap();
 
         let expected = Expression::BinaryOperation {
             left: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::Power,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
             operator: Operator::Times,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            right: Box::new(Expression::int(5).into()),
         };
 
         assert_eq!(result, expected);
@@ -285,13 +463,13 @@ mod test {
             Token::Operator(Operator::Plus),
             Token::Literal(Literal::Integer(4)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 3);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 3).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::Plus,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            right: Box::new(Expression::int(4).into()),
         };
 
         assert_eq!(result, expected);
@@ -306,17 +484,17 @@ mod test {
             Token::Operator(Operator::Times),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 3);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 3).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::Plus,
             right: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                left: Box::new(Expression::int(4).into()),
                 operator: Operator::Times,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            }),
+                right: Box::new(Expression::int(5).into()),
+            }.into()),
         };
 
         assert_eq!(result, expected);
@@ -331,17 +509,17 @@ mod test {
             Token::Operator(Operator::Plus),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 3);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 3).unwrap();
 
         let expected = Expression::BinaryOperation {
             left: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::Times,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
             operator: Operator::Plus,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            right: Box::new(Expression::int(5).into()),
         };
 
         assert_eq!(result, expected);
@@ -354,13 +532,13 @@ mod test {
             Token::Operator(Operator::BitwiseLeftShift),
             Token::Literal(Literal::Integer(4)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 4);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 4).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::BitwiseLeftShift,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            right: Box::new(Expression::int(4).into()),
         };
 
         assert_eq!(result, expected);
@@ -375,17 +553,17 @@ mod test {
             Token::Operator(Operator::Plus),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 4);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 4).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::BitwiseLeftShift,
             right: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                left: Box::new(Expression::int(4).into()),
                 operator: Operator::Plus,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            }),
+                right: Box::new(Expression::int(5).into()),
+            }.into()),
         };
 
         assert_eq!(result, expected);
@@ -400,17 +578,17 @@ mod test {
             Token::Operator(Operator::BitwiseLeftShift),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 4);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 4).unwrap();
 
         let expected = Expression::BinaryOperation {
             left: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::Plus,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
             operator: Operator::BitwiseLeftShift,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            right: Box::new(Expression::int(5).into()),
         };
 
         assert_eq!(result, expected);
@@ -424,13 +602,13 @@ mod test {
             Token::Operator(Operator::LessThan),
             Token::Literal(Literal::Integer(4)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 5);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 5).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::LessThan,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            right: Box::new(Expression::int(4).into()),
         };
 
         assert_eq!(result, expected);
@@ -445,17 +623,17 @@ mod test {
             Token::Operator(Operator::BitwiseLeftShift),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 5);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 5).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::LessThan,
             right: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                left: Box::new(Expression::int(4).into()),
                 operator: Operator::BitwiseLeftShift,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            }),
+                right: Box::new(Expression::int(5).into()),
+            }.into()),
         };
 
         assert_eq!(result, expected);
@@ -470,17 +648,17 @@ mod test {
             Token::Operator(Operator::LessThan),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 5);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 5).unwrap();
 
         let expected = Expression::BinaryOperation {
             left: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::BitwiseLeftShift,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
             operator: Operator::LessThan,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            right: Box::new(Expression::int(5).into()),
         };
 
         assert_eq!(result, expected);
@@ -493,13 +671,13 @@ mod test {
             Token::Operator(Operator::Equal),
             Token::Literal(Literal::Integer(4)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 6);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 6).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::Equal,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            right: Box::new(Expression::int(4).into()),
         };
 
         assert_eq!(result, expected);
@@ -514,17 +692,17 @@ mod test {
             Token::Operator(Operator::LessThan),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 6);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 6).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::Equal,
             right: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                left: Box::new(Expression::int(4).into()),
                 operator: Operator::LessThan,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            }),
+                right: Box::new(Expression::int(5).into()),
+            }.into()),
         };
 
         assert_eq!(result, expected);
@@ -539,17 +717,17 @@ mod test {
             Token::Operator(Operator::Equal),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 6);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 6).unwrap();
 
         let expected = Expression::BinaryOperation {
             left: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::LessThan,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
             operator: Operator::Equal,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            right: Box::new(Expression::int(5).into()),
         };
 
         assert_eq!(result, expected);
@@ -562,13 +740,13 @@ mod test {
             Token::Operator(Operator::BitwiseAnd),
             Token::Literal(Literal::Integer(4)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 7);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 7).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::BitwiseAnd,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            right: Box::new(Expression::int(4).into()),
         };
 
         assert_eq!(result, expected);
@@ -583,17 +761,17 @@ mod test {
             Token::Operator(Operator::Equal),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 7);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 7).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::BitwiseAnd,
             right: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                left: Box::new(Expression::int(4).into()),
                 operator: Operator::Equal,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            }),
+                right: Box::new(Expression::int(5).into()),
+            }.into()),
         };
 
         assert_eq!(result, expected);
@@ -608,17 +786,17 @@ mod test {
             Token::Operator(Operator::BitwiseAnd),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 7);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 7).unwrap();
 
         let expected = Expression::BinaryOperation {
             left: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::Equal,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
             operator: Operator::BitwiseAnd,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            right: Box::new(Expression::int(5).into()),
         };
 
         assert_eq!(result, expected);
@@ -631,13 +809,13 @@ mod test {
             Token::Operator(Operator::BitwiseXor),
             Token::Literal(Literal::Integer(4)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 8);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 8).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::BitwiseXor,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            right: Box::new(Expression::int(4).into()),
         };
 
         assert_eq!(result, expected);
@@ -652,17 +830,17 @@ mod test {
             Token::Operator(Operator::BitwiseAnd),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 8);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 8).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::BitwiseXor,
             right: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                left: Box::new(Expression::int(4).into()),
                 operator: Operator::BitwiseAnd,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            }),
+                right: Box::new(Expression::int(5).into()),
+            }.into()),
         };
 
         assert_eq!(result, expected);
@@ -677,17 +855,17 @@ mod test {
             Token::Operator(Operator::BitwiseXor),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 8);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 8).unwrap();
 
         let expected = Expression::BinaryOperation {
             left: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::BitwiseAnd,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
             operator: Operator::BitwiseXor,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            right: Box::new(Expression::int(5).into()),
         };
 
         assert_eq!(result, expected);
@@ -700,13 +878,13 @@ mod test {
             Token::Operator(Operator::BitwiseOr),
             Token::Literal(Literal::Integer(4)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 9);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 9).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::BitwiseOr,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            right: Box::new(Expression::int(4).into()),
         };
 
         assert_eq!(result, expected);
@@ -721,17 +899,17 @@ mod test {
             Token::Operator(Operator::BitwiseXor),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 9);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 9).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::BitwiseOr,
             right: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                left: Box::new(Expression::int(4).into()),
                 operator: Operator::BitwiseXor,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            }),
+                right: Box::new(Expression::int(5).into()),
+            }.into()),
         };
 
         assert_eq!(result, expected);
@@ -746,17 +924,17 @@ mod test {
             Token::Operator(Operator::BitwiseOr),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 9);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 9).unwrap();
 
         let expected = Expression::BinaryOperation {
             left: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::BitwiseXor,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
             operator: Operator::BitwiseOr,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            right: Box::new(Expression::int(5).into()),
         };
 
         assert_eq!(result, expected);
@@ -769,13 +947,13 @@ mod test {
             Token::Operator(Operator::And),
             Token::Literal(Literal::Integer(4)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 10);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 10).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::And,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            right: Box::new(Expression::int(4).into()),
         };
 
         assert_eq!(result, expected);
@@ -790,17 +968,17 @@ mod test {
             Token::Operator(Operator::BitwiseOr),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 10);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 10).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::And,
             right: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                left: Box::new(Expression::int(4).into()),
                 operator: Operator::BitwiseOr,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            }),
+                right: Box::new(Expression::int(5).into()),
+            }.into()),
         };
 
         assert_eq!(result, expected);
@@ -815,17 +993,17 @@ mod test {
             Token::Operator(Operator::And),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 10);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 10).unwrap();
 
         let expected = Expression::BinaryOperation {
             left: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::BitwiseOr,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
             operator: Operator::And,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            right: Box::new(Expression::int(5).into()),
         };
 
         assert_eq!(result, expected);
@@ -838,13 +1016,13 @@ mod test {
             Token::Operator(Operator::Or),
             Token::Literal(Literal::Integer(4)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 11);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 11).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::Or,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            right: Box::new(Expression::int(4).into()),
         };
 
         assert_eq!(result, expected);
@@ -859,17 +1037,17 @@ mod test {
             Token::Operator(Operator::And),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 11);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 11).unwrap();
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            left: Box::new(Expression::int(3).into()),
             operator: Operator::Or,
             right: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                left: Box::new(Expression::int(4).into()),
                 operator: Operator::And,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            }),
+                right: Box::new(Expression::int(5).into()),
+            }.into()),
         };
 
         assert_eq!(result, expected);
@@ -884,17 +1062,17 @@ mod test {
             Token::Operator(Operator::Or),
             Token::Literal(Literal::Integer(5)),
         ];
-        let tokens = &mut tokens.iter().peekable();
-        let result = parse_binary_expression_with_precedence(tokens, 11);
+        let tokens = &mut TokenStream::new(&tokens);
+        let result = parse_binary_expression_with_precedence(tokens, 11).unwrap();
 
         let expected = Expression::BinaryOperation {
             left: Box::new(Expression::BinaryOperation {
-                left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                left: Box::new(Expression::int(3).into()),
                 operator: Operator::And,
-                right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            }),
+                right: Box::new(Expression::int(4).into()),
+            }.into()),
             operator: Operator::Or,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            right: Box::new(Expression::int(5).into()),
         };
 
         assert_eq!(result, expected);
@@ -911,12 +1089,12 @@ mod test {
         ];
 
         let expected = Expression::TernaryCondition {
-            condition: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
-            true_value: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-            false_value: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+            condition: Box::new(Expression::int(3).into()),
+            true_value: Box::new(Expression::int(4).into()),
+            false_value: Box::new(Expression::int(5).into()),
         };
 
-        assert_eq!(parse_expression(&mut tokens.iter().peekable()), expected);
+        assert_eq!(parse_expression(&mut TokenStream::new(&tokens)).unwrap(), expected);
     }
 
     #[test]
@@ -934,15 +1112,15 @@ mod test {
         let expected = Expression::TernaryCondition {
             condition:
                 Box::new(Expression::BinaryOperation {
-                    left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+                    left: Box::new(Expression::int(3).into()),
                     operator: Operator::Or,
-                    right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
-                }),
-            true_value: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-            false_value: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(6)))),
+                    right: Box::new(Expression::int(4).into()),
+                }.into()),
+            true_value: Box::new(Expression::int(5).into()),
+            false_value: Box::new(Expression::int(6).into()),
         };
 
-        assert_eq!(parse_expression(&mut tokens.iter().peekable()), expected);
+        assert_eq!(parse_expression(&mut TokenStream::new(&tokens)).unwrap(), expected);
     }
 
     #[test]
@@ -958,17 +1136,17 @@ mod test {
         ];
 
         let expected = Expression::TernaryCondition {
-            condition: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
+            condition: Box::new(Expression::int(3).into()),
             true_value:
                 Box::new(Expression::BinaryOperation {
-                    left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+                    left: Box::new(Expression::int(4).into()),
                     operator: Operator::Or,
-                    right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
-                }),
-            false_value: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(6)))),
+                    right: Box::new(Expression::int(5).into()),
+                }.into()),
+            false_value: Box::new(Expression::int(6).into()),
         };
 
-        assert_eq!(parse_expression(&mut tokens.iter().peekable()), expected);
+        assert_eq!(parse_expression(&mut TokenStream::new(&tokens)).unwrap(), expected);
     }
 
     #[test]
@@ -984,17 +1162,63 @@ mod test {
         ];
 
         let expected = Expression::TernaryCondition {
-            condition: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3)))),
-            true_value: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(4)))),
+            condition: Box::new(Expression::int(3).into()),
+            true_value: Box::new(Expression::int(4).into()),
             false_value:
                 Box::new(Expression::BinaryOperation {
-                    left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(5)))),
+                    left: Box::new(Expression::int(5).into()),
                     operator: Operator::Or,
-                    right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(6)))),
-                }),
+                    right: Box::new(Expression::int(6).into()),
+                }.into()),
         };
 
-        assert_eq!(parse_expression(&mut tokens.iter().peekable()), expected);
+        assert_eq!(parse_expression(&mut TokenStream::new(&tokens)).unwrap(), expected);
     }
 
+    // The branches are whole expressions, so a ternary in either of them needs no parentheses
+    #[test]
+    fn test_nested_ternaries() {
+        let parse_source = |source: &str| {
+            let (tokens, spans) = tokenize_with_spans(source, &LexOptions::default());
+            parse_expression(&mut TokenStream::with_spans(&tokens, &spans)).unwrap()
+        };
+        let ternary = |condition: Expression, true_value: Expression, false_value: Expression| Expression::TernaryCondition {
+            condition: Box::new(condition.into()),
+            true_value: Box::new(true_value.into()),
+            false_value: Box::new(false_value.into()),
+        };
+
+        let expected = ternary(Expression::ident("a"), Expression::ident("b"), ternary(Expression::ident("c"), Expression::ident("d"), Expression::ident("e")));
+        assert_eq!(parse_source("a ? b : c ? d : e"), expected);
+
+        let expected = ternary(Expression::ident("a"), ternary(Expression::ident("b"), Expression::ident("c"), Expression::ident("d")), Expression::ident("e"));
+        assert_eq!(parse_source("a ? b ? c : d : e"), expected);
+    }
+
+    #[test]
+    fn test_binary_operation_span() {
+        let (tokens, spans) = tokenize_with_spans("  a + b * 2\n", &LexOptions::default());
+        let result = parse_expression(&mut TokenStream::with_spans(&tokens, &spans)).unwrap();
+
+        assert_eq!(result.span, Span::new(2, 11));
+        match result.node {
+            Expression::BinaryOperation { left, right, .. } => {
+                assert_eq!(left.span, Span::new(2, 3));
+                assert_eq!(right.span, Span::new(6, 11));
+            },
+            _ => panic!("Expected a binary operation, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_unary_and_cast_spans() {
+        let (tokens, spans) = tokenize_with_spans("-x as float", &LexOptions::default());
+        let result = parse_expression(&mut TokenStream::with_spans(&tokens, &spans)).unwrap();
+
+        assert_eq!(result.span, Span::new(0, 11));
+        match result.node {
+            Expression::Cast(cast) => assert_eq!(cast.value.span, Span::new(0, 2)),
+            _ => panic!("Expected a cast, found {:?}", result),
+        }
+    }
 }
@@ -1,99 +1,111 @@
-use std::iter::Peekable;
-use std::slice::Iter;
-
-use crate::elements::Identifier;
+use crate::elements::{Identifier, Keyword, Operator};
 use crate::tokens::Token;
+use crate::parser::token_stream::TokenStream;
 use crate::tree;
+use crate::parser::errors::ParseError;
 use crate::parser::statement_parser::parse_statement_block_between_braces;
 
-use crate::parser::utils::{handle_parse_error, handle_parse_error_for_option};
+use crate::parser::utils::{handle_parse_error, handle_parse_error_for_option, peek_is, expect};
 
 
-pub fn parse_function_block(tokens: &mut Peekable<Iter<Token>>) -> tree::Function {
-    let name = parse_function_name(tokens);
-    let parameters = parse_parameter_list(tokens);
-    let return_type = parse_function_return_type(tokens);
-    let body = parse_statement_block_between_braces(tokens);
+pub fn parse_function_block(tokens: &mut TokenStream) -> Result<tree::Function, ParseError> {
+    let name = parse_function_name(tokens)?;
+    let parameters = parse_parameter_list(tokens)?;
+    let return_type = parse_function_return_type(tokens)?;
+    let body = parse_statement_block_between_braces(tokens)?;
 
-    tree::Function {
+    Ok(tree::Function {
         name,
         parameters,
         return_type,
         body: Box::new(body),
-    }
+        doc: None,
+        is_const: false,
+    })
 }
 
 
-fn parse_function_name(tokens: &mut Peekable<Iter<Token>>) -> Identifier {
+fn parse_function_name(tokens: &mut TokenStream) -> Result<Identifier, ParseError> {
     match tokens.next() {
-        Some(Token::Identifier(identifier)) => identifier.clone(),
-        _ => handle_parse_error_for_option("Expected identifier after function keyword", tokens.peek()),
+        Some(Token::Identifier(identifier)) => Ok(identifier.clone()),
+        token => handle_parse_error_for_option(tokens, "Expected identifier after function keyword", token),
     }
 }
 
 
-fn parse_parameter_list(tokens: &mut Peekable<Iter<Token>>) -> Vec<tree::Parameter> {
-    if let Some(token) = tokens.next() {
-        match token {
-            Token::OpenParen => {},
-            _ => handle_parse_error("Expected a parameter list starting with an open parenthesis", token),
-        }
-    }
+fn parse_parameter_list(tokens: &mut TokenStream) -> Result<Vec<tree::Parameter>, ParseError> {
+    expect(tokens, Token::OpenParen, "Expected a parameter list starting with an open parenthesis")?;
 
     let mut parameters = vec![];
+    let mut kind = tree::ParameterKind::Positional;
 
     while let Some(token) = tokens.next() {
         match token {
             Token::Newline => continue,
             Token::ListSeparator => {
                 if let Some(Token::ListSeparator) = tokens.peek() {
-                    handle_parse_error_for_option::<()>("Expected a parameter", tokens.peek());
+                    handle_parse_error_for_option::<()>(tokens, "Expected a parameter", tokens.peek())?;
                 }
             },
             Token::CloseParen => break,
             Token::Identifier(_) => {
-                parameters.push(parse_parameter(token, tokens))
+                parameters.push(tree::Parameter { kind: kind.clone(), ..parse_parameter(token, tokens)? })
+            },
+            Token::Keyword(Keyword::Variable) => match tokens.next() {
+                Some(token @ Token::Identifier(_)) => parameters.push(tree::Parameter {
+                    kind: kind.clone(),
+                    is_mutable: true,
+                    ..parse_parameter(token, tokens)?
+                }),
+                token => handle_parse_error_for_option(tokens, "Expected a parameter name after var", token)?,
+            },
+
+            // A bare star marks the rest of the parameters as keyword-only. The lexer reads a star
+            // after an opening parenthesis or a comma as a spread, and elsewhere as a times operator.
+            Token::Spread | Token::Operator(Operator::Times) => if kind == tree::ParameterKind::Positional {
+                kind = tree::ParameterKind::KeywordOnly;
+            } else {
+                handle_parse_error(tokens, "Expected only one keyword-only marker", token)?
             },
-            _ => handle_parse_error("Expected a parameter or a closing parenthesis", token),
+            _ => handle_parse_error(tokens, "Expected a parameter or a closing parenthesis", token)?,
         }
     }
 
-    parameters
+    Ok(parameters)
 }
 
 
-fn parse_parameter(current: &Token, tokens: &mut Peekable<Iter<Token>>) -> tree::Parameter {
+pub fn parse_parameter(current: &Token, tokens: &mut TokenStream) -> Result<tree::Parameter, ParseError> {
     let name = match current {
         Token::Identifier(identifier) => identifier.clone(),
-        _ => handle_parse_error("Expected an identifier", current),
+        _ => handle_parse_error(tokens, "Expected an identifier", current)?,
     };
 
-    match tokens.peek() {
-        Some(Token::Colon) => { tokens.next(); }
-        _ => handle_parse_error_for_option("Expected colon after parameter name", tokens.peek()),
-    }
+    expect(tokens, Token::Colon, "Expected colon after parameter name")?;
 
     let param_type = match tokens.next() {
         Some(Token::Identifier(identifier)) => identifier.clone(),
-        _ => handle_parse_error_for_option("Expected a type identifier after colon", tokens.peek()),
+        token => handle_parse_error_for_option(tokens, "Expected a type identifier after colon", token)?,
     };
 
-    tree::Parameter {
+    Ok(tree::Parameter {
         name,
         param_type,
-    }
+        kind: tree::ParameterKind::Positional,
+        is_mutable: false,
+    })
 }
 
 
-fn parse_function_return_type(tokens: &mut Peekable<Iter<Token>>) -> Option<Identifier> {
-    if let Some(Token::Colon) = tokens.peek() {
+fn parse_function_return_type(tokens: &mut TokenStream) -> Result<Option<Identifier>, ParseError> {
+    if peek_is(tokens, &Token::Colon) {
         tokens.next();  // Consume the colon
         match tokens.next() {
-            Some(Token::Identifier(identifier)) => Some(identifier.clone()),
-            _ => handle_parse_error_for_option("Expected type identifier after function parameters", tokens.peek()),
+            Some(Token::Identifier(identifier)) => Ok(Some(identifier.clone())),
+            token => handle_parse_error_for_option(tokens, "Expected type identifier after function parameters", token),
         }
     } else {
-        None
+        Ok(None)
     }
 }
 
@@ -110,7 +122,7 @@ mod test {
 
         let expected = Identifier::Simple("foo".to_string());
 
-        assert_eq!(parse_function_name(&mut tokens.iter().peekable()), expected);
+        assert_eq!(parse_function_name(&mut TokenStream::new(&tokens)).unwrap(), expected);
     }
 
     #[test]
@@ -127,10 +139,12 @@ mod test {
             tree::Parameter {
                 name: Identifier::Simple("x".to_string()),
                 param_type: Identifier::Simple("int".to_string()),
+                kind: tree::ParameterKind::Positional,
+                is_mutable: false,
             }
         ];
 
-        assert_eq!(parse_parameter_list(&mut tokens.iter().peekable()), expected);
+        assert_eq!(parse_parameter_list(&mut TokenStream::new(&tokens)).unwrap(), expected);
     }
 
     #[test]
@@ -151,14 +165,100 @@ mod test {
             tree::Parameter {
                 name: Identifier::Simple("x".to_string()),
                 param_type: Identifier::Simple("int".to_string()),
+                kind: tree::ParameterKind::Positional,
+                is_mutable: false,
+            },
+            tree::Parameter {
+                name: Identifier::Simple("y".to_string()),
+                param_type: Identifier::Simple("int".to_string()),
+                kind: tree::ParameterKind::Positional,
+                is_mutable: false,
+            }
+        ];
+
+        assert_eq!(parse_parameter_list(&mut TokenStream::new(&tokens)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_parameter_list_with_keyword_only_marker() {
+        let tokens = vec![
+            Token::OpenParen,
+            Token::Identifier(Identifier::Simple("x".to_string())),
+            Token::Colon,
+            Token::Identifier(Identifier::Simple("int".to_string())),
+            Token::ListSeparator,
+            Token::Spread,
+            Token::ListSeparator,
+            Token::Identifier(Identifier::Simple("y".to_string())),
+            Token::Colon,
+            Token::Identifier(Identifier::Simple("int".to_string())),
+            Token::CloseParen,
+        ];
+
+        let expected = vec![
+            tree::Parameter {
+                name: Identifier::Simple("x".to_string()),
+                param_type: Identifier::Simple("int".to_string()),
+                kind: tree::ParameterKind::Positional,
+                is_mutable: false,
             },
             tree::Parameter {
                 name: Identifier::Simple("y".to_string()),
                 param_type: Identifier::Simple("int".to_string()),
+                kind: tree::ParameterKind::KeywordOnly,
+                is_mutable: false,
+            }
+        ];
+
+        assert_eq!(parse_parameter_list(&mut TokenStream::new(&tokens)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_parameter_list_with_two_keyword_only_markers() {
+        let tokens = vec![
+            Token::OpenParen,
+            Token::Spread,
+            Token::ListSeparator,
+            Token::Spread,
+            Token::CloseParen,
+        ];
+
+        let result = parse_parameter_list(&mut TokenStream::new(&tokens));
+
+        assert_eq!(result.unwrap_err().message, "Expected only one keyword-only marker");
+    }
+
+    #[test]
+    fn test_parse_parameter_list_with_var_parameter() {
+        let tokens = vec![
+            Token::OpenParen,
+            Token::Keyword(Keyword::Variable),
+            Token::Identifier(Identifier::Simple("values".to_string())),
+            Token::Colon,
+            Token::Identifier(Identifier::Simple("array".to_string())),
+            Token::ListSeparator,
+            Token::Identifier(Identifier::Simple("x".to_string())),
+            Token::Colon,
+            Token::Identifier(Identifier::Simple("int".to_string())),
+            Token::CloseParen,
+        ];
+
+        let expected = vec![
+            tree::Parameter {
+                name: Identifier::Simple("values".to_string()),
+                param_type: Identifier::Simple("array".to_string()),
+                kind: tree::ParameterKind::Positional,
+                is_mutable: true,
+            },
+            tree::Parameter {
+                name: Identifier::Simple("x".to_string()),
+                param_type: Identifier::Simple("int".to_string()),
+                kind: tree::ParameterKind::Positional,
+                is_mutable: false,
             }
         ];
 
-        assert_eq!(parse_parameter_list(&mut tokens.iter().peekable()), expected);
+        assert_eq!(parse_parameter_list(&mut TokenStream::new(&tokens)).unwrap(), expected);
     }
 
     #[test]
@@ -172,9 +272,11 @@ mod test {
         let expected = tree::Parameter {
             name: Identifier::Simple("x".to_string()),
             param_type: Identifier::Simple("int".to_string()),
+            kind: tree::ParameterKind::Positional,
+            is_mutable: false,
         };
 
-        assert_eq!(parse_parameter(&current, &mut tokens.iter().peekable()), expected);
+        assert_eq!(parse_parameter(&current, &mut TokenStream::new(&tokens)).unwrap(), expected);
     }
 
     #[test]
@@ -186,7 +288,7 @@ mod test {
 
         let expected = Some(Identifier::Simple("int".to_string()));
 
-        assert_eq!(parse_function_return_type(&mut tokens.iter().peekable()), expected);
+        assert_eq!(parse_function_return_type(&mut TokenStream::new(&tokens)).unwrap(), expected);
     }
 
     #[test]
@@ -195,9 +297,9 @@ mod test {
             Token::OpenBrace,
             Token::Newline,
         ];
-        let iter_tokens = &mut tokens.iter().peekable();
+        let iter_tokens = &mut TokenStream::new(&tokens);
 
-        assert_eq!(parse_function_return_type(iter_tokens), None);
+        assert_eq!(parse_function_return_type(iter_tokens).unwrap(), None);
         assert_eq!(Token::OpenBrace, *iter_tokens.next().unwrap());
     }
 
@@ -222,15 +324,19 @@ mod test {
                 tree::Parameter {
                     name: Identifier::Simple("x".to_string()),
                     param_type: Identifier::Simple("int".to_string()),
+                    kind: tree::ParameterKind::Positional,
+                    is_mutable: false,
                 }
             ],
             return_type: Some(Identifier::Simple("float".to_string())),
             body: Box::new(tree::StatementBlock {
                 statements: vec![],
             }),
+            doc: None,
+            is_const: false,
         };
 
-        assert_eq!(parse_function_block(&mut tokens.iter().peekable()), expected);
+        assert_eq!(parse_function_block(&mut TokenStream::new(&tokens)).unwrap(), expected);
     }
 
 }
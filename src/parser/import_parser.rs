@@ -1,13 +1,249 @@
-use std::iter::Peekable;
-use std::slice::Iter;
-
-use crate::elements::Identifier;
+use crate::elements::{Identifier, Keyword, Operator};
 use crate::tokens::Token;
+use crate::parser::token_stream::TokenStream;
 use crate::tree;
 
-use crate::parser::utils::handle_parse_error;
+use crate::parser::errors::ParseError;
+use crate::parser::utils::{handle_parse_error, handle_parse_error_for_option, expect};
+
+
+pub fn parse_using_block(tokens: &mut TokenStream) -> Result<Vec<tree::Import>, ParseError> {
+    expect(tokens, Token::OpenBrace, "Expected an open brace after using")?;
+
+    let mut imports = vec![];
+
+    while let Some(token) = tokens.peek() {
+        match token {
+            Token::Newline | Token::TrailingComment(_) => {
+                tokens.next();
+            },
+            Token::CloseBrace => {
+                tokens.next();
+                return Ok(imports);
+            },
+            _ => imports.extend(parse_import_line(tokens)?),
+        }
+    }
+
+    handle_parse_error_for_option(tokens, "Expected a closing brace at the end of the using block", tokens.peek())
+}
+
+
+// Parses a line like `a, b as bee, c from mod`, where each name may have its own alias and all
+// names share the source module at the end of the line. A star in place of a name imports
+// everything from the source, and can be mixed with named imports.
+fn parse_import_line(tokens: &mut TokenStream) -> Result<Vec<tree::Import>, ParseError> {
+    // None stands for the wildcard
+    let mut names: Vec<Option<(Identifier, Identifier)>> = vec![];
+
+    loop {
+        let name = match tokens.next() {
+            Some(Token::Identifier(identifier)) => identifier.clone(),
+            // The lexer reads a star at the start of a line as a spread, and after a brace as a times
+            Some(Token::Spread | Token::Operator(Operator::Times)) => {
+                names.push(None);
+                match tokens.next() {
+                    Some(Token::ListSeparator) => continue,
+                    Some(Token::Keyword(Keyword::From)) => break,
+                    token => handle_parse_error_for_option(tokens, "Expected 'from' followed by a source module", token)?,
+                }
+            },
+            token => handle_parse_error_for_option(tokens, "Expected a name to import", token)?,
+        };
+
+        let alias = match tokens.peek() {
+            Some(Token::Keyword(Keyword::As)) => {
+                tokens.next();
+                match tokens.next() {
+                    Some(Token::Identifier(alias)) => alias.clone(),
+                    token => handle_parse_error_for_option(tokens, "Expected an alias after 'as'", token)?,
+                }
+            },
+            _ => name.clone(),
+        };
+
+        names.push(Some((name, alias)));
+
+        match tokens.next() {
+            Some(Token::ListSeparator) => continue,
+            Some(Token::Keyword(Keyword::From)) => break,
+            token => handle_parse_error_for_option(tokens, "Expected 'from' followed by a source module", token)?,
+        }
+    }
+
+    let source = match tokens.next() {
+        Some(Token::Identifier(identifier)) => identifier.clone(),
+        token => handle_parse_error_for_option(tokens, "Expected a source module after 'from'", token)?,
+    };
+
+    match tokens.peek() {
+        Some(Token::Newline | Token::Semicolon) => { tokens.next(); },
+        Some(Token::CloseBrace) | Some(Token::TrailingComment(_)) => {},
+        Some(token) => handle_parse_error(tokens, "Expected a new line or semicolon after an import", token)?,
+        None => {},
+    }
+
+    Ok(names.into_iter()
+        .map(|name| match name {
+            Some((name, alias)) => tree::Import { name, alias, source: source.clone(), is_wildcard: false },
+            None => tree::Import { name: source.clone(), alias: source.clone(), source: source.clone(), is_wildcard: true },
+        })
+        .collect())
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn identifier(name: &str) -> Token {
+        Token::Identifier(Identifier::Simple(name.to_string()))
+    }
+
+    fn import(name: &str, alias: &str, source: &str) -> tree::Import {
+        tree::Import {
+            name: Identifier::Simple(name.to_string()),
+            alias: Identifier::Simple(alias.to_string()),
+            source: Identifier::Simple(source.to_string()),
+            is_wildcard: false,
+        }
+    }
+
+    fn wildcard(source: &str) -> tree::Import {
+        let source = Identifier::Simple(source.to_string());
+        tree::Import { name: source.clone(), alias: source.clone(), source, is_wildcard: true }
+    }
+
+    #[test]
+    fn test_parse_mixed_aliases() {
+        let tokens = vec![
+            Token::OpenBrace,
+            identifier("a"),
+            Token::ListSeparator,
+            identifier("b"),
+            Token::Keyword(Keyword::As),
+            identifier("bee"),
+            Token::ListSeparator,
+            identifier("c"),
+            Token::Keyword(Keyword::From),
+            identifier("mod"),
+            Token::Newline,
+            Token::CloseBrace,
+        ];
+
+        let expected = vec![
+            import("a", "a", "mod"),
+            import("b", "bee", "mod"),
+            import("c", "c", "mod"),
+        ];
+
+        assert_eq!(parse_using_block(&mut TokenStream::new(&tokens)), Ok(expected));
+    }
+
+    #[test]
+    fn test_parse_multiple_lines() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::Newline,
+            identifier("sqrt"),
+            Token::Keyword(Keyword::As),
+            identifier("root"),
+            Token::Keyword(Keyword::From),
+            identifier("math"),
+            Token::Newline,
+            identifier("sin"),
+            Token::Keyword(Keyword::From),
+            identifier("trig"),
+            Token::CloseBrace,
+        ];
+
+        let expected = vec![
+            import("sqrt", "root", "math"),
+            import("sin", "sin", "trig"),
+        ];
+
+        assert_eq!(parse_using_block(&mut TokenStream::new(&tokens)), Ok(expected));
+    }
+
+    #[test]
+    fn test_dangling_as_is_an_error() {
+        let tokens = vec![
+            Token::OpenBrace,
+            identifier("a"),
+            Token::Keyword(Keyword::As),
+            Token::Keyword(Keyword::From),
+            identifier("mod"),
+            Token::CloseBrace,
+        ];
+
+        let error = parse_using_block(&mut TokenStream::new(&tokens)).unwrap_err();
+        assert_eq!(error.message, "Expected an alias after 'as'");
+        assert_eq!(error.found, Some("Keyword(From)".to_string()));
+    }
+
+    #[test]
+    fn test_missing_from_is_an_error() {
+        let tokens = vec![
+            Token::OpenBrace,
+            identifier("a"),
+            Token::ListSeparator,
+            identifier("b"),
+            Token::Newline,
+            Token::CloseBrace,
+        ];
+
+        let error = parse_using_block(&mut TokenStream::new(&tokens)).unwrap_err();
+        assert_eq!(error.message, "Expected 'from' followed by a source module");
+    }
+
+    #[test]
+    fn test_parse_wildcard_import() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::Newline,
+            Token::Spread,
+            Token::Keyword(Keyword::From),
+            identifier("math"),
+            Token::Newline,
+            Token::CloseBrace,
+        ];
+
+        assert_eq!(parse_using_block(&mut TokenStream::new(&tokens)), Ok(vec![wildcard("math")]));
+    }
+
+    #[test]
+    fn test_parse_wildcard_with_named_imports() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::Operator(Operator::Times),
+            Token::ListSeparator,
+            identifier("sqrt"),
+            Token::Keyword(Keyword::As),
+            identifier("root"),
+            Token::Keyword(Keyword::From),
+            identifier("math"),
+            Token::CloseBrace,
+        ];
+
+        let expected = vec![wildcard("math"), import("sqrt", "root", "math")];
+
+        assert_eq!(parse_using_block(&mut TokenStream::new(&tokens)), Ok(expected));
+    }
+
+    #[test]
+    fn test_wildcard_cannot_have_an_alias() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::Operator(Operator::Times),
+            Token::Keyword(Keyword::As),
+            identifier("m"),
+            Token::Keyword(Keyword::From),
+            identifier("math"),
+            Token::CloseBrace,
+        ];
 
+        let result = parse_using_block(&mut TokenStream::new(&tokens));
 
-pub fn parse_using_block(tokens: &mut Peekable<Iter<Token>>) -> tree::Import {
-    panic!("Not implemented");
+        assert_eq!(result.unwrap_err().message, "Expected 'from' followed by a source module");
+    }
 }
@@ -1,17 +1,159 @@
 use crate::tokens::Token;
+use crate::span::Span;
+use crate::tree::{Function, StatementBlock, Expression};
+use crate::lexer::is_spread_position;
+use crate::elements::{Keyword, Operator};
+use crate::parser::token_stream::TokenStream;
 
 mod utils;
+mod errors;
+mod token_stream;
+mod precedence;
 
 mod module_parser;
 mod import_parser;
 mod function_parser;
+mod struct_parser;
 pub mod statement_parser;
 mod expression_parser;
 mod atomic_parser;
 
+pub use errors::ParseError;
+pub use precedence::PrecedenceTable;
+pub use token_stream::DEFAULT_MAX_STATEMENTS;
 
-pub fn parse(tokens: &[Token]) -> crate::tree::Module {
-    let mut input = tokens.iter().peekable();
+
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    // Ignore new lines, so that statements are only ended by semicolons and braces
+    pub free_form: bool,
+    // Parsing stops with an error once the module has more statements than this, counting the
+    // statements in every block
+    pub max_statements: usize,
+}
+
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions { free_form: false, max_statements: DEFAULT_MAX_STATEMENTS }
+    }
+}
+
+
+pub fn parse(tokens: &[Token]) -> Result<crate::tree::Module, ParseError> {
+    let mut input = TokenStream::new(tokens);
+
+    module_parser::parse_module(&mut input)
+}
+
+
+// Parses with a custom operator precedence, for languages built on top of this one
+pub fn parse_with_precedence(tokens: &[Token], precedence: &PrecedenceTable) -> Result<crate::tree::Module, ParseError> {
+    let mut input = TokenStream::new(tokens).with_precedence(precedence);
 
     module_parser::parse_module(&mut input)
 }
+
+
+// Free-form parsing drops the new lines before parsing, which leaves semicolons and braces to end
+// the statements
+pub fn parse_with_options(tokens: &[Token], spans: &[Span], options: &ParseOptions) -> Result<crate::tree::Module, ParseError> {
+    let (free_form_tokens, free_form_spans);
+    let (tokens, spans) = if options.free_form {
+        (free_form_tokens, free_form_spans) = drop_newlines(tokens, spans);
+        (&free_form_tokens[..], &free_form_spans[..])
+    } else {
+        (tokens, spans)
+    };
+    let mut input = TokenStream::with_spans(tokens, spans).with_max_statements(options.max_statements);
+
+    module_parser::parse_module(&mut input)
+}
+
+
+// Keeps the span of every token that's left. The lexer reads a star at the start of a line as a
+// spread, but without the new line it's a multiplication unless it starts an element of a list.
+fn drop_newlines(tokens: &[Token], spans: &[Span]) -> (Vec<Token>, Vec<Span>) {
+    let mut kept_tokens = vec![];
+    let mut kept_spans = vec![];
+    for (index, token) in tokens.iter().enumerate() {
+        let token = match token {
+            Token::Newline => continue,
+            Token::Spread if !is_spread_position(kept_tokens.last()) => Token::Operator(Operator::Times),
+            token => token.clone(),
+        };
+        kept_tokens.push(token);
+        kept_spans.extend(spans.get(index));
+    }
+    (kept_tokens, kept_spans)
+}
+
+
+// Parses tokens along with their spans, so that the nodes of the tree are given spans as well
+pub fn parse_with_spans(tokens: &[Token], spans: &[Span]) -> Result<crate::tree::Module, ParseError> {
+    let mut input = TokenStream::with_spans(tokens, spans);
+
+    module_parser::parse_module(&mut input)
+}
+
+
+// Parses every statement it can, rather than stopping at the first error. A statement that fails to
+// parse is skipped up to the end of its line, and its error is collected with the others, so a module
+// is given even when there are errors, as long as they are all within statements. An error outside
+// of a statement still stops parsing, and is the last error given, with no module.
+pub fn parse_with_recovery(tokens: &[Token], spans: &[Span]) -> (Option<crate::tree::Module>, Vec<ParseError>) {
+    let mut input = TokenStream::with_spans(tokens, spans).with_recovery();
+
+    let module = module_parser::parse_module(&mut input);
+    let mut errors = input.take_errors();
+    match module {
+        Ok(module) => (Some(module), errors),
+        Err(error) => {
+            errors.push(error);
+            (None, errors)
+        },
+    }
+}
+
+
+// Parses a single function starting at its fun keyword, so that an editor can reparse just the
+// function that changed instead of the whole module
+pub fn parse_function(tokens: &[Token]) -> Result<Function, ParseError> {
+    let mut input = TokenStream::new(tokens);
+
+    utils::expect(&mut input, Token::Keyword(Keyword::Function), "Expected a function")?;
+    function_parser::parse_function_block(&mut input)
+}
+
+
+// Parses a single block of statements enclosed in braces, so that an editor can reparse just the
+// block that changed. It takes the tokens with their spans, leaving lex errors to the caller.
+pub fn parse_statement_block(tokens: &[Token], spans: &[Span]) -> Result<StatementBlock, ParseError> {
+    let mut input = TokenStream::with_spans(tokens, spans);
+
+    statement_parser::parse_statement_block_between_braces(&mut input)
+}
+
+
+// Parses statements on their own, without the functions, structs and imports of a module, as
+// entered in the REPL
+pub fn parse_statements(tokens: &[Token]) -> Result<StatementBlock, ParseError> {
+    let mut input = TokenStream::new(tokens);
+
+    statement_parser::parse_statements_until_end_of_module(&mut input)
+}
+
+
+// Parses a single expression, which must use every token apart from new lines before the end
+pub fn parse_expression(tokens: &[Token]) -> Result<Expression, ParseError> {
+    let mut input = TokenStream::new(tokens);
+
+    let expression = expression_parser::parse_expression(&mut input)?;
+    while let Some(Token::Newline | Token::TrailingComment(_)) = input.peek() {
+        input.next();
+    }
+    if input.peek() != Some(&Token::EndOfModule) {
+        utils::handle_parse_error_for_option::<()>(&input, "Expected the end of the expression", input.peek())?;
+    }
+    Ok(expression.node)
+}
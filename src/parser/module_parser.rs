@@ -1,68 +1,97 @@
-use std::iter::Peekable;
-use std::slice::Iter;
-
 use crate::elements::{Identifier, Keyword};
 use crate::tokens::Token;
+use crate::parser::token_stream::TokenStream;
 use crate::tree::{self, Module};
+use crate::span::Spanned;
 
-use crate::parser::utils::handle_parse_error;
+use crate::parser::errors::ParseError;
+use crate::parser::utils::{handle_parse_error, expect};
 use crate::parser::import_parser::parse_using_block;
 use crate::parser::function_parser::parse_function_block;
+use crate::parser::struct_parser::parse_struct_definition;
 
 use super::statement_parser::parse_statements_until_end_of_module;
 
 
-pub fn parse_module(tokens: &mut Peekable<Iter<Token>>) -> Module {
+pub fn parse_module(tokens: &mut TokenStream) -> Result<Module, ParseError> {
 
     let mut has_import = false;
     let mut has_function = false;
     let mut has_statements = false;
 
     let mut imports: Vec<tree::Import> = vec![];
-    let mut functions: Vec<tree::Function> = vec![];
+    let mut structs: Vec<Spanned<tree::StructDefinition>> = vec![];
+    let mut functions: Vec<Spanned<tree::Function>> = vec![];
     let mut statements: tree::StatementBlock = tree::StatementBlock::empty();
 
+    let mut doc: Option<String> = None;
+
     while let Some(token) = tokens.peek() {
         match token {
-            Token::Newline => {
+            Token::Newline | Token::TrailingComment(_) => {
                 tokens.next();
             },
 
             Token::Keyword(Keyword::Using) => {
                 if !has_import && !has_function && !has_statements {
                     tokens.next();
-                    imports.push(parse_using_block(tokens));
+                    imports = parse_using_block(tokens)?;
                     has_import = true;
                 } else {
-                    handle_parse_error::<()>("Only one using block is allowed and must be at the top of the module", token);
+                    handle_parse_error::<()>(tokens, "Only one using block is allowed and must be at the top of the module", token)?;
                 }
             },
 
-            Token::Keyword(Keyword::Function) => {
+            Token::Comment(comment) => {
+                doc = Some(comment.clone());
+                tokens.next();
+            },
+
+            Token::Keyword(Keyword::Function | Keyword::Const) => {
                 if !has_statements {
-                    tokens.next();
-                    functions.push(parse_function_block(tokens));
+                    let start = tokens.peek_span();
+                    let is_const = *token == Token::Keyword(Keyword::Const);
+                    if is_const {
+                        tokens.next();
+                    }
+                    expect(tokens, Token::Keyword(Keyword::Function), "Expected fun after const")?;
+                    let mut function = parse_function_block(tokens)?;
+                    function.doc = doc.take();
+                    function.is_const = is_const;
+                    functions.push(Spanned::new(function, tokens.span_from(start)));
                     has_function = true;
                 } else {
-                    handle_parse_error::<()>("Function blocks must come before any statements", token);
+                    handle_parse_error::<()>(tokens, "Function blocks must come before any statements", token)?;
                 }
             }
 
+            Token::Keyword(Keyword::Struct) => {
+                if !has_statements {
+                    let start = tokens.peek_span();
+                    tokens.next();
+                    let definition = parse_struct_definition(tokens)?;
+                    structs.push(Spanned::new(definition, tokens.span_from(start)));
+                } else {
+                    handle_parse_error::<()>(tokens, "Struct definitions must come before any statements", token)?;
+                }
+            },
+
             Token::EndOfModule => break,
 
             _ => {
-                statements = parse_statements_until_end_of_module(tokens);
-                if statements.statements.len() > 0 {
+                statements = parse_statements_until_end_of_module(tokens)?;
+                if !statements.statements.is_empty() {
                     has_statements = true;
                 }
             },
         }
     }
 
-    Module {
+    Ok(Module {
         name: Identifier::Simple("main".to_string()),
-        imports: imports,
-        functions: functions,
-        statements: statements,
-    }
+        imports,
+        structs,
+        functions,
+        statements,
+    })
 }
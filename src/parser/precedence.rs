@@ -0,0 +1,86 @@
+use std::sync::LazyLock;
+
+use crate::elements::Operator;
+
+
+// Unary operators and casts sit between the first level and the rest, and have no binary operators
+pub const UNARY_LEVEL: usize = 1;
+
+
+pub static DEFAULT_PRECEDENCE: LazyLock<PrecedenceTable> = LazyLock::new(PrecedenceTable::default);
+
+
+// The binary operators at each level of precedence, from the tightest binding to the loosest.
+// The right operand of an operator at the first level may have a unary operator, as in 2 ** -1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecedenceTable {
+    levels: Vec<Vec<Operator>>,
+}
+
+
+impl Default for PrecedenceTable {
+    fn default() -> PrecedenceTable {
+        PrecedenceTable {
+            levels: vec![
+                vec![Operator::Power],
+                vec![], // Unary operators and casts
+                vec![Operator::Times, Operator::Divide, Operator::Modulo],
+                vec![Operator::Plus, Operator::Minus],
+                vec![Operator::BitwiseLeftShift, Operator::BitwiseRightShift],
+                vec![Operator::LessThan, Operator::LessThanOrEqual, Operator::GreaterThan, Operator::GreaterThanOrEqual, Operator::In],
+                vec![Operator::Equal, Operator::NotEqual, Operator::Is, Operator::IsNot],
+                vec![Operator::BitwiseAnd],
+                vec![Operator::BitwiseXor],
+                vec![Operator::BitwiseOr],
+                vec![Operator::And],
+                vec![Operator::Or],
+                // Like in Rust, a range binds loosest, so a membership test of one needs parentheses
+                vec![Operator::Range, Operator::InclusiveRange],
+            ],
+        }
+    }
+}
+
+
+impl PrecedenceTable {
+    pub fn num_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn operators(&self, level: usize) -> &[Operator] {
+        &self.levels[level]
+    }
+
+    pub fn level_of(&self, operator: &Operator) -> Option<usize> {
+        self.levels.iter().position(|operators| operators.contains(operator))
+    }
+
+    // Moves an operator to the given level, taking it out of the level it was in before
+    pub fn set_precedence(&mut self, operator: Operator, level: usize) {
+        if level == UNARY_LEVEL || level >= self.levels.len() {
+            panic!("Invalid precedence level for a binary operator: {}", level)
+        }
+
+        for operators in self.levels.iter_mut() {
+            operators.retain(|existing| *existing != operator);
+        }
+        self.levels[level].push(operator);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_set_precedence_moves_operator() {
+        let mut table = PrecedenceTable::default();
+        assert_eq!(table.level_of(&Operator::Plus), Some(3));
+
+        table.set_precedence(Operator::Plus, 2);
+
+        assert_eq!(table.level_of(&Operator::Plus), Some(2));
+        assert_eq!(table.operators(3), &[Operator::Minus]);
+    }
+}
@@ -1,127 +1,397 @@
-use std::iter::Peekable;
-use std::slice::Iter;
-
-use crate::elements::{ Identifier, Literal, Operator, Keyword };
+use crate::elements::{ Identifier, Operator, Keyword };
 use crate::tokens::Token;
+use crate::parser::token_stream::TokenStream;
+use crate::span::Spanned;
 use crate::tree::{
     StatementBlock, Statement,
     Expression, AtomicExpression, AssignmentStatement, Reference, DeclarationStatement, ConditionalStatement, LoopStatement, ReturnStatement,
+    ForStatement, RepeatStatement, BreakStatement, ContinueStatement, TryStatement,
 };
 
-use crate::parser::utils::{ handle_parse_error_for_option, handle_expression_parse_error };
+use crate::parser::errors::ParseError;
+use crate::parser::utils::{ handle_parse_error_for_option, handle_expression_parse_error, peek_is, expect };
 use crate::parser::expression_parser::parse_expression;
 
 
-pub fn parse_statements_until_end_of_module(tokens: &mut Peekable<Iter<Token>>) -> StatementBlock {
+pub fn parse_statements_until_end_of_module(tokens: &mut TokenStream) -> Result<StatementBlock, ParseError> {
     parse_statement_block(tokens, Token::EndOfModule)
 }
 
 
-pub fn parse_statement_block_between_braces(tokens: &mut Peekable<Iter<Token>>) -> StatementBlock {
-    if tokens.next() != Some(&Token::OpenBrace) {
-        handle_parse_error_for_option::<()>("Expected a statement block starting with open brace, found {:?}", tokens.peek());
-    }
+pub fn parse_statement_block_between_braces(tokens: &mut TokenStream) -> Result<StatementBlock, ParseError> {
+    expect(tokens, Token::OpenBrace, "Expected a statement block starting with open brace")?;
     parse_statement_block(tokens, Token::CloseBrace)
 }
 
 
-fn parse_statement_block(tokens: &mut Peekable<Iter<Token>>, end: Token) -> StatementBlock {
+fn parse_statement_block(tokens: &mut TokenStream, end: Token) -> Result<StatementBlock, ParseError> {
     match end {
         Token::CloseBrace | Token::EndOfModule => {},
         _ => panic!("This should not happen. A statement block should always be enclosed with braces or be at the top level of a module.")
     }
 
-    let mut statements = vec![];
+    let mut statements: Vec<Spanned<Statement>> = vec![];
 
     while let Some(token) = tokens.peek() {
+        let count = statements.len();
         match token {
-            Token::Newline => { tokens.next(); },
+            Token::Newline | Token::Semicolon => { tokens.next(); },
+
+            Token::TrailingComment(comment) => {
+                if let Some(statement) = statements.last_mut() {
+                    statement.trailing_comment = Some(comment.clone());
+                }
+                tokens.next();
+            },
 
             Token::CloseBrace => {
                 if end == Token::CloseBrace {
                     tokens.next(); break
                 } else {
-                    handle_parse_error_for_option::<()>("Unexpected closing brace", tokens.peek());
+                    handle_parse_error_for_option::<()>(tokens, "Unexpected closing brace", tokens.peek())?;
                 }
             },
             Token::EndOfModule => {
                 if end == Token::EndOfModule {
                     break
                 } else {
-                    handle_parse_error_for_option::<()>("Unexpected end of module", tokens.peek());
+                    handle_parse_error_for_option::<()>(tokens, "Unexpected end of module", tokens.peek())?;
                 }
             },
 
-            Token::Keyword(Keyword::If) => statements.push(parse_if_statement(tokens)),
-
-            Token::Keyword(Keyword::While) => statements.push(parse_while_statement(tokens)),
-
-            _ => statements.push(parse_statement(tokens)),
+            _ => {
+                let start = tokens.current_position();
+                match parse_block_item(tokens) {
+                    Ok(statement) => statements.push(statement),
+                    Err(error) if tokens.is_recovering() => {
+                        tokens.record_error(error);
+                        skip_to_statement_end(tokens, start);
+                    },
+                    Err(error) => return Err(error),
+                }
+            },
+        }
+        if statements.len() > count {
+            tokens.count_statement()?;
         }
     }
 
-    StatementBlock {
+    Ok(StatementBlock {
         statements,
-    }
+    })
 }
 
 
-fn parse_if_statement(tokens: &mut Peekable<Iter<Token>>) -> Statement {
-    if tokens.next() != Some(&Token::Keyword(Keyword::If)) {
-        handle_parse_error_for_option::<()>("Expected if keyword", tokens.peek());
+fn parse_block_item(tokens: &mut TokenStream) -> Result<Spanned<Statement>, ParseError> {
+    match tokens.peek() {
+        Some(Token::OpenBrace) => parse_spanned(tokens, parse_block_statement),
+        Some(Token::Keyword(Keyword::Do)) => parse_spanned(tokens, parse_do_statement),
+        Some(Token::Keyword(Keyword::If)) => parse_spanned(tokens, parse_if_statement),
+        Some(Token::Keyword(Keyword::While)) => parse_spanned(tokens, parse_while_statement),
+        Some(Token::Keyword(Keyword::For)) => parse_spanned(tokens, parse_for_statement),
+        Some(Token::Keyword(Keyword::Repeat)) => parse_spanned(tokens, parse_repeat_statement),
+        Some(Token::Keyword(Keyword::Try)) => parse_spanned(tokens, parse_try_statement),
+        Some(Token::Identifier(_)) if is_loop_label(tokens) => parse_spanned(tokens, parse_labeled_loop),
+        _ => parse_statement(tokens),
     }
+}
 
-    let condition = parse_expression(tokens);
-    println!("Condition: {:?}", condition);
-    let body = parse_statement_block_between_braces(tokens);
 
-    let else_body = if let Some(Token::Keyword(Keyword::Else)) = tokens.peek() {
+// Skips the rest of a statement that failed to parse, up to the new line, semicolon or closing
+// brace that ends it, so parsing can carry on with the next statement. A simple statement is taken
+// with its new line before it's parsed, so there's nothing left to skip once it has failed. When
+// the statement opened a brace, everything up to the matching closing brace is skipped too, so the
+// rest of its block isn't read as statements of the enclosing block.
+fn skip_to_statement_end(tokens: &mut TokenStream, start: usize) {
+    let mut depth = tokens.consumed_since(start).iter().fold(0usize, |depth, token| match token {
+        Token::OpenBrace => depth + 1,
+        Token::CloseBrace => depth.saturating_sub(1),
+        _ => depth,
+    });
+
+    let has_progressed = tokens.current_position() > start;
+    if depth == 0 && has_progressed && matches!(tokens.previous(), Some(Token::Newline | Token::Semicolon)) {
+        return;
+    }
+    while let Some(token) = tokens.peek() {
+        match token {
+            Token::EndOfModule => break,
+            Token::Newline | Token::Semicolon | Token::CloseBrace if depth == 0 => break,
+            Token::OpenBrace => depth += 1,
+            Token::CloseBrace => {
+                depth -= 1;
+                if depth == 0 {
+                    tokens.next();
+                    break;
+                }
+            },
+            _ => {},
+        }
         tokens.next();
-        Some(Box::new(parse_statement_block_between_braces(tokens)))
+    }
+}
+
+
+fn parse_spanned(tokens: &mut TokenStream, parse: fn(&mut TokenStream) -> Result<Statement, ParseError>) -> Result<Spanned<Statement>, ParseError> {
+    let start = tokens.peek_span();
+    let statement = parse(tokens)?;
+    Ok(Spanned::new(statement, tokens.span_from(start)))
+}
+
+
+fn parse_block_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    Ok(Statement::Block(parse_statement_block_between_braces(tokens)?))
+}
+
+
+// `do { ... }` is the same as a bare block, but can't be mistaken for anything else
+fn parse_do_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    expect(tokens, Token::Keyword(Keyword::Do), "Expected do keyword")?;
+    parse_block_statement(tokens)
+}
+
+
+fn parse_if_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    expect(tokens, Token::Keyword(Keyword::If), "Expected if keyword")?;
+    parse_conditional(tokens)
+}
+
+
+// An elif is the same as an else if, so it's parsed as an else block containing only the nested conditional
+fn parse_elif_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    expect(tokens, Token::Keyword(Keyword::Elif), "Expected elif keyword")?;
+    parse_conditional(tokens)
+}
+
+
+fn parse_conditional(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    let condition = parse_expression(tokens)?.node;
+    let body = parse_statement_block_between_braces(tokens)?;
+    let else_body = if peek_is(tokens, &Token::Keyword(Keyword::Elif)) {
+        let conditional = parse_spanned(tokens, parse_elif_statement)?;
+        Some(Box::new(StatementBlock { statements: vec![conditional] }))
     } else {
-        None
+        parse_else_block(tokens)?
     };
 
-    Statement::Conditional(ConditionalStatement {
+    Ok(Statement::Conditional(ConditionalStatement {
         condition,
         body: Box::new(body),
         else_body,
-    })
+    }))
 }
 
 
-fn parse_while_statement(tokens: &mut Peekable<Iter<Token>>) -> Statement {
-    if tokens.next() != Some(&Token::Keyword(Keyword::While)) {
-        handle_parse_error_for_option::<()>("Expected while keyword", tokens.peek());
-    }
+fn parse_while_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    expect(tokens, Token::Keyword(Keyword::While), "Expected while keyword")?;
 
-    let condition = parse_expression(tokens);
-    let body = parse_statement_block_between_braces(tokens);
+    let condition = parse_expression(tokens)?.node;
+    let body = parse_statement_block_between_braces(tokens)?;
+    let else_body = parse_else_block(tokens)?;
 
-    Statement::Loop(LoopStatement {
+    Ok(Statement::Loop(LoopStatement {
         condition,
         body: Box::new(body),
-    })
+        label: None,
+        else_body,
+    }))
+}
+
+
+fn parse_for_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    expect(tokens, Token::Keyword(Keyword::For), "Expected for keyword")?;
+
+    let variables = parse_loop_variables(tokens)?;
+    let iterable = parse_expression(tokens)?.node;
+    let body = parse_statement_block_between_braces(tokens)?;
+    let else_body = parse_else_block(tokens)?;
+
+    Ok(Statement::For(ForStatement {
+        variables,
+        iterable,
+        body: Box::new(body),
+        label: None,
+        else_body,
+    }))
+}
+
+
+// `repeat 3 { ... }` runs the block three times, and can be written `repeat 3 times { ... }`
+fn parse_repeat_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    expect(tokens, Token::Keyword(Keyword::Repeat), "Expected repeat keyword")?;
+
+    let count = parse_expression(tokens)?.node;
+    if peek_is(tokens, &Token::Identifier(Identifier::Simple("times".to_string()))) {
+        tokens.next();
+    }
+    let body = parse_statement_block_between_braces(tokens)?;
+
+    Ok(Statement::Repeat(RepeatStatement {
+        count,
+        body: Box::new(body),
+    }))
+}
+
+
+// The one or two variables of a for loop or a comprehension, and the in that follows them
+pub fn parse_loop_variables(tokens: &mut TokenStream) -> Result<Vec<Identifier>, ParseError> {
+    let mut variables = vec![];
+    loop {
+        match tokens.next() {
+            Some(Token::Identifier(Identifier::Simple(name))) => variables.push(Identifier::Simple(name.clone())),
+            token => handle_parse_error_for_option(tokens, "Expected a loop variable", token)?,
+        }
+        if variables.len() == 2 || !peek_is(tokens, &Token::ListSeparator) {
+            break;
+        }
+        tokens.next();
+    }
+
+    expect(tokens, Token::Keyword(Keyword::In), "Expected in after the loop variables")?;
+    Ok(variables)
+}
+
+
+// An else if is parsed as an else block containing only the nested if statement
+fn parse_else_block(tokens: &mut TokenStream) -> Result<Option<Box<StatementBlock>>, ParseError> {
+    if !peek_is(tokens, &Token::Keyword(Keyword::Else)) {
+        return Ok(None);
+    }
+    tokens.next();
+
+    if peek_is(tokens, &Token::Keyword(Keyword::If)) {
+        let conditional = parse_spanned(tokens, parse_if_statement)?;
+        Ok(Some(Box::new(StatementBlock { statements: vec![conditional] })))
+    } else {
+        Ok(Some(Box::new(parse_statement_block_between_braces(tokens)?)))
+    }
 }
 
 
-pub fn parse_statement(all_tokens: &mut Peekable<Iter<Token>>) -> Statement {
-    let tokens_vec = consume_statement_tokens(all_tokens);
-    let tokens = &mut tokens_vec.iter().peekable();
+fn parse_try_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    expect(tokens, Token::Keyword(Keyword::Try), "Expected try keyword")?;
+
+    let body = parse_statement_block_between_braces(tokens)?;
+
+    let (handler_var, handler_body) = if peek_is(tokens, &Token::Keyword(Keyword::Except)) {
+        tokens.next();
+        let handler_var = match tokens.peek() {
+            Some(Token::Identifier(Identifier::Simple(name))) => {
+                tokens.next();
+                Some(Identifier::Simple(name.clone()))
+            },
+            _ => None,
+        };
+        (handler_var, Some(Box::new(parse_statement_block_between_braces(tokens)?)))
+    } else {
+        (None, None)
+    };
+
+    Ok(Statement::Try(TryStatement {
+        body: Box::new(body),
+        handler_var,
+        handler_body,
+    }))
+}
+
+
+fn is_loop_label(tokens: &TokenStream) -> bool {
+    let mut lookahead = tokens.clone();
+    matches!(
+        (lookahead.next(), lookahead.next(), lookahead.next()),
+        (Some(Token::Identifier(_)), Some(Token::Colon), Some(Token::Keyword(Keyword::While | Keyword::For)))
+    )
+}
+
+
+fn parse_labeled_loop(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    let label = match tokens.next() {
+        Some(Token::Identifier(identifier)) => identifier.clone(),
+        token => handle_parse_error_for_option(tokens, "Expected a loop label", token)?,
+    };
 
-    if tokens.peek() == Some(&&Token::Keyword(Keyword::Return)) {
+    expect(tokens, Token::Colon, "Expected colon after loop label")?;
+
+    let statement = if peek_is(tokens, &Token::Keyword(Keyword::For)) {
+        parse_for_statement(tokens)?
+    } else {
+        parse_while_statement(tokens)?
+    };
+
+    match statement {
+        Statement::Loop(loop_statement) => Ok(Statement::Loop(LoopStatement {
+            label: Some(label),
+            ..loop_statement
+        })),
+        Statement::For(for_statement) => Ok(Statement::For(ForStatement {
+            label: Some(label),
+            ..for_statement
+        })),
+        _ => panic!("This should not happen. A while or for statement should always parse to a loop."),
+    }
+}
+
+
+// A statement must use all of its tokens, so that two statements without a new line or semicolon
+// between them are an error rather than the second being dropped
+pub fn parse_statement(all_tokens: &mut TokenStream) -> Result<Spanned<Statement>, ParseError> {
+    let tokens = &mut consume_statement_tokens(all_tokens);
+    let statement = parse_spanned(tokens, parse_simple_statement)?;
+    if tokens.peek().is_some() {
+        handle_parse_error_for_option::<()>(tokens, "Expected a new line or semicolon at the end of the statement", tokens.peek())?;
+    }
+    Ok(statement)
+}
+
+
+fn parse_simple_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    if peek_is(tokens, &Token::Keyword(Keyword::Return)) {
         return parse_return_statement(tokens);
     }
 
-    let is_variable = match tokens.peek() {
+    if let Some(Token::Keyword(Keyword::Break | Keyword::Continue)) = tokens.peek() {
+        return parse_loop_control_statement(tokens);
+    }
+
+    if peek_is(tokens, &Token::Keyword(Keyword::Delete)) {
+        return parse_delete_statement(tokens);
+    }
+
+    if peek_is(tokens, &Token::Keyword(Keyword::Global)) {
+        tokens.next();
+        return match tokens.next() {
+            Some(Token::Identifier(name @ Identifier::Simple(_))) => Ok(Statement::Global(name.clone())),
+            token => handle_parse_error_for_option(tokens, "Expected a variable name after global", token),
+        };
+    }
+
+    if peek_is(tokens, &Token::Keyword(Keyword::Raise)) {
+        tokens.next();
+        return Ok(Statement::Raise(parse_expression(tokens)?.node));
+    }
+
+    if peek_is(tokens, &Token::Keyword(Keyword::Pass)) {
+        tokens.next();
+        if tokens.peek().is_some() {
+            handle_parse_error_for_option::<()>(tokens, "Unexpected token after pass", tokens.peek())?;
+        }
+        return Ok(Statement::Pass);
+    }
+
+    // A declaration with let is the same as one without a keyword, and is immutable
+    let (is_variable, is_declaration) = match tokens.peek() {
         Some(Token::Keyword(Keyword::Variable)) => {
             tokens.next();
-            true
+            (true, true)
         },
-        _ => false,
+        Some(Token::Keyword(Keyword::Let)) => {
+            tokens.next();
+            (false, true)
+        },
+        _ => (false, false),
     };
 
-    let mut left = parse_expression(tokens);
+    let left = parse_expression(tokens)?;
 
     let token = tokens.peek();
     match token {
@@ -133,89 +403,147 @@ pub fn parse_statement(all_tokens: &mut Peekable<Iter<Token>>) -> Statement {
             parse_declaration_statement(left, tokens, is_variable)
         }
 
-        Some(Token::Assign) => if is_variable {
-            handle_parse_error_for_option("A variable declaration must be followed by a type", token)
+        Some(Token::Assign) => if is_declaration {
+            handle_parse_error_for_option(tokens, "A variable declaration must be followed by a type", token)
         } else {
             tokens.next();
-            let right = parse_expression(tokens);
-            convert_assignment_statement(left, right)
+            let mut targets = vec![left];
+            let mut right = parse_expression(tokens)?;
+
+            // Assignments chain, so in a = b = 0 the value is assigned to both a and b
+            while peek_is(tokens, &Token::Assign) {
+                tokens.next();
+                targets.push(right);
+                right = parse_expression(tokens)?;
+            }
+            convert_assignment_statement(targets, right.node)
         },
 
         Some(Token::BinaryAssign(operator)) => {
             tokens.next();
-            let right = get_binary_expansion(left.clone(), operator, parse_expression(tokens));
-            convert_assignment_statement(left, right)
+            let right = get_binary_expansion(left.clone(), operator, parse_expression(tokens)?);
+            convert_assignment_statement(vec![left], right)
         },
 
-        None => Statement::Expression(left),
+        None | Some(Token::EndOfModule) => Ok(Statement::Expression(left.node)),
 
         _ => {
-            handle_parse_error_for_option("Unrecognised token in statement", token)
+            handle_parse_error_for_option(tokens, "Unrecognised token in statement", token)
         },
     }
 }
 
 
-fn parse_return_statement(tokens: &mut Peekable<Iter<Token>>) -> Statement {
-    if tokens.next() != Some(&Token::Keyword(Keyword::Return)) {
-        handle_parse_error_for_option::<()>("Expected return keyword", tokens.peek());
+fn parse_return_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    expect(tokens, Token::Keyword(Keyword::Return), "Expected return keyword")?;
+
+    // The tokens of the statement have already been separated out, so a bare return has none left
+    if tokens.peek().is_none() {
+        return Ok(Statement::Return(ReturnStatement { value: None }));
     }
 
-    let value = parse_expression(tokens);
-    Statement::Return(ReturnStatement {
-        value,
-    })
+    let first = parse_expression(tokens)?.node;
+
+    // A comma after the first value returns all of the values as a tuple
+    let value = if peek_is(tokens, &Token::ListSeparator) {
+        let mut values = vec![first];
+        while peek_is(tokens, &Token::ListSeparator) {
+            tokens.next();
+            values.push(parse_expression(tokens)?.node);
+        }
+        Expression::Tuple(values)
+    } else {
+        first
+    };
+
+    Ok(Statement::Return(ReturnStatement {
+        value: Some(value),
+    }))
 }
 
 
-fn consume_statement_tokens(tokens: &mut Peekable<Iter<Token>>) -> Vec<Token> {
-    let mut statement_tokens = vec![];
+fn parse_delete_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    expect(tokens, Token::Keyword(Keyword::Delete), "Expected del keyword")?;
 
-    while let Some(token) = tokens.peek() {
-        match token {
-            Token::CloseBrace => break,  // Don't consume a closing brace
+    let reference = convert_expression_to_reference(parse_expression(tokens)?)?;
 
-            Token::Newline => {
-                tokens.next();  // New line is consumed
-                break
-            },
+    if tokens.peek().is_some() {
+        handle_parse_error_for_option::<()>(tokens, "Unexpected token after deleted reference", tokens.peek())?;
+    }
 
-            _ => {
-                let next = tokens.next();
-                match next {
-                    Some(token) => statement_tokens.push(token.clone()),
-                    None => handle_parse_error_for_option("Expected a token", next),
-                }
-            }
-        }
+    Ok(Statement::Delete(reference))
+}
+
+
+fn parse_loop_control_statement(tokens: &mut TokenStream) -> Result<Statement, ParseError> {
+    let keyword = tokens.next();
+
+    let label = match tokens.next() {
+        Some(Token::Identifier(Identifier::Simple(name))) => Some(Identifier::Simple(name.clone())),
+        None => None,
+        token => handle_parse_error_for_option(tokens, "Expected a loop label or the end of the statement", token)?,
+    };
+
+    if tokens.peek().is_some() {
+        handle_parse_error_for_option::<()>(tokens, "Unexpected token after loop label", tokens.peek())?;
+    }
+
+    match keyword {
+        Some(Token::Keyword(Keyword::Break)) => Ok(Statement::Break(BreakStatement { label })),
+        Some(Token::Keyword(Keyword::Continue)) => Ok(Statement::Continue(ContinueStatement { label })),
+        token => handle_parse_error_for_option(tokens, "Expected break or continue keyword", token),
+    }
+}
+
+
+fn consume_statement_tokens<'a>(tokens: &mut TokenStream<'a>) -> TokenStream<'a> {
+    // Don't consume a closing brace or the end of the module, since they also end the enclosing
+    // block. Braces opened within the statement, like those of a map literal, are kept together.
+    let mut depth = 0;
+    let statement_tokens = tokens.consume_while(|token| match token {
+        Token::OpenBrace => { depth += 1; true },
+        Token::CloseBrace if depth > 0 => { depth -= 1; true },
+        Token::CloseBrace | Token::EndOfModule => false,
+        Token::Newline | Token::Semicolon | Token::TrailingComment(_) => depth > 0,
+        _ => true,
+    });
+
+    if peek_is(tokens, &Token::Newline) || peek_is(tokens, &Token::Semicolon) {
+        tokens.next();  // The end of the statement is consumed
     }
 
     statement_tokens
 }
 
 
-fn convert_assignment_statement(left: Expression, right: Expression) -> Statement {
-    Statement::Assignment(
+fn convert_assignment_statement(targets: Vec<Spanned<Expression>>, right: Expression) -> Result<Statement, ParseError> {
+    Ok(Statement::Assignment(
         AssignmentStatement {
-            reference: convert_expression_to_reference(left),
+            references: targets.into_iter().map(convert_expression_to_reference).collect::<Result<_, _>>()?,
             value: right,
         }
-    )
+    ))
 }
 
 
-fn convert_expression_to_reference(expression: Expression) -> Reference {
-    match expression {
-        Expression::Atomic(AtomicExpression::Identifier(identifier)) => Reference::Identifier(identifier),
+fn convert_expression_to_reference(expression: Spanned<Expression>) -> Result<Reference, ParseError> {
+    match expression.node {
+        Expression::Atomic(AtomicExpression::Identifier(identifier)) => Ok(Reference::Identifier(identifier)),
 
-        // TODO: Array index
+        Expression::Atomic(AtomicExpression::ArrayIndex(array_index)) => {
+            let array = Spanned::new(Expression::Atomic(*array_index.array), expression.span);
+            Ok(Reference::ArrayReference {
+                array: Box::new(convert_expression_to_reference(array)?),
+                index: array_index.index,
+            })
+        },
 
-        _ => handle_expression_parse_error("Expected a reference before an assignment.", &expression)
+        _ => handle_expression_parse_error("Expected a reference", &expression)
     }
 }
 
 
-fn get_binary_expansion(left: Expression, operator: &Operator, right: Expression) -> Expression {
+fn get_binary_expansion(left: Spanned<Expression>, operator: &Operator, right: Spanned<Expression>) -> Expression {
     Expression::BinaryOperation {
         left: Box::new(left),
         operator: operator.clone(),
@@ -224,33 +552,37 @@ fn get_binary_expansion(left: Expression, operator: &Operator, right: Expression
 }
 
 
-fn parse_declaration_statement(left: Expression, tokens: &mut Peekable<Iter<Token>>, is_variable: bool) -> Statement {
-    let name = match left {
+fn parse_declaration_statement(left: Spanned<Expression>, tokens: &mut TokenStream, is_variable: bool) -> Result<Statement, ParseError> {
+    let name = match &left.node {
         Expression::Atomic(AtomicExpression::Identifier(identifier)) => identifier.clone(),
-        _ => handle_expression_parse_error("Expected an identifier in a declaration statement", &left),
+        _ => handle_expression_parse_error("Expected an identifier in a declaration statement", &left)?,
     };
 
     let var_type = match tokens.next() {
         Some(Token::Identifier(identifier)) => identifier.clone(),
-        token => handle_parse_error_for_option("Expected a type after colon", token),
+        token => handle_parse_error_for_option(tokens, "Expected a type after colon", token)?,
     };
 
     let value = match tokens.next() {
-        Some(Token::Assign) => parse_expression(tokens),
-        _ => handle_parse_error_for_option("Expected variable to be initialised", tokens.peek()),
+        Some(Token::Assign) => parse_expression(tokens)?.node,
+        _ => handle_parse_error_for_option(tokens, "Expected variable to be initialised", tokens.peek())?,
     };
 
-    Statement::Declaration(DeclarationStatement {
+    Ok(Statement::Declaration(DeclarationStatement {
         name,
         var_type,
         value,
         is_mutable: is_variable,
-    })
+    }))
 }
 
 
 #[cfg(test)]
 mod test {
+    use crate::elements::Literal;
+    use crate::lexer::tokenize;
+    use crate::tree::{ArrayIndex, FunctionCallExpression};
+
     use super::*;
 
     #[test]
@@ -259,9 +591,9 @@ mod test {
             Token::OpenBrace,
             Token::CloseBrace,
         ];
-        let mut tokens = tokens.iter().peekable();
+        let mut tokens = TokenStream::new(&tokens);
 
-        let result = parse_statement_block_between_braces(&mut tokens);
+        let result = parse_statement_block_between_braces(&mut tokens).unwrap();
 
         let expected = StatementBlock {
             statements: vec![],
@@ -277,9 +609,9 @@ mod test {
             Token::Newline,
             Token::CloseBrace,
         ];
-        let mut tokens = tokens.iter().peekable();
+        let mut tokens = TokenStream::new(&tokens);
 
-        let result = parse_statement_block_between_braces(&mut tokens);
+        let result = parse_statement_block_between_braces(&mut tokens).unwrap();
 
         let expected = StatementBlock {
             statements: vec![],
@@ -298,18 +630,18 @@ mod test {
             Token::Newline,
             Token::CloseBrace,
         ];
-        let mut tokens = tokens.iter().peekable();
+        let mut tokens = TokenStream::new(&tokens);
 
-        let result = parse_statement_block_between_braces(&mut tokens);
+        let result = parse_statement_block_between_braces(&mut tokens).unwrap();
 
         let expected = StatementBlock {
             statements: vec![
                 Statement::Assignment(
                     AssignmentStatement {
-                        reference: Reference::Identifier(Identifier::Simple("identifier".to_string())),
+                        references: vec![Reference::Identifier(Identifier::Simple("identifier".to_string()))],
                         value: Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))),
                     }
-                )
+                ).into()
             ],
         };
 
@@ -330,28 +662,60 @@ mod test {
             Token::Newline,
             Token::CloseBrace,
         ];
-        let mut tokens = tokens.iter().peekable();
+        let mut tokens = TokenStream::new(&tokens);
 
-        let result = parse_statement_block_between_braces(&mut tokens);
+        let result = parse_statement_block_between_braces(&mut tokens).unwrap();
 
         let expected = StatementBlock {
             statements: vec![
                 Statement::Assignment(
                     AssignmentStatement {
-                        reference: Reference::Identifier(Identifier::Simple("first".to_string())),
+                        references: vec![Reference::Identifier(Identifier::Simple("first".to_string()))],
                         value: Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))),
                     }
-                ),
+                ).into(),
                 Statement::Assignment(
                     AssignmentStatement {
-                        reference: Reference::Identifier(Identifier::Simple("second".to_string())),
+                        references: vec![Reference::Identifier(Identifier::Simple("second".to_string()))],
                         value: Expression::Atomic(AtomicExpression::Literal(Literal::Integer(2))),
                     }
-                ),
+                ).into(),
+            ],
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_statement_block_final_call_without_newline() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::Identifier(Identifier::Simple("print".to_string())),
+            Token::OpenParen,
+            Token::Identifier(Identifier::Simple("x".to_string())),
+            Token::CloseParen,
+            Token::CloseBrace,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+
+        let result = parse_statement_block_between_braces(&mut tokens).unwrap();
+
+        let expected = StatementBlock {
+            statements: vec![
+                Statement::Expression(Expression::Atomic(AtomicExpression::FunctionCall(
+                    FunctionCallExpression {
+                        name: Identifier::Simple("print".to_string()),
+                        parameters: vec![
+                            Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("x".to_string()))),
+                        ],
+                        keyword_arguments: vec![],
+                    }
+                ))).into()
             ],
         };
 
         assert_eq!(result, expected);
+        assert_eq!(tokens.peek(), None);
     }
 
     #[test]
@@ -361,13 +725,13 @@ mod test {
             Token::Assign,
             Token::Literal(Literal::Integer(1)),
         ];
-        let mut tokens = tokens.iter().peekable();
+        let mut tokens = TokenStream::new(&tokens);
 
-        let result = parse_statement(&mut tokens);
+        let result = parse_statement(&mut tokens).unwrap();
 
         let expected = Statement::Assignment(
             AssignmentStatement {
-                reference: Reference::Identifier(Identifier::Simple("identifier".to_string())),
+                references: vec![Reference::Identifier(Identifier::Simple("identifier".to_string()))],
                 value: Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))),
             }
         );
@@ -375,6 +739,47 @@ mod test {
         assert_eq!(result, expected)
     }
 
+    #[test]
+    fn test_parse_chained_assignment_statement() {
+        let tokens = vec![
+            Token::Identifier(Identifier::Simple("a".to_string())),
+            Token::Assign,
+            Token::Identifier(Identifier::Simple("b".to_string())),
+            Token::Assign,
+            Token::Literal(Literal::Integer(0)),
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+
+        let result = parse_statement(&mut tokens).unwrap();
+
+        let expected = Statement::Assignment(
+            AssignmentStatement {
+                references: vec![
+                    Reference::Identifier(Identifier::Simple("a".to_string())),
+                    Reference::Identifier(Identifier::Simple("b".to_string())),
+                ],
+                value: Expression::Atomic(AtomicExpression::Literal(Literal::Integer(0))),
+            }
+        );
+
+        assert_eq!(result, expected)
+    }
+
+    #[test]
+    fn test_chained_assignment_to_non_reference() {
+        let tokens = vec![
+            Token::Identifier(Identifier::Simple("a".to_string())),
+            Token::Assign,
+            Token::Literal(Literal::Integer(1)),
+            Token::Assign,
+            Token::Literal(Literal::Integer(0)),
+        ];
+
+        let result = parse_statement(&mut TokenStream::new(&tokens));
+
+        assert_eq!(result.unwrap_err().message, "Expected a reference");
+    }
+
     #[test]
     fn test_parse_binary_assignment_statement() {
         let tokens = vec![
@@ -382,17 +787,17 @@ mod test {
             Token::BinaryAssign(Operator::Plus),
             Token::Literal(Literal::Integer(1)),
         ];
-        let mut tokens = tokens.iter().peekable();
+        let mut tokens = TokenStream::new(&tokens);
 
-        let result = parse_statement(&mut tokens);
+        let result = parse_statement(&mut tokens).unwrap();
 
         let expected = Statement::Assignment(
             AssignmentStatement {
-                reference: Reference::Identifier(Identifier::Simple("identifier".to_string())),
+                references: vec![Reference::Identifier(Identifier::Simple("identifier".to_string()))],
                 value: Expression::BinaryOperation {
-                    left: Box::new(Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("identifier".to_string())))),
+                    left: Box::new(Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("identifier".to_string()))).into()),
                     operator: Operator::Plus,
-                    right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))),
+                    right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))).into()),
                 },
             }
         );
@@ -405,9 +810,9 @@ mod test {
         let tokens = vec![
             Token::Literal(Literal::Integer(1)),
         ];
-        let mut tokens = tokens.iter().peekable();
+        let mut tokens = TokenStream::new(&tokens);
 
-        let result = parse_statement(&mut tokens);
+        let result = parse_statement(&mut tokens).unwrap();
 
         let expected = Statement::Expression(
             Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))
@@ -426,9 +831,9 @@ mod test {
             Token::Identifier(Identifier::Simple("second".to_string())),
         ];
 
-        let mut tokens = tokens_vec.iter().peekable();
+        let mut tokens = TokenStream::new(&tokens_vec);
 
-        let result = consume_statement_tokens(&mut tokens);
+        let result: Vec<Token> = consume_statement_tokens(&mut tokens).cloned().collect();
 
         let expected = vec![
             Token::Identifier(Identifier::Simple("first".to_string())),
@@ -445,11 +850,11 @@ mod test {
         let left = Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("identifier".to_string())));
         let right = Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)));
 
-        let result = convert_assignment_statement(left, right);
+        let result = convert_assignment_statement(vec![left.into()], right).unwrap();
 
         let expected = Statement::Assignment(
             AssignmentStatement {
-                reference: Reference::Identifier(Identifier::Simple("identifier".to_string())),
+                references: vec![Reference::Identifier(Identifier::Simple("identifier".to_string()))],
                 value: Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))),
             }
         );
@@ -463,12 +868,12 @@ mod test {
         let operator = Operator::Plus;
         let right = Expression::Atomic(AtomicExpression::Literal(Literal::Integer(2)));
 
-        let result = get_binary_expansion(left, &operator, right);
+        let result = get_binary_expansion(left.into(), &operator, right.into());
 
         let expected = Expression::BinaryOperation {
-            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))),
+            left: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))).into()),
             operator: Operator::Plus,
-            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(2)))),
+            right: Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(2))).into()),
         };
 
         assert_eq!(result, expected);
@@ -482,9 +887,9 @@ mod test {
             Token::Assign,
             Token::Literal(Literal::Integer(1)),
         ];
-        let mut tokens = tokens.iter().peekable();
+        let mut tokens = TokenStream::new(&tokens);
 
-        let result = parse_declaration_statement(left, &mut tokens, true);
+        let result = parse_declaration_statement(left.into(), &mut tokens, true).unwrap();
 
         let expected = Statement::Declaration(
             DeclarationStatement {
@@ -506,9 +911,9 @@ mod test {
             Token::Assign,
             Token::Literal(Literal::Integer(1)),
         ];
-        let mut tokens = tokens.iter().peekable();
+        let mut tokens = TokenStream::new(&tokens);
 
-        let result = parse_declaration_statement(left, &mut tokens, false);
+        let result = parse_declaration_statement(left.into(), &mut tokens, false).unwrap();
 
         let expected = Statement::Declaration(
             DeclarationStatement {
@@ -522,6 +927,223 @@ mod test {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_parse_let_declaration() {
+        let parse_source = |source: &str| {
+            let tokens = tokenize(source);
+            parse_statement_block(&mut TokenStream::new(&tokens), Token::EndOfModule)
+        };
+
+        let expected = Statement::Declaration(DeclarationStatement {
+            name: Identifier::Simple("x".to_string()),
+            var_type: Identifier::Simple("int".to_string()),
+            value: Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))),
+            is_mutable: false,
+        });
+
+        assert_eq!(parse_source("let x: int = 1\n").unwrap().statements, vec![expected]);
+        assert_eq!(parse_source("let x: int = 1\n"), parse_source("x: int = 1\n"));
+        assert_eq!(parse_source("let x = 1\n").unwrap_err().message, "A variable declaration must be followed by a type");
+    }
+
+    #[test]
+    fn test_parse_statement_without_newline_before_end_of_module() {
+        let tokens = vec![
+            Token::Identifier(Identifier::Simple("x".to_string())),
+            Token::Assign,
+            Token::Literal(Literal::Integer(1)),
+            Token::EndOfModule,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_statements_until_end_of_module(&mut tokens).unwrap();
+
+        let expected = StatementBlock {
+            statements: vec![
+                Statement::Assignment(AssignmentStatement {
+                    references: vec![Reference::Identifier(Identifier::Simple("x".to_string()))],
+                    value: Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))),
+                }).into()
+            ],
+        };
+
+        assert_eq!(result, expected);
+        assert_eq!(tokens.next(), Some(&Token::EndOfModule));
+    }
+
+    #[test]
+    fn test_parse_return_statement() {
+        let tokens = vec![
+            Token::Keyword(Keyword::Return),
+            Token::Identifier(Identifier::Simple("a".to_string())),
+        ];
+        let result = parse_statement(&mut TokenStream::new(&tokens)).unwrap();
+
+        let expected = Statement::Return(ReturnStatement {
+            value: Some(Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("a".to_string())))),
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_bare_return_statement() {
+        let tokens = vec![Token::Keyword(Keyword::Return), Token::Newline];
+        let result = parse_statement(&mut TokenStream::new(&tokens)).unwrap();
+
+        assert_eq!(result, Statement::Return(ReturnStatement { value: None }));
+    }
+
+    #[test]
+    fn test_parse_return_statement_with_multiple_values() {
+        let tokens = vec![
+            Token::Keyword(Keyword::Return),
+            Token::Identifier(Identifier::Simple("a".to_string())),
+            Token::ListSeparator,
+            Token::Identifier(Identifier::Simple("b".to_string())),
+        ];
+        let result = parse_statement(&mut TokenStream::new(&tokens)).unwrap();
+
+        let expected = Statement::Return(ReturnStatement {
+            value: Some(Expression::Tuple(vec![
+                Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("a".to_string()))),
+                Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("b".to_string()))),
+            ])),
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_raise_statement() {
+        let tokens = vec![
+            Token::Keyword(Keyword::Raise),
+            Token::Literal(Literal::String("boom".to_string())),
+        ];
+        let result = parse_statement(&mut TokenStream::new(&tokens)).unwrap();
+
+        assert_eq!(result, Statement::Raise(Expression::Atomic(AtomicExpression::Literal(Literal::String("boom".to_string())))));
+    }
+
+    #[test]
+    fn test_parse_delete_variable() {
+        let tokens = vec![
+            Token::Keyword(Keyword::Delete),
+            Token::Identifier(Identifier::Simple("x".to_string())),
+        ];
+        let result = parse_statement(&mut TokenStream::new(&tokens)).unwrap();
+
+        assert_eq!(result, Statement::Delete(Reference::Identifier(Identifier::Simple("x".to_string()))));
+    }
+
+    #[test]
+    fn test_parse_delete_array_element() {
+        let tokens = vec![
+            Token::Keyword(Keyword::Delete),
+            Token::Identifier(Identifier::Simple("arr".to_string())),
+            Token::OpenSquareBracket,
+            Token::Literal(Literal::Integer(0)),
+            Token::CloseSquareBracket,
+        ];
+        let result = parse_statement(&mut TokenStream::new(&tokens)).unwrap();
+
+        let expected = Statement::Delete(Reference::ArrayReference {
+            array: Box::new(Reference::Identifier(Identifier::Simple("arr".to_string()))),
+            index: ArrayIndex::Single(Box::new(Expression::Atomic(AtomicExpression::Literal(Literal::Integer(0))).into())),
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_block_containing_only_pass() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::Keyword(Keyword::Pass),
+            Token::Newline,
+            Token::CloseBrace,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+
+        let result = parse_statement_block_between_braces(&mut tokens).unwrap();
+
+        assert_eq!(result, StatementBlock { statements: vec![Statement::Pass.into()] });
+    }
+
+    #[test]
+    fn test_parse_nested_block_statement() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::OpenBrace,
+            Token::Literal(Literal::Integer(1)),
+            Token::Newline,
+            Token::CloseBrace,
+            Token::Newline,
+            Token::OpenBrace,
+            Token::CloseBrace,
+            Token::CloseBrace,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_statement_block_between_braces(&mut tokens).unwrap();
+
+        let expected = StatementBlock {
+            statements: vec![
+                Statement::Block(StatementBlock {
+                    statements: vec![
+                        Statement::Expression(
+                            Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))
+                        ).into()
+                    ],
+                }).into(),
+                Statement::Block(StatementBlock::empty()).into(),
+            ],
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_do_block_statement() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::Keyword(Keyword::Do),
+            Token::OpenBrace,
+            Token::Literal(Literal::Integer(1)),
+            Token::Newline,
+            Token::CloseBrace,
+            Token::CloseBrace,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_statement_block_between_braces(&mut tokens).unwrap();
+
+        let expected = StatementBlock {
+            statements: vec![
+                Statement::Block(StatementBlock {
+                    statements: vec![
+                        Statement::Expression(
+                            Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))
+                        ).into()
+                    ],
+                }).into(),
+            ],
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_do_without_a_block() {
+        let tokens = vec![
+            Token::OpenBrace,
+            Token::Keyword(Keyword::Do),
+            Token::Literal(Literal::Integer(1)),
+            Token::CloseBrace,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_statement_block_between_braces(&mut tokens);
+
+        assert_eq!(result.unwrap_err().message, "Expected a statement block starting with open brace");
+    }
+
     #[test]
     fn test_parse_while_loop() {
         let tokens = vec![
@@ -531,8 +1153,8 @@ mod test {
             Token::Literal(Literal::Integer(1)),
             Token::CloseBrace,
         ];
-        let mut tokens = tokens.iter().peekable();
-        let result = parse_while_statement(&mut tokens);
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_while_statement(&mut tokens).unwrap();
 
         let expected = Statement::Loop(
             LoopStatement {
@@ -541,15 +1163,238 @@ mod test {
                     statements: vec![
                         Statement::Expression(
                             Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))
-                        )
+                        ).into()
+                    ],
+                }),
+                label: None,
+                else_body: None,
+            }
+        );
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_repeat_statement() {
+        let body = || Box::new(StatementBlock {
+            statements: vec![
+                Statement::Expression(
+                    Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))
+                ).into()
+            ],
+        });
+        let expected = Statement::Repeat(RepeatStatement {
+            count: Expression::Atomic(AtomicExpression::Literal(Literal::Integer(3))),
+            body: body(),
+        });
+
+        let tokens = vec![
+            Token::Keyword(Keyword::Repeat),
+            Token::Literal(Literal::Integer(3)),
+            Token::OpenBrace,
+            Token::Literal(Literal::Integer(1)),
+            Token::CloseBrace,
+        ];
+        assert_eq!(parse_repeat_statement(&mut TokenStream::new(&tokens)), Ok(expected.clone()));
+
+        let tokens = vec![
+            Token::Keyword(Keyword::Repeat),
+            Token::Literal(Literal::Integer(3)),
+            Token::Identifier(Identifier::Simple("times".to_string())),
+            Token::OpenBrace,
+            Token::Literal(Literal::Integer(1)),
+            Token::CloseBrace,
+        ];
+        assert_eq!(parse_repeat_statement(&mut TokenStream::new(&tokens)), Ok(expected));
+    }
+
+    #[test]
+    fn test_parse_repeat_without_a_block() {
+        let tokens = vec![
+            Token::Keyword(Keyword::Repeat),
+            Token::Literal(Literal::Integer(3)),
+            Token::Newline,
+        ];
+        let result = parse_repeat_statement(&mut TokenStream::new(&tokens));
+
+        assert_eq!(result.unwrap_err().message, "Expected a statement block starting with open brace");
+    }
+
+    #[test]
+    fn test_parse_for_loop_with_two_variables() {
+        let tokens = vec![
+            Token::Keyword(Keyword::For),
+            Token::Identifier(Identifier::Simple("key".to_string())),
+            Token::ListSeparator,
+            Token::Identifier(Identifier::Simple("value".to_string())),
+            Token::Keyword(Keyword::In),
+            Token::Identifier(Identifier::Simple("entries".to_string())),
+            Token::OpenBrace,
+            Token::Identifier(Identifier::Simple("value".to_string())),
+            Token::CloseBrace,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_for_statement(&mut tokens).unwrap();
+
+        let expected = Statement::For(
+            ForStatement {
+                variables: vec![Identifier::Simple("key".to_string()), Identifier::Simple("value".to_string())],
+                iterable: Expression::ident("entries"),
+                body: Box::new(StatementBlock {
+                    statements: vec![Statement::Expression(Expression::ident("value")).into()],
+                }),
+                label: None,
+                else_body: None,
+            }
+        );
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_labeled_for_loop() {
+        let tokens = tokenize("outer: for item in items {\n break outer\n }\n");
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_statement_block(&mut tokens, Token::EndOfModule).unwrap();
+
+        let expected = Statement::For(ForStatement {
+            variables: vec![Identifier::Simple("item".to_string())],
+            iterable: Expression::ident("items"),
+            body: Box::new(StatementBlock {
+                statements: vec![Statement::Break(BreakStatement { label: Some(Identifier::Simple("outer".to_string())) }).into()],
+            }),
+            label: Some(Identifier::Simple("outer".to_string())),
+            else_body: None,
+        });
+
+        assert_eq!(result.statements, vec![expected]);
+    }
+
+    #[test]
+    fn test_parse_for_loop_with_else() {
+        let tokens = tokenize("for item in items {\n break\n } else {\n 1\n }\n");
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_for_statement(&mut tokens).unwrap();
+
+        let expected = Statement::For(ForStatement {
+            variables: vec![Identifier::Simple("item".to_string())],
+            iterable: Expression::ident("items"),
+            body: Box::new(StatementBlock {
+                statements: vec![Statement::Break(BreakStatement { label: None }).into()],
+            }),
+            label: None,
+            else_body: Some(Box::new(StatementBlock {
+                statements: vec![Statement::Expression(Expression::int(1)).into()],
+            })),
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_while_loop_with_else() {
+        let tokens = vec![
+            Token::Keyword(Keyword::While),
+            Token::Literal(Literal::Boolean(true)),
+            Token::OpenBrace,
+            Token::Keyword(Keyword::Break),
+            Token::CloseBrace,
+            Token::Keyword(Keyword::Else),
+            Token::OpenBrace,
+            Token::Literal(Literal::Integer(1)),
+            Token::CloseBrace,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_while_statement(&mut tokens).unwrap();
+
+        let expected = Statement::Loop(
+            LoopStatement {
+                condition: Expression::Atomic(AtomicExpression::Literal(Literal::Boolean(true))),
+                body: Box::new(StatementBlock {
+                    statements: vec![
+                        Statement::Break(BreakStatement { label: None }).into()
                     ],
                 }),
+                label: None,
+                else_body: Some(Box::new(StatementBlock {
+                    statements: vec![
+                        Statement::Expression(
+                            Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))
+                        ).into()
+                    ],
+                })),
             }
         );
 
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_parse_labeled_while_loop() {
+        let tokens = vec![
+            Token::Identifier(Identifier::Simple("outer".to_string())),
+            Token::Colon,
+            Token::Keyword(Keyword::While),
+            Token::Literal(Literal::Boolean(true)),
+            Token::OpenBrace,
+            Token::Keyword(Keyword::Break),
+            Token::Identifier(Identifier::Simple("outer".to_string())),
+            Token::Newline,
+            Token::CloseBrace,
+            Token::EndOfModule,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_statements_until_end_of_module(&mut tokens).unwrap();
+
+        let expected = StatementBlock {
+            statements: vec![
+                Statement::Loop(LoopStatement {
+                    condition: Expression::Atomic(AtomicExpression::Literal(Literal::Boolean(true))),
+                    body: Box::new(StatementBlock {
+                        statements: vec![
+                            Statement::Break(BreakStatement {
+                                label: Some(Identifier::Simple("outer".to_string())),
+                            }).into()
+                        ],
+                    }),
+                    label: Some(Identifier::Simple("outer".to_string())),
+                    else_body: None,
+                }).into()
+            ],
+        };
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_unlabeled_break() {
+        let tokens = vec![
+            Token::Keyword(Keyword::Break),
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+
+        let result = parse_statement(&mut tokens).unwrap();
+
+        assert_eq!(result, Statement::Break(BreakStatement { label: None }));
+    }
+
+    #[test]
+    fn test_parse_labeled_continue() {
+        let tokens = vec![
+            Token::Keyword(Keyword::Continue),
+            Token::Identifier(Identifier::Simple("outer".to_string())),
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+
+        let result = parse_statement(&mut tokens).unwrap();
+
+        let expected = Statement::Continue(ContinueStatement {
+            label: Some(Identifier::Simple("outer".to_string())),
+        });
+
+        assert_eq!(result, expected);
+    }
+
     #[test]
     fn test_parse_if_statement() {
         let tokens = vec![
@@ -559,8 +1404,8 @@ mod test {
             Token::Literal(Literal::Integer(1)),
             Token::CloseBrace,
         ];
-        let mut tokens = tokens.iter().peekable();
-        let result = parse_if_statement(&mut tokens);
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_if_statement(&mut tokens).unwrap();
 
         let expected = Statement::Conditional(
             ConditionalStatement {
@@ -569,7 +1414,7 @@ mod test {
                     statements: vec![
                         Statement::Expression(
                             Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))
-                        )
+                        ).into()
                     ],
                 }),
                 else_body: None,
@@ -592,8 +1437,8 @@ mod test {
             Token::Literal(Literal::Integer(2)),
             Token::CloseBrace,
         ];
-        let mut tokens = tokens.iter().peekable();
-        let result = parse_if_statement(&mut tokens);
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_if_statement(&mut tokens).unwrap();
 
         let expected = Statement::Conditional(
             ConditionalStatement {
@@ -602,14 +1447,14 @@ mod test {
                     statements: vec![
                         Statement::Expression(
                             Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1)))
-                        )
+                        ).into()
                     ],
                 }),
                 else_body: Some(Box::new(StatementBlock {
                     statements: vec![
                         Statement::Expression(
                             Expression::Atomic(AtomicExpression::Literal(Literal::Integer(2)))
-                        )
+                        ).into()
                     ],
                 })),
             }
@@ -618,4 +1463,100 @@ mod test {
         assert_eq!(result, expected);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_parse_else_if_as_nested_conditional() {
+        let tokens = vec![
+            Token::Keyword(Keyword::If),
+            Token::Identifier(Identifier::Simple("a".to_string())),
+            Token::OpenBrace,
+            Token::Literal(Literal::Integer(1)),
+            Token::CloseBrace,
+            Token::Keyword(Keyword::Else),
+            Token::Keyword(Keyword::If),
+            Token::Identifier(Identifier::Simple("b".to_string())),
+            Token::OpenBrace,
+            Token::Literal(Literal::Integer(2)),
+            Token::CloseBrace,
+            Token::Keyword(Keyword::Else),
+            Token::OpenBrace,
+            Token::Literal(Literal::Integer(3)),
+            Token::CloseBrace,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_if_statement(&mut tokens).unwrap();
+
+        let expected = Statement::Conditional(ConditionalStatement {
+            condition: Expression::ident("a"),
+            body: Box::new(StatementBlock { statements: vec![Statement::Expression(Expression::int(1)).into()] }),
+            else_body: Some(Box::new(StatementBlock {
+                statements: vec![
+                    Statement::Conditional(ConditionalStatement {
+                        condition: Expression::ident("b"),
+                        body: Box::new(StatementBlock { statements: vec![Statement::Expression(Expression::int(2)).into()] }),
+                        else_body: Some(Box::new(StatementBlock { statements: vec![Statement::Expression(Expression::int(3)).into()] })),
+                    }).into()
+                ],
+            })),
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_elif_like_else_if() {
+        let parse_source = |source: &str| {
+            let tokens = tokenize(source);
+            parse_if_statement(&mut TokenStream::new(&tokens)).unwrap()
+        };
+
+        assert_eq!(
+            parse_source("if a {\n 1\n } elif b {\n 2\n } elif c {\n 3\n } else {\n 4\n }\n"),
+            parse_source("if a {\n 1\n } else if b {\n 2\n } else if c {\n 3\n } else {\n 4\n }\n"),
+        );
+    }
+
+    #[test]
+    fn test_parse_try_statement_with_handler() {
+        let tokens = vec![
+            Token::Keyword(Keyword::Try),
+            Token::OpenBrace,
+            Token::Literal(Literal::Integer(1)),
+            Token::CloseBrace,
+            Token::Keyword(Keyword::Except),
+            Token::Identifier(Identifier::Simple("e".to_string())),
+            Token::OpenBrace,
+            Token::Identifier(Identifier::Simple("e".to_string())),
+            Token::CloseBrace,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_try_statement(&mut tokens).unwrap();
+
+        let expected = Statement::Try(TryStatement {
+            body: Box::new(StatementBlock { statements: vec![Statement::Expression(Expression::int(1)).into()] }),
+            handler_var: Some(Identifier::Simple("e".to_string())),
+            handler_body: Some(Box::new(StatementBlock { statements: vec![Statement::Expression(Expression::ident("e")).into()] })),
+        });
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_parse_try_statement_without_handler() {
+        let tokens = vec![
+            Token::Keyword(Keyword::Try),
+            Token::OpenBrace,
+            Token::Literal(Literal::Integer(1)),
+            Token::CloseBrace,
+        ];
+        let mut tokens = TokenStream::new(&tokens);
+        let result = parse_try_statement(&mut tokens).unwrap();
+
+        let expected = Statement::Try(TryStatement {
+            body: Box::new(StatementBlock { statements: vec![Statement::Expression(Expression::int(1)).into()] }),
+            handler_var: None,
+            handler_body: None,
+        });
+
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::elements::Identifier;
+use crate::tokens::Token;
+use crate::parser::token_stream::TokenStream;
+use crate::tree;
+use crate::parser::errors::ParseError;
+use crate::parser::function_parser::parse_parameter;
+
+use crate::parser::utils::{handle_parse_error, handle_parse_error_for_option, expect};
+
+
+// Parses a struct definition after the struct keyword, like `Point { x: int, y: int }`. Fields are
+// written like parameters, separated by commas or new lines. The name must be capitalised, since
+// only a capitalised name starts a struct literal.
+pub fn parse_struct_definition(tokens: &mut TokenStream) -> Result<tree::StructDefinition, ParseError> {
+    let name = match tokens.next() {
+        Some(token @ Token::Identifier(Identifier::Simple(name))) if !name.starts_with(|c: char| c.is_uppercase()) => {
+            handle_parse_error(tokens, "Expected a struct name that starts with a capital letter", token)?
+        },
+        Some(Token::Identifier(identifier @ Identifier::Simple(_))) => identifier.clone(),
+        token => handle_parse_error_for_option(tokens, "Expected a name after struct keyword", token)?,
+    };
+
+    expect(tokens, Token::OpenBrace, "Expected an open brace after the struct name")?;
+
+    let mut fields: Vec<tree::Parameter> = vec![];
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Newline | Token::ListSeparator => continue,
+            Token::CloseBrace => return Ok(tree::StructDefinition { name, fields }),
+            Token::Identifier(_) => {
+                let field = parse_parameter(token, tokens)?;
+                if fields.iter().any(|existing| existing.name == field.name) {
+                    handle_parse_error(tokens, "Duplicate field in struct definition", token)?;
+                }
+                fields.push(field);
+            },
+            _ => handle_parse_error(tokens, "Expected a field or a closing brace", token)?,
+        }
+    }
+
+    handle_parse_error_for_option(tokens, "Expected a closing brace at the end of the struct", None)
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::tokenize;
+
+    use super::*;
+
+    #[test]
+    fn test_duplicate_field_is_an_error() {
+        let tokens = tokenize("Point { x: int, x: int }");
+
+        assert!(parse_struct_definition(&mut TokenStream::new(&tokens)).is_err());
+    }
+
+    #[test]
+    fn test_struct_name_must_be_capitalised() {
+        let tokens = tokenize("point { x: int }");
+        let error = parse_struct_definition(&mut TokenStream::new(&tokens)).unwrap_err();
+        assert_eq!(error.message, "Expected a struct name that starts with a capital letter");
+
+        let tokens = tokenize("Point { x: int }");
+        assert!(parse_struct_definition(&mut TokenStream::new(&tokens)).is_ok());
+    }
+}
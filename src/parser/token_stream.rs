@@ -0,0 +1,201 @@
+use crate::tokens::Token;
+use crate::span::Span;
+use crate::parser::precedence::{PrecedenceTable, DEFAULT_PRECEDENCE};
+use crate::parser::errors::ParseError;
+use crate::parser::utils::handle_parse_error_for_option;
+
+
+// Far more than any program written by hand, while keeping untrusted input from using up the memory
+pub const DEFAULT_MAX_STATEMENTS: usize = 1_000_000;
+
+
+// The tokens being parsed, along with their spans in the source code if they are known, the
+// operator precedence to parse them with, and how many more statements may be parsed. Cloning a
+// stream is cheap, so it can be used for lookahead. When recovering, the errors of the statements
+// that failed to parse are kept here while parsing carries on.
+#[derive(Debug, Clone)]
+pub struct TokenStream<'a> {
+    tokens: &'a [Token],
+    spans: &'a [Span],
+    position: usize,
+    precedence: &'a PrecedenceTable,
+    statements_left: usize,
+    recovering: bool,
+    errors: Vec<ParseError>,
+}
+
+
+impl<'a> TokenStream<'a> {
+    pub fn new(tokens: &'a [Token]) -> TokenStream<'a> {
+        TokenStream::with_spans(tokens, &[])
+    }
+
+    pub fn with_spans(tokens: &'a [Token], spans: &'a [Span]) -> TokenStream<'a> {
+        TokenStream {
+            tokens,
+            spans,
+            position: 0,
+            precedence: &DEFAULT_PRECEDENCE,
+            statements_left: DEFAULT_MAX_STATEMENTS,
+            recovering: false,
+            errors: vec![],
+        }
+    }
+
+    pub fn with_precedence(self, precedence: &'a PrecedenceTable) -> TokenStream<'a> {
+        TokenStream { precedence, ..self }
+    }
+
+    pub fn with_max_statements(self, max_statements: usize) -> TokenStream<'a> {
+        TokenStream { statements_left: max_statements, ..self }
+    }
+
+    pub fn with_recovery(self) -> TokenStream<'a> {
+        TokenStream { recovering: true, ..self }
+    }
+
+    pub fn is_recovering(&self) -> bool {
+        self.recovering
+    }
+
+    pub fn record_error(&mut self, error: ParseError) {
+        self.errors.push(error);
+    }
+
+    pub fn take_errors(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn precedence(&self) -> &'a PrecedenceTable {
+        self.precedence
+    }
+
+    // Counts a statement that has been parsed, failing once there are more than the limit
+    pub fn count_statement(&mut self) -> Result<(), ParseError> {
+        match self.statements_left.checked_sub(1) {
+            Some(statements_left) => {
+                self.statements_left = statements_left;
+                Ok(())
+            },
+            None => handle_parse_error_for_option(self, "Too many statements", self.peek()),
+        }
+    }
+
+    // The most recently consumed token
+    pub fn previous(&self) -> Option<&'a Token> {
+        self.position.checked_sub(1).and_then(|index| self.tokens.get(index))
+    }
+
+    pub fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+
+    // The span of the next token, or an empty span after the last token at the end of the stream
+    pub fn peek_span(&self) -> Span {
+        match self.spans.get(self.position) {
+            Some(span) => *span,
+            None => {
+                let end = self.previous_span().end;
+                Span::new(end, end)
+            },
+        }
+    }
+
+    // The span of a token borrowed from this stream
+    pub fn span_of(&self, token: &Token) -> Span {
+        self.tokens.iter()
+            .position(|candidate| std::ptr::eq(candidate, token))
+            .and_then(|index| self.spans.get(index))
+            .copied()
+            .unwrap_or_else(|| self.peek_span())
+    }
+
+    // How many tokens have been consumed, to find the tokens consumed after this point later on
+    pub fn current_position(&self) -> usize {
+        self.position
+    }
+
+    // The tokens consumed since the stream was at the given position
+    pub fn consumed_since(&self, start: usize) -> &'a [Token] {
+        &self.tokens[start.min(self.position)..self.position]
+    }
+
+    // The span of the most recently consumed token
+    pub fn previous_span(&self) -> Span {
+        match self.position.checked_sub(1).and_then(|index| self.spans.get(index)) {
+            Some(span) => *span,
+            None => Span::default(),
+        }
+    }
+
+    // The span from the start of the given span to the end of the most recently consumed token
+    pub fn span_from(&self, start: Span) -> Span {
+        start.merge(self.previous_span())
+    }
+
+    // Consumes tokens while the predicate holds, returning them as a stream of their own
+    pub fn consume_while(&mut self, mut predicate: impl FnMut(&Token) -> bool) -> TokenStream<'a> {
+        let start = self.position;
+        while self.peek().is_some_and(&mut predicate) {
+            self.position += 1;
+        }
+
+        TokenStream {
+            tokens: &self.tokens[start..self.position],
+            spans: self.spans.get(start..self.position).unwrap_or(&[]),
+            position: 0,
+            precedence: self.precedence,
+            statements_left: self.statements_left,
+            // Errors within the sub-stream are returned rather than recovered from, since its
+            // collected errors would be lost with it
+            recovering: false,
+            errors: vec![],
+        }
+    }
+}
+
+
+impl<'a> Iterator for TokenStream<'a> {
+    type Item = &'a Token;
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some(token)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_spans_follow_tokens() {
+        let tokens = vec![Token::OpenParen, Token::CloseParen];
+        let spans = vec![Span::new(0, 1), Span::new(2, 3)];
+        let mut stream = TokenStream::with_spans(&tokens, &spans);
+
+        assert_eq!(stream.peek_span(), Span::new(0, 1));
+        stream.next();
+        stream.next();
+        assert_eq!(stream.previous_span(), Span::new(2, 3));
+        assert_eq!(stream.peek_span(), Span::new(3, 3));
+        assert_eq!(stream.span_from(Span::new(0, 1)), Span::new(0, 3));
+    }
+
+    #[test]
+    fn test_consume_while_keeps_spans() {
+        let tokens = vec![Token::Dot, Token::Dot, Token::Newline];
+        let spans = vec![Span::new(0, 1), Span::new(1, 2), Span::new(2, 3)];
+        let mut stream = TokenStream::with_spans(&tokens, &spans);
+
+        let mut dots = stream.consume_while(|token| *token == Token::Dot);
+
+        assert_eq!(dots.next(), Some(&Token::Dot));
+        assert_eq!(dots.next(), Some(&Token::Dot));
+        assert_eq!(dots.next(), None);
+        assert_eq!(dots.previous_span(), Span::new(1, 2));
+        assert_eq!(stream.next(), Some(&Token::Newline));
+    }
+}
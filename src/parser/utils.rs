@@ -1,22 +1,80 @@
 use std::fmt::Debug;
 
 use crate::tokens::Token;
-use crate::tree::Expression;
+use crate::span::Spanned;
+use crate::parser::errors::ParseError;
+use crate::parser::token_stream::TokenStream;
 
 
-pub fn handle_parse_error<T>(message: &str, token: &Token) -> T {
-    panic!("Parse error: {}\n\n Found {:?}\n", message, token);
+pub fn handle_parse_error<T>(tokens: &TokenStream, message: &str, token: &Token) -> Result<T, ParseError> {
+    Err(ParseError::new(message, Some(format!("{:?}", token)), tokens.span_of(token)))
 }
 
 
-pub fn handle_parse_error_for_option<T>(message: &str, token: Option<&impl Debug>) -> T {
+pub fn handle_parse_error_for_option<T>(tokens: &TokenStream, message: &str, token: Option<&Token>) -> Result<T, ParseError> {
     match token {
-        Some(t) => panic!("Parse error: {}\n Found {:?}\n\n", message, t),
-        None => panic!("Parse error: {}\n\n Found EOF\n\n", message)
-    };
+        Some(token) => handle_parse_error(tokens, message, token),
+        None => Err(ParseError::new(message, None, tokens.peek_span())),
+    }
 }
 
 
-pub fn handle_expression_parse_error<T>(message: &str, expression: &Expression) -> T {
-    panic!("Parse error: {}\n\n Found {:?}\n", message, expression);
-}
\ No newline at end of file
+pub fn handle_expression_parse_error<T>(message: &str, expression: &Spanned<impl Debug>) -> Result<T, ParseError> {
+    Err(ParseError::new(message, Some(format!("{:?}", expression.node)), expression.span))
+}
+
+
+pub fn peek_is(tokens: &TokenStream, expected: &Token) -> bool {
+    tokens.peek() == Some(expected)
+}
+
+
+// Consumes the next token, which must be the expected one
+pub fn expect(tokens: &mut TokenStream, expected: Token, message: &str) -> Result<(), ParseError> {
+    match tokens.peek() {
+        Some(token) if *token == expected => {
+            tokens.next();
+            Ok(())
+        },
+        token => handle_parse_error_for_option(tokens, message, token),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::span::Span;
+
+    use super::*;
+
+    #[test]
+    fn test_expect_matching_token() {
+        let tokens = vec![Token::OpenBrace, Token::CloseBrace];
+        let mut tokens = TokenStream::new(&tokens);
+
+        assert_eq!(expect(&mut tokens, Token::OpenBrace, "Expected an open brace"), Ok(()));
+        assert!(peek_is(&tokens, &Token::CloseBrace));
+    }
+
+    #[test]
+    fn test_expect_mismatched_token() {
+        let tokens = vec![Token::OpenParen];
+        let spans = vec![Span::new(3, 4)];
+        let mut tokens = TokenStream::with_spans(&tokens, &spans);
+
+        let expected = ParseError::new("Expected an open brace", Some("OpenParen".to_string()), Span::new(3, 4));
+
+        assert_eq!(expect(&mut tokens, Token::OpenBrace, "Expected an open brace"), Err(expected));
+        assert!(peek_is(&tokens, &Token::OpenParen));
+    }
+
+    #[test]
+    fn test_expect_at_end_of_input() {
+        let tokens = vec![];
+        let mut tokens = TokenStream::new(&tokens);
+
+        let result = expect(&mut tokens, Token::CloseParen, "Expected a closing parenthesis");
+
+        assert_eq!(result, Err(ParseError::new("Expected a closing parenthesis", None, Span::default())));
+    }
+}
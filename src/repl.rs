@@ -1,14 +1,21 @@
 use std::io::{stdin, stdout, Write};
+use std::process;
 
 
-use sheeppig::lexer::tokenize;
-use sheeppig::parser::parse;
+use sheeppig::lexer::{try_tokenize_with_spans, LexOptions, LexError};
+use sheeppig::tokens::{format_tokens, Token};
+use sheeppig::elements::Keyword;
+use sheeppig::parser::{parse, parse_statements, parse_expression, ParseError};
+use sheeppig::diagnostics::Diagnostic;
+use sheeppig::tree::StatementBlock;
+use sheeppig::interpreter::{Environment, Value, RuntimeError, ControlFlow, execute_statement, evaluate_expression};
 
 
 pub fn repl() {
     println!("REPL v0.1.0");
 
     let input = stdin();
+    let mut environment = Environment::new();
 
     loop {
         print!(":> ");
@@ -22,10 +29,147 @@ pub fn repl() {
             break;
         }
 
-        let tokens = tokenize(&buffer);
-        println!("\n -- Tokens: {:?} \n", tokens);
+        // Shows how a line is tokenized instead of running it
+        if let Some(line) = buffer.strip_prefix(":tokens ") {
+            match try_tokenize_with_spans(line, &LexOptions::default()) {
+                Ok((tokens, _)) => println!("{}", format_tokens(&tokens)),
+                Err(error) => println!("{}", format_lex_error(&error)),
+            }
+            continue;
+        }
+
+        match run_line(&buffer, &mut environment) {
+            Ok(Some(value)) => println!("{}", value),
+            Ok(None) => {},
+            Err(message) => println!("{}", message),
+        }
+    }
+}
+
+
+// How a line is parsed. Only a whole module can define functions and structs or import anything,
+// while anything else is an expression or some statements.
+#[derive(Debug, PartialEq)]
+enum LineKind {
+    Module,
+    Expression,
+    Statements,
+}
+
+
+fn classify_line(tokens: &[Token]) -> LineKind {
+    let first = tokens.iter().find(|token| !matches!(token, Token::Newline | Token::Comment(_)));
+    match first {
+        Some(Token::Keyword(Keyword::Function | Keyword::Const | Keyword::Struct | Keyword::Using)) => LineKind::Module,
+        _ if parse_expression(tokens).is_ok() => LineKind::Expression,
+        _ => LineKind::Statements,
+    }
+}
+
+
+// Runs a line, giving the value of the expression or of the last statement if it's an expression.
+// Variables, functions and structs stay in the environment for the following lines. Calling exit
+// ends the REPL.
+fn run_line(line: &str, environment: &mut Environment) -> Result<Option<Value>, String> {
+    let (tokens, _) = try_tokenize_with_spans(line, &LexOptions::default()).map_err(|error| format_lex_error(&error))?;
+
+    match classify_line(&tokens) {
+        LineKind::Module => {
+            let module = parse(&tokens).map_err(|error| format_error(&error))?;
+            for definition in module.structs {
+                environment.define_struct(definition.node);
+            }
+            for function in module.functions {
+                environment.define_function(function.node);
+            }
+            run_statements(&module.statements, environment)
+        },
+        LineKind::Expression => {
+            let expression = parse_expression(&tokens).map_err(|error| format_error(&error))?;
+            match evaluate_expression(&expression, environment) {
+                Err(RuntimeError { exit_code: Some(code), .. }) => process::exit(code),
+                Err(error) => Err(error.to_string()),
+                Ok(Value::None) => Ok(None),
+                Ok(value) => Ok(Some(value)),
+            }
+        },
+        LineKind::Statements => {
+            let block = parse_statements(&tokens).map_err(|error| format_error(&error))?;
+            run_statements(&block, environment)
+        },
+    }
+}
+
+
+fn run_statements(block: &StatementBlock, environment: &mut Environment) -> Result<Option<Value>, String> {
+    let mut value = None;
+    for statement in &block.statements {
+        value = match execute_statement(statement, environment) {
+            Err(RuntimeError { exit_code: Some(code), .. }) => process::exit(code),
+            Err(error) => return Err(error.to_string()),
+            Ok(ControlFlow::Value(Value::None)) => None,
+            Ok(ControlFlow::Value(result)) => Some(result),
+            Ok(_) => None,
+        };
+    }
+    Ok(value)
+}
+
+
+fn format_error(error: &ParseError) -> String {
+    Diagnostic::from(error).to_string()
+}
+
+
+fn format_lex_error(error: &LexError) -> String {
+    Diagnostic::from(error).to_string()
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expression_prints_its_value() {
+        assert_eq!(run_line("1 + 2\n", &mut Environment::new()), Ok(Some(Value::Integer(3))));
+    }
+
+    #[test]
+    fn test_line_that_does_not_lex_is_an_error() {
+        let mut environment = Environment::new();
+
+        assert_eq!(run_line("$\n", &mut environment), Err("Error: Unexpected character: $".to_string()));
+        assert_eq!(run_line("i++\n", &mut environment), Err("Error: '++' is not supported, use '+= 1' instead".to_string()));
+        assert_eq!(run_line("3\n", &mut environment), Ok(Some(Value::Integer(3))));
+    }
+
+    #[test]
+    fn test_classify_line() {
+        let classify = |line: &str| classify_line(&try_tokenize_with_spans(line, &LexOptions::default()).unwrap().0);
+
+        assert_eq!(classify("1 + 2\n"), LineKind::Expression);
+        assert_eq!(classify("if ready { 1 } else { 2 }\n"), LineKind::Expression);
+        assert_eq!(classify("x: int = 4\n"), LineKind::Statements);
+        assert_eq!(classify("x = 1; y = 2\n"), LineKind::Statements);
+        assert_eq!(classify("while false { pass }\n"), LineKind::Statements);
+        assert_eq!(classify("fun double(x: int): int { return x * 2 }\n"), LineKind::Module);
+        assert_eq!(classify("struct Point { x: int, y: int }\n"), LineKind::Module);
+    }
+
+    #[test]
+    fn test_function_defined_on_one_line_is_called_on_the_next() {
+        let mut environment = Environment::new();
+
+        assert_eq!(run_line("fun double(x: int): int {\n return x * 2\n }\n", &mut environment), Ok(None));
+        assert_eq!(run_line("double(4)\n", &mut environment), Ok(Some(Value::Integer(8))));
+    }
+
+    #[test]
+    fn test_declaration_has_no_value_but_is_kept() {
+        let mut environment = Environment::new();
 
-        let expression = parse(&tokens);
-        println!("\n -- Expression: {:?} \n", expression);
+        assert_eq!(run_line("x: int = 4\n", &mut environment), Ok(None));
+        assert_eq!(run_line("x * 2\n", &mut environment), Ok(Some(Value::Integer(8))));
     }
-}
\ No newline at end of file
+}
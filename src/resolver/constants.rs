@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use crate::elements::{Identifier, Literal};
+use crate::span::Spanned;
+use crate::tree::{Function, StatementBlock, Statement, Expression, AtomicExpression};
+use crate::interpreter::{Environment, Value, evaluate_expression};
+
+
+// Recursion in const functions is cut off well before the interpreter's usual limit
+const MAX_CONST_CALL_DEPTH: usize = 64;
+// Loops in const functions are cut off after this many runs of a block, so they can't hang the checker
+const MAX_CONST_STEPS: usize = 100_000;
+
+
+// Evaluates an expression made only of literals, operators, constants and calls to const functions,
+// for values that must be known before the program runs. Anything else, like a call to another
+// function or a mutable variable, gives None, as does an expression that fails to evaluate, like a
+// division by zero. Const functions run without any builtins, so they can't have side effects.
+pub fn const_eval(expression: &Expression, constants: &HashMap<String, Literal>, functions: &[Spanned<Function>]) -> Option<Literal> {
+    let const_functions: Vec<&Function> = functions.iter()
+        .map(|function| &function.node)
+        .filter(|function| function.is_const)
+        .collect();
+    if !is_constant(expression, constants, &const_functions) {
+        return None;
+    }
+
+    let mut environment = Environment::without_builtins();
+    environment.set_max_call_depth(MAX_CONST_CALL_DEPTH);
+    environment.set_max_steps(MAX_CONST_STEPS);
+    for function in const_functions {
+        environment.define_function(function.clone());
+    }
+    for (name, value) in constants {
+        environment.declare(&Identifier::Simple(name.clone()), Value::from_literal(value));
+    }
+
+    match evaluate_expression(expression, &environment).ok()? {
+        Value::Integer(value) => Some(Literal::Integer(value)),
+        Value::Float(value) => Some(Literal::Float(value)),
+        Value::Boolean(value) => Some(Literal::Boolean(value)),
+        Value::Char(value) => Some(Literal::Char(value)),
+        Value::String(value) => Some(Literal::String(value)),
+        Value::None => Some(Literal::None),
+        _ => None,
+    }
+}
+
+
+// The immutable declarations directly in the block whose values are constant. Each one can use the
+// constants declared before it.
+pub fn collect_constants(block: &StatementBlock, functions: &[Spanned<Function>]) -> HashMap<String, Literal> {
+    let mut constants = HashMap::new();
+
+    for statement in &block.statements {
+        if let Statement::Declaration(declaration) = &statement.node {
+            if declaration.is_mutable {
+                continue;
+            }
+            if let Some(value) = const_eval(&declaration.value, &constants, functions) {
+                constants.insert(declaration.name.as_string(), value);
+            }
+        }
+    }
+
+    constants
+}
+
+
+fn is_constant(expression: &Expression, constants: &HashMap<String, Literal>, functions: &[&Function]) -> bool {
+    let is_constant = |expression: &Expression| is_constant(expression, constants, functions);
+
+    match expression {
+        Expression::TernaryCondition { condition, true_value, false_value } => {
+            is_constant(condition) && is_constant(true_value) && is_constant(false_value)
+        },
+        Expression::BinaryOperation { left, right, .. } => is_constant(left) && is_constant(right),
+        Expression::UnaryOperation { operand, .. } => is_constant(operand),
+        Expression::Cast(cast) => is_constant(&cast.value),
+
+        Expression::Atomic(AtomicExpression::Literal(_)) => true,
+        Expression::Atomic(AtomicExpression::Identifier(name)) => constants.contains_key(&name.as_string()),
+        Expression::Atomic(AtomicExpression::Parenthesized(parenthesized)) => is_constant(&parenthesized.value),
+        Expression::Atomic(AtomicExpression::FunctionCall(call)) => {
+            functions.iter().any(|function| function.name == call.name) && call.arguments().all(is_constant)
+        },
+
+        _ => false,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    use super::*;
+
+    fn parse_expression(source: &str) -> Expression {
+        let module = parse(&tokenize(source)).unwrap();
+        match &module.statements.statements.last().unwrap().node {
+            Statement::Expression(expression) => expression.clone(),
+            statement => panic!("Expected an expression statement, found {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn test_const_eval_literals() {
+        assert_eq!(const_eval(&parse_expression("2 + 3"), &HashMap::new(), &[]), Some(Literal::Integer(5)));
+    }
+
+    #[test]
+    fn test_const_eval_constant_reference() {
+        let module = parse(&tokenize("size: int = 2 * 2\nvar count: int = 3\n")).unwrap();
+        let constants = collect_constants(&module.statements, &[]);
+
+        assert_eq!(const_eval(&parse_expression("size * 2"), &constants, &[]), Some(Literal::Integer(8)));
+        assert_eq!(const_eval(&parse_expression("count + 1"), &constants, &[]), None);
+    }
+
+    #[test]
+    fn test_const_eval_non_constant_expressions() {
+        assert_eq!(const_eval(&parse_expression("abs(-1)"), &HashMap::new(), &[]), None);
+        assert_eq!(const_eval(&parse_expression("1 / 0"), &HashMap::new(), &[]), None);
+    }
+
+    #[test]
+    fn test_const_eval_const_function_call() {
+        let source = "const fun square(x: int): int {\n return x * x\n }\n\nfun cube(x: int): int {\n return x * x * x\n }\n";
+        let module = parse(&tokenize(source)).unwrap();
+
+        assert_eq!(const_eval(&parse_expression("square(5)"), &HashMap::new(), &module.functions), Some(Literal::Integer(25)));
+        assert_eq!(const_eval(&parse_expression("cube(5)"), &HashMap::new(), &module.functions), None);
+    }
+
+    #[test]
+    fn test_const_function_recursion_is_capped() {
+        let source = "const fun forever(x: int): int {\n return forever(x + 1)\n }\n";
+        let module = parse(&tokenize(source)).unwrap();
+
+        assert_eq!(const_eval(&parse_expression("forever(0)"), &HashMap::new(), &module.functions), None);
+    }
+
+    #[test]
+    fn test_const_function_loop_is_capped() {
+        let source = "const fun forever(): int {\n while true {\n pass\n }\n return 0\n }\n";
+        let module = parse(&tokenize(source)).unwrap();
+
+        assert_eq!(const_eval(&parse_expression("forever()"), &HashMap::new(), &module.functions), None);
+    }
+
+    #[test]
+    fn test_const_function_cannot_call_builtins() {
+        let source = "const fun size(x: int): int {\n return abs(x)\n }\n";
+        let module = parse(&tokenize(source)).unwrap();
+
+        assert_eq!(const_eval(&parse_expression("size(-1)"), &HashMap::new(), &module.functions), None);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::elements::Identifier;
+use crate::span::{Span, Spanned};
+use crate::tokens::Token;
+use crate::tree::{Module, Function, StatementBlock, Statement};
+
+use super::nested_blocks;
+
+
+// Finds where names are declared, for go-to-definition in an editor. Names in the tree don't have spans
+// of their own, so each one is found among the tokens within the span of the statement or function
+// that declares it.
+pub struct SourceMap<'a> {
+    tokens: &'a [Token],
+    spans: &'a [Span],
+}
+
+
+impl<'a> SourceMap<'a> {
+    // The tokens and spans must be the ones the module was parsed from
+    pub fn new(tokens: &'a [Token], spans: &'a [Span]) -> SourceMap<'a> {
+        SourceMap { tokens, spans }
+    }
+
+    // Every function, parameter and declared variable in the module, with the span of its name
+    pub fn definitions(&self, module: &Module) -> Vec<(Identifier, Span)> {
+        let mut definitions = vec![];
+
+        for function in &module.functions {
+            definitions.extend(self.function_name(function));
+            definitions.extend(self.parameters(function));
+            self.collect_declarations(&function.body, &mut definitions);
+        }
+        self.collect_declarations(&module.statements, &mut definitions);
+
+        definitions
+    }
+
+    // The span of the declaration of the name under the cursor, following the same scoping rules
+    // as the interpreter. Names that aren't declared in the module have no definition.
+    pub fn definition_at(&self, module: &Module, offset: usize) -> Option<Span> {
+        let name = self.identifier_at(offset)?;
+
+        let mut scopes = vec![];
+        let result = match module.functions.iter().find(|function| contains(function.span, offset)) {
+            Some(function) => {
+                scopes.push(self.parameters(function));
+                self.find_in_block(&function.body, name, offset, &mut scopes)
+            },
+            None => self.find_in_block(&module.statements, name, offset, &mut scopes),
+        };
+
+        result.or_else(|| {
+            module.functions.iter()
+                .filter(|function| function.name == *name)
+                .find_map(|function| self.function_name(function))
+                .map(|(_, span)| span)
+        })
+    }
+
+    fn identifier_at(&self, offset: usize) -> Option<&'a Identifier> {
+        self.tokens.iter().zip(self.spans)
+            .find_map(|(token, span)| match token {
+                Token::Identifier(identifier) if contains(*span, offset) => Some(identifier),
+                _ => None,
+            })
+    }
+
+    // The first time the name appears within the span, after the start offset
+    fn name_span(&self, name: &Identifier, within: Span, after: usize) -> Option<Span> {
+        self.tokens.iter().zip(self.spans)
+            .find(|(token, span)| {
+                span.start >= within.start.max(after) && span.end <= within.end && **token == Token::Identifier(name.clone())
+            })
+            .map(|(_, span)| *span)
+    }
+
+    fn function_name(&self, function: &Spanned<Function>) -> Option<(Identifier, Span)> {
+        let span = self.name_span(&function.name, function.span, 0)?;
+        Some((function.name.clone(), span))
+    }
+
+    fn parameters(&self, function: &Spanned<Function>) -> Vec<(Identifier, Span)> {
+        let after = self.function_name(function).map_or(function.span.start, |(_, span)| span.end);
+        function.parameters.iter()
+            .filter_map(|parameter| {
+                let span = self.name_span(&parameter.name, function.span, after)?;
+                Some((parameter.name.clone(), span))
+            })
+            .collect()
+    }
+
+    fn declaration(&self, statement: &Spanned<Statement>) -> Option<(Identifier, Span)> {
+        match &statement.node {
+            Statement::Declaration(declaration) => {
+                let span = self.name_span(&declaration.name, statement.span, 0)?;
+                Some((declaration.name.clone(), span))
+            },
+            _ => None,
+        }
+    }
+
+    fn collect_declarations(&self, block: &StatementBlock, definitions: &mut Vec<(Identifier, Span)>) {
+        for statement in &block.statements {
+            definitions.extend(self.declaration(statement));
+            for nested in nested_blocks(statement) {
+                self.collect_declarations(nested, definitions);
+            }
+        }
+    }
+
+    // Declarations are visible from the statement after them to the end of their block, and a
+    // declaration can use a variable of the same name from an enclosing scope in its value
+    fn find_in_block(&self, block: &StatementBlock, name: &Identifier, offset: usize, scopes: &mut Vec<Vec<(Identifier, Span)>>) -> Option<Span> {
+        scopes.push(vec![]);
+        let mut result = None;
+
+        for statement in &block.statements {
+            if statement.span.start > offset {
+                break;
+            }
+
+            if !contains(statement.span, offset) {
+                if let Some(declaration) = self.declaration(statement) {
+                    scopes.last_mut().unwrap().push(declaration);
+                }
+                continue;
+            }
+
+            if let Some((_, span)) = self.declaration(statement).filter(|(_, span)| contains(*span, offset)) {
+                result = Some(span);
+                break;
+            }
+
+            result = match nested_blocks(statement).into_iter().find(|nested| block_contains(nested, offset)) {
+                Some(nested) => self.find_in_block(nested, name, offset, scopes),
+                None => lookup(scopes, name),
+            };
+            break;
+        }
+
+        let result = result.or_else(|| lookup(scopes, name));
+        scopes.pop();
+        result
+    }
+}
+
+
+fn lookup(scopes: &[Vec<(Identifier, Span)>], name: &Identifier) -> Option<Span> {
+    scopes.iter().rev()
+        .flat_map(|scope| scope.iter().rev())
+        .find(|(declared, _)| declared == name)
+        .map(|(_, span)| *span)
+}
+
+
+fn block_contains(block: &StatementBlock, offset: usize) -> bool {
+    block.statements.iter().any(|statement| contains(statement.span, offset))
+}
+
+
+fn contains(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset < span.end
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::{tokenize_with_spans, LexOptions};
+    use crate::parser::parse_with_spans;
+
+    use super::*;
+
+    const SOURCE: &str = concat!(
+        "fun main(count: int) {\n",
+        "    x: int = count\n",
+        "    if true {\n",
+        "        x: int = x + 1\n",
+        "        print(x)\n",
+        "    }\n",
+        "    print(x)\n",
+        "}\n",
+    );
+
+    fn definition_of_use(source: &str, use_at: usize) -> Option<Span> {
+        let (tokens, spans) = tokenize_with_spans(source, &LexOptions::default());
+        let module = parse_with_spans(&tokens, &spans).unwrap();
+        SourceMap::new(&tokens, &spans).definition_at(&module, use_at)
+    }
+
+    fn offset_of(pattern: &str) -> usize {
+        SOURCE.find(pattern).unwrap()
+    }
+
+    #[test]
+    fn test_variable_use_resolves_to_its_declaration() {
+        let outer = offset_of("x: int = count");
+        let outer_declaration = Span::new(outer, outer + 1);
+
+        assert_eq!(definition_of_use(SOURCE, offset_of("x)\n}")), Some(outer_declaration));
+    }
+
+    #[test]
+    fn test_shadowing_declaration_in_nested_block() {
+        let inner = offset_of("x: int = x + 1");
+        let outer = offset_of("x: int = count");
+
+        assert_eq!(definition_of_use(SOURCE, offset_of("x)\n    }")), Some(Span::new(inner, inner + 1)));
+        assert_eq!(definition_of_use(SOURCE, offset_of("x + 1")), Some(Span::new(outer, outer + 1)));
+    }
+
+    #[test]
+    fn test_parameter_and_undeclared_names() {
+        let parameter = offset_of("count");
+
+        assert_eq!(definition_of_use(SOURCE, offset_of("count\n")), Some(Span::new(parameter, parameter + 5)));
+        assert_eq!(definition_of_use(SOURCE, offset_of("print")), None);
+    }
+
+    #[test]
+    fn test_definitions_index() {
+        let (tokens, spans) = tokenize_with_spans(SOURCE, &LexOptions::default());
+        let module = parse_with_spans(&tokens, &spans).unwrap();
+
+        let names = SourceMap::new(&tokens, &spans).definitions(&module).into_iter()
+            .map(|(name, span)| (name.as_string(), &SOURCE[span.start..span.end]))
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec![
+            ("main".to_string(), "main"),
+            ("count".to_string(), "count"),
+            ("x".to_string(), "x"),
+            ("x".to_string(), "x"),
+        ]);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::diagnostics::Diagnostic;
+use crate::elements::{Identifier, Literal};
+use crate::tree::{Module, StatementBlock, Statement, LoopStatement};
+
+use super::nested_blocks;
+
+
+// Warns about loops that can never end, since their condition is `true` and nothing in their body
+// leaves them. The loops still run, so these are warnings rather than errors.
+pub fn resolve_module(module: &Module) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    for function in &module.functions {
+        resolve_block(&function.body, &mut diagnostics);
+    }
+    resolve_block(&module.statements, &mut diagnostics);
+
+    diagnostics
+}
+
+
+fn resolve_block(block: &StatementBlock, diagnostics: &mut Vec<Diagnostic>) {
+    for statement in &block.statements {
+        if let Statement::Loop(loop_statement) = &statement.node {
+            if is_endless(loop_statement) {
+                diagnostics.push(Diagnostic::warning("Loop condition is always true and the loop has no break, so it never ends"));
+            }
+        }
+        for nested in nested_blocks(statement) {
+            resolve_block(nested, diagnostics);
+        }
+    }
+}
+
+
+fn is_endless(loop_statement: &LoopStatement) -> bool {
+    loop_statement.condition.as_literal() == Some(&Literal::Boolean(true))
+        && !leaves_loop(&loop_statement.body, &mut vec![])
+}
+
+
+// Whether the block has a break out of the loop it's the body of, or a return. Nested loops are
+// entered with their labels, since an unlabeled break or one with their label only leaves them.
+fn leaves_loop(block: &StatementBlock, nested_labels: &mut Vec<Option<Identifier>>) -> bool {
+    block.statements.iter().any(|statement| match &statement.node {
+        Statement::Break(break_statement) => match &break_statement.label {
+            None => nested_labels.is_empty(),
+            Some(label) => !nested_labels.contains(&Some(label.clone())),
+        },
+
+        Statement::Return(_) => true,
+
+        Statement::Loop(loop_statement) => {
+            nested_labels.push(loop_statement.label.clone());
+            let leaves = leaves_loop(&loop_statement.body, nested_labels);
+            nested_labels.pop();
+            leaves || loop_statement.else_body.as_ref().is_some_and(|else_body| leaves_loop(else_body, nested_labels))
+        },
+
+        Statement::For(for_statement) => {
+            nested_labels.push(for_statement.label.clone());
+            let leaves = leaves_loop(&for_statement.body, nested_labels);
+            nested_labels.pop();
+            leaves || for_statement.else_body.as_ref().is_some_and(|else_body| leaves_loop(else_body, nested_labels))
+        },
+
+        Statement::Repeat(repeat_statement) => {
+            nested_labels.push(None);
+            let leaves = leaves_loop(&repeat_statement.body, nested_labels);
+            nested_labels.pop();
+            leaves
+        },
+
+        statement => nested_blocks(statement).into_iter().any(|nested| leaves_loop(nested, nested_labels)),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    use super::*;
+
+    fn resolve_source(source: &str) -> Vec<Diagnostic> {
+        resolve_module(&parse(&tokenize(source)).unwrap())
+    }
+
+    #[test]
+    fn test_loop_with_break_has_no_warning() {
+        let source = "while true {\n break\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_loop_without_break_is_a_warning() {
+        let source = "var x: int = 0\n while true {\n x = 1\n }\n";
+        assert_eq!(
+            resolve_source(source),
+            vec![Diagnostic::warning("Loop condition is always true and the loop has no break, so it never ends")]
+        );
+    }
+
+    #[test]
+    fn test_break_from_a_nested_loop() {
+        let source = "outer: while true {\n while true {\n break outer\n }\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+
+        let source = "while true {\n while true {\n break\n }\n }\n";
+        assert_eq!(
+            resolve_source(source),
+            vec![Diagnostic::warning("Loop condition is always true and the loop has no break, so it never ends")]
+        );
+    }
+}
@@ -0,0 +1,33 @@
+mod statement_resolver;
+mod usage_resolver;
+mod definitions;
+mod loop_resolver;
+mod constants;
+
+pub use definitions::SourceMap;
+pub use constants::{const_eval, collect_constants};
+
+use crate::diagnostics::Diagnostic;
+use crate::tree::{Module, StatementBlock, Statement};
+
+
+pub fn resolve(module: &Module) -> Vec<Diagnostic> {
+    let mut diagnostics = statement_resolver::resolve_module(module);
+    diagnostics.extend(usage_resolver::resolve_module(module));
+    diagnostics.extend(loop_resolver::resolve_module(module));
+    diagnostics
+}
+
+
+// The statement blocks directly within a statement
+pub(crate) fn nested_blocks(statement: &Statement) -> Vec<&StatementBlock> {
+    match statement {
+        Statement::Block(block) => vec![block],
+        Statement::Conditional(conditional) => [Some(&*conditional.body), conditional.else_body.as_deref()].into_iter().flatten().collect(),
+        Statement::Loop(loop_statement) => [Some(&*loop_statement.body), loop_statement.else_body.as_deref()].into_iter().flatten().collect(),
+        Statement::For(for_statement) => [Some(&*for_statement.body), for_statement.else_body.as_deref()].into_iter().flatten().collect(),
+        Statement::Repeat(repeat_statement) => vec![&repeat_statement.body],
+        Statement::Try(try_statement) => [Some(&*try_statement.body), try_statement.handler_body.as_deref()].into_iter().flatten().collect(),
+        _ => vec![],
+    }
+}
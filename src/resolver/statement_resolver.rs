@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use crate::diagnostics::Diagnostic;
+use crate::elements::{Identifier, Literal};
+use crate::span::Spanned;
+use crate::tree::{Module, Function, StatementBlock, Statement, Expression};
+use crate::interpreter::Value;
+use crate::resolver::constants::{const_eval, collect_constants};
+
+
+struct Context<'a> {
+    // Labels of the loops enclosing the current statement, innermost last
+    loop_labels: Vec<Option<Identifier>>,
+    in_function: bool,
+    // The constants of the module, known only at its top level, since a nested block may declare a
+    // variable of the same name
+    constants: HashMap<String, Literal>,
+    functions: &'a [Spanned<Function>],
+    diagnostics: Vec<Diagnostic>,
+}
+
+
+impl Context<'_> {
+    fn new(functions: &[Spanned<Function>]) -> Context<'_> {
+        Context { loop_labels: vec![], in_function: false, constants: HashMap::new(), functions, diagnostics: vec![] }
+    }
+}
+
+
+pub fn resolve_module(module: &Module) -> Vec<Diagnostic> {
+    let mut context = Context::new(&module.functions);
+
+    // Functions are found by name alone, whatever their parameters, so a second definition would
+    // replace the first
+    for (index, function) in module.functions.iter().enumerate() {
+        if module.functions[..index].iter().any(|earlier| earlier.name == function.name) {
+            context.diagnostics.push(Diagnostic::error(&format!("Function '{}' is defined more than once", function.name.as_string())));
+        }
+    }
+
+    context.in_function = true;
+    for function in &module.functions {
+        resolve_block(&function.body, &mut context);
+    }
+    context.in_function = false;
+    context.constants = collect_constants(&module.statements, &module.functions);
+    for statement in &module.statements.statements {
+        resolve_statement(statement, &mut context);
+    }
+
+    context.diagnostics
+}
+
+
+fn resolve_block(block: &StatementBlock, context: &mut Context) {
+    let constants = std::mem::take(&mut context.constants);
+    for statement in &block.statements {
+        resolve_statement(statement, context);
+    }
+    context.constants = constants;
+}
+
+
+fn resolve_statement(statement: &Statement, context: &mut Context) {
+    match statement {
+        Statement::Block(block) => resolve_block(block, context),
+
+        Statement::Conditional(conditional) => {
+            resolve_block(&conditional.body, context);
+            if let Some(else_body) = &conditional.else_body {
+                resolve_block(else_body, context);
+            }
+        },
+
+        Statement::Loop(loop_statement) => {
+            context.loop_labels.push(loop_statement.label.clone());
+            resolve_block(&loop_statement.body, context);
+            context.loop_labels.pop();
+            if let Some(else_body) = &loop_statement.else_body {
+                resolve_block(else_body, context);
+            }
+        },
+
+        Statement::For(for_statement) => {
+            context.loop_labels.push(for_statement.label.clone());
+            resolve_block(&for_statement.body, context);
+            context.loop_labels.pop();
+            if let Some(else_body) = &for_statement.else_body {
+                resolve_block(else_body, context);
+            }
+        },
+
+        Statement::Repeat(repeat_statement) => {
+            check_repeat_count(&repeat_statement.count, context);
+            context.loop_labels.push(None);
+            resolve_block(&repeat_statement.body, context);
+            context.loop_labels.pop();
+        },
+
+        Statement::Try(try_statement) => {
+            resolve_block(&try_statement.body, context);
+            if let Some(handler_body) = &try_statement.handler_body {
+                resolve_block(handler_body, context);
+            }
+        },
+
+        Statement::Break(break_statement) => resolve_loop_control("break", &break_statement.label, context),
+        Statement::Continue(continue_statement) => resolve_loop_control("continue", &continue_statement.label, context),
+
+        Statement::Global(name) => if !context.in_function {
+            context.diagnostics.push(Diagnostic::error(&format!("'global {}' outside of a function", name.as_string())));
+        },
+
+        Statement::Declaration(_) | Statement::Assignment(_) | Statement::Expression(_) | Statement::Return(_)
+        | Statement::Delete(_) | Statement::Pass | Statement::Raise(_) => {},
+    }
+}
+
+
+// A repeat count that is known before the program runs has to be an int that isn't negative
+fn check_repeat_count(count: &Expression, context: &mut Context) {
+    let message = match const_eval(count, &context.constants, context.functions) {
+        Some(Literal::Integer(count)) if count < 0 => format!("Repeat count can't be negative, found {}", count),
+        Some(Literal::Integer(_)) | None => return,
+        Some(count) => format!("Repeat count must be an int, found {}", Value::from_literal(&count).type_name()),
+    };
+    context.diagnostics.push(Diagnostic::error(&message));
+}
+
+
+fn resolve_loop_control(keyword: &str, label: &Option<Identifier>, context: &mut Context) {
+    if context.loop_labels.is_empty() {
+        context.diagnostics.push(Diagnostic::error(&format!("'{}' outside of a loop", keyword)));
+        return;
+    }
+
+    if let Some(label) = label {
+        if !context.loop_labels.contains(&Some(label.clone())) {
+            context.diagnostics.push(Diagnostic::error(&format!("Undefined loop label '{}'", label.as_string())));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    use super::*;
+
+    fn resolve_source(source: &str) -> Vec<Diagnostic> {
+        resolve_module(&parse(&tokenize(source)).unwrap())
+    }
+
+    #[test]
+    fn test_labeled_break_resolves() {
+        let source = "outer: while true {\n while true {\n break outer\n }\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_unknown_label_is_an_error() {
+        let source = "outer: while true {\n continue inner\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::error("Undefined loop label 'inner'")]);
+    }
+
+    #[test]
+    fn test_label_is_not_visible_after_its_loop() {
+        let source = "outer: while true {\n }\n while true {\n break outer\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::error("Undefined loop label 'outer'")]);
+    }
+
+    #[test]
+    fn test_global_outside_function_is_an_error() {
+        let source = "var count: int = 0\n global count\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::error("'global count' outside of a function")]);
+    }
+
+    #[test]
+    fn test_break_outside_loop_is_an_error() {
+        let source = "fun main() {\n break\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::error("'break' outside of a loop")]);
+    }
+
+    #[test]
+    fn test_duplicate_main_is_an_error() {
+        let source = "fun main() {\n print(1)\n }\n\nfun main() {\n print(2)\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::error("Function 'main' is defined more than once")]);
+
+        let source = "fun main() {\n print(1)\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_functions_with_the_same_name_and_different_parameters_are_an_error() {
+        let source = "fun area(x: int) {\n return x\n }\n\nfun area(x: int, y: int) {\n return x * y\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::error("Function 'area' is defined more than once")]);
+    }
+
+    #[test]
+    fn test_constant_repeat_count_is_checked() {
+        let source = "const fun half(x: int): int {\n return x / 2\n }\n\nsize: int = 2 - 5\nrepeat size {\n pass\n }\nrepeat half(-4) {\n pass\n }\nrepeat 1.5 {\n pass\n }\nrepeat half(4) {\n pass\n }\n";
+        assert_eq!(resolve_source(source), vec![
+            Diagnostic::error("Repeat count can't be negative, found -3"),
+            Diagnostic::error("Repeat count can't be negative, found -2"),
+            Diagnostic::error("Repeat count must be an int, found float"),
+        ]);
+    }
+
+    #[test]
+    fn test_constant_is_not_known_in_a_nested_block() {
+        let source = "size: int = 3\nif true {\n size: int = -1\n repeat size {\n pass\n }\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+}
@@ -0,0 +1,613 @@
+use crate::diagnostics::Diagnostic;
+use crate::interpreter::BUILTIN_FUNCTIONS;
+use crate::elements::Identifier;
+use crate::tree::{
+    Module, Function, Parameter, StatementBlock, Statement, Expression, AtomicExpression, FunctionCallExpression, ArrayIndex, Reference,
+};
+
+
+// Builtins that change the array passed as their first argument
+const MUTATING_FUNCTIONS: &[&str] = &["push", "pop"];
+
+
+struct Binding {
+    name: String,
+    kind: &'static str,
+    is_read: bool,
+    // Parameters without a var marker can't be assigned to or have their array changed
+    is_fixed: bool,
+}
+
+
+struct Context {
+    // Bindings declared in each enclosing scope, innermost last
+    scopes: Vec<Vec<Binding>>,
+    // Names declared further on in the block of each enclosing scope, which can't be used yet
+    later_declarations: Vec<Vec<String>>,
+    // Names that can be called, which are the functions of the module, the builtins and the imports
+    functions: Vec<String>,
+    // The sources of the imports, whose functions can all be called by qualified names
+    sources: Vec<String>,
+    // Names declared global or read without a binding inside functions, which may be globals of the module
+    global_uses: Vec<String>,
+    // Variables declared at the top level of the module, known while the functions are resolved
+    module_globals: Vec<String>,
+    // The globals that the function being resolved has declared with `global`
+    declared_globals: Vec<String>,
+    // The name of the function being resolved when it's a const function, which can't change any variable
+    const_function: Option<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+
+impl Context {
+    fn new(functions: Vec<String>, sources: Vec<String>) -> Context {
+        Context { scopes: vec![], later_declarations: vec![], functions, sources, global_uses: vec![], module_globals: vec![], declared_globals: vec![], const_function: None, diagnostics: vec![] }
+    }
+
+    fn declare(&mut self, name: &Identifier, kind: &'static str) {
+        let name = name.as_string();
+        if let Some(later) = self.later_declarations.last_mut() {
+            if let Some(index) = later.iter().position(|later| *later == name) {
+                later.remove(index);
+            }
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Binding { name, kind, is_read: false, is_fixed: false });
+        }
+    }
+
+    // The declarations directly in a block are only in scope after them, so using one of their names
+    // before that, when no earlier binding has it, is an error
+    fn expect_declarations(&mut self, block: &StatementBlock) {
+        if let Some(later) = self.later_declarations.last_mut() {
+            later.extend(block.statements.iter().filter_map(|statement| match &statement.node {
+                Statement::Declaration(declaration) => Some(declaration.name.as_string()),
+                _ => None,
+            }));
+        }
+    }
+
+    fn check_declared_before_use(&mut self, name: &str) {
+        let is_declared_later = !self.is_declared(name)
+            && !self.functions.iter().any(|function| function == name)
+            && self.later_declarations.iter().flatten().any(|later| later == name);
+        if is_declared_later {
+            self.diagnostics.push(Diagnostic::error(&format!("Variable '{}' is used before its declaration", name)));
+        }
+    }
+
+    fn declare_parameter(&mut self, parameter: &Parameter) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Binding { name: parameter.name.as_string(), kind: "Parameter", is_read: false, is_fixed: !parameter.is_mutable });
+        }
+    }
+
+    // Compound identifiers like `a.b` read the binding of their first part
+    fn read(&mut self, name: &Identifier) {
+        let name = match name {
+            Identifier::Simple(name) => name,
+            Identifier::Compound(names) => &names[0],
+        };
+
+        let binding = self.scopes.iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|binding| binding.name == *name);
+
+        match binding {
+            Some(binding) => binding.is_read = true,
+            None => {
+                self.check_declared_before_use(name);
+                self.global_uses.push(name.clone());
+            },
+        }
+    }
+
+    // The module's variables that functions use are read, even if the module's statements never read them
+    fn read_globals(&mut self, names: &[String]) {
+        if let Some(scope) = self.scopes.last_mut() {
+            for binding in scope.iter_mut().filter(|binding| names.contains(&binding.name)) {
+                binding.is_read = true;
+            }
+        }
+    }
+
+    fn mutate(&mut self, name: &Identifier) {
+        let name = name.as_string();
+        self.check_declared_before_use(&name);
+        if let Some(function) = &self.const_function {
+            self.diagnostics.push(Diagnostic::error(&format!("Const function '{}' can't change '{}'", function, name)));
+            return;
+        }
+        let binding = self.scopes.iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|binding| binding.name == name);
+
+        if binding.is_some_and(|binding| binding.is_fixed) {
+            self.diagnostics.push(Diagnostic::error(&format!("Parameter '{}' can't be changed unless it's marked var", name)));
+        }
+    }
+
+    // A function can only assign to a global of the module after declaring it with `global`, unless a
+    // local of the same name shadows it
+    fn check_global_assignment(&mut self, name: &Identifier) {
+        let name = name.as_string();
+        let is_undeclared_global = !self.is_declared(&name)
+            && self.module_globals.contains(&name)
+            && !self.declared_globals.contains(&name);
+        if is_undeclared_global {
+            self.diagnostics.push(Diagnostic::error(&format!(
+                "Cannot assign to global variable '{}' in a function without declaring it global", name
+            )));
+        }
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        self.scopes.iter().flatten().any(|binding| binding.name == name)
+    }
+
+    fn enter_scope(&mut self) {
+        self.scopes.push(vec![]);
+        self.later_declarations.push(vec![]);
+    }
+
+    fn exit_scope(&mut self) {
+        self.later_declarations.pop();
+        let scope = self.scopes.pop().unwrap_or_default();
+        for binding in scope {
+            if !binding.is_read && !binding.name.starts_with('_') {
+                self.diagnostics.push(Diagnostic::warning(&format!("{} '{}' is never read", binding.kind, binding.name)));
+            }
+        }
+    }
+}
+
+
+pub fn resolve_module(module: &Module) -> Vec<Diagnostic> {
+    let imports: Vec<_> = module.imports.iter().filter(|import| !import.is_wildcard).collect();
+    let functions = module.functions.iter().map(|function| function.name.as_string())
+        .chain(imports.iter().map(|import| import.alias.as_string()))
+        .chain(BUILTIN_FUNCTIONS.iter().map(|(name, _)| name.to_string()))
+        .collect();
+    let sources = module.imports.iter().map(|import| import.source.as_string()).collect();
+    let mut context = Context::new(functions, sources);
+
+    context.module_globals = module.statements.statements.iter()
+        .filter_map(|statement| match &statement.node {
+            Statement::Declaration(declaration) => Some(declaration.name.as_string()),
+            _ => None,
+        })
+        .collect();
+    for function in &module.functions {
+        resolve_function(function, &mut context);
+    }
+    context.module_globals.clear();
+    let global_uses = std::mem::take(&mut context.global_uses);
+
+    context.enter_scope();
+    resolve_block(&module.statements, &mut context);
+    context.read_globals(&global_uses);
+    context.exit_scope();
+
+    context.diagnostics
+}
+
+
+fn resolve_function(function: &Function, context: &mut Context) {
+    context.declared_globals.clear();
+    context.const_function = function.is_const.then(|| function.name.as_string());
+    context.enter_scope();
+    for parameter in &function.parameters {
+        context.declare_parameter(parameter);
+    }
+    resolve_block(&function.body, context);
+    context.exit_scope();
+}
+
+
+fn resolve_block(block: &StatementBlock, context: &mut Context) {
+    context.expect_declarations(block);
+    for statement in &block.statements {
+        resolve_statement(statement, context);
+    }
+}
+
+
+fn resolve_nested_block(block: &StatementBlock, context: &mut Context) {
+    context.enter_scope();
+    resolve_block(block, context);
+    context.exit_scope();
+}
+
+
+fn resolve_statement(statement: &Statement, context: &mut Context) {
+    match statement {
+        Statement::Declaration(declaration) => {
+            resolve_expression(&declaration.value, context);
+            context.declare(&declaration.name, "Variable");
+        },
+
+        // Assigning to a variable doesn't read it, but assigning to an element reads the array, whose
+        // change every reference to it sees, and the indices
+        Statement::Assignment(assignment) => {
+            resolve_expression(&assignment.value, context);
+            for reference in &assignment.references {
+                match reference {
+                    Reference::Identifier(name) => context.check_global_assignment(name),
+                    Reference::ArrayReference { .. } => context.read(reference.root()),
+                }
+                context.mutate(reference.root());
+                resolve_reference(reference, context);
+            }
+        },
+
+        Statement::Expression(expression) => resolve_expression(expression, context),
+        Statement::Return(return_statement) => if let Some(value) = &return_statement.value {
+            resolve_expression(value, context);
+        },
+        Statement::Raise(value) => resolve_expression(value, context),
+
+        Statement::Delete(reference) => {
+            if let Reference::ArrayReference { .. } = reference {
+                context.read(reference.root());
+            }
+            context.mutate(reference.root());
+            resolve_reference(reference, context);
+        },
+
+        Statement::Global(name) => {
+            if let Some(function) = &context.const_function {
+                context.diagnostics.push(Diagnostic::error(&format!("Const function '{}' can't use global '{}'", function, name.as_string())));
+            }
+            context.global_uses.push(name.as_string());
+            context.declared_globals.push(name.as_string());
+        },
+        Statement::Pass => {},
+
+        Statement::Block(block) => resolve_nested_block(block, context),
+
+        Statement::Conditional(conditional) => {
+            resolve_expression(&conditional.condition, context);
+            resolve_nested_block(&conditional.body, context);
+            if let Some(else_body) = &conditional.else_body {
+                resolve_nested_block(else_body, context);
+            }
+        },
+
+        Statement::Loop(loop_statement) => {
+            resolve_expression(&loop_statement.condition, context);
+            resolve_nested_block(&loop_statement.body, context);
+            if let Some(else_body) = &loop_statement.else_body {
+                resolve_nested_block(else_body, context);
+            }
+        },
+
+        Statement::For(for_statement) => {
+            resolve_expression(&for_statement.iterable, context);
+            context.enter_scope();
+            for variable in &for_statement.variables {
+                context.declare(variable, "Loop variable");
+            }
+            resolve_nested_block(&for_statement.body, context);
+            context.exit_scope();
+            if let Some(else_body) = &for_statement.else_body {
+                resolve_nested_block(else_body, context);
+            }
+        },
+
+        Statement::Repeat(repeat_statement) => {
+            resolve_expression(&repeat_statement.count, context);
+            resolve_nested_block(&repeat_statement.body, context);
+        },
+
+        Statement::Try(try_statement) => {
+            resolve_nested_block(&try_statement.body, context);
+            if let Some(handler_body) = &try_statement.handler_body {
+                context.enter_scope();
+                if let Some(handler_var) = &try_statement.handler_var {
+                    context.declare(handler_var, "Error variable");
+                }
+                resolve_nested_block(handler_body, context);
+                context.exit_scope();
+            }
+        },
+
+        Statement::Break(_) | Statement::Continue(_) => {},
+    }
+}
+
+
+fn resolve_reference(reference: &Reference, context: &mut Context) {
+    match reference {
+        Reference::Identifier(_) => {},
+        Reference::ArrayReference { array, index } => {
+            resolve_reference(array, context);
+            resolve_array_index(index, context);
+        },
+    }
+}
+
+
+fn resolve_expression(expression: &Expression, context: &mut Context) {
+    match expression {
+        Expression::TernaryCondition { condition, true_value, false_value } => {
+            resolve_expression(condition, context);
+            resolve_expression(true_value, context);
+            resolve_expression(false_value, context);
+        },
+
+        Expression::BinaryOperation { left, right, .. } => {
+            resolve_expression(left, context);
+            resolve_expression(right, context);
+        },
+
+        Expression::UnaryOperation { operand, .. } => resolve_expression(operand, context),
+        Expression::Cast(cast) => resolve_expression(&cast.value, context),
+        Expression::Assignment(assignment) => {
+            resolve_expression(&assignment.value, context);
+            context.check_global_assignment(&assignment.target);
+            context.mutate(&assignment.target);
+        },
+        Expression::Tuple(values) => {
+            for value in values {
+                resolve_expression(value, context);
+            }
+        },
+        Expression::Spread(spread) => resolve_expression(&spread.value, context),
+        Expression::Atomic(atom) => resolve_atomic(atom, context),
+    }
+}
+
+
+fn resolve_atomic(atom: &AtomicExpression, context: &mut Context) {
+    match atom {
+        AtomicExpression::Literal(_) => {},
+        AtomicExpression::Identifier(identifier) => context.read(identifier),
+
+        AtomicExpression::FunctionCall(call) => {
+            resolve_call(call, context);
+            for argument in call.arguments() {
+                resolve_expression(argument, context);
+            }
+        },
+
+        AtomicExpression::Parenthesized(parenthesized) => resolve_expression(&parenthesized.value, context),
+
+        AtomicExpression::ArrayLiteral(array) => {
+            for value in &array.values {
+                resolve_expression(value, context);
+            }
+        },
+
+        AtomicExpression::MapLiteral(map) => {
+            for (key, value) in &map.entries {
+                resolve_expression(key, context);
+                resolve_expression(value, context);
+            }
+        },
+
+        AtomicExpression::Comprehension(comprehension) => {
+            resolve_expression(&comprehension.iterable, context);
+            context.enter_scope();
+            for variable in &comprehension.variables {
+                context.declare(variable, "Loop variable");
+            }
+            if let Some(condition) = &comprehension.condition {
+                resolve_expression(condition, context);
+            }
+            resolve_expression(&comprehension.output, context);
+            context.exit_scope();
+        },
+
+        AtomicExpression::StructLiteral(literal) => {
+            for (_, value) in &literal.fields {
+                resolve_expression(value, context);
+            }
+        },
+
+        AtomicExpression::ArrayIndex(array_index) => {
+            resolve_atomic(&array_index.array, context);
+            resolve_array_index(&array_index.index, context);
+        },
+    }
+}
+
+
+// A qualified name like `math.sqrt` is called through an imported module, a source imported with a
+// wildcard, or a variable holding it
+fn resolve_call(call: &FunctionCallExpression, context: &mut Context) {
+    let name = call.name.as_string();
+    let is_defined = match &call.name {
+        Identifier::Simple(_) => context.functions.contains(&name),
+        Identifier::Compound(names) => context.functions.contains(&name)
+            || context.functions.contains(&names[0])
+            || context.sources.iter().any(|source| name.starts_with(&format!("{}.", source)))
+            || context.is_declared(&names[0]),
+    };
+
+    if is_defined {
+        context.read(&call.name);
+    } else {
+        context.diagnostics.push(Diagnostic::error(&format!("Undefined function '{}'", name)));
+    }
+
+    if MUTATING_FUNCTIONS.contains(&name.as_str()) {
+        if let Some(Expression::Atomic(AtomicExpression::Identifier(array))) = call.parameters.first() {
+            context.mutate(array);
+        }
+    }
+}
+
+
+fn resolve_array_index(index: &ArrayIndex, context: &mut Context) {
+    match index {
+        ArrayIndex::Single(index) => resolve_expression(index, context),
+        ArrayIndex::Slice { start, end } => {
+            if let Some(start) = start {
+                resolve_expression(start, context);
+            }
+            if let Some(end) = end {
+                resolve_expression(end, context);
+            }
+        },
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    use super::*;
+
+    fn resolve_source(source: &str) -> Vec<Diagnostic> {
+        resolve_module(&parse(&tokenize(source)).unwrap())
+    }
+
+    #[test]
+    fn test_unused_local_is_a_warning() {
+        let source = "fun main() {\n x: int = 1\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::warning("Variable 'x' is never read")]);
+    }
+
+    #[test]
+    fn test_unused_parameter_is_a_warning() {
+        let source = "fun first(a: int, b: int): int {\n return a\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::warning("Parameter 'b' is never read")]);
+    }
+
+    #[test]
+    fn test_used_variable_has_no_warning() {
+        let source = "fun main() {\n x: int = 1\n if true {\n print(x)\n }\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_assigned_but_never_read_is_a_warning() {
+        let source = "fun main() {\n var x: int = 1\n x = 2\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::warning("Variable 'x' is never read")]);
+    }
+
+    #[test]
+    fn test_global_used_only_by_functions_has_no_warning() {
+        let source = "fun count() {\n global total\n total += 1\n }\n\n fun show() {\n print(limit)\n }\n\n var total: int = 0\n limit: int = 10\n unused: int = 0\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::warning("Variable 'unused' is never read")]);
+    }
+
+    #[test]
+    fn test_assigning_an_undeclared_global_in_a_function_is_an_error() {
+        let source = "fun reset() {\n total = 0\n }\n\n fun count() {\n global total\n total += 1\n }\n\n var total: int = 0\n total = 1\n";
+        assert_eq!(resolve_source(source), vec![
+            Diagnostic::error("Cannot assign to global variable 'total' in a function without declaring it global"),
+        ]);
+    }
+
+    #[test]
+    fn test_local_shadowing_a_global_can_be_assigned() {
+        let source = "fun reset() {\n total: int = 0\n total = 1\n print(total)\n }\n\n var total: int = 0\n print(total)\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_underscore_names_are_exempt() {
+        let source = "fun main(_unused: int) {\n _x: int = 1\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_variable_goes_out_of_scope() {
+        let source = "fun main() {\n if true {\n x: int = 1\n }\n print(x)\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::warning("Variable 'x' is never read")]);
+    }
+
+    #[test]
+    fn test_qualified_call_to_an_imported_module() {
+        let source = "using {\n math from std\n }\n\nfun main() {\n print(math.sqrt(2.0))\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_qualified_call_to_the_source_of_an_import() {
+        let source = "using {\n sqrt from math\n }\n\nfun main() {\n print(math.sqrt(2.0))\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_qualified_call_under_a_wildcard_import() {
+        let source = "using {\n * from math\n }\n\nfun main() {\n print(math.anything(2.0))\n print(other.sqrt(2.0))\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::error("Undefined function 'other.sqrt'")]);
+    }
+
+    #[test]
+    fn test_wildcard_import_does_not_define_the_source_as_a_function() {
+        let source = "using {\n *, sqrt from math\n }\n\nfun main() {\n print(sqrt(2.0))\n print(math(2.0))\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::error("Undefined function 'math'")]);
+    }
+
+    #[test]
+    fn test_call_without_an_import_is_an_error() {
+        let source = "fun main() {\n print(sqrt(2.0))\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::error("Undefined function 'sqrt'")]);
+    }
+
+    #[test]
+    fn test_changing_an_unmarked_parameter_is_an_error() {
+        let source = "fun fill(values: array, count: int) {\n push(values, count)\n count = 0\n }\n";
+        assert_eq!(resolve_source(source), vec![
+            Diagnostic::error("Parameter 'values' can't be changed unless it's marked var"),
+            Diagnostic::error("Parameter 'count' can't be changed unless it's marked var"),
+        ]);
+    }
+
+    #[test]
+    fn test_array_parameter_changed_by_index_is_read() {
+        let source = "fun clear(var values: array) {\n values[0] = 5\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+
+        let source = "fun clear(var values: array) {\n del values[0]\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+
+        let source = "fun clear(var count: int) {\n count = 5\n }\n";
+        assert_eq!(resolve_source(source), vec![Diagnostic::warning("Parameter 'count' is never read")]);
+    }
+
+    #[test]
+    fn test_changing_a_var_parameter() {
+        let source = "fun fill(var values: array) {\n push(values, 1)\n values[0] = 2\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_use_before_declaration_is_an_error() {
+        let source = "fun main() {\n var x: int = 0\n x = y\n if true {\n print(y)\n }\n var y: int = 1\n print(x, y)\n }\n";
+        assert_eq!(resolve_source(source), vec![
+            Diagnostic::error("Variable 'y' is used before its declaration"),
+            Diagnostic::error("Variable 'y' is used before its declaration"),
+        ]);
+    }
+
+    #[test]
+    fn test_use_of_an_earlier_binding_is_not_before_declaration() {
+        let source = "fun main(y: int) {\n print(y)\n if true {\n print(y)\n y: int = 2\n print(y)\n }\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_functions_can_be_used_before_their_definition() {
+        let source = "fun main() {\n total: int = double(2)\n print(total)\n }\n\nfun double(x: int): int {\n return x * 2\n }\n";
+        assert_eq!(resolve_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_const_function_cannot_change_variables() {
+        let source = "const fun count(var x: int): int {\n global total\n x = 1\n total := x\n return x\n }\n\nvar total: int = 0\n";
+        assert_eq!(resolve_source(source), vec![
+            Diagnostic::error("Const function 'count' can't use global 'total'"),
+            Diagnostic::error("Const function 'count' can't change 'x'"),
+            Diagnostic::error("Const function 'count' can't change 'total'"),
+        ]);
+    }
+}
@@ -0,0 +1,445 @@
+use crate::elements::{Identifier, Literal, Operator};
+use crate::tree::{
+    Module, Import, StructDefinition, Function, StatementBlock, Statement, Expression, AtomicExpression,
+    ArrayIndex, Reference,
+};
+
+
+const INDENT: &str = "    ";
+
+
+// Prints a syntax tree back out as source code. Parentheses are kept in the tree, so the printed
+// source parses back to the same tree, although the original formatting is lost.
+pub fn print_source(module: &Module) -> String {
+    let mut output = String::new();
+
+    if !module.imports.is_empty() {
+        write_line(&mut output, 0, "using {");
+        for import in &module.imports {
+            write_line(&mut output, 1, &format_import(import));
+        }
+        write_line(&mut output, 0, "}");
+    }
+    for definition in &module.structs {
+        print_struct(definition, &mut output);
+    }
+    for function in &module.functions {
+        print_function(function, &mut output);
+    }
+    print_block(&module.statements, 0, &mut output);
+
+    output
+}
+
+
+pub fn format_expression(expression: &Expression) -> String {
+    match expression {
+        Expression::TernaryCondition { condition, true_value, false_value } => format!(
+            "{} ? {} : {}", format_expression(condition), format_expression(true_value), format_expression(false_value)
+        ),
+
+        Expression::BinaryOperation { left, operator, right } => format!(
+            "{} {} {}", format_expression(left), format_operator(operator), format_expression(right)
+        ),
+
+        Expression::UnaryOperation { operator, operand } => format!("{}{}", format_operator(operator), format_expression(operand)),
+
+        Expression::Cast(cast) => format!("{} as {}", format_expression(&cast.value), cast.target_type.as_string()),
+
+        Expression::Assignment(assignment) => format!("{} := {}", assignment.target.as_string(), format_expression(&assignment.value)),
+
+        // Tuples are only written without parentheses, after return
+        Expression::Tuple(values) => format_list(values),
+
+        Expression::Spread(spread) => format!("*{}", format_expression(&spread.value)),
+
+        Expression::Atomic(atom) => format_atomic(atom),
+    }
+}
+
+
+// Floats are printed in their shortest form that reads back to the same value, which switches to
+// scientific notation for very large and very small values, like 1e-7 or 1e16
+pub fn format_literal(literal: &Literal) -> String {
+    match literal {
+        Literal::Float(value) => format!("{:?}", value),
+        Literal::Integer(value) => value.to_string(),
+        Literal::Char(value) => format!("'{}'", escape(&value.to_string(), '\'')),
+        Literal::String(value) => format!("\"{}\"", escape(value, '"')),
+        Literal::Boolean(value) => value.to_string(),
+        Literal::None => "None".to_string(),
+        Literal::TypedInteger(value, number_type) => format!("{}{}", value, number_type.suffix()),
+        Literal::TypedFloat(value, number_type) => format!("{:?}{}", value, number_type.suffix()),
+    }
+}
+
+
+fn escape(text: &str, quote: char) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\0' => escaped.push_str("\\0"),
+            '\x07' => escaped.push_str("\\a"),
+            '\x08' => escaped.push_str("\\b"),
+            '\x0C' => escaped.push_str("\\f"),
+            '\\' => escaped.push_str("\\\\"),
+            c if c == quote => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+
+fn write_line(output: &mut String, depth: usize, text: &str) {
+    output.push_str(&INDENT.repeat(depth));
+    output.push_str(text);
+    output.push('\n');
+}
+
+
+fn format_import(import: &Import) -> String {
+    if import.is_wildcard {
+        format!("* from {}", import.source.as_string())
+    } else if import.alias == import.name {
+        format!("{} from {}", import.name.as_string(), import.source.as_string())
+    } else {
+        format!("{} as {} from {}", import.name.as_string(), import.alias.as_string(), import.source.as_string())
+    }
+}
+
+
+fn print_struct(definition: &StructDefinition, output: &mut String) {
+    write_line(output, 0, &format!("struct {} {{", definition.name.as_string()));
+    for field in &definition.fields {
+        write_line(output, 1, &format!("{}: {}", field.name.as_string(), field.param_type.as_string()));
+    }
+    write_line(output, 0, "}");
+}
+
+
+fn print_function(function: &Function, output: &mut String) {
+    if let Some(doc) = &function.doc {
+        for line in doc.lines() {
+            write_line(output, 0, &format!("# {}", line));
+        }
+    }
+
+    let mut parameters = function.parameters.iter()
+        .map(|parameter| {
+            let keyword = if parameter.is_mutable { "var " } else { "" };
+            format!("{}{}: {}", keyword, parameter.name.as_string(), parameter.param_type.as_string())
+        })
+        .collect::<Vec<String>>();
+    if function.positional_count() < parameters.len() {
+        parameters.insert(function.positional_count(), "*".to_string());
+    }
+    let parameters = parameters.join(", ");
+
+    let return_type = match &function.return_type {
+        Some(return_type) => format!(": {}", return_type.as_string()),
+        None => String::new(),
+    };
+
+    let keyword = if function.is_const { "const fun" } else { "fun" };
+    write_line(output, 0, &format!("{} {}({}){} {{", keyword, function.name.as_string(), parameters, return_type));
+    print_block(&function.body, 1, output);
+    write_line(output, 0, "}");
+}
+
+
+fn print_block(block: &StatementBlock, depth: usize, output: &mut String) {
+    for statement in &block.statements {
+        print_statement(statement, depth, output);
+        if let Some(comment) = &statement.trailing_comment {
+            output.pop();
+            output.push_str(&format_trailing_comment(comment));
+            output.push('\n');
+        }
+    }
+}
+
+
+// Comments from a block comment may span several lines, which a line comment can't hold
+fn format_trailing_comment(comment: &str) -> String {
+    if comment.contains('\n') {
+        format!("  /* {} */", comment)
+    } else {
+        format!("  # {}", comment)
+    }
+}
+
+
+fn print_statement(statement: &Statement, depth: usize, output: &mut String) {
+    match statement {
+        Statement::Declaration(declaration) => {
+            let keyword = if declaration.is_mutable { "var " } else { "" };
+            write_line(output, depth, &format!(
+                "{}{}: {} = {}", keyword, declaration.name.as_string(), declaration.var_type.as_string(),
+                format_expression(&declaration.value)
+            ));
+        },
+
+        Statement::Assignment(assignment) => {
+            let references = assignment.references.iter().map(format_reference).collect::<Vec<_>>();
+            write_line(output, depth, &format!("{} = {}", references.join(" = "), format_expression(&assignment.value)));
+        },
+
+        Statement::Expression(expression) => write_line(output, depth, &format_expression(expression)),
+
+        Statement::Return(return_statement) => match &return_statement.value {
+            Some(value) => write_line(output, depth, &format!("return {}", format_expression(value))),
+            None => write_line(output, depth, "return"),
+        },
+
+        Statement::Delete(reference) => write_line(output, depth, &format!("del {}", format_reference(reference))),
+
+        Statement::Global(name) => write_line(output, depth, &format!("global {}", name.as_string())),
+
+        Statement::Pass => write_line(output, depth, "pass"),
+
+        Statement::Raise(value) => write_line(output, depth, &format!("raise {}", format_expression(value))),
+
+        Statement::Block(block) => {
+            write_line(output, depth, "{");
+            print_block(block, depth + 1, output);
+            write_line(output, depth, "}");
+        },
+
+        Statement::Conditional(conditional) => {
+            write_line(output, depth, &format!("if {} {{", format_expression(&conditional.condition)));
+            print_block(&conditional.body, depth + 1, output);
+            if let Some(else_body) = &conditional.else_body {
+                write_line(output, depth, "} else {");
+                print_block(else_body, depth + 1, output);
+            }
+            write_line(output, depth, "}");
+        },
+
+        Statement::Loop(loop_statement) => {
+            let label = match &loop_statement.label {
+                Some(label) => format!("{}: ", label.as_string()),
+                None => String::new(),
+            };
+            write_line(output, depth, &format!("{}while {} {{", label, format_expression(&loop_statement.condition)));
+            print_block(&loop_statement.body, depth + 1, output);
+            if let Some(else_body) = &loop_statement.else_body {
+                write_line(output, depth, "} else {");
+                print_block(else_body, depth + 1, output);
+            }
+            write_line(output, depth, "}");
+        },
+
+        Statement::For(for_statement) => {
+            let label = match &for_statement.label {
+                Some(label) => format!("{}: ", label.as_string()),
+                None => String::new(),
+            };
+            let variables = for_statement.variables.iter().map(Identifier::as_string).collect::<Vec<String>>();
+            write_line(output, depth, &format!(
+                "{}for {} in {} {{", label, variables.join(", "), format_expression(&for_statement.iterable)
+            ));
+            print_block(&for_statement.body, depth + 1, output);
+            if let Some(else_body) = &for_statement.else_body {
+                write_line(output, depth, "} else {");
+                print_block(else_body, depth + 1, output);
+            }
+            write_line(output, depth, "}");
+        },
+
+        Statement::Repeat(repeat_statement) => {
+            write_line(output, depth, &format!("repeat {} {{", format_expression(&repeat_statement.count)));
+            print_block(&repeat_statement.body, depth + 1, output);
+            write_line(output, depth, "}");
+        },
+
+        Statement::Break(break_statement) => write_line(output, depth, &with_label("break", &break_statement.label)),
+        Statement::Continue(continue_statement) => write_line(output, depth, &with_label("continue", &continue_statement.label)),
+
+        Statement::Try(try_statement) => {
+            write_line(output, depth, "try {");
+            print_block(&try_statement.body, depth + 1, output);
+            if let Some(handler_body) = &try_statement.handler_body {
+                write_line(output, depth, &match &try_statement.handler_var {
+                    Some(handler_var) => format!("}} except {} {{", handler_var.as_string()),
+                    None => "} except {".to_string(),
+                });
+                print_block(handler_body, depth + 1, output);
+            }
+            write_line(output, depth, "}");
+        },
+    }
+}
+
+
+fn with_label(keyword: &str, label: &Option<Identifier>) -> String {
+    match label {
+        Some(label) => format!("{} {}", keyword, label.as_string()),
+        None => keyword.to_string(),
+    }
+}
+
+
+fn format_reference(reference: &Reference) -> String {
+    match reference {
+        Reference::Identifier(identifier) => identifier.as_string(),
+        Reference::ArrayReference { array, index } => format!("{}{}", format_reference(array), format_array_index(index)),
+    }
+}
+
+
+fn format_atomic(atom: &AtomicExpression) -> String {
+    match atom {
+        AtomicExpression::Literal(literal) => format_literal(literal),
+
+        AtomicExpression::Identifier(identifier) => identifier.as_string(),
+
+        AtomicExpression::FunctionCall(call) => {
+            let arguments = call.parameters.iter()
+                .map(format_expression)
+                .chain(call.keyword_arguments.iter().map(|(name, value)| format!("{} = {}", name.as_string(), format_expression(value))))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("{}({})", call.name.as_string(), arguments)
+        },
+
+        AtomicExpression::Parenthesized(parenthesized) => format!("({})", format_expression(&parenthesized.value)),
+
+        AtomicExpression::ArrayLiteral(array) => format!("[{}]", format_list(&array.values)),
+
+        AtomicExpression::MapLiteral(map) => {
+            let entries = map.entries.iter()
+                .map(|(key, value)| format!("{}: {}", format_expression(key), format_expression(value)))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("{{{}}}", entries)
+        },
+
+        AtomicExpression::Comprehension(comprehension) => {
+            let variables = comprehension.variables.iter().map(Identifier::as_string).collect::<Vec<String>>().join(", ");
+            let condition = comprehension.condition.as_ref()
+                .map(|condition| format!(" if {}", format_expression(condition)))
+                .unwrap_or_default();
+            format!(
+                "[{} for {} in {}{}]",
+                format_expression(&comprehension.output), variables, format_expression(&comprehension.iterable), condition
+            )
+        },
+
+        AtomicExpression::StructLiteral(literal) => {
+            let fields = literal.fields.iter()
+                .map(|(name, value)| format!("{}: {}", name.as_string(), format_expression(value)))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("{} {{ {} }}", literal.name.as_string(), fields)
+        },
+
+        AtomicExpression::ArrayIndex(array_index) => format!(
+            "{}{}", format_atomic(&array_index.array), format_array_index(&array_index.index)
+        ),
+    }
+}
+
+
+fn format_list(values: &[Expression]) -> String {
+    values.iter()
+        .map(format_expression)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+
+fn format_array_index(index: &ArrayIndex) -> String {
+    match index {
+        ArrayIndex::Single(value) => format!("[{}]", format_expression(value)),
+        ArrayIndex::Slice { start, end } => format!(
+            "[{}:{}]",
+            start.as_ref().map(|start| format_expression(start)).unwrap_or_default(),
+            end.as_ref().map(|end| format_expression(end)).unwrap_or_default(),
+        ),
+    }
+}
+
+
+pub fn format_operator(operator: &Operator) -> &'static str {
+    match operator {
+        Operator::Plus => "+",
+        Operator::Minus => "-",
+        Operator::Times => "*",
+        Operator::Divide => "/",
+        Operator::Modulo => "%",
+        Operator::Power => "**",
+
+        Operator::And => "&&",
+        Operator::Or => "||",
+        Operator::Not => "!",
+
+        Operator::BitwiseAnd => "&",
+        Operator::BitwiseOr => "|",
+        Operator::BitwiseXor => "^",
+        Operator::BitwiseLeftShift => "<<",
+        Operator::BitwiseRightShift => ">>",
+        Operator::BitwiseNot => "~",
+
+        Operator::Equal => "==",
+        Operator::NotEqual => "!=",
+        Operator::LessThan => "<",
+        Operator::GreaterThan => ">",
+        Operator::LessThanOrEqual => "<=",
+        Operator::GreaterThanOrEqual => ">=",
+
+        Operator::In => "in",
+        Operator::Is => "is",
+        Operator::IsNot => "is not",
+        Operator::Range => "..",
+        Operator::InclusiveRange => "..=",
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::{tokenize, tokenize_with_options, LexOptions};
+    use crate::parser::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_format_float_literals() {
+        assert_eq!(format_literal(&Literal::Float(4.5)), "4.5");
+        assert_eq!(format_literal(&Literal::Float(1e-7)), "1e-7");
+        assert_eq!(format_literal(&Literal::Float(1.2345e67)), "1.2345e67");
+        assert_eq!(format_literal(&Literal::Float(1340000.0)), "1340000.0");
+    }
+
+    #[test]
+    fn test_print_function() {
+        let source = "fun add(a: int, b: int): int {\n    return (a + b) * -2\n}\n";
+        let module = parse(&tokenize(source)).unwrap();
+
+        assert_eq!(print_source(&module), source);
+    }
+
+    #[test]
+    fn test_print_escaped_literals() {
+        let source = "f(\"a\\\"b\\n\", '\\'')\n";
+        let module = parse(&tokenize(source)).unwrap();
+
+        assert_eq!(print_source(&module), source);
+    }
+
+    #[test]
+    fn test_print_trailing_comments() {
+        let source = "x: int = 1  # the value\nif x > 0 {\n    x = 2  # positive\n}  # done\n";
+        let tokens = tokenize_with_options(source, &LexOptions { trivia: true, ..LexOptions::default() });
+        let module = parse(&tokens).unwrap();
+
+        assert_eq!(print_source(&module), source);
+    }
+}
@@ -0,0 +1,134 @@
+use std::ops::Deref;
+
+
+// A range of byte offsets into the source code, with the end being exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    // The smallest span covering both this span and the other one
+    pub fn merge(&self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+
+// A line and column in the source code, both counted from 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+
+impl Position {
+    pub fn at_offset(source: &str, offset: usize, tab_width: usize) -> Position {
+        let mut position = Position { line: 1, column: 1 };
+        let mut previous = None;
+
+        // A CRLF pair is a single line break, like a lone LF or CR
+        for c in source[..offset].chars() {
+            match c {
+                '\n' if previous == Some('\r') => {},
+                '\n' | '\r' => position = Position { line: position.line + 1, column: 1 },
+                '\t' if tab_width > 0 => position.column = ((position.column - 1) / tab_width + 1) * tab_width + 1,
+                _ => position.column += 1,
+            }
+            previous = Some(c);
+        }
+
+        position
+    }
+}
+
+
+// A node of the tree or a token along with where it came from in the source code.
+// Spans are ignored when comparing, so trees parsed from differently formatted source are equal.
+#[derive(Debug, Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+    // A comment after a statement on the same line, only kept when lexing with trivia. Like the
+    // span, it's ignored when comparing.
+    pub trailing_comment: Option<String>,
+}
+
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Spanned<T> {
+        Spanned { node, span, trailing_comment: None }
+    }
+}
+
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Spanned<T> {
+        Spanned { node, span: Span::default(), trailing_comment: None }
+    }
+}
+
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Spanned<T>) -> bool {
+        self.node == other.node
+    }
+}
+
+
+impl<T: PartialEq> PartialEq<T> for Spanned<T> {
+    fn eq(&self, other: &T) -> bool {
+        self.node == *other
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_merge_spans() {
+        assert_eq!(Span::new(4, 6).merge(Span::new(1, 3)), Span::new(1, 6));
+    }
+
+    #[test]
+    fn test_position_at_offset() {
+        assert_eq!(Position::at_offset("ab\ncd", 4, 4), Position { line: 2, column: 2 });
+        assert_eq!(Position::at_offset("a\tb", 2, 4), Position { line: 1, column: 5 });
+        assert_eq!(Position::at_offset("abcd\tb", 5, 4), Position { line: 1, column: 9 });
+    }
+
+    #[test]
+    fn test_position_after_crlf() {
+        assert_eq!(Position::at_offset("ab\r\ncd", 5, 4), Position { line: 2, column: 2 });
+        assert_eq!(Position::at_offset("ab\r\n\r\ncd", 6, 4), Position { line: 3, column: 1 });
+        assert_eq!(Position::at_offset("ab\rcd", 4, 4), Position { line: 2, column: 2 });
+    }
+
+    #[test]
+    fn test_spans_are_ignored_when_comparing() {
+        assert_eq!(Spanned::new('a', Span::new(0, 1)), Spanned::new('a', Span::new(5, 6)));
+        assert_ne!(Spanned::new('a', Span::new(0, 1)), Spanned::new('b', Span::new(0, 1)));
+        assert_eq!(Spanned::new('a', Span::new(0, 1)), 'a');
+    }
+}
@@ -1,4 +1,9 @@
+use std::fmt;
+
 use crate::elements::{Identifier, Literal, Operator, Keyword};
+use crate::source_printer::{format_literal, format_operator};
+use crate::span::Span;
+use crate::diagnostics::escape_json;
 
 #[derive(Debug,  Clone, PartialEq)]
 pub enum Token {
@@ -12,18 +17,208 @@ pub enum Token {
 
     ListSeparator,
     Dot,
+    // `..` leaves out the end of the range, while `..=` includes it
+    Range,
+    InclusiveRange,
     Colon,
+    // Ends a statement like a new line, and is the only thing that does when parsing free-form
+    Semicolon,
 
     Newline,
     EndOfModule,
 
+    // Trivia, only produced when lexing with trivia enabled
+    Comment(String),
+    // A comment after code on the same line
+    TrailingComment(String),
+
     // Operators
     Operator(Operator),
     TernaryCondition,
+    // A star before an array element or argument, rather than a multiplication
+    Spread,
     Assign,
     BinaryAssign(Operator),
+    // Assigns within an expression, which is a different token to = so it can't be mistaken for ==
+    InlineAssign,
+    // Passes the value on its left as the first argument of the call on its right
+    Pipe,
 
     Keyword(Keyword),
     Literal(Literal),
     Identifier(Identifier),
+
+    // Malformed source with the reason, only produced when lexing leniently
+    Error(String),
+}
+
+
+// Tokens display as the source they were read from, except for the ones with no text of their own
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::OpenParen => write!(f, "("),
+            Token::CloseParen => write!(f, ")"),
+            Token::OpenBrace => write!(f, "{{"),
+            Token::CloseBrace => write!(f, "}}"),
+            Token::OpenSquareBracket => write!(f, "["),
+            Token::CloseSquareBracket => write!(f, "]"),
+
+            Token::ListSeparator => write!(f, ","),
+            Token::Dot => write!(f, "."),
+            Token::Range => write!(f, ".."),
+            Token::InclusiveRange => write!(f, "..="),
+            Token::Colon => write!(f, ":"),
+            Token::Semicolon => write!(f, ";"),
+
+            Token::Newline => write!(f, "⏎"),
+            Token::EndOfModule => write!(f, "<end>"),
+
+            Token::Comment(comment) | Token::TrailingComment(comment) => write!(f, "/* {} */", comment),
+
+            Token::Operator(operator) => write!(f, "{}", format_operator(operator)),
+            Token::TernaryCondition => write!(f, "?"),
+            Token::Spread => write!(f, "*"),
+            Token::Assign => write!(f, "="),
+            Token::BinaryAssign(operator) => write!(f, "{}=", format_operator(operator)),
+            Token::InlineAssign => write!(f, ":="),
+            Token::Pipe => write!(f, "|>"),
+
+            Token::Keyword(keyword) => write!(f, "{}", keyword.as_str()),
+            Token::Literal(literal) => write!(f, "{}", format_literal(literal)),
+            Token::Identifier(identifier) => write!(f, "{}", identifier.as_string()),
+            Token::Error(message) => write!(f, "<error: {}>", message),
+        }
+    }
+}
+
+
+// An approximation of the source that the tokens were read from, with a space between each token
+// and a marker for the end of each line
+pub fn format_tokens(tokens: &[Token]) -> String {
+    tokens.iter()
+        .map(|token| token.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+
+// The tokens as a JSON array with one object per line, each giving the kind of the token, the
+// value it carries if any, and the start and end offsets of its span
+pub fn tokens_to_json(tokens: &[Token], spans: &[Span]) -> String {
+    let objects: Vec<String> = tokens.iter()
+        .zip(spans)
+        .map(|(token, span)| token_to_json(token, *span))
+        .collect();
+
+    if objects.is_empty() {
+        "[]".to_string()
+    } else {
+        format!("[\n  {}\n]", objects.join(",\n  "))
+    }
+}
+
+
+fn token_to_json(token: &Token, span: Span) -> String {
+    let value = match token {
+        Token::Comment(text) | Token::TrailingComment(text) | Token::Error(text) => Some(json_string(text)),
+        Token::Operator(operator) | Token::BinaryAssign(operator) => Some(json_string(format_operator(operator))),
+        Token::Keyword(keyword) => Some(json_string(keyword.as_str())),
+        Token::Literal(literal) => Some(literal_to_json(literal)),
+        Token::Identifier(identifier) => Some(json_string(&identifier.as_string())),
+        _ => None,
+    };
+
+    match value {
+        Some(value) => format!(
+            "{{\"kind\": \"{}\", \"value\": {}, \"start\": {}, \"end\": {}}}",
+            token_kind(token), value, span.start, span.end
+        ),
+        None => format!("{{\"kind\": \"{}\", \"start\": {}, \"end\": {}}}", token_kind(token), span.start, span.end),
+    }
+}
+
+
+fn token_kind(token: &Token) -> &'static str {
+    match token {
+        Token::OpenParen => "open_paren",
+        Token::CloseParen => "close_paren",
+        Token::OpenBrace => "open_brace",
+        Token::CloseBrace => "close_brace",
+        Token::OpenSquareBracket => "open_square_bracket",
+        Token::CloseSquareBracket => "close_square_bracket",
+
+        Token::ListSeparator => "list_separator",
+        Token::Dot => "dot",
+        Token::Range => "range",
+        Token::InclusiveRange => "inclusive_range",
+        Token::Colon => "colon",
+        Token::Semicolon => "semicolon",
+
+        Token::Newline => "newline",
+        Token::EndOfModule => "end_of_module",
+
+        Token::Comment(_) => "comment",
+        Token::TrailingComment(_) => "trailing_comment",
+
+        Token::Operator(_) => "operator",
+        Token::TernaryCondition => "ternary_condition",
+        Token::Spread => "spread",
+        Token::Assign => "assign",
+        Token::BinaryAssign(_) => "binary_assign",
+        Token::InlineAssign => "inline_assign",
+        Token::Pipe => "pipe",
+
+        Token::Keyword(_) => "keyword",
+        Token::Literal(_) => "literal",
+        Token::Identifier(_) => "identifier",
+        Token::Error(_) => "error",
+    }
+}
+
+
+// Numbers and booleans are given as JSON values, and chars and strings as JSON strings. A float
+// too large to read as a number has no JSON value, so it's given as null like None.
+fn literal_to_json(literal: &Literal) -> String {
+    match literal {
+        Literal::Float(value) | Literal::TypedFloat(value, _) if !value.is_finite() => "null".to_string(),
+        Literal::Char(_) | Literal::String(_) => json_string(&literal.to_display_string()),
+        Literal::None => "null".to_string(),
+        literal => literal.to_display_string(),
+    }
+}
+
+
+fn json_string(text: &str) -> String {
+    format!("\"{}\"", escape_json(text))
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::{tokenize_with_spans, LexOptions};
+
+    use super::*;
+
+    #[test]
+    fn test_tokens_to_json() {
+        let (tokens, spans) = tokenize_with_spans("x = 'a' + 1.5\n", &LexOptions::default());
+
+        assert_eq!(tokens_to_json(&tokens, &spans), concat!(
+            "[\n",
+            "  {\"kind\": \"identifier\", \"value\": \"x\", \"start\": 0, \"end\": 1},\n",
+            "  {\"kind\": \"assign\", \"start\": 2, \"end\": 3},\n",
+            "  {\"kind\": \"literal\", \"value\": \"a\", \"start\": 4, \"end\": 7},\n",
+            "  {\"kind\": \"operator\", \"value\": \"+\", \"start\": 8, \"end\": 9},\n",
+            "  {\"kind\": \"literal\", \"value\": 1.5, \"start\": 10, \"end\": 13},\n",
+            "  {\"kind\": \"newline\", \"start\": 13, \"end\": 14},\n",
+            "  {\"kind\": \"end_of_module\", \"start\": 14, \"end\": 14}\n",
+            "]",
+        ));
+    }
+
+    #[test]
+    fn test_no_tokens_is_an_empty_array() {
+        assert_eq!(tokens_to_json(&[], &[]), "[]");
+    }
 }
@@ -1,19 +1,40 @@
 
 use crate::elements::{Identifier, Literal, Operator};
+use crate::span::Spanned;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Module {
     pub name: Identifier,
     pub imports: Vec<Import>,
-    pub functions: Vec<Function>,
+    pub structs: Vec<Spanned<StructDefinition>>,
+    pub functions: Vec<Spanned<Function>>,
     pub statements: StatementBlock,
 }
 
+impl Module {
+    pub fn function(&self, name: &Identifier) -> Option<&Function> {
+        self.functions.iter()
+            .find(|function| function.name == *name)
+            .map(|function| &function.node)
+    }
+
+    pub fn main_function(&self) -> Option<&Function> {
+        self.function(&Identifier::Simple("main".to_string()))
+    }
+
+    pub fn import(&self, alias: &Identifier) -> Option<&Import> {
+        self.imports.iter().find(|import| import.alias == *alias)
+    }
+}
+
+// A wildcard import, `* from math`, brings in everything from the source under the source's own
+// name, as in `math.sqrt`, so its name and alias are the source
 #[derive(Debug, Clone, PartialEq)]
 pub struct Import {
     pub name: Identifier,
     pub alias: Identifier,
     pub source: Identifier,
+    pub is_wildcard: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -22,17 +43,74 @@ pub struct Function {
     pub parameters: Vec<Parameter>,
     pub return_type: Option<Identifier>,
     pub body: Box<StatementBlock>,
+    pub doc: Option<String>,
+    // Calls to a const function with constant arguments can be evaluated before the program runs
+    pub is_const: bool,
+}
+
+impl Function {
+    // The number of parameters before the keyword-only marker
+    pub fn positional_count(&self) -> usize {
+        self.parameters.iter()
+            .take_while(|parameter| parameter.kind == ParameterKind::Positional)
+            .count()
+    }
+
+    // Whether a call with this many positional arguments and these keyword arguments gives every
+    // parameter exactly one value. Any parameter can be passed by name, but keyword-only ones must be.
+    pub fn check_arguments(&self, positional_count: usize, keywords: &[&Identifier]) -> Result<(), String> {
+        let name = self.name.as_string();
+        let found = positional_count + keywords.len();
+
+        if positional_count > self.positional_count() && self.positional_count() < self.parameters.len() {
+            return Err(format!(
+                "Keyword-only parameter '{}' of function '{}' can't be passed positionally",
+                self.parameters[self.positional_count()].name.as_string(), name
+            ));
+        }
+
+        for (index, keyword) in keywords.iter().enumerate() {
+            match self.parameters.iter().position(|parameter| parameter.name == **keyword) {
+                None => return Err(format!("Function '{}' has no parameter '{}'", name, keyword.as_string())),
+                Some(position) if position < positional_count || keywords[..index].contains(keyword) => {
+                    return Err(format!("Parameter '{}' of function '{}' is given more than once", keyword.as_string(), name));
+                },
+                Some(_) => {},
+            }
+        }
+
+        if found != self.parameters.len() {
+            return Err(format!("Function '{}' expects {} arguments, found {}", name, self.parameters.len(), found));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDefinition {
+    pub name: Identifier,
+    pub fields: Vec<Parameter>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Parameter {
     pub name: Identifier,
     pub param_type: Identifier,
+    pub kind: ParameterKind,
+    // Only parameters marked with var may be assigned to, or have their array changed in place
+    pub is_mutable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterKind {
+    Positional,
+    // Parameters after a * marker can't be passed positionally
+    KeywordOnly,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StatementBlock {
-    pub statements: Vec<Statement>,
+    pub statements: Vec<Spanned<Statement>>,
 }
 
 impl StatementBlock {
@@ -47,9 +125,21 @@ pub enum Statement {
     Assignment(AssignmentStatement),
     Expression(Expression),
     Return(ReturnStatement),
+    Delete(Reference),
+    // Assignments to the name later in the function change the global rather than a local
+    Global(Identifier),
+    // Does nothing, for a block that is intentionally empty
+    Pass,
+    Block(StatementBlock),
 
     Conditional(ConditionalStatement),
     Loop(LoopStatement),
+    For(ForStatement),
+    Repeat(RepeatStatement),
+    Break(BreakStatement),
+    Continue(ContinueStatement),
+    Try(TryStatement),
+    Raise(Expression),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -62,13 +152,15 @@ pub struct DeclarationStatement {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct AssignmentStatement {
-    pub reference: Reference,
+    // A chained assignment like a = b = 0 has several references, which all get the same value
+    pub references: Vec<Reference>,
     pub value: Expression,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReturnStatement {
-    pub value: Expression,
+    // A bare return has no value, and returns None
+    pub value: Option<Expression>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -82,27 +174,111 @@ pub struct ConditionalStatement {
 pub struct LoopStatement {
     pub condition: Expression,
     pub body: Box<StatementBlock>,
+    pub label: Option<Identifier>,
+    // Runs when the loop finishes without a break
+    pub else_body: Option<Box<StatementBlock>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForStatement {
+    // A map gives its keys to a single variable, or its keys and values to two variables
+    pub variables: Vec<Identifier>,
+    pub iterable: Expression,
+    pub body: Box<StatementBlock>,
+    pub label: Option<Identifier>,
+    // Runs when the loop finishes without a break
+    pub else_body: Option<Box<StatementBlock>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatStatement {
+    // Evaluated once, before the first run of the body
+    pub count: Expression,
+    pub body: Box<StatementBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakStatement {
+    pub label: Option<Identifier>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContinueStatement {
+    pub label: Option<Identifier>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TryStatement {
+    pub body: Box<StatementBlock>,
+    // The error is bound to the handler variable, if there is one, while the handler runs
+    pub handler_var: Option<Identifier>,
+    // Without an except clause, errors in the body are raised again
+    pub handler_body: Option<Box<StatementBlock>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     TernaryCondition {
-        condition: Box<Expression>,
-        true_value: Box<Expression>,
-        false_value: Box<Expression>,
+        condition: Box<Spanned<Expression>>,
+        true_value: Box<Spanned<Expression>>,
+        false_value: Box<Spanned<Expression>>,
     },
     BinaryOperation {
-        left: Box<Expression>,
+        left: Box<Spanned<Expression>>,
         operator: Operator,
-        right: Box<Expression>,
+        right: Box<Spanned<Expression>>,
     },
     UnaryOperation {
         operator: Operator,
-        operand: Box<Expression>,
+        operand: Box<Spanned<Expression>>,
     },
+    Cast(CastExpression),
+    Assignment(AssignmentExpression),
+    Tuple(Vec<Expression>),
+    Spread(SpreadExpression),
     Atomic(AtomicExpression),
 }
 
+impl Expression {
+    pub fn int(value: i64) -> Expression {
+        Expression::Atomic(AtomicExpression::Literal(Literal::Integer(value)))
+    }
+
+    pub fn float(value: f64) -> Expression {
+        Expression::Atomic(AtomicExpression::Literal(Literal::Float(value)))
+    }
+
+    pub fn ident(name: &str) -> Expression {
+        Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple(name.to_string())))
+    }
+
+    pub fn as_literal(&self) -> Option<&Literal> {
+        match self {
+            Expression::Atomic(AtomicExpression::Literal(literal)) => Some(literal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastExpression {
+    pub value: Box<Spanned<Expression>>,
+    pub target_type: Identifier,
+}
+
+// Assigns to a variable that is already declared, giving the assigned value, as in (n := next()) > 0
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignmentExpression {
+    pub target: Identifier,
+    pub value: Box<Spanned<Expression>>,
+}
+
+// Only allowed as an element of an array literal or an argument of a function call
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpreadExpression {
+    pub value: Box<Spanned<Expression>>,
+}
+
 #[derive(Debug, Clone, PartialEq, )]
 pub enum AtomicExpression {
     Literal(Literal),
@@ -110,7 +286,10 @@ pub enum AtomicExpression {
     FunctionCall(FunctionCallExpression),
     Parenthesized(ParenthesizedExpression),
     ArrayLiteral(ArrayLiteralExpression),
+    MapLiteral(MapLiteralExpression),
     ArrayIndex(ArrayIndexExpression),
+    Comprehension(ComprehensionExpression),
+    StructLiteral(StructLiteralExpression),
 }
 
 
@@ -118,12 +297,22 @@ pub enum AtomicExpression {
 pub struct FunctionCallExpression {
     pub name: Identifier,
     pub parameters: Vec<Expression>,
+    // Arguments passed by the name of their parameter, as in `scale(x, factor = 2)`, which come
+    // after the positional ones
+    pub keyword_arguments: Vec<(Identifier, Expression)>,
+}
+
+impl FunctionCallExpression {
+    // The values of the positional arguments followed by those of the keyword arguments
+    pub fn arguments(&self) -> impl Iterator<Item = &Expression> {
+        self.parameters.iter().chain(self.keyword_arguments.iter().map(|(_, value)| value))
+    }
 }
 
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ParenthesizedExpression {
-    pub value: Box<Expression>,
+    pub value: Box<Spanned<Expression>>,
 }
 
 
@@ -133,6 +322,31 @@ pub struct ArrayLiteralExpression {
 }
 
 
+// An array built from the output for each item of the iterable, like `[x * 2 for x in values if x > 0]`.
+// The loop variables are unpacked like those of a for statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComprehensionExpression {
+    pub output: Box<Spanned<Expression>>,
+    pub variables: Vec<Identifier>,
+    pub iterable: Box<Spanned<Expression>>,
+    pub condition: Option<Box<Spanned<Expression>>>,
+}
+
+
+// The fields may be given in any order, but each field of the struct must be given once
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructLiteralExpression {
+    pub name: Identifier,
+    pub fields: Vec<(Identifier, Expression)>,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapLiteralExpression {
+    pub entries: Vec<(Expression, Expression)>,
+}
+
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ArrayIndexExpression {
     pub array: Box<AtomicExpression>,
@@ -141,10 +355,10 @@ pub struct ArrayIndexExpression {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ArrayIndex {
-    Single(Box<Expression>),
+    Single(Box<Spanned<Expression>>),
     Slice {
-        start: Option<Box<Expression>>,
-        end: Option<Box<Expression>>,
+        start: Option<Box<Spanned<Expression>>>,
+        end: Option<Box<Spanned<Expression>>>,
     },
 }
 
@@ -157,3 +371,76 @@ pub enum Reference {
         index: ArrayIndex,
     },
 }
+
+impl Reference {
+    // The variable that the reference changes, which for an element is the variable holding the array
+    pub fn root(&self) -> &Identifier {
+        match self {
+            Reference::Identifier(name) => name,
+            Reference::ArrayReference { array, .. } => array.root(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn empty_function(name: &str) -> Function {
+        Function {
+            name: Identifier::Simple(name.to_string()),
+            parameters: vec![],
+            return_type: None,
+            body: Box::new(StatementBlock::empty()),
+            doc: None,
+            is_const: false,
+        }
+    }
+
+    fn module_with_functions(names: &[&str]) -> Module {
+        Module {
+            name: Identifier::Simple("main".to_string()),
+            imports: vec![],
+            structs: vec![],
+            functions: names.iter().map(|name| empty_function(name).into()).collect(),
+            statements: StatementBlock::empty(),
+        }
+    }
+
+    #[test]
+    fn test_function_lookup() {
+        let module = module_with_functions(&["main", "helper"]);
+
+        assert_eq!(module.function(&Identifier::Simple("main".to_string())), Some(&module.functions[0].node));
+        assert_eq!(module.function(&Identifier::Simple("helper".to_string())), Some(&module.functions[1].node));
+    }
+
+    #[test]
+    fn test_function_lookup_miss() {
+        let module = module_with_functions(&["main", "helper"]);
+
+        assert_eq!(module.function(&Identifier::Simple("missing".to_string())), None);
+    }
+
+    #[test]
+    fn test_main_function_lookup() {
+        let module = module_with_functions(&["helper"]);
+
+        assert_eq!(module.main_function(), None);
+    }
+
+    #[test]
+    fn test_expression_constructors() {
+        assert_eq!(Expression::int(1), Expression::Atomic(AtomicExpression::Literal(Literal::Integer(1))));
+        assert_eq!(Expression::float(2.5), Expression::Atomic(AtomicExpression::Literal(Literal::Float(2.5))));
+        assert_eq!(Expression::ident("x"), Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("x".to_string()))));
+    }
+
+    #[test]
+    fn test_expression_as_literal() {
+        assert_eq!(Expression::int(1).as_literal(), Some(&Literal::Integer(1)));
+        assert_eq!(Expression::ident("x").as_literal(), None);
+        assert_eq!(Expression::Tuple(vec![Expression::int(1)]).as_literal(), None);
+    }
+}
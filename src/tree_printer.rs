@@ -0,0 +1,426 @@
+use crate::elements::{Identifier, Literal};
+use crate::tree::{
+    Module, Import, StructDefinition, Function, StatementBlock, Statement, Expression, AtomicExpression,
+    ArrayIndex, Reference,
+};
+
+
+// Prints a syntax tree as an indented outline, one node per line with its children indented
+// below it. This is intended for debugging and for writing readable parser tests.
+pub fn print_tree(module: &Module) -> String {
+    let mut output = String::new();
+
+    write_line(&mut output, 0, &format!("module {}", module.name.as_string()));
+    for import in &module.imports {
+        print_import(import, 1, &mut output);
+    }
+    for definition in &module.structs {
+        print_struct(definition, 1, &mut output);
+    }
+    for function in &module.functions {
+        print_function(function, 1, &mut output);
+    }
+    print_block(&module.statements, 1, &mut output);
+
+    output
+}
+
+
+pub fn print_expression(expression: &Expression) -> String {
+    let mut output = String::new();
+    write_expression(expression, 0, &mut output);
+    output
+}
+
+
+fn write_line(output: &mut String, depth: usize, text: &str) {
+    output.push_str(&"  ".repeat(depth));
+    output.push_str(text);
+    output.push('\n');
+}
+
+
+fn print_import(import: &Import, depth: usize, output: &mut String) {
+    if import.is_wildcard {
+        write_line(output, depth, &format!("import * from {}", import.source.as_string()));
+        return;
+    }
+    write_line(output, depth, &format!(
+        "import {} as {} from {}", import.name.as_string(), import.alias.as_string(), import.source.as_string()
+    ));
+}
+
+
+fn print_struct(definition: &StructDefinition, depth: usize, output: &mut String) {
+    write_line(output, depth, &format!("struct {}", definition.name.as_string()));
+    for field in &definition.fields {
+        write_line(output, depth + 1, &format!("{}: {}", field.name.as_string(), field.param_type.as_string()));
+    }
+}
+
+
+fn print_function(function: &Function, depth: usize, output: &mut String) {
+    let mut parameters = function.parameters.iter()
+        .map(|parameter| {
+            let keyword = if parameter.is_mutable { "var " } else { "" };
+            format!("{}{}: {}", keyword, parameter.name.as_string(), parameter.param_type.as_string())
+        })
+        .collect::<Vec<String>>();
+    if function.positional_count() < parameters.len() {
+        parameters.insert(function.positional_count(), "*".to_string());
+    }
+    let parameters = parameters.join(", ");
+
+    let return_type = match &function.return_type {
+        Some(return_type) => format!(": {}", return_type.as_string()),
+        None => String::new(),
+    };
+
+    let keyword = if function.is_const { "const function" } else { "function" };
+    write_line(output, depth, &format!("{} {}({}){}", keyword, function.name.as_string(), parameters, return_type));
+    if let Some(doc) = &function.doc {
+        write_line(output, depth + 1, &format!("doc {:?}", doc));
+    }
+    print_block(&function.body, depth + 1, output);
+}
+
+
+fn print_block(block: &StatementBlock, depth: usize, output: &mut String) {
+    for statement in &block.statements {
+        print_statement(statement, depth, output);
+    }
+}
+
+
+fn print_statement(statement: &Statement, depth: usize, output: &mut String) {
+    match statement {
+        Statement::Declaration(declaration) => {
+            let keyword = if declaration.is_mutable { "declare var" } else { "declare" };
+            write_line(output, depth, &format!(
+                "{} {}: {}", keyword, declaration.name.as_string(), declaration.var_type.as_string()
+            ));
+            write_expression(&declaration.value, depth + 1, output);
+        },
+
+        Statement::Assignment(assignment) => {
+            write_line(output, depth, "assign");
+            for reference in &assignment.references {
+                print_reference(reference, depth + 1, output);
+            }
+            write_expression(&assignment.value, depth + 1, output);
+        },
+
+        Statement::Expression(expression) => write_expression(expression, depth, output),
+
+        Statement::Return(return_statement) => {
+            write_line(output, depth, "return");
+            if let Some(value) = &return_statement.value {
+                write_expression(value, depth + 1, output);
+            }
+        },
+
+        Statement::Delete(reference) => {
+            write_line(output, depth, "delete");
+            print_reference(reference, depth + 1, output);
+        },
+
+        Statement::Global(name) => write_line(output, depth, &format!("global {}", name.as_string())),
+
+        Statement::Pass => write_line(output, depth, "pass"),
+
+        Statement::Raise(value) => {
+            write_line(output, depth, "raise");
+            write_expression(value, depth + 1, output);
+        },
+
+        Statement::Block(block) => {
+            write_line(output, depth, "block");
+            print_block(block, depth + 1, output);
+        },
+
+        Statement::Conditional(conditional) => {
+            write_line(output, depth, "if");
+            write_expression(&conditional.condition, depth + 1, output);
+            write_line(output, depth, "then");
+            print_block(&conditional.body, depth + 1, output);
+            if let Some(else_body) = &conditional.else_body {
+                write_line(output, depth, "else");
+                print_block(else_body, depth + 1, output);
+            }
+        },
+
+        Statement::Loop(loop_statement) => {
+            write_line(output, depth, &with_label("while", &loop_statement.label));
+            write_expression(&loop_statement.condition, depth + 1, output);
+            write_line(output, depth, "do");
+            print_block(&loop_statement.body, depth + 1, output);
+            if let Some(else_body) = &loop_statement.else_body {
+                write_line(output, depth, "else");
+                print_block(else_body, depth + 1, output);
+            }
+        },
+
+        Statement::For(for_statement) => {
+            write_line(output, depth, &with_label("for", &for_statement.label));
+            let variables = for_statement.variables.iter().map(Identifier::as_string).collect::<Vec<String>>();
+            write_line(output, depth + 1, &variables.join(", "));
+            write_line(output, depth, "in");
+            write_expression(&for_statement.iterable, depth + 1, output);
+            write_line(output, depth, "do");
+            print_block(&for_statement.body, depth + 1, output);
+            if let Some(else_body) = &for_statement.else_body {
+                write_line(output, depth, "else");
+                print_block(else_body, depth + 1, output);
+            }
+        },
+
+        Statement::Repeat(repeat_statement) => {
+            write_line(output, depth, "repeat");
+            write_expression(&repeat_statement.count, depth + 1, output);
+            write_line(output, depth, "times");
+            print_block(&repeat_statement.body, depth + 1, output);
+        },
+
+        Statement::Break(break_statement) => write_line(output, depth, &with_label("break", &break_statement.label)),
+        Statement::Continue(continue_statement) => write_line(output, depth, &with_label("continue", &continue_statement.label)),
+
+        Statement::Try(try_statement) => {
+            write_line(output, depth, "try");
+            print_block(&try_statement.body, depth + 1, output);
+            if let Some(handler_body) = &try_statement.handler_body {
+                write_line(output, depth, &match &try_statement.handler_var {
+                    Some(handler_var) => format!("except {}", handler_var.as_string()),
+                    None => "except".to_string(),
+                });
+                print_block(handler_body, depth + 1, output);
+            }
+        },
+    }
+}
+
+
+fn with_label(keyword: &str, label: &Option<Identifier>) -> String {
+    match label {
+        Some(label) => format!("{} {}", keyword, label.as_string()),
+        None => keyword.to_string(),
+    }
+}
+
+
+fn print_reference(reference: &Reference, depth: usize, output: &mut String) {
+    match reference {
+        Reference::Identifier(identifier) => write_line(output, depth, &format!("identifier {}", identifier.as_string())),
+        Reference::ArrayReference { array, index } => {
+            write_line(output, depth, "index");
+            print_reference(array, depth + 1, output);
+            print_array_index(index, depth + 1, output);
+        },
+    }
+}
+
+
+fn write_expression(expression: &Expression, depth: usize, output: &mut String) {
+    match expression {
+        Expression::TernaryCondition { condition, true_value, false_value } => {
+            write_line(output, depth, "ternary");
+            write_expression(condition, depth + 1, output);
+            write_expression(true_value, depth + 1, output);
+            write_expression(false_value, depth + 1, output);
+        },
+
+        Expression::BinaryOperation { left, operator, right } => {
+            write_line(output, depth, &format!("binary {:?}", operator));
+            write_expression(left, depth + 1, output);
+            write_expression(right, depth + 1, output);
+        },
+
+        Expression::UnaryOperation { operator, operand } => {
+            write_line(output, depth, &format!("unary {:?}", operator));
+            write_expression(operand, depth + 1, output);
+        },
+
+        Expression::Cast(cast) => {
+            write_line(output, depth, &format!("cast {}", cast.target_type.as_string()));
+            write_expression(&cast.value, depth + 1, output);
+        },
+
+        Expression::Assignment(assignment) => {
+            write_line(output, depth, &format!("inline assign {}", assignment.target.as_string()));
+            write_expression(&assignment.value, depth + 1, output);
+        },
+
+        Expression::Tuple(values) => {
+            write_line(output, depth, "tuple");
+            for value in values {
+                write_expression(value, depth + 1, output);
+            }
+        },
+
+        Expression::Spread(spread) => {
+            write_line(output, depth, "spread");
+            write_expression(&spread.value, depth + 1, output);
+        },
+
+        Expression::Atomic(atom) => print_atomic(atom, depth, output),
+    }
+}
+
+
+fn print_atomic(atom: &AtomicExpression, depth: usize, output: &mut String) {
+    match atom {
+        AtomicExpression::Literal(literal) => write_line(output, depth, &format!("literal {}", format_literal(literal))),
+
+        AtomicExpression::Identifier(identifier) => write_line(output, depth, &format!("identifier {}", identifier.as_string())),
+
+        AtomicExpression::FunctionCall(call) => {
+            write_line(output, depth, &format!("call {}", call.name.as_string()));
+            for parameter in &call.parameters {
+                write_expression(parameter, depth + 1, output);
+            }
+            for (name, value) in &call.keyword_arguments {
+                write_line(output, depth + 1, &format!("keyword {}", name.as_string()));
+                write_expression(value, depth + 2, output);
+            }
+        },
+
+        AtomicExpression::Parenthesized(parenthesized) => {
+            write_line(output, depth, "group");
+            write_expression(&parenthesized.value, depth + 1, output);
+        },
+
+        AtomicExpression::ArrayLiteral(array) => {
+            write_line(output, depth, "array");
+            for value in &array.values {
+                write_expression(value, depth + 1, output);
+            }
+        },
+
+        AtomicExpression::MapLiteral(map) => {
+            write_line(output, depth, "map");
+            for (key, value) in &map.entries {
+                write_line(output, depth + 1, "entry");
+                write_expression(key, depth + 2, output);
+                write_expression(value, depth + 2, output);
+            }
+        },
+
+        AtomicExpression::Comprehension(comprehension) => {
+            write_line(output, depth, "comprehension");
+            write_expression(&comprehension.output, depth + 1, output);
+            let variables = comprehension.variables.iter().map(Identifier::as_string).collect::<Vec<String>>();
+            write_line(output, depth + 1, "for");
+            write_line(output, depth + 2, &variables.join(", "));
+            write_line(output, depth + 1, "in");
+            write_expression(&comprehension.iterable, depth + 2, output);
+            if let Some(condition) = &comprehension.condition {
+                write_line(output, depth + 1, "if");
+                write_expression(condition, depth + 2, output);
+            }
+        },
+
+        AtomicExpression::StructLiteral(literal) => {
+            write_line(output, depth, &format!("struct {}", literal.name.as_string()));
+            for (name, value) in &literal.fields {
+                write_line(output, depth + 1, &format!("field {}", name.as_string()));
+                write_expression(value, depth + 2, output);
+            }
+        },
+
+        AtomicExpression::ArrayIndex(array_index) => {
+            write_line(output, depth, "index");
+            print_atomic(&array_index.array, depth + 1, output);
+            print_array_index(&array_index.index, depth + 1, output);
+        },
+    }
+}
+
+
+fn print_array_index(index: &ArrayIndex, depth: usize, output: &mut String) {
+    match index {
+        ArrayIndex::Single(value) => write_expression(value, depth, output),
+        ArrayIndex::Slice { start, end } => {
+            write_line(output, depth, "slice");
+            match start {
+                Some(start) => write_expression(start, depth + 1, output),
+                None => write_line(output, depth + 1, "start"),
+            }
+            match end {
+                Some(end) => write_expression(end, depth + 1, output),
+                None => write_line(output, depth + 1, "end"),
+            }
+        },
+    }
+}
+
+
+fn format_literal(literal: &Literal) -> String {
+    match literal {
+        Literal::Float(value) => format!("{:?}", value),
+        Literal::Integer(value) => value.to_string(),
+        Literal::Char(value) => format!("{:?}", value),
+        Literal::String(value) => format!("{:?}", value),
+        Literal::Boolean(value) => value.to_string(),
+        Literal::None => "None".to_string(),
+        Literal::TypedInteger(value, number_type) => format!("{}{}", value, number_type.suffix()),
+        Literal::TypedFloat(value, number_type) => format!("{:?}{}", value, number_type.suffix()),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_print_function() {
+        let module = parse(&tokenize("fun add(a: int, b: int): int {\n return a + b\n }\n")).unwrap();
+
+        assert_eq!(print_tree(&module), concat!(
+            "module main\n",
+            "  function add(a: int, b: int): int\n",
+            "    return\n",
+            "      binary Plus\n",
+            "        identifier a\n",
+            "        identifier b\n",
+        ));
+    }
+
+    #[test]
+    fn test_print_statements() {
+        let module = parse(&tokenize("var x: int = 1\n while x < 3 {\n x = x + 1\n }\n")).unwrap();
+
+        assert_eq!(print_tree(&module), concat!(
+            "module main\n",
+            "  declare var x: int\n",
+            "    literal 1\n",
+            "  while\n",
+            "    binary LessThan\n",
+            "      identifier x\n",
+            "      literal 3\n",
+            "  do\n",
+            "    assign\n",
+            "      identifier x\n",
+            "      binary Plus\n",
+            "        identifier x\n",
+            "        literal 1\n",
+        ));
+    }
+
+    #[test]
+    fn test_print_literals() {
+        let module = parse(&tokenize("f(1.5, 'c', \"s\", true, None)\n")).unwrap();
+
+        assert_eq!(print_tree(&module), concat!(
+            "module main\n",
+            "  call f\n",
+            "    literal 1.5\n",
+            "    literal 'c'\n",
+            "    literal \"s\"\n",
+            "    literal true\n",
+            "    literal None\n",
+        ));
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use crate::diagnostics::Diagnostic;
+use crate::elements::Identifier;
+use crate::tree::{
+    Module, Function, StatementBlock, Statement, Expression, AtomicExpression, FunctionCallExpression, ArrayIndex, Reference,
+};
+
+
+struct Context<'a> {
+    functions: HashMap<String, &'a Function>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+
+// Checks the arguments of each call to a function defined in the module against its parameters.
+// Calls to other functions, and calls with a spread argument, can't be checked.
+pub fn check_calls(module: &Module) -> Vec<Diagnostic> {
+    let mut context = Context {
+        functions: module.functions.iter()
+            .map(|function| (function.name.as_string(), &function.node))
+            .collect(),
+        diagnostics: vec![],
+    };
+
+    for function in &module.functions {
+        check_block(&function.body, &mut context);
+    }
+    check_block(&module.statements, &mut context);
+
+    context.diagnostics
+}
+
+
+fn check_block(block: &StatementBlock, context: &mut Context) {
+    for statement in &block.statements {
+        check_statement(statement, context);
+    }
+}
+
+
+fn check_statement(statement: &Statement, context: &mut Context) {
+    match statement {
+        Statement::Declaration(declaration) => check_expression(&declaration.value, context),
+
+        Statement::Assignment(assignment) => {
+            check_expression(&assignment.value, context);
+            for reference in &assignment.references {
+                check_reference(reference, context);
+            }
+        },
+
+        Statement::Expression(expression) => check_expression(expression, context),
+        Statement::Return(return_statement) => if let Some(value) = &return_statement.value {
+            check_expression(value, context);
+        },
+        Statement::Raise(value) => check_expression(value, context),
+        Statement::Delete(reference) => check_reference(reference, context),
+        Statement::Block(block) => check_block(block, context),
+
+        Statement::Conditional(conditional) => {
+            check_expression(&conditional.condition, context);
+            check_block(&conditional.body, context);
+            if let Some(else_body) = &conditional.else_body {
+                check_block(else_body, context);
+            }
+        },
+
+        Statement::Loop(loop_statement) => {
+            check_expression(&loop_statement.condition, context);
+            check_block(&loop_statement.body, context);
+            if let Some(else_body) = &loop_statement.else_body {
+                check_block(else_body, context);
+            }
+        },
+
+        Statement::For(for_statement) => {
+            check_expression(&for_statement.iterable, context);
+            check_block(&for_statement.body, context);
+            if let Some(else_body) = &for_statement.else_body {
+                check_block(else_body, context);
+            }
+        },
+
+        Statement::Repeat(repeat_statement) => {
+            check_expression(&repeat_statement.count, context);
+            check_block(&repeat_statement.body, context);
+        },
+
+        Statement::Try(try_statement) => {
+            check_block(&try_statement.body, context);
+            if let Some(handler_body) = &try_statement.handler_body {
+                check_block(handler_body, context);
+            }
+        },
+
+        Statement::Global(_) | Statement::Pass | Statement::Break(_) | Statement::Continue(_) => {},
+    }
+}
+
+
+fn check_reference(reference: &Reference, context: &mut Context) {
+    if let Reference::ArrayReference { array, index } = reference {
+        check_reference(array, context);
+        check_array_index(index, context);
+    }
+}
+
+
+fn check_expression(expression: &Expression, context: &mut Context) {
+    match expression {
+        Expression::TernaryCondition { condition, true_value, false_value } => {
+            check_expression(condition, context);
+            check_expression(true_value, context);
+            check_expression(false_value, context);
+        },
+
+        Expression::BinaryOperation { left, right, .. } => {
+            check_expression(left, context);
+            check_expression(right, context);
+        },
+
+        Expression::UnaryOperation { operand, .. } => check_expression(operand, context),
+        Expression::Cast(cast) => check_expression(&cast.value, context),
+        Expression::Assignment(assignment) => check_expression(&assignment.value, context),
+        Expression::Spread(spread) => check_expression(&spread.value, context),
+
+        Expression::Tuple(values) => {
+            for value in values {
+                check_expression(value, context);
+            }
+        },
+
+        Expression::Atomic(atom) => check_atomic(atom, context),
+    }
+}
+
+
+fn check_atomic(atom: &AtomicExpression, context: &mut Context) {
+    match atom {
+        AtomicExpression::Literal(_) | AtomicExpression::Identifier(_) => {},
+
+        AtomicExpression::FunctionCall(call) => {
+            check_arguments(call, context);
+            for argument in call.arguments() {
+                check_expression(argument, context);
+            }
+        },
+
+        AtomicExpression::Parenthesized(parenthesized) => check_expression(&parenthesized.value, context),
+
+        AtomicExpression::ArrayLiteral(array) => {
+            for value in &array.values {
+                check_expression(value, context);
+            }
+        },
+
+        AtomicExpression::MapLiteral(map) => {
+            for (key, value) in &map.entries {
+                check_expression(key, context);
+                check_expression(value, context);
+            }
+        },
+
+        AtomicExpression::Comprehension(comprehension) => {
+            check_expression(&comprehension.iterable, context);
+            if let Some(condition) = &comprehension.condition {
+                check_expression(condition, context);
+            }
+            check_expression(&comprehension.output, context);
+        },
+
+        AtomicExpression::StructLiteral(literal) => {
+            for (_, value) in &literal.fields {
+                check_expression(value, context);
+            }
+        },
+
+        AtomicExpression::ArrayIndex(array_index) => {
+            check_atomic(&array_index.array, context);
+            check_array_index(&array_index.index, context);
+        },
+    }
+}
+
+
+fn check_array_index(index: &ArrayIndex, context: &mut Context) {
+    match index {
+        ArrayIndex::Single(index) => check_expression(index, context),
+        ArrayIndex::Slice { start, end } => {
+            if let Some(start) = start {
+                check_expression(start, context);
+            }
+            if let Some(end) = end {
+                check_expression(end, context);
+            }
+        },
+    }
+}
+
+
+fn check_arguments(call: &FunctionCallExpression, context: &mut Context) {
+    let function = match context.functions.get(&call.name.as_string()) {
+        Some(function) => *function,
+        None => return,
+    };
+    if call.parameters.iter().any(|argument| matches!(argument, Expression::Spread(_))) {
+        return;
+    }
+
+    let keywords: Vec<&Identifier> = call.keyword_arguments.iter().map(|(name, _)| name).collect();
+    if let Err(message) = function.check_arguments(call.parameters.len(), &keywords) {
+        context.diagnostics.push(Diagnostic::error(&message));
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    use super::*;
+
+    fn check_source(source: &str) -> Vec<Diagnostic> {
+        check_calls(&parse(&tokenize(source)).unwrap())
+    }
+
+    #[test]
+    fn test_keyword_only_parameter_passed_positionally() {
+        let source = "fun scale(x: int, *, factor: int) {\n return x\n }\n\n scale(1, 2)\n";
+        assert_eq!(
+            check_source(source),
+            vec![Diagnostic::error("Keyword-only parameter 'factor' of function 'scale' can't be passed positionally")]
+        );
+    }
+
+    #[test]
+    fn test_keyword_arguments() {
+        let source = "fun scale(x: int, *, factor: int) {\n return x * factor\n }\n\n scale(1, factor = 2)\n scale(factor = 2, x = 1)\n";
+        assert_eq!(check_source(source), vec![]);
+
+        let source = "fun scale(x: int, *, factor: int) {\n return x * factor\n }\n\n scale(1, size = 2)\n scale(1, x = 2)\n scale(1)\n";
+        assert_eq!(check_source(source), vec![
+            Diagnostic::error("Function 'scale' has no parameter 'size'"),
+            Diagnostic::error("Parameter 'x' of function 'scale' is given more than once"),
+            Diagnostic::error("Function 'scale' expects 2 arguments, found 1"),
+        ]);
+    }
+
+    #[test]
+    fn test_wrong_number_of_arguments() {
+        let source = "fun add(x: int, y: int) {\n return x + y\n }\n\n add(add(1, 2))\n";
+        assert_eq!(
+            check_source(source),
+            vec![Diagnostic::error("Function 'add' expects 2 arguments, found 1")]
+        );
+    }
+}
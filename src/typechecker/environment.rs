@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use crate::elements::Identifier;
+use crate::typechecker::Type;
+
+
+// Known types of the functions in a module and of the variables in the function being checked.
+// Names whose type can't be determined are simply left out.
+pub struct TypeEnvironment {
+    functions: HashMap<String, Type>,
+    variables: HashMap<String, Type>,
+}
+
+
+impl TypeEnvironment {
+    pub fn new() -> TypeEnvironment {
+        TypeEnvironment { functions: HashMap::new(), variables: HashMap::new() }
+    }
+
+    pub fn function_type(&self, name: &Identifier) -> Option<&Type> {
+        self.functions.get(&name.as_string())
+    }
+
+    pub fn set_function_type(&mut self, name: &Identifier, function_type: Type) {
+        self.functions.insert(name.as_string(), function_type);
+    }
+
+    pub fn variable_type(&self, name: &Identifier) -> Option<&Type> {
+        self.variables.get(&name.as_string())
+    }
+
+    pub fn set_variable_type(&mut self, name: &Identifier, variable_type: Type) {
+        self.variables.insert(name.as_string(), variable_type);
+    }
+
+    pub fn clear_variables(&mut self) {
+        self.variables.clear();
+    }
+}
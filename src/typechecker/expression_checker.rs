@@ -0,0 +1,161 @@
+use crate::elements::Operator;
+use crate::tree::{Expression, AtomicExpression};
+use crate::typechecker::Type;
+use crate::typechecker::environment::TypeEnvironment;
+
+
+// Returns the static type of an expression, or None if it can't be determined
+pub fn infer_expression_type(expression: &Expression, environment: &TypeEnvironment) -> Option<Type> {
+    match expression {
+        Expression::TernaryCondition { true_value, false_value, .. } => {
+            let true_type = infer_expression_type(true_value, environment)?;
+            let false_type = infer_expression_type(false_value, environment)?;
+            if true_type == false_type { Some(true_type) } else { None }
+        },
+
+        Expression::BinaryOperation { left, operator, right } => {
+            let left_type = infer_expression_type(left, environment);
+            let right_type = infer_expression_type(right, environment);
+            infer_binary_operation_type(operator, left_type?, right_type?)
+        },
+
+        Expression::UnaryOperation { operator, operand } => match operator {
+            Operator::Not => Some(Type::Boolean),
+            Operator::BitwiseNot => Some(Type::Integer),
+            _ => infer_expression_type(operand, environment),
+        },
+
+        Expression::Cast(cast) => Type::from_identifier(&cast.target_type),
+        Expression::Assignment(assignment) => infer_expression_type(&assignment.value, environment),
+
+        Expression::Tuple(_) | Expression::Spread(_) => None,
+
+        Expression::Atomic(atom) => infer_atomic_type(atom, environment),
+    }
+}
+
+
+// The type of the elements of an array literal whose types are known, where ints and floats together
+// are floats. Elements of other types that disagree give the first two types that differ instead.
+pub fn infer_element_type(values: &[Expression], environment: &TypeEnvironment) -> Result<Option<Type>, (Type, Type)> {
+    let mut element_type = None;
+    for value in values {
+        let value_type = match infer_expression_type(value, environment) {
+            Some(value_type) => value_type,
+            None => continue,
+        };
+        element_type = Some(match element_type {
+            None => value_type,
+            Some(element_type) if element_type == value_type => element_type,
+            Some(Type::Integer | Type::Float) if matches!(value_type, Type::Integer | Type::Float) => Type::Float,
+            Some(element_type) => return Err((element_type, value_type)),
+        });
+    }
+    Ok(element_type)
+}
+
+
+fn infer_binary_operation_type(operator: &Operator, left: Type, right: Type) -> Option<Type> {
+    match operator {
+        Operator::And | Operator::Or |
+        Operator::Equal | Operator::NotEqual |
+        Operator::LessThan | Operator::GreaterThan |
+        Operator::LessThanOrEqual | Operator::GreaterThanOrEqual |
+        Operator::In | Operator::Is | Operator::IsNot => Some(Type::Boolean),
+
+        Operator::BitwiseAnd | Operator::BitwiseOr | Operator::BitwiseXor |
+        Operator::BitwiseLeftShift | Operator::BitwiseRightShift => Some(Type::Integer),
+
+        // A range is an array, which has no type yet
+        Operator::Range | Operator::InclusiveRange => None,
+
+        // A power of two ints is an int unless the exponent is negative, which isn't known until it runs
+        Operator::Power => match (left, right) {
+            (Type::Integer, Type::Integer) => None,
+            (Type::Integer | Type::Float, Type::Integer | Type::Float) => Some(Type::Float),
+            _ => None,
+        },
+
+        _ => match (left, right) {
+            (Type::Char, Type::Integer) if matches!(operator, Operator::Plus | Operator::Minus) => Some(Type::Char),
+            (Type::Integer, Type::Char) if *operator == Operator::Plus => Some(Type::Char),
+            (Type::Integer, Type::Integer) => Some(Type::Integer),
+            (Type::Integer | Type::Float, Type::Integer | Type::Float) => Some(Type::Float),
+            (Type::String, Type::String) if *operator == Operator::Plus => Some(Type::String),
+            _ => None,
+        },
+    }
+}
+
+
+fn infer_atomic_type(atom: &AtomicExpression, environment: &TypeEnvironment) -> Option<Type> {
+    match atom {
+        AtomicExpression::Literal(literal) => Some(Type::from_literal(literal)),
+        AtomicExpression::Identifier(identifier) => environment.variable_type(identifier).cloned(),
+        AtomicExpression::FunctionCall(call) => environment.function_type(&call.name).cloned(),
+        AtomicExpression::Parenthesized(parenthesized) => infer_expression_type(&parenthesized.value, environment),
+        AtomicExpression::ArrayLiteral(_)
+            | AtomicExpression::MapLiteral(_)
+            | AtomicExpression::ArrayIndex(_)
+            | AtomicExpression::Comprehension(_)
+            | AtomicExpression::StructLiteral(_) => None,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::elements::{Identifier, Literal};
+    use crate::tree::CastExpression;
+
+    use super::*;
+
+    fn literal(literal: Literal) -> Expression {
+        Expression::Atomic(AtomicExpression::Literal(literal))
+    }
+
+    #[test]
+    fn test_infer_mixed_arithmetic() {
+        let expression = Expression::BinaryOperation {
+            left: Box::new(literal(Literal::Integer(1)).into()),
+            operator: Operator::Times,
+            right: Box::new(literal(Literal::Float(2.0)).into()),
+        };
+
+        assert_eq!(infer_expression_type(&expression, &TypeEnvironment::new()), Some(Type::Float));
+    }
+
+    #[test]
+    fn test_infer_element_type() {
+        let environment = TypeEnvironment::new();
+
+        let values = [literal(Literal::Integer(1)), literal(Literal::Float(2.0))];
+        assert_eq!(infer_element_type(&values, &environment), Ok(Some(Type::Float)));
+
+        let values = [literal(Literal::Integer(1)), literal(Literal::String("two".to_string())), literal(Literal::Float(3.0))];
+        assert_eq!(infer_element_type(&values, &environment), Err((Type::Integer, Type::String)));
+
+        assert_eq!(infer_element_type(&[], &environment), Ok(None));
+    }
+
+    #[test]
+    fn test_infer_variables_and_casts() {
+        let mut environment = TypeEnvironment::new();
+        environment.set_variable_type(&Identifier::Simple("x".to_string()), Type::Char);
+
+        let variable = Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("x".to_string())));
+        assert_eq!(infer_expression_type(&variable, &environment), Some(Type::Char));
+
+        let cast = Expression::Cast(CastExpression {
+            value: Box::new(variable.into()),
+            target_type: Identifier::Simple("int".to_string()),
+        });
+        assert_eq!(infer_expression_type(&cast, &environment), Some(Type::Integer));
+    }
+
+    #[test]
+    fn test_unknown_identifier_has_no_type() {
+        let variable = Expression::Atomic(AtomicExpression::Identifier(Identifier::Simple("y".to_string())));
+        assert_eq!(infer_expression_type(&variable, &TypeEnvironment::new()), None);
+    }
+}
@@ -0,0 +1,433 @@
+use crate::diagnostics::Diagnostic;
+use crate::elements::{Literal, Operator};
+use crate::span::Spanned;
+use crate::tree::{Module, Function, StatementBlock, Statement, Expression, AtomicExpression};
+use crate::resolver::nested_blocks;
+use crate::typechecker::Type;
+use crate::typechecker::environment::TypeEnvironment;
+use crate::typechecker::expression_checker::{infer_expression_type, infer_element_type};
+
+
+pub fn check_module(module: &Module) -> Vec<Diagnostic> {
+    let mut environment = TypeEnvironment::new();
+    let mut diagnostics = vec![];
+
+    for function in &module.functions {
+        if let Some(return_type) = function.return_type.as_ref().and_then(Type::from_identifier) {
+            environment.set_function_type(&function.name, return_type);
+        }
+    }
+
+    // Functions are checked in order, so calls to an unannotated function only have a known
+    // type once that function's return type has been inferred.
+    for function in &module.functions {
+        match check_function(function, &mut environment) {
+            Ok(Some(return_type)) => environment.set_function_type(&function.name, return_type),
+            Ok(None) => {},
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        }
+        // The variable types recorded while checking the function are still in the environment
+        check_expression_types(&function.body, &environment, &mut diagnostics);
+    }
+
+    environment.clear_variables();
+    check_expression_types(&module.statements, &environment, &mut diagnostics);
+
+    diagnostics
+}
+
+
+// Both branches of a conditional expression, written as a ternary or an if expression, must have
+// the same type when both types are known. The elements of an array literal should too, but arrays
+// may hold mixed values, so that's only a warning. Dividing by a literal zero is an error where
+// it's sure to fail. All are reported at the enclosing statement.
+fn check_expression_types(block: &StatementBlock, environment: &TypeEnvironment, diagnostics: &mut Vec<Diagnostic>) {
+    for statement in &block.statements {
+        let expressions: Vec<&Expression> = match &statement.node {
+            Statement::Declaration(declaration) => vec![&declaration.value],
+            Statement::Assignment(assignment) => vec![&assignment.value],
+            Statement::Expression(expression) | Statement::Raise(expression) => vec![expression],
+            Statement::Return(return_statement) => return_statement.value.iter().collect(),
+            Statement::Conditional(conditional) => vec![&conditional.condition],
+            Statement::Loop(loop_statement) => vec![&loop_statement.condition],
+            Statement::For(for_statement) => vec![&for_statement.iterable],
+            Statement::Repeat(repeat_statement) => vec![&repeat_statement.count],
+            Statement::Block(_) | Statement::Try(_) | Statement::Delete(_) | Statement::Global(_)
+            | Statement::Pass | Statement::Break(_) | Statement::Continue(_) => vec![],
+        };
+
+        for expression in expressions {
+            if let Some(message) = find_branch_mismatch(expression, environment) {
+                diagnostics.push(Diagnostic::error(&message).with_span(statement.span));
+            }
+            if let Some(message) = find_mixed_array(expression, environment) {
+                diagnostics.push(Diagnostic::warning(&message).with_span(statement.span));
+            }
+            if let Some(message) = find_division_by_zero(expression, environment) {
+                diagnostics.push(Diagnostic::error(message).with_span(statement.span));
+            }
+        }
+        for nested in nested_blocks(statement) {
+            check_expression_types(nested, environment, diagnostics);
+        }
+    }
+}
+
+
+fn find_branch_mismatch(expression: &Expression, environment: &TypeEnvironment) -> Option<String> {
+    let find = |expression: &Expression| find_branch_mismatch(expression, environment);
+
+    match expression {
+        Expression::TernaryCondition { condition, true_value, false_value } => {
+            let true_type = infer_expression_type(true_value, environment);
+            let false_type = infer_expression_type(false_value, environment);
+            if let (Some(true_type), Some(false_type)) = (true_type, false_type) {
+                if true_type != false_type {
+                    return Some(format!(
+                        "Branches of the conditional expression have different types {} and {}", true_type, false_type
+                    ));
+                }
+            }
+            find(condition).or_else(|| find(true_value)).or_else(|| find(false_value))
+        },
+
+        Expression::BinaryOperation { left, right, .. } => find(left).or_else(|| find(right)),
+        Expression::UnaryOperation { operand, .. } => find(operand),
+        Expression::Cast(cast) => find(&cast.value),
+        Expression::Assignment(assignment) => find(&assignment.value),
+        Expression::Spread(spread) => find(&spread.value),
+        Expression::Tuple(values) => values.iter().find_map(find),
+
+        Expression::Atomic(AtomicExpression::Parenthesized(parenthesized)) => find(&parenthesized.value),
+        Expression::Atomic(AtomicExpression::FunctionCall(call)) => call.arguments().find_map(find),
+        Expression::Atomic(AtomicExpression::ArrayLiteral(array)) => array.values.iter().find_map(find),
+
+        _ => None,
+    }
+}
+
+
+fn find_mixed_array(expression: &Expression, environment: &TypeEnvironment) -> Option<String> {
+    let find = |expression: &Expression| find_mixed_array(expression, environment);
+
+    match expression {
+        Expression::TernaryCondition { condition, true_value, false_value } => {
+            find(condition).or_else(|| find(true_value)).or_else(|| find(false_value))
+        },
+
+        Expression::BinaryOperation { left, right, .. } => find(left).or_else(|| find(right)),
+        Expression::UnaryOperation { operand, .. } => find(operand),
+        Expression::Cast(cast) => find(&cast.value),
+        Expression::Assignment(assignment) => find(&assignment.value),
+        Expression::Spread(spread) => find(&spread.value),
+        Expression::Tuple(values) => values.iter().find_map(find),
+
+        Expression::Atomic(AtomicExpression::Parenthesized(parenthesized)) => find(&parenthesized.value),
+        Expression::Atomic(AtomicExpression::FunctionCall(call)) => call.arguments().find_map(find),
+        Expression::Atomic(AtomicExpression::ArrayLiteral(array)) => match infer_element_type(&array.values, environment) {
+            Err((first, other)) => Some(format!("Array elements have different types {} and {}", first, other)),
+            Ok(_) => array.values.iter().find_map(find),
+        },
+
+        _ => None,
+    }
+}
+
+
+// A zero divisor fails at runtime, except in a division of a float, which gives infinity. So a
+// division is only reported when its dividend is known to be an int, while a modulo always is.
+fn find_division_by_zero(expression: &Expression, environment: &TypeEnvironment) -> Option<&'static str> {
+    let find = |expression: &Expression| find_division_by_zero(expression, environment);
+
+    match expression {
+        Expression::TernaryCondition { condition, true_value, false_value } => {
+            find(condition).or_else(|| find(true_value)).or_else(|| find(false_value))
+        },
+
+        Expression::BinaryOperation { left, operator, right } => {
+            let is_integer_division = || infer_expression_type(left, environment) == Some(Type::Integer);
+            let message = match (operator, right.as_literal()) {
+                (Operator::Divide, Some(Literal::Integer(0))) if is_integer_division() => Some("Division by zero"),
+                (Operator::Modulo, Some(Literal::Integer(0))) => Some("Modulo by zero"),
+                (Operator::Modulo, Some(Literal::Float(divisor))) if *divisor == 0.0 => Some("Modulo by zero"),
+                _ => None,
+            };
+            message.or_else(|| find(left)).or_else(|| find(right))
+        },
+        Expression::UnaryOperation { operand, .. } => find(operand),
+        Expression::Cast(cast) => find(&cast.value),
+        Expression::Assignment(assignment) => find(&assignment.value),
+        Expression::Spread(spread) => find(&spread.value),
+        Expression::Tuple(values) => values.iter().find_map(find),
+
+        Expression::Atomic(AtomicExpression::Parenthesized(parenthesized)) => find(&parenthesized.value),
+        Expression::Atomic(AtomicExpression::FunctionCall(call)) => call.arguments().find_map(find),
+        Expression::Atomic(AtomicExpression::ArrayLiteral(array)) => array.values.iter().find_map(find),
+
+        _ => None,
+    }
+}
+
+
+// Checks the returns of a function against its annotated return type, or infers the return
+// type from the returns if there is no annotation. A return that doesn't match the annotation is
+// reported at the return.
+pub fn check_function(function: &Function, environment: &mut TypeEnvironment) -> Result<Option<Type>, Diagnostic> {
+    environment.clear_variables();
+    for parameter in &function.parameters {
+        if let Some(parameter_type) = Type::from_identifier(&parameter.param_type) {
+            environment.set_variable_type(&parameter.name, parameter_type);
+        }
+    }
+
+    let mut returns = vec![];
+    let has_returns = collect_return_types(&function.body, environment, &mut returns);
+
+    // The trailing expression of a function body is its implicit return value
+    if let Some(statement) = function.body.statements.last() {
+        if let Statement::Expression(expression) = &statement.node {
+            if let Some(return_type) = infer_expression_type(expression, environment) {
+                returns.push(Spanned::new(Some(return_type), statement.span));
+            }
+        }
+    }
+
+    let name = function.name.as_string();
+
+    if let Some(annotation) = &function.return_type {
+        let expected = Type::from_identifier(annotation);
+        for return_type in &returns {
+            let message = match &return_type.node {
+                None if expected != Some(Type::None) => format!(
+                    "Function '{}' should return {} but has a bare return", name, annotation.as_string()
+                ),
+                Some(actual) if expected.as_ref().is_some_and(|expected| expected != actual) => format!(
+                    "Function '{}' should return {} but returns {}", name, annotation.as_string(), actual
+                ),
+                _ => continue,
+            };
+            return Err(Diagnostic::error(&message).with_span(return_type.span));
+        }
+        return Ok(expected);
+    }
+
+    // Without an annotation, a bare return returns None like any other
+    let return_types: Vec<Type> = returns.into_iter()
+        .map(|return_type| return_type.node.unwrap_or(Type::None))
+        .collect();
+
+    match return_types.split_first() {
+        Some((first, rest)) => match rest.iter().find(|return_type| *return_type != first) {
+            Some(other) => Err(Diagnostic::error(&format!(
+                "Function '{}' has conflicting return types {} and {}", name, first, other
+            ))),
+            None => Ok(Some(first.clone())),
+        },
+        None if has_returns => Ok(None),
+        None => Ok(Some(Type::None)),
+    }
+}
+
+
+// Collects the known types of the values returned anywhere in a block, with the span of each
+// return, recording the types of declared variables along the way. A bare return has no type.
+// Returns whether the block contains any return statement.
+fn collect_return_types(block: &StatementBlock, environment: &mut TypeEnvironment, return_types: &mut Vec<Spanned<Option<Type>>>) -> bool {
+    let mut has_returns = false;
+
+    for statement in &block.statements {
+        match &statement.node {
+            Statement::Declaration(declaration) => {
+                if let Some(var_type) = Type::from_identifier(&declaration.var_type) {
+                    environment.set_variable_type(&declaration.name, var_type);
+                }
+            },
+
+            Statement::Return(return_statement) => {
+                has_returns = true;
+                match &return_statement.value {
+                    Some(value) => if let Some(return_type) = infer_expression_type(value, environment) {
+                        return_types.push(Spanned::new(Some(return_type), statement.span));
+                    },
+                    None => return_types.push(Spanned::new(None, statement.span)),
+                }
+            },
+
+            Statement::Block(block) => {
+                has_returns |= collect_return_types(block, environment, return_types);
+            },
+
+            Statement::Conditional(conditional) => {
+                has_returns |= collect_return_types(&conditional.body, environment, return_types);
+                if let Some(else_body) = &conditional.else_body {
+                    has_returns |= collect_return_types(else_body, environment, return_types);
+                }
+            },
+
+            Statement::Loop(loop_statement) => {
+                has_returns |= collect_return_types(&loop_statement.body, environment, return_types);
+                if let Some(else_body) = &loop_statement.else_body {
+                    has_returns |= collect_return_types(else_body, environment, return_types);
+                }
+            },
+
+            Statement::For(for_statement) => {
+                has_returns |= collect_return_types(&for_statement.body, environment, return_types);
+                if let Some(else_body) = &for_statement.else_body {
+                    has_returns |= collect_return_types(else_body, environment, return_types);
+                }
+            },
+
+            Statement::Repeat(repeat_statement) => {
+                has_returns |= collect_return_types(&repeat_statement.body, environment, return_types);
+            },
+
+            Statement::Try(try_statement) => {
+                has_returns |= collect_return_types(&try_statement.body, environment, return_types);
+                if let Some(handler_body) = &try_statement.handler_body {
+                    has_returns |= collect_return_types(handler_body, environment, return_types);
+                }
+            },
+
+            Statement::Assignment(_) | Statement::Expression(_) | Statement::Delete(_) | Statement::Global(_) | Statement::Pass
+            | Statement::Raise(_) | Statement::Break(_) | Statement::Continue(_) => {},
+        }
+    }
+
+    has_returns
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::lexer::{tokenize, tokenize_with_spans, LexOptions};
+    use crate::parser::{parse, parse_with_spans};
+    use crate::span::Span;
+
+    use super::*;
+
+    fn check_source(source: &str) -> Vec<Diagnostic> {
+        let (tokens, spans) = tokenize_with_spans(source, &LexOptions::default());
+        check_module(&parse_with_spans(&tokens, &spans).unwrap())
+    }
+
+    // The span of the first occurrence of the text in the source
+    fn span_of(source: &str, text: &str) -> Span {
+        let start = source.find(text).unwrap();
+        Span::new(start, start + text.len())
+    }
+
+    fn infer_source(source: &str) -> Result<Option<Type>, Diagnostic> {
+        let module = parse(&tokenize(source)).unwrap();
+        check_function(&module.functions[0], &mut TypeEnvironment::new())
+    }
+
+    #[test]
+    fn test_infer_consistent_returns() {
+        let source = "fun sign(x: int) {\n if x < 0 {\n return -1\n }\n return 1\n }\n";
+        assert_eq!(infer_source(source), Ok(Some(Type::Integer)));
+    }
+
+    #[test]
+    fn test_infer_trailing_implicit_return() {
+        let source = "fun half(x: float) {\n if x < 0.0 {\n return 0.0\n }\n x / 2\n }\n";
+        assert_eq!(infer_source(source), Ok(Some(Type::Float)));
+    }
+
+    #[test]
+    fn test_infer_function_without_returns() {
+        let source = "fun nothing() {\n }\n";
+        assert_eq!(infer_source(source), Ok(Some(Type::None)));
+    }
+
+    #[test]
+    fn test_conflicting_returns_are_an_error() {
+        let source = "fun mixed(x: bool) {\n if x {\n return 1\n }\n return \"x\"\n }\n";
+        assert_eq!(
+            check_source(source),
+            vec![Diagnostic::error("Function 'mixed' has conflicting return types int and string")]
+        );
+    }
+
+    #[test]
+    fn test_inferred_type_is_used_by_callers() {
+        let source = "fun one() {\n return 1\n }\n\n fun two(): string {\n return one() + one()\n }\n";
+        assert_eq!(
+            check_source(source),
+            vec![Diagnostic::error("Function 'two' should return string but returns int").with_span(span_of(source, "return one() + one()"))]
+        );
+    }
+
+    #[test]
+    fn test_if_expression_branches_must_have_the_same_type() {
+        let source = "fun label(x: int) {\n name: string = if x > 0 { \"positive\" } else { x }\n print(name)\n }\n";
+        assert_eq!(
+            check_source(source),
+            vec![Diagnostic::error("Branches of the conditional expression have different types string and int")
+                .with_span(span_of(source, "name: string = if x > 0 { \"positive\" } else { x }"))]
+        );
+
+        let source = "fun label(x: int) {\n name: string = if x > 0 { \"positive\" } else { \"other\" }\n print(name)\n }\n";
+        assert_eq!(check_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_mixed_array_literal_is_a_warning() {
+        let source = "fun main() {\n values: array = [1, \"two\", 3.0]\n print(values)\n }\n";
+        assert_eq!(
+            check_source(source),
+            vec![Diagnostic::warning("Array elements have different types int and string")
+                .with_span(span_of(source, "values: array = [1, \"two\", 3.0]"))]
+        );
+    }
+
+    #[test]
+    fn test_array_literal_of_one_type_has_no_warning() {
+        let source = "fun main(x: int) {\n values: array = [1, x, 3]\n print(values, [], [1, 2.5])\n }\n";
+        assert_eq!(check_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_division_of_an_int_by_a_literal_zero_is_an_error() {
+        let source = "fun main(x: int) {\n print(x / 0)\n y: float = x % 0.0\n return (x + 1) % 0\n }\n";
+        assert_eq!(check_source(source), vec![
+            Diagnostic::error("Division by zero").with_span(span_of(source, "print(x / 0)")),
+            Diagnostic::error("Modulo by zero").with_span(span_of(source, "y: float = x % 0.0")),
+            Diagnostic::error("Modulo by zero").with_span(span_of(source, "return (x + 1) % 0")),
+        ]);
+    }
+
+    #[test]
+    fn test_division_that_may_not_fail_is_not_an_error() {
+        let source = "fun main(x: int, y: float, values: array) {\n print(x / y, x % y, x / 0.0)\n print(y / 0, values[0] / 0)\n }\n";
+        assert_eq!(check_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_matching_returns_have_no_errors() {
+        let source = "fun name(x: int): string {\n if x > 0 {\n return \"positive\"\n }\n \"other\"\n }\n";
+        assert_eq!(check_source(source), vec![]);
+    }
+
+    #[test]
+    fn test_mismatched_return_is_reported_at_the_return() {
+        let source = "fun name(x: int): string {\n if x > 0 {\n return x\n }\n return \"other\"\n }\n";
+        assert_eq!(
+            check_source(source),
+            vec![Diagnostic::error("Function 'name' should return string but returns int").with_span(span_of(source, "return x"))]
+        );
+    }
+
+    #[test]
+    fn test_bare_return_in_typed_function_is_an_error() {
+        let source = "fun count(x: int): int {\n if x < 0 {\n return\n }\n return x\n }\n";
+        let bare_return = source.find("return\n").unwrap();
+        assert_eq!(
+            check_source(source),
+            vec![Diagnostic::error("Function 'count' should return int but has a bare return").with_span(Span::new(bare_return, bare_return + 6))]
+        );
+
+        let source = "fun stop(x: int) {\n if x < 0 {\n return\n }\n }\n";
+        assert_eq!(check_source(source), vec![]);
+    }
+}
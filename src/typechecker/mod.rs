@@ -0,0 +1,17 @@
+mod types;
+mod environment;
+mod expression_checker;
+mod function_checker;
+mod call_checker;
+
+pub use types::Type;
+
+use crate::diagnostics::Diagnostic;
+use crate::tree::Module;
+
+
+pub fn check(module: &Module) -> Vec<Diagnostic> {
+    let mut diagnostics = function_checker::check_module(module);
+    diagnostics.extend(call_checker::check_calls(module));
+    diagnostics
+}
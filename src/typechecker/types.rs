@@ -0,0 +1,60 @@
+use std::fmt;
+
+use crate::elements::{Identifier, Literal};
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Integer,
+    Float,
+    Boolean,
+    Char,
+    String,
+    None,
+}
+
+
+impl Type {
+    pub fn from_identifier(identifier: &Identifier) -> Option<Type> {
+        match identifier.as_string().as_str() {
+            "int" => Some(Type::Integer),
+            "float" => Some(Type::Float),
+            "bool" => Some(Type::Boolean),
+            "char" => Some(Type::Char),
+            "string" => Some(Type::String),
+            "None" => Some(Type::None),
+            _ => None,
+        }
+    }
+
+    pub fn from_literal(literal: &Literal) -> Type {
+        match literal {
+            Literal::Integer(_) => Type::Integer,
+            Literal::Float(_) => Type::Float,
+            Literal::Boolean(_) => Type::Boolean,
+            Literal::Char(_) => Type::Char,
+            Literal::String(_) => Type::String,
+            Literal::None => Type::None,
+            Literal::TypedInteger(_, _) => Type::Integer,
+            Literal::TypedFloat(_, _) => Type::Float,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Type::Integer => "int",
+            Type::Float => "float",
+            Type::Boolean => "bool",
+            Type::Char => "char",
+            Type::String => "string",
+            Type::None => "None",
+        }
+    }
+}
+
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,51 @@
+use std::process::{Command, Output};
+
+
+fn run_check(file_path: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_sheeppig"))
+        .args(["check", file_path])
+        .output()
+        .expect("Failed to run sheeppig")
+}
+
+
+#[test]
+fn test_check_valid_file() {
+    let output = run_check("./samples/test_samples/adding.sp");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+}
+
+#[test]
+fn test_check_file_with_type_error() {
+    let output = run_check("./samples/test_samples/type_error.sp");
+
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "./samples/test_samples/type_error.sp:6:5: Error: Function 'two' should return string but returns int\n"
+    );
+}
+
+#[test]
+fn test_check_file_with_lex_error() {
+    let output = run_check("./samples/test_samples/lex_error.sp");
+
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "./samples/test_samples/lex_error.sp:3:6: Error: '++' is not supported, use '+= 1' instead\n"
+    );
+}
+
+#[test]
+fn test_check_file_with_malformed_number() {
+    let output = run_check("./samples/test_samples/malformed_number.sp");
+
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "./samples/test_samples/malformed_number.sp:2:16: Error: Malformed number literal 1e\n"
+    );
+}
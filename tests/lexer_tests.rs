@@ -3,7 +3,7 @@ mod test_utils;
 use test_utils::read_file;
 
 use sheeppig::elements::{Identifier, Literal, Operator, Keyword};
-use sheeppig::tokens::Token;
+use sheeppig::tokens::{Token, format_tokens};
 use sheeppig::lexer::tokenize;
 
 
@@ -33,6 +33,15 @@ fn test_tokenise_hello_world() {
     assert_eq!(tokens, expected);
 }
 
+#[test]
+fn test_format_hello_world_tokens() {
+    let source_code = read_file("./samples/test_samples/hello_world.sp");
+
+    let tokens = tokenize(&source_code);
+
+    assert_eq!(format_tokens(&tokens), "fun main ( ) { print ( \"Hello, world!\" ) ⏎ } <end>");
+}
+
 #[test]
 fn test_tokenise_adding() {
     let source_code = read_file("./samples/test_samples/adding.sp");
@@ -373,4 +382,95 @@ fn test_tokenise_comments() {
     ];
 
     assert_eq!(tokens, expected);
-}
\ No newline at end of file
+}
+#[test]
+fn test_tokenise_spread() {
+    let tokens = tokenize("f(*args, 2 * [*values])");
+
+    let expected = vec![
+        Token::Identifier(Identifier::Simple("f".to_string())),
+        Token::OpenParen,
+        Token::Spread,
+        Token::Identifier(Identifier::Simple("args".to_string())),
+        Token::ListSeparator,
+        Token::Literal(Literal::Integer(2)),
+        Token::Operator(Operator::Times),
+        Token::OpenSquareBracket,
+        Token::Spread,
+        Token::Identifier(Identifier::Simple("values".to_string())),
+        Token::CloseSquareBracket,
+        Token::CloseParen,
+        Token::EndOfModule,
+    ];
+
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn test_tokenise_pipe() {
+    let tokens = tokenize("a | b || c |> f");
+
+    let expected = vec![
+        Token::Identifier(Identifier::Simple("a".to_string())),
+        Token::Operator(Operator::BitwiseOr),
+        Token::Identifier(Identifier::Simple("b".to_string())),
+        Token::Operator(Operator::Or),
+        Token::Identifier(Identifier::Simple("c".to_string())),
+        Token::Pipe,
+        Token::Identifier(Identifier::Simple("f".to_string())),
+        Token::EndOfModule,
+    ];
+
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn test_tokenise_semicolons() {
+    let tokens = tokenize("a; b");
+
+    let expected = vec![
+        Token::Identifier(Identifier::Simple("a".to_string())),
+        Token::Semicolon,
+        Token::Identifier(Identifier::Simple("b".to_string())),
+        Token::EndOfModule,
+    ];
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn test_tokenise_dots() {
+    let integer = |value: i64| Token::Literal(Literal::Integer(value));
+
+    assert_eq!(tokenize("1..2"), vec![integer(1), Token::Range, integer(2), Token::EndOfModule]);
+    assert_eq!(tokenize("1..=2"), vec![integer(1), Token::InclusiveRange, integer(2), Token::EndOfModule]);
+    assert_eq!(tokenize(".5"), vec![Token::Literal(Literal::Float(0.5)), Token::EndOfModule]);
+    assert_eq!(tokenize("1.5..2"), vec![Token::Literal(Literal::Float(1.5)), Token::Range, integer(2), Token::EndOfModule]);
+
+    // A dot between identifiers joins them into one compound identifier
+    assert_eq!(
+        tokenize("a.b"),
+        vec![Token::Identifier(Identifier::Compound(vec!["a".to_string(), "b".to_string()])), Token::EndOfModule]
+    );
+    assert_eq!(
+        tokenize("a..b"),
+        vec![
+            Token::Identifier(Identifier::Simple("a".to_string())),
+            Token::Range,
+            Token::Identifier(Identifier::Simple("b".to_string())),
+            Token::EndOfModule,
+        ]
+    );
+}
+
+#[test]
+fn test_tokenise_inline_assignment() {
+    let x = || Token::Identifier(Identifier::Simple("x".to_string()));
+    let one = || Token::Literal(Literal::Integer(1));
+
+    assert_eq!(tokenize("x := 1"), vec![x(), Token::InlineAssign, one(), Token::EndOfModule]);
+    assert_eq!(tokenize("x : = 1"), vec![x(), Token::Colon, Token::Assign, one(), Token::EndOfModule]);
+    assert_eq!(
+        tokenize("x: int = 1"),
+        vec![x(), Token::Colon, Token::Identifier(Identifier::Simple("int".to_string())), Token::Assign, one(), Token::EndOfModule]
+    );
+}
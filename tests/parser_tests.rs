@@ -1,56 +1,29 @@
 mod test_utils;
 
-use test_utils::read_file;
+use test_utils::{read_file, assert_parses_to, assert_parses_with_options_to};
 
 use sheeppig::elements::{Identifier, Literal, Operator, Keyword};
-use sheeppig::tree::{Statement, Expression, Module, Function, StatementBlock, FunctionCallExpression, AtomicExpression, DeclarationStatement};
-use sheeppig::lexer::tokenize;
-use sheeppig::parser::parse;
+use sheeppig::tokens::Token;
+use sheeppig::tree::{Statement, Expression, Module, Function, StatementBlock, AtomicExpression, DeclarationStatement};
+use sheeppig::lexer::{tokenize, tokenize_with_options, tokenize_with_spans, try_tokenize_with_spans, position, LexOptions};
+use sheeppig::parser::{
+    parse, parse_with_spans, parse_with_precedence, parse_with_options, parse_with_recovery, parse_function, parse_statement_block, PrecedenceTable, ParseOptions,
+};
+use sheeppig::tree_printer::print_tree;
+use sheeppig::span::Span;
+use sheeppig::source_printer::print_source;
 
 
 #[test]
 fn test_parse_hello_world() {
     let source_code = read_file("./samples/test_samples/hello_world.sp");
 
-    let tokens = tokenize(&source_code);
-    let tree = parse(&tokens);
-
-    let func_call = FunctionCallExpression {
-        name: Identifier::Simple("print".to_string()),
-        parameters: vec![
-            Expression::Atomic(
-                AtomicExpression::Literal(
-                    Literal::String("Hello, world!".to_string())
-                )
-            )
-        ]
-    };
-
-    let func_body = Box::new(StatementBlock {
-        statements: vec![
-            Statement::Expression(
-                Expression::Atomic(
-                    AtomicExpression::FunctionCall(func_call)
-                ),
-            ),
-        ]
-    });
-
-    let expected = Module {
-        name: Identifier::Simple("main".to_string()),
-        imports: vec![],
-        functions: vec![
-            Function {
-                name: Identifier::Simple("main".to_string()),
-                parameters: vec![],
-                return_type: None,
-                body: func_body,
-            }
-        ],
-        statements: StatementBlock::empty(),
-    };
-
-    assert_eq!(tree, expected);
+    assert_parses_to(&source_code, r#"
+        module main
+          function main()
+            call print
+              literal "Hello, world!"
+    "#);
 }
 
 #[test]
@@ -58,7 +31,7 @@ fn test_parse_comments() {
     let source_code = read_file("./samples/test_samples/comments.sp");
 
     let tokens = tokenize(&source_code);
-    let tree = parse(&tokens);
+    let tree = parse(&tokens).unwrap();
 
     let func_body = Box::new(StatementBlock {
         statements: vec![
@@ -71,21 +44,21 @@ fn test_parse_comments() {
                     )
                 ),
                 is_mutable: true,
-            }),
+            }).into(),
             Statement::Declaration(DeclarationStatement {
                 name: Identifier::Simple("b".to_string()),
                 var_type: Identifier::Simple("int".to_string()),
                 value: Expression::BinaryOperation {
                     left: Box::new(Expression::Atomic(
                         AtomicExpression::Literal(Literal::Integer(1))
-                    )),
+                    ).into()),
                     operator: Operator::Plus,
                     right: Box::new(Expression::Atomic(
                         AtomicExpression::Literal(Literal::Integer(2))
-                    )),
+                    ).into()),
                 },
                 is_mutable: false,
-            }),
+            }).into(),
             Statement::Declaration(DeclarationStatement {
                 name: Identifier::Simple("c".to_string()),
                 var_type: Identifier::Simple("int".to_string()),
@@ -95,23 +68,529 @@ fn test_parse_comments() {
                     )
                 ),
                 is_mutable: false,
-            })
+            }).into()
         ]
     });
 
     let expected = Module {
         name: Identifier::Simple("main".to_string()),
         imports: vec![],
+        structs: vec![],
         functions: vec![
             Function {
                 name: Identifier::Simple("main".to_string()),
                 parameters: vec![],
                 return_type: None,
                 body: func_body,
-            }
+                doc: None,
+                is_const: false,
+            }.into()
         ],
         statements: StatementBlock::empty(),
     };
 
     assert_eq!(tree, expected);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_parse_doc_comments() {
+    let source_code = read_file("./samples/test_samples/doc_comments.sp");
+
+    let tokens = tokenize_with_options(&source_code, &LexOptions { trivia: true, ..LexOptions::default() });
+    let tree = parse(&tokens).unwrap();
+
+    assert_eq!(tree.functions.len(), 2);
+    assert_eq!(tree.functions[0].doc, Some("Adds two numbers together\nand returns the result".to_string()));
+    assert_eq!(tree.functions[1].doc, None);
+}
+
+#[test]
+fn test_parse_doc_comments_without_trivia() {
+    let source_code = read_file("./samples/test_samples/doc_comments.sp");
+
+    let tokens = tokenize(&source_code);
+    let tree = parse(&tokens).unwrap();
+
+    assert_eq!(tree.functions[0].doc, None);
+}
+
+#[test]
+fn test_parse_trailing_comments() {
+    let source_code = "x: int = 1  # the value\n# own line\ny: int = 2 /* block */\nz: int = 3\n";
+
+    let tokens = tokenize_with_options(source_code, &LexOptions { trivia: true, ..LexOptions::default() });
+    let tree = parse(&tokens).unwrap();
+
+    let comments: Vec<Option<&str>> = tree.statements.statements.iter()
+        .map(|statement| statement.trailing_comment.as_deref())
+        .collect();
+    assert_eq!(comments, vec![Some("the value"), Some("block"), None]);
+}
+
+#[test]
+fn test_parse_import() {
+    let source_code = read_file("./samples/test_samples/import.sp");
+
+    assert_parses_to(&source_code, r#"
+        module main
+          import sqrt as square_root from math.utils
+          import sin as sin from math.trig
+          import cos as cos from math.trig
+          function main()
+    "#);
+}
+
+#[test]
+fn test_parse_with_spans() {
+    let source_code = "fun main() {\n    x = 1 + 2\n}\n";
+
+    let (tokens, spans) = tokenize_with_spans(source_code, &LexOptions::default());
+    let tree = parse_with_spans(&tokens, &spans).unwrap();
+
+    let function = &tree.functions[0];
+    assert_eq!(function.span, Span::new(0, 28));
+
+    let statement = &function.body.statements[0];
+    assert_eq!(&source_code[statement.span.start..statement.span.end], "x = 1 + 2");
+}
+
+#[test]
+fn test_trees_differing_only_in_spans_are_equal() {
+    let parse_source = |source: &str| {
+        let (tokens, spans) = tokenize_with_spans(source, &LexOptions::default());
+        parse_with_spans(&tokens, &spans).unwrap()
+    };
+
+    let tree = parse_source("fun main() {\n    x = 1 + 2\n}\n");
+    let other = parse_source("fun   main() {\nx=1+2\n}\n");
+
+    assert_ne!(tree.functions[0].body.statements[0].span, other.functions[0].body.statements[0].span);
+    assert_eq!(tree, other);
+}
+
+#[test]
+fn test_reprinted_floats_reparse_to_equal_values() {
+    let source_code = read_file("./samples/test_samples/arrays.sp");
+
+    let tree = parse(&tokenize(&source_code)).unwrap();
+    let printed = print_source(&tree);
+    let reparsed = parse(&tokenize(&printed)).unwrap();
+
+    assert!(printed.contains("1e-6"));
+    assert_eq!(reparsed, tree);
+}
+
+#[test]
+fn test_parse_single_function() {
+    let tokens = tokenize("fun first() {\n}\nfun second(x: int): int {\n    return x\n}\n");
+    let start = tokens.iter().rposition(|token| *token == Token::Keyword(Keyword::Function)).unwrap();
+
+    let function = parse_function(&tokens[start..]).unwrap();
+
+    assert_eq!(function.name, Identifier::Simple("second".to_string()));
+    assert_eq!(function.return_type, Some(Identifier::Simple("int".to_string())));
+    assert_eq!(function.body.statements.len(), 1);
+}
+
+#[test]
+fn test_parse_single_statement_block() {
+    let source = "{\n    x = 1\n    y = x + 2\n}";
+    let (tokens, spans) = try_tokenize_with_spans(source, &LexOptions::default()).unwrap();
+    let block = parse_statement_block(&tokens, &spans).unwrap();
+
+    assert_eq!(block.statements.len(), 2);
+    assert!(matches!(block.statements[1].node, Statement::Assignment(_)));
+    let span = block.statements[1].span;
+    assert_eq!(&source[span.start..span.end], "y = x + 2");
+}
+
+#[test]
+fn test_parse_with_custom_precedence() {
+    let tokens = tokenize("1 + 2 * 3\n");
+
+    let mut precedence = PrecedenceTable::default();
+    precedence.set_precedence(Operator::Plus, 0);
+    let tree = parse_with_precedence(&tokens, &precedence).unwrap();
+
+    assert_eq!(print_tree(&tree), concat!(
+        "module main\n",
+        "  binary Times\n",
+        "    binary Plus\n",
+        "      literal 1\n",
+        "      literal 2\n",
+        "    literal 3\n",
+    ));
+    assert_ne!(tree, parse(&tokens).unwrap());
+}
+
+#[test]
+fn test_parse_map_literals_beside_blocks() {
+    assert_parses_to("x = {\"a\": 1}\n{\n    y = {}\n}\n", r#"
+        module main
+          assign
+            identifier x
+            map
+              entry
+                literal "a"
+                literal 1
+          block
+            assign
+              identifier y
+              map
+    "#);
+}
+
+#[test]
+fn test_parse_pipe_chain() {
+    assert_parses_to("x + 1 |> f(2) |> g\n", r#"
+        module main
+          call g
+            call f
+              binary Plus
+                identifier x
+                literal 1
+              literal 2
+    "#);
+
+    let tokens = tokenize("x |> 1\n");
+    assert!(parse(&tokens).is_err());
+}
+
+#[test]
+fn test_parse_in_as_loop_separator_and_membership() {
+    assert_parses_to("for x in values {\n    if x in seen {\n        pass\n    }\n}\n", r#"
+        module main
+          for
+            x
+          in
+            identifier values
+          do
+            if
+              binary In
+                identifier x
+                identifier seen
+            then
+              pass
+    "#);
+}
+
+#[test]
+fn test_parse_comprehensions() {
+    assert_parses_to("[x * 2 for x in values]\n", r#"
+        module main
+          comprehension
+            binary Times
+              identifier x
+              literal 2
+            for
+              x
+            in
+              identifier values
+    "#);
+
+    assert_parses_to("[key for key, value in scores if value > 0]\n", r#"
+        module main
+          comprehension
+            identifier key
+            for
+              key, value
+            in
+              identifier scores
+            if
+              binary GreaterThan
+                identifier value
+                literal 0
+    "#);
+
+    let tokens = tokenize("[x for x in values\n");
+    assert!(parse(&tokens).is_err());
+}
+
+#[test]
+fn test_parse_too_many_statements() {
+    let options = ParseOptions { max_statements: 100, ..ParseOptions::default() };
+
+    let tokens = tokenize(&"x = 1\n".repeat(101));
+    let error = parse_with_options(&tokens, &[], &options).unwrap_err();
+    assert_eq!(error.message, "Too many statements");
+
+    // Statements in nested blocks count towards the limit as well
+    let tokens = tokenize(&format!("while true {{\n{}}}\n", "x = 1\n".repeat(100)));
+    assert!(parse_with_options(&tokens, &[], &options).is_err());
+
+    let tokens = tokenize(&"x = 1\n".repeat(100));
+    assert!(parse_with_options(&tokens, &[], &options).is_ok());
+}
+
+#[test]
+fn test_parse_with_recovery_skips_a_broken_statement() {
+    let source_code = "fun main() {\n    x: int = 1\n    y = )\n    print(x)\n}\n";
+    let options = LexOptions::default();
+    let (tokens, spans) = tokenize_with_spans(source_code, &options);
+
+    let (module, errors) = parse_with_recovery(&tokens, &spans);
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(position(source_code, errors[0].span, &options).line, 3);
+    assert_eq!(module.unwrap().functions[0].body.statements.len(), 2);
+    assert!(parse_with_spans(&tokens, &spans).is_err());
+}
+
+#[test]
+fn test_parse_with_recovery_closes_a_block_ending_in_a_broken_statement() {
+    let source_code = "fun main() {\n    x: int = 1\n    y = x +\n}\nz: int = 2; w = 3 +; print(z)\n";
+    let (tokens, spans) = tokenize_with_spans(source_code, &LexOptions::default());
+
+    let (module, errors) = parse_with_recovery(&tokens, &spans);
+
+    let module = module.unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(module.functions[0].body.statements.len(), 1);
+    assert_eq!(module.statements.statements.len(), 2);
+}
+
+#[test]
+fn test_parse_free_form() {
+    let source_code = "var total: int = 1 +\n    2; if total > 2 {\n    total =\n        0; print(\n        total\n    )\n}\n";
+    let free_form = ParseOptions { free_form: true, ..ParseOptions::default() };
+
+    assert_parses_with_options_to(source_code, &free_form, r#"
+        module main
+          declare var total: int
+            binary Plus
+              literal 1
+              literal 2
+          if
+            binary GreaterThan
+              identifier total
+              literal 2
+          then
+            assign
+              identifier total
+              literal 0
+            call print
+              identifier total
+    "#);
+
+    // Without a semicolon, the statements on separate lines run together
+    let tokens = tokenize("x = 1\ny = 2\n");
+    assert!(parse(&tokens).is_ok());
+    assert!(parse_with_options(&tokens, &[], &free_form).is_err());
+}
+
+#[test]
+fn test_parse_free_form_expression_across_lines() {
+    let free_form = ParseOptions { free_form: true, ..ParseOptions::default() };
+
+    assert_parses_with_options_to("y: int = x\n    * 3; print(\n    *values)\n", &free_form, r#"
+        module main
+          declare y: int
+            binary Times
+              identifier x
+              literal 3
+          call print
+            spread
+              identifier values
+    "#);
+}
+
+#[test]
+fn test_free_form_error_has_a_span() {
+    let source = "x = 1\ny = 2\n";
+    let (tokens, spans) = tokenize_with_spans(source, &LexOptions::default());
+    let free_form = ParseOptions { free_form: true, ..ParseOptions::default() };
+
+    let error = parse_with_options(&tokens, &spans, &free_form).unwrap_err();
+    assert_eq!(&source[error.span.start..error.span.end], "y");
+}
+
+#[test]
+fn test_parse_semicolons_between_statements() {
+    assert_parses_to("x = 1; y = 2\n", r#"
+        module main
+          assign
+            identifier x
+            literal 1
+          assign
+            identifier y
+            literal 2
+    "#);
+}
+
+#[test]
+fn test_parse_struct_definition() {
+    assert_parses_to("struct Point {\n    x: int, y: int\n    label: string\n}\n", r#"
+        module main
+          struct Point
+            x: int
+            y: int
+            label: string
+    "#);
+}
+
+#[test]
+fn test_parse_struct_literal() {
+    assert_parses_to("p: Point = Point { x: 1, y: 2 * 3 }\nprint(p.x)\n", r#"
+        module main
+          declare p: Point
+            struct Point
+              field x
+                literal 1
+              field y
+                binary Times
+                  literal 2
+                  literal 3
+          call print
+            identifier p.x
+    "#);
+
+    // A brace after a condition still starts the block it guards
+    assert_parses_to("if Ready {\n    x = 1\n}\n", r#"
+        module main
+          if
+            identifier Ready
+          then
+            assign
+              identifier x
+              literal 1
+    "#);
+}
+
+#[test]
+fn test_parse_ranges() {
+    assert_parses_to("for i in 0..n + 1 {\n    pass\n}\n", r#"
+        module main
+          for
+            i
+          in
+            binary Range
+              literal 0
+              binary Plus
+                identifier n
+                literal 1
+          do
+            pass
+    "#);
+
+    assert_parses_to("'a'..='z'\n", r#"
+        module main
+          binary InclusiveRange
+            literal 'a'
+            literal 'z'
+    "#);
+}
+
+#[test]
+fn test_parse_inline_assignment() {
+    assert_parses_to("if (n := compute()) > 0 {\n    use(n)\n}\n", r#"
+        module main
+          if
+            binary GreaterThan
+              group
+                inline assign n
+                  call compute
+              literal 0
+          then
+            call use
+              identifier n
+    "#);
+
+    let tokens = tokenize("values[0] := 1\n");
+    assert!(parse(&tokens).is_err());
+}
+
+#[test]
+fn test_parse_identity_operators() {
+    assert_parses_to("x is None\n", r#"
+        module main
+          binary Is
+            identifier x
+            literal None
+    "#);
+
+    assert_parses_to("x is not None && y\n", r#"
+        module main
+          binary And
+            binary IsNot
+              identifier x
+              literal None
+            identifier y
+    "#);
+}
+
+#[test]
+fn test_parse_global_statement() {
+    assert_parses_to("fun count() {\n    global total\n    total += 1\n}\n", r#"
+        module main
+          function count()
+            global total
+            assign
+              identifier total
+              binary Plus
+                identifier total
+                literal 1
+    "#);
+}
+
+#[test]
+fn test_parse_if_expression() {
+    assert_parses_to("x = if c { 1 } else { 2 }\n", r#"
+        module main
+          assign
+            identifier x
+            group
+              ternary
+                identifier c
+                literal 1
+                literal 2
+    "#);
+
+    assert_parses_to("x = if a {\n    1\n} else if b {\n    2\n} else {\n    3\n}\n", r#"
+        module main
+          assign
+            identifier x
+            group
+              ternary
+                identifier a
+                literal 1
+                ternary
+                  identifier b
+                  literal 2
+                  literal 3
+    "#);
+
+    let tokens = tokenize("x = if c { 1 }\n");
+    assert!(parse(&tokens).is_err());
+}
+
+#[test]
+fn test_parse_const_function() {
+    assert_parses_to("const fun square(x: int): int {\n    return x * x\n}\n", r#"
+        module main
+          const function square(x: int): int
+            return
+              binary Times
+                identifier x
+                identifier x
+    "#);
+}
+
+#[test]
+fn test_parse_empty_sources() {
+    let empty = Module {
+        name: Identifier::Simple("main".to_string()),
+        imports: vec![],
+        structs: vec![],
+        functions: vec![],
+        statements: StatementBlock::empty(),
+    };
+
+    for source_code in ["", "  \n\t\n", "# just a comment\n/* and a block */\n"] {
+        let tokens = tokenize(source_code);
+        assert_eq!(tokens, vec![Token::EndOfModule]);
+        assert_eq!(parse(&tokens).unwrap(), empty);
+
+        let tokens = tokenize_with_options(source_code, &LexOptions { trivia: true, ..LexOptions::default() });
+        assert_eq!(parse(&tokens).unwrap(), empty);
+    }
+}
@@ -1,8 +1,47 @@
 use std::fs::read_to_string;
 
+use sheeppig::lexer::{tokenize, tokenize_with_spans, LexOptions};
+use sheeppig::parser::{parse, parse_with_options, ParseOptions};
+use sheeppig::tree_printer::print_tree;
+
 
 pub fn read_file(file_path: &str) -> String {
-    let input = read_to_string(file_path)
-        .expect("Failed to read input file");
-    input
+    read_to_string(file_path)
+        .expect("Failed to read input file")
+}
+
+
+// Parses the source and compares the printed tree to the expected outline. The expected outline
+// may be indented as a whole so it can be written inline in a test.
+#[allow(dead_code)]
+pub fn assert_parses_to(source_code: &str, expected: &str) {
+    let tree = parse(&tokenize(source_code)).unwrap();
+    assert_eq!(print_tree(&tree), remove_common_indent(expected));
+}
+
+
+#[allow(dead_code)]
+pub fn assert_parses_with_options_to(source_code: &str, options: &ParseOptions, expected: &str) {
+    let (tokens, spans) = tokenize_with_spans(source_code, &LexOptions::default());
+    let tree = parse_with_options(&tokens, &spans, options).unwrap();
+    assert_eq!(print_tree(&tree), remove_common_indent(expected));
+}
+
+
+#[allow(dead_code)]
+fn remove_common_indent(text: &str) -> String {
+    let lines: Vec<&str> = text.lines()
+        .skip_while(|line| line.trim().is_empty())
+        .collect();
+
+    let indent = lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| format!("{}\n", &line[indent..]))
+        .collect()
 }